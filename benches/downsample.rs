@@ -0,0 +1,58 @@
+//! Per-candidate cost of mixing down to baseband, with and without a
+//! shared forward transform.
+//!
+//! Run with `cargo bench --bench downsample`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rustyft8::sync::{downsample_200hz, Downsampler, NMAX};
+
+/// Candidate frequencies per run, roughly one busy band's worth
+const CANDIDATES: usize = 50;
+
+/// Runs averaged per measurement
+const RUNS: u32 = 5;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        f();
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let signal: Vec<f32> = (0..NMAX)
+        .map(|i| (i as f32 * 0.7).sin() + 0.3 * (i as f32 * 0.013).cos())
+        .collect();
+    let frequencies: Vec<f32> = (0..CANDIDATES).map(|i| 200.0 + 55.0 * i as f32).collect();
+
+    let one_off = time(|| {
+        for &f0 in &frequencies {
+            black_box(downsample_200hz(&signal, f0).unwrap());
+        }
+    });
+    let shared = time(|| {
+        let downsampler = Downsampler::new(&signal).unwrap();
+        for &f0 in &frequencies {
+            black_box(downsampler.downsample(f0).unwrap());
+        }
+    });
+
+    let per = |d: Duration| d / CANDIDATES as u32;
+    println!("{} candidates, mean of {} runs", CANDIDATES, RUNS);
+    println!(
+        "downsample_200hz:        {:>10.2?} per candidate",
+        per(one_off)
+    );
+    println!(
+        "Downsampler::downsample: {:>10.2?} per candidate",
+        per(shared)
+    );
+    println!(
+        "speedup:                 {:>10.1}x",
+        one_off.as_secs_f64() / shared.as_secs_f64()
+    );
+}
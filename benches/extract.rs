@@ -0,0 +1,52 @@
+//! Per-candidate cost of demodulating symbols into the four LLR variants,
+//! dominated by the nsym = 3 bit metrics (512 tone combinations per
+//! group of three symbols).
+//!
+//! Run with `cargo bench --bench extract`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rustyft8::sync::{extract_symbols_all_llr_with, Candidate, Downsampler, SyncMetric, NMAX};
+
+/// Candidates per run, roughly one busy band's worth
+const CANDIDATES: usize = 50;
+
+/// Runs averaged per measurement
+const RUNS: u32 = 5;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        f();
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let signal: Vec<f32> = (0..NMAX)
+        .map(|i| (i as f32 * 0.7).sin() + 0.3 * (i as f32 * 0.013).cos())
+        .collect();
+    let downsampler = Downsampler::new(&signal).unwrap();
+    let candidates: Vec<Candidate> = (0..CANDIDATES)
+        .map(|i| Candidate {
+            frequency: 200.0 + 55.0 * i as f32,
+            time_offset: 0.0,
+            sync_power: 1.0,
+            sync_metric: SyncMetric::AllCostas,
+        })
+        .collect();
+
+    let elapsed = time(|| {
+        for candidate in &candidates {
+            black_box(extract_symbols_all_llr_with(&downsampler, candidate).unwrap());
+        }
+    });
+
+    println!("{} candidates, mean of {} runs", CANDIDATES, RUNS);
+    println!(
+        "extract_symbols_all_llr_with: {:>10.2?} per candidate",
+        elapsed / CANDIDATES as u32
+    );
+}
@@ -0,0 +1,54 @@
+//! Cost of bringing the spectrogram up to date after a subtraction:
+//! recomputing only the rows around the subtracted signals against a full
+//! recompute.
+//!
+//! Run with `cargo bench --bench spectra`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rustyft8::modulation::TONE_SPACING;
+use rustyft8::sync::{compute_spectra, compute_spectra_region, NMAX};
+
+/// Runs averaged per measurement
+const RUNS: u32 = 10;
+
+/// Rows recomputed either side of a subtracted signal, as by the decoder
+const MARGIN_HZ: f32 = 2.0 * TONE_SPACING;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        f();
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let signal: Vec<f32> = (0..NMAX)
+        .map(|i| (i as f32 * 0.7).sin() + 0.3 * (i as f32 * 0.013).cos())
+        .collect();
+    let mut spectra = compute_spectra(&signal).unwrap();
+
+    let full = time(|| {
+        black_box(compute_spectra(&signal).unwrap());
+    });
+    println!("mean of {} runs", RUNS);
+    println!("compute_spectra:                 {:>10.2?}", full);
+
+    // One signal's rows, then wider ranges as left by several
+    let one_signal = 7.0 * TONE_SPACING + 2.0 * MARGIN_HZ;
+    for width in [one_signal, 100.0, 150.0, 200.0, 300.0] {
+        let region = time(|| {
+            compute_spectra_region(&signal, 1000.0, 1000.0 + width, &mut spectra).unwrap();
+            black_box(&spectra);
+        });
+        println!(
+            "compute_spectra_region {:>4.0} Hz: {:>10.2?} ({:.2}x full)",
+            width,
+            region,
+            region.as_secs_f64() / full.as_secs_f64()
+        );
+    }
+}
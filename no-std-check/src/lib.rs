@@ -0,0 +1,40 @@
+//! The RustyFt8 receiver built without `std`
+//!
+//! This crate is `no_std` and depends on `rustyft8` with default features
+//! off, so building it checks that the decode pipeline needs only `alloc`,
+//! and its test runs a full decode through the `libm` maths and built-in
+//! FFT. Run it with
+//! `cargo test --manifest-path no-std-check/Cargo.toml`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use rustyft8::decoder::{decode_ft8, DecodeError, DecoderConfig};
+
+/// Decode one 15 s, 12 kHz window with the default configuration,
+/// returning the message texts.
+pub fn decode_window(signal: &[f32]) -> Result<Vec<String>, DecodeError> {
+    let decodes = decode_ft8(signal, &DecoderConfig::default(), None)?;
+    Ok(decodes.into_iter().map(|d| d.message).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustyft8::message::tx_cq;
+    use rustyft8::modulation::BandSynthesizer;
+
+    #[test]
+    fn test_decode_without_std() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1200.0, 0.2, 1.0)
+            .add_signal(&tx_cq("N0YPR", "DM42").unwrap(), 1800.0, -0.3, 0.5)
+            .synthesize();
+        let decodes = decode_window(&signal).unwrap();
+        assert_eq!(decodes, ["CQ K1ABC FN42", "CQ N0YPR DM42"]);
+    }
+}
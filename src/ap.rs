@@ -0,0 +1,299 @@
+//! A-priori (AP) hints for decoding expected messages
+//!
+//! During a QSO the station knows much of what the other side will send
+//! next: its own callsign, the DX station's, often the message type. WSJT-X
+//! feeds that knowledge to the LDPC decoder as a-priori bits, replacing the
+//! channel LLRs of the known message bits with confident values and holding
+//! them fixed, which lets much weaker signals decode.
+//!
+//! [`build_apmask`] turns one of the common expectations into the 174-bit
+//! hint/mask pair, [`apply_ap`] writes the hints into a candidate's LLRs,
+//! and [`decode_hybrid_with_ap`](crate::ldpc::decode_hybrid_with_ap)
+//! decodes the result. Only the 77 message bits are ever masked; the CRC
+//! and parity bits are always left to the channel.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+
+use crate::ldpc::N;
+use crate::message::{
+    classify_callsign, encode_variant, CallsignHashCache, CallsignKind, GridReport, MessageError,
+    MessageVariant, MESSAGE_BITS,
+};
+
+/// Bits 0..58 of a standard message: both callsign fields with their
+/// suffix flags
+const CALL_FIELD_BITS: usize = 58;
+
+/// Position of `i3`, the last three message bits
+const I3_BITS: core::ops::Range<usize> = 74..77;
+
+/// How far above the strongest channel LLR [`apply_ap`] places the hints
+const AP_MARGIN: f32 = 1.01;
+
+/// A message pattern whose known fields become a-priori bits.
+///
+/// Callsigns that do not fit the 28-bit standard field (compound calls
+/// such as `PJ4/K1ABC`, or plain non-standard ones) are expected in the
+/// form a sender must use for them: as a 22-bit hash in a standard
+/// message, or in full in a non-standard-call (i3=4) message where that
+/// message type can carry the pattern. `/P` calls are not supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApPattern {
+    /// `CQ <my_call> ...`: the CQ and calling station known, any grid.
+    /// With a non-standard `my_call` the whole i3=4 CQ is known.
+    Cq,
+    /// `<dx_call> <my_call> ...`: both callsigns known; the rest may be a
+    /// grid, a report with or without `R`, `RRR`, `RR73` or `73`
+    Report,
+    /// `<dx_call> <my_call> RR73`: the whole message known
+    Rr73,
+}
+
+/// How a callsign is written in a template: standard calls as they are
+/// (with the `/R` flag split off), others bracketed to be hashed.
+fn template_call(call: &str) -> Result<(String, bool), MessageError> {
+    let upper = call.trim().to_uppercase();
+    match classify_callsign(&upper) {
+        CallsignKind::Standard if !upper.ends_with("/P") => {
+            let rover = upper.ends_with("/R");
+            let base = upper.trim_end_matches("/R").to_string();
+            Ok((base, rover))
+        }
+        CallsignKind::Compound | CallsignKind::NonStandard => Ok((format!("<{}>", upper), false)),
+        _ => Err(MessageError::InvalidCallsign(call.to_string())),
+    }
+}
+
+/// Whether a template call is a hash of a non-standard callsign.
+fn is_hashed(call: &(String, bool)) -> bool {
+    call.0.starts_with('<')
+}
+
+/// The message carrying the known fields, and how many of its leading
+/// bits are known besides `i3`.
+fn template(
+    pattern: ApPattern,
+    my_call: &str,
+    dx_call: Option<&str>,
+) -> Result<(MessageVariant, usize), MessageError> {
+    let my = template_call(my_call)?;
+    let standard =
+        |call1: (String, bool), call2: (String, bool), payload| MessageVariant::Standard {
+            call1: call1.0,
+            call1_suffix: call1.1,
+            call2: call2.0,
+            call2_suffix: call2.1,
+            r_flag: false,
+            payload,
+        };
+
+    if pattern == ApPattern::Cq {
+        if is_hashed(&my) {
+            let full_call = my.0.trim_matches(|c| c == '<' || c == '>').to_string();
+            let cq = MessageVariant::NonStandardCall {
+                hashed_call: String::new(),
+                full_call,
+                hashed_first: false,
+                payload: GridReport::Blank,
+                cq: true,
+            };
+            return Ok((cq, MESSAGE_BITS));
+        }
+        let cq = standard(("CQ".into(), false), my, GridReport::Blank);
+        return Ok((cq, CALL_FIELD_BITS));
+    }
+
+    let dx_call = dx_call.ok_or_else(|| MessageError::InvalidCallsign(String::new()))?;
+    let dx = template_call(dx_call)?;
+    if pattern == ApPattern::Report {
+        return Ok((standard(dx, my, GridReport::Blank), CALL_FIELD_BITS));
+    }
+
+    // RR73 to or from a non-standard call goes in full in an i3=4 message,
+    // with the other call hashed
+    let variant = match (is_hashed(&dx), is_hashed(&my)) {
+        (false, true) | (true, false) => {
+            let hashed_first = is_hashed(&my);
+            let (hashed, full) = if hashed_first { (dx, my) } else { (my, dx) };
+            MessageVariant::NonStandardCall {
+                hashed_call: format!("<{}>", hashed.0),
+                full_call: full.0.trim_matches(|c| c == '<' || c == '>').to_string(),
+                hashed_first,
+                payload: GridReport::Rr73,
+                cq: false,
+            }
+        }
+        _ => standard(dx, my, GridReport::Rr73),
+    };
+    Ok((variant, MESSAGE_BITS))
+}
+
+/// Build the a-priori hints and mask for messages matching `pattern`.
+///
+/// `dx_call` is required for [`ApPattern::Report`] and
+/// [`ApPattern::Rr73`]. Returns 174 hints, `+1.0` for a known 1 bit and
+/// `-1.0` for a known 0 (following the positive-LLR-means-1 convention),
+/// `0.0` elsewhere, and the 174-entry mask marking the known bits. Pass
+/// both to [`apply_ap`] and then the mask to
+/// [`decode_hybrid_with_ap`](crate::ldpc::decode_hybrid_with_ap).
+pub fn build_apmask(
+    pattern: ApPattern,
+    my_call: &str,
+    dx_call: Option<&str>,
+) -> Result<(Vec<f32>, Vec<bool>), MessageError> {
+    let (variant, known) = template(pattern, my_call, dx_call)?;
+    let mut bits = bitarr![u8, Msb0; 0; 80];
+    encode_variant(
+        &variant,
+        &mut bits[..MESSAGE_BITS],
+        &mut CallsignHashCache::new(),
+    )?;
+
+    let mut mask = vec![false; N];
+    mask[..known].fill(true);
+    mask[I3_BITS].fill(true);
+    let hints = (0..N)
+        .map(|i| match (mask[i], i < MESSAGE_BITS && bits[i]) {
+            (false, _) => 0.0,
+            (true, true) => 1.0,
+            (true, false) => -1.0,
+        })
+        .collect();
+    Ok((hints, mask))
+}
+
+/// Replace the masked LLRs of a candidate with the a-priori hints.
+///
+/// As in WSJT-X, the hints are scaled just above the strongest channel
+/// LLR so the decoder trusts them more than any received bit.
+pub fn apply_ap(llr: &mut [f32], hints: &[f32], mask: &[bool]) {
+    let magnitude = AP_MARGIN * llr.iter().fold(0.0f32, |m, l| m.max(l.abs()));
+    for ((l, &h), &m) in llr.iter_mut().zip(hints).zip(mask) {
+        if m {
+            *l = h * magnitude;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::encode;
+
+    /// Whether the encoded `text` agrees with every hinted bit.
+    fn consistent(text: &str, hints: &[f32], mask: &[bool]) -> bool {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode(text, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+        (0..MESSAGE_BITS)
+            .filter(|&i| mask[i])
+            .all(|i| bits[i] == (hints[i] > 0.0))
+    }
+
+    #[test]
+    fn test_cq_pattern() {
+        let (hints, mask) = build_apmask(ApPattern::Cq, "K1ABC", None).unwrap();
+        assert_eq!((hints.len(), mask.len()), (N, N));
+        assert_eq!(mask.iter().filter(|&&m| m).count(), 58 + 3);
+        assert!(mask[MESSAGE_BITS..].iter().all(|&m| !m));
+        assert!(consistent("CQ K1ABC FN42", &hints, &mask));
+        assert!(consistent("CQ K1ABC", &hints, &mask));
+        assert!(!consistent("CQ W9XYZ FN42", &hints, &mask));
+        assert!(!consistent("W9XYZ K1ABC FN42", &hints, &mask));
+
+        // A non-standard call's CQ is i3=4 and known in full
+        let (hints, mask) = build_apmask(ApPattern::Cq, "PJ4/K1ABC", None).unwrap();
+        assert!(mask[..MESSAGE_BITS].iter().all(|&m| m));
+        assert!(consistent("CQ PJ4/K1ABC", &hints, &mask));
+        assert!(!consistent("CQ PJ4/K1ABD", &hints, &mask));
+    }
+
+    #[test]
+    fn test_report_pattern_matches_grids_and_reports() {
+        let (hints, mask) = build_apmask(ApPattern::Report, "K1ABC", Some("W9XYZ")).unwrap();
+        for text in [
+            "W9XYZ K1ABC -10",
+            "W9XYZ K1ABC R+05",
+            "W9XYZ K1ABC FN42",
+            "W9XYZ K1ABC R FN42",
+            "W9XYZ K1ABC RRR",
+            "W9XYZ K1ABC 73",
+        ] {
+            assert!(consistent(text, &hints, &mask), "{}", text);
+        }
+        assert!(!consistent("K1ABC W9XYZ -10", &hints, &mask));
+        assert!(!consistent("W9XYZ K1ABD -10", &hints, &mask));
+
+        // A non-standard call is sent hashed alongside a report
+        let (hints, mask) = build_apmask(ApPattern::Report, "PJ4/K1ABC", Some("W9XYZ")).unwrap();
+        assert!(consistent("W9XYZ <PJ4/K1ABC> -10", &hints, &mask));
+        assert!(!consistent("W9XYZ <PJ4/K1ABD> -10", &hints, &mask));
+    }
+
+    #[test]
+    fn test_rr73_pattern() {
+        let (hints, mask) = build_apmask(ApPattern::Rr73, "K1ABC", Some("W9XYZ/R")).unwrap();
+        assert!(mask[..MESSAGE_BITS].iter().all(|&m| m));
+        assert!(consistent("W9XYZ/R K1ABC RR73", &hints, &mask));
+        assert!(!consistent("W9XYZ/R K1ABC RRR", &hints, &mask));
+        assert!(!consistent("W9XYZ K1ABC RR73", &hints, &mask));
+
+        // Either call non-standard: sent in full in an i3=4 message
+        let (hints, mask) = build_apmask(ApPattern::Rr73, "PJ4/K1ABC", Some("W9XYZ")).unwrap();
+        assert!(consistent("<W9XYZ> PJ4/K1ABC RR73", &hints, &mask));
+        let (hints, mask) = build_apmask(ApPattern::Rr73, "K1ABC", Some("YW18FIFA")).unwrap();
+        assert!(consistent("YW18FIFA <K1ABC> RR73", &hints, &mask));
+    }
+
+    #[test]
+    fn test_invalid_calls() {
+        assert!(matches!(
+            build_apmask(ApPattern::Report, "K1ABC", None),
+            Err(MessageError::InvalidCallsign(_))
+        ));
+        assert_eq!(
+            build_apmask(ApPattern::Cq, "CQ", None),
+            Err(MessageError::InvalidCallsign("CQ".into()))
+        );
+        assert!(build_apmask(ApPattern::Cq, "G4ABC/P", None).is_err());
+        assert!(build_apmask(ApPattern::Rr73, "K1ABC", Some("!!")).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_ap_decodes_weak_rr73() {
+        use crate::ldpc::{self, decode_hybrid, decode_hybrid_with_ap, DecodeDepth, K};
+
+        let mut msg = bitarr![u8, Msb0; 0; 80];
+        encode(
+            "W9XYZ K1ABC RR73",
+            &mut msg[..77],
+            &mut CallsignHashCache::new(),
+        )
+        .unwrap();
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        crate::crc::append_crc(&msg[..77], &mut m91[..K]);
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        ldpc::encode(&m91[..K], &mut cw[..N]);
+
+        // Far too noisy to decode unaided
+        let sigma = 1.4f32;
+        let mut rng = crate::channel::Rng::new(7);
+        let mut llr: Vec<f32> = (0..N)
+            .map(|i| {
+                let s = if cw[i] { 1.0 } else { -1.0 };
+                2.0 * (s + sigma * rng.gaussian()) / (sigma * sigma)
+            })
+            .collect();
+        assert!(decode_hybrid(&llr, DecodeDepth::BpOsdHybrid).is_none());
+
+        let (hints, mask) = build_apmask(ApPattern::Rr73, "K1ABC", Some("W9XYZ")).unwrap();
+        apply_ap(&mut llr, &hints, &mask);
+        let (decoded, _, _) =
+            decode_hybrid_with_ap(&llr, Some(&mask), DecodeDepth::BpOsdHybrid).unwrap();
+        assert_eq!(decoded, m91[..K].to_bitvec());
+    }
+}
@@ -0,0 +1,338 @@
+//! Propagation channel models for decoder stress testing
+//!
+//! A [`ChannelModel`] transforms a real audio buffer in place. The models are
+//! deterministic for a given seed so failing decoder tests can be replayed.
+//!
+//! Noise levels follow the WSJT-X convention: SNR is the ratio of signal
+//! power to noise power in a 2500 Hz reference bandwidth, with the signal
+//! taken to be a unit-amplitude FT8 transmission (power ½).
+
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use num_complex::Complex32;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::fft::analytic_signal;
+use crate::modulation::SAMPLE_RATE;
+
+/// Noise reference bandwidth for SNR figures, in Hz
+pub const SNR_REFERENCE_BANDWIDTH: f32 = 2500.0;
+
+/// A propagation or noise model applied to an audio buffer.
+pub trait ChannelModel {
+    /// Apply the channel to `signal`, sampled at `sample_rate` Hz.
+    fn apply(&self, signal: &mut [f32], sample_rate: f32);
+}
+
+/// Small deterministic PRNG (SplitMix64) with Gaussian output.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub(crate) fn uniform(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Standard normal (Box–Muller)
+    pub(crate) fn gaussian(&mut self) -> f32 {
+        let u1 = 1.0 - self.uniform();
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+    }
+}
+
+/// Additive white Gaussian noise at a given SNR (dB in 2500 Hz).
+#[derive(Debug, Clone)]
+pub struct Awgn {
+    pub snr_db: f32,
+    pub seed: u64,
+}
+
+impl Awgn {
+    pub fn new(snr_db: f32, seed: u64) -> Self {
+        Self { snr_db, seed }
+    }
+
+    /// Noise standard deviation per sample at `sample_rate`.
+    pub fn sigma(&self, sample_rate: f32) -> f32 {
+        noise_sigma(self.snr_db, SNR_REFERENCE_BANDWIDTH, sample_rate)
+    }
+}
+
+/// Standard deviation per sample of white noise `snr_db` below a
+/// unit-amplitude signal, measured in `bandwidth_hz`.
+fn noise_sigma(snr_db: f32, bandwidth_hz: f32, sample_rate: f32) -> f32 {
+    let snr = 10f32.powf(snr_db / 10.0);
+    let noise_power = 0.5 / snr * (sample_rate / 2.0) / bandwidth_hz;
+    noise_power.sqrt()
+}
+
+/// Add white Gaussian noise to 12 kHz audio such as
+/// [`transmit`](crate::modulation::transmit) produces.
+///
+/// `snr_db` is relative to a unit-amplitude signal, with the noise power
+/// measured in `bandwidth_hz` rather than the usual 2500 Hz. The same
+/// `seed` always adds the same noise.
+pub fn add_awgn(signal: &mut [f32], snr_db: f32, bandwidth_hz: f32, seed: u64) {
+    let sigma = noise_sigma(snr_db, bandwidth_hz, SAMPLE_RATE);
+    let mut rng = Rng::new(seed);
+    for s in signal.iter_mut() {
+        *s += sigma * rng.gaussian();
+    }
+}
+
+impl ChannelModel for Awgn {
+    fn apply(&self, signal: &mut [f32], sample_rate: f32) {
+        let sigma = self.sigma(sample_rate);
+        let mut rng = Rng::new(self.seed);
+        for s in signal.iter_mut() {
+            *s += sigma * rng.gaussian();
+        }
+    }
+}
+
+/// Flat Rayleigh fading with a Clarke (Jakes) Doppler spectrum.
+///
+/// The complex channel gain is a sum of equal-power sinusoids at
+/// `doppler_hz · cos(α)` with random arrival angles and phases, normalized
+/// to unit mean power. `doppler_hz` is the maximum Doppler shift; HF
+/// ionospheric channels are typically 0.1–2 Hz.
+#[derive(Debug, Clone)]
+pub struct RayleighFading {
+    pub doppler_hz: f32,
+    pub seed: u64,
+}
+
+impl RayleighFading {
+    /// Number of scatterers in the sum-of-sinusoids model
+    const PATHS: usize = 16;
+
+    pub fn new(doppler_hz: f32, seed: u64) -> Self {
+        Self { doppler_hz, seed }
+    }
+
+    fn gains(&self, len: usize, sample_rate: f32) -> Vec<Complex32> {
+        let mut rng = Rng::new(self.seed);
+        let paths: Vec<(f32, f32, f32)> = (0..Self::PATHS)
+            .map(|_| {
+                let alpha = 2.0 * PI * rng.uniform();
+                let freq = 2.0 * PI * self.doppler_hz * alpha.cos() / sample_rate;
+                (freq, 2.0 * PI * rng.uniform(), 2.0 * PI * rng.uniform())
+            })
+            .collect();
+        let scale = 1.0 / (Self::PATHS as f32).sqrt();
+        (0..len)
+            .map(|i| {
+                let t = i as f32;
+                let (re, im) = paths.iter().fold((0.0, 0.0), |(re, im), &(w, pi, pq)| {
+                    (re + (w * t + pi).cos(), im + (w * t + pq).cos())
+                });
+                Complex32::new(re, im) * scale
+            })
+            .collect()
+    }
+}
+
+impl ChannelModel for RayleighFading {
+    fn apply(&self, signal: &mut [f32], sample_rate: f32) {
+        let analytic = analytic_signal(signal);
+        let gains = self.gains(signal.len(), sample_rate);
+        for ((s, z), g) in signal.iter_mut().zip(analytic).zip(gains) {
+            *s = (z * g).re;
+        }
+    }
+}
+
+/// Static multipath: a sum of delayed, scaled copies of the signal.
+///
+/// Each tap is `(delay_s, gain)`; delays are rounded to whole samples and
+/// a tap at zero delay represents the direct path.
+#[derive(Debug, Clone)]
+pub struct Multipath {
+    pub taps: Vec<(f32, f32)>,
+}
+
+impl Multipath {
+    pub fn new(taps: Vec<(f32, f32)>) -> Self {
+        Self { taps }
+    }
+}
+
+impl ChannelModel for Multipath {
+    fn apply(&self, signal: &mut [f32], sample_rate: f32) {
+        let input = signal.to_vec();
+        signal.fill(0.0);
+        for &(delay_s, gain) in &self.taps {
+            let delay = (delay_s * sample_rate).round().max(0.0) as usize;
+            if delay >= signal.len() {
+                continue;
+            }
+            for (out, &x) in signal[delay..].iter_mut().zip(&input) {
+                *out += gain * x;
+            }
+        }
+    }
+}
+
+/// Two-ray propagation: the direct path plus one echo that arrives
+/// `delay_s` later, scaled by `gain` and shifted by `doppler_hz`.
+///
+/// The two paths beat against each other at the Doppler difference, so the
+/// signal fades in and out as on a moving or ionospheric link.
+#[derive(Debug, Clone)]
+pub struct TwoRay {
+    /// Delay of the echo behind the direct path in seconds, rounded to
+    /// whole samples
+    pub delay_s: f32,
+    /// Amplitude of the echo relative to the direct path
+    pub gain: f32,
+    /// Frequency shift of the echo in Hz, which sets the fading rate
+    pub doppler_hz: f32,
+}
+
+impl TwoRay {
+    /// An echo `delay_s` seconds behind the direct path, at `gain` times
+    /// its amplitude and shifted by `doppler_hz`.
+    pub fn new(delay_s: f32, gain: f32, doppler_hz: f32) -> Self {
+        Self {
+            delay_s,
+            gain,
+            doppler_hz,
+        }
+    }
+}
+
+impl ChannelModel for TwoRay {
+    fn apply(&self, signal: &mut [f32], sample_rate: f32) {
+        let analytic = analytic_signal(signal);
+        let delay = (self.delay_s * sample_rate).round().max(0.0) as usize;
+        let w = 2.0 * PI * self.doppler_hz / sample_rate;
+        for (i, s) in signal.iter_mut().enumerate().skip(delay) {
+            let echo = analytic[i - delay] * Complex32::from_polar(self.gain, w * i as f32);
+            *s += echo.re;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(signal: &[f32]) -> f32 {
+        signal.iter().map(|s| s * s).sum::<f32>() / signal.len() as f32
+    }
+
+    fn tone(freq: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * PI * freq * i as f32 / 12000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_awgn_level() {
+        let mut noise = vec![0.0f32; 120_000];
+        let awgn = Awgn::new(-10.0, 1);
+        awgn.apply(&mut noise, 12000.0);
+        // 0.5 / 0.1 * 6000 / 2500 = 12
+        assert!((power(&noise) - 12.0).abs() < 0.3, "{}", power(&noise));
+
+        let mut again = vec![0.0f32; 120_000];
+        awgn.apply(&mut again, 12000.0);
+        assert_eq!(noise, again);
+    }
+
+    #[test]
+    fn test_rayleigh_mean_power() {
+        let mut signal = tone(1500.0, 180_000);
+        RayleighFading::new(1.0, 7).apply(&mut signal, 12000.0);
+        let p = power(&signal);
+        assert!(p > 0.1 && p < 2.0, "mean power {}", p);
+
+        // 1 Hz fading changes the envelope noticeably within 15 s
+        let block_power: Vec<f32> = signal.chunks(6000).map(power).collect();
+        let max = block_power.iter().cloned().fold(0.0, f32::max);
+        let min = block_power.iter().cloned().fold(f32::MAX, f32::min);
+        assert!(max > 4.0 * min, "min {} max {}", min, max);
+    }
+
+    #[test]
+    fn test_multipath_taps() {
+        let mut signal = vec![0.0f32; 100];
+        signal[10] = 1.0;
+        Multipath::new(vec![(0.0, 1.0), (0.001, -0.5)]).apply(&mut signal, 12000.0);
+        assert_eq!(signal[10], 1.0);
+        assert_eq!(signal[22], -0.5);
+        assert_eq!(signal.iter().filter(|s| **s != 0.0).count(), 2);
+    }
+
+    #[test]
+    fn test_two_ray_fades_at_doppler_difference() {
+        let mut signal = tone(1500.0, 24_000);
+        TwoRay::new(0.002, 1.0, 1.0).apply(&mut signal, 12000.0);
+        assert!(signal[..24].iter().all(|&s| s.abs() <= 1.0));
+        // Equal paths cancel twice a second and add to double amplitude
+        // in between
+        let block_power: Vec<f32> = signal[24..].chunks(600).map(power).collect();
+        let max = block_power.iter().cloned().fold(0.0, f32::max);
+        let min = block_power.iter().cloned().fold(f32::MAX, f32::min);
+        assert!(max > 1.8 && min < 0.1, "min {} max {}", min, max);
+    }
+
+    #[test]
+    fn test_add_awgn_decode_rate() {
+        use crate::decoder::{decode_ft8, DecoderConfig};
+        use crate::message::CallsignHashCache;
+        use crate::modulation::channel::add_awgn;
+        use crate::modulation::transmit;
+
+        let clean = transmit("CQ K1ABC FN42", 1200.0, &mut CallsignHashCache::new()).unwrap();
+        let noisy = |snr_db: f32, seed: u64| {
+            let mut signal = clean.clone();
+            add_awgn(&mut signal, snr_db, SNR_REFERENCE_BANDWIDTH, seed);
+            signal
+        };
+        assert_eq!(noisy(-5.0, 9), noisy(-5.0, 9));
+        assert_ne!(noisy(-5.0, 9), noisy(-5.0, 10));
+
+        // The same noise as the Awgn channel model, referred to 2500 Hz
+        let mut via_model = clean.clone();
+        Awgn::new(-5.0, 9).apply(&mut via_model, SAMPLE_RATE);
+        assert_eq!(via_model, noisy(-5.0, 9));
+        // 3 dB more noise in twice the bandwidth is the same density
+        let mut wide = clean.clone();
+        add_awgn(&mut wide, -8.0103, 5000.0, 9);
+        let diff = wide
+            .iter()
+            .zip(&via_model)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(diff < 1e-3, "{}", diff);
+
+        let decoded = |snr_db: f32| {
+            (0..4)
+                .filter(|&seed| {
+                    decode_ft8(&noisy(snr_db, seed), &DecoderConfig::default(), None)
+                        .unwrap()
+                        .iter()
+                        .any(|d| d.message == "CQ K1ABC FN42")
+                })
+                .count()
+        };
+        assert_eq!(decoded(10.0), 4);
+        assert!(decoded(-25.0) <= 1);
+    }
+}
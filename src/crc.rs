@@ -0,0 +1,186 @@
+//! FT8 14-bit CRC
+//!
+//! WSJT-X protects the 77 message bits with a 14-bit CRC (polynomial 0x2757).
+//! The CRC is computed over the 77 bits zero-extended to 82 bits, and the
+//! result is appended to form the 91-bit LDPC input. [`compute`] and
+//! [`verify`] apply it to bits obtained elsewhere, such as the output of
+//! another decoder.
+
+use bitvec::prelude::*;
+
+/// CRC-14 generator polynomial (without the implicit x^14 term)
+pub const CRC_POLYNOMIAL: u16 = 0x2757;
+
+/// Width of the CRC in bits
+pub const CRC_WIDTH: usize = 14;
+
+/// Number of bits the CRC is computed over (77 message bits + 5 zero bits)
+const CRC_INPUT_BITS: usize = 82;
+
+/// Message bits covered by the CRC
+const MESSAGE_BITS: usize = 77;
+
+/// Message bits followed by the CRC
+const PAYLOAD_BITS: usize = MESSAGE_BITS + CRC_WIDTH;
+
+/// The 14-bit CRC of the first 77 bits of `bits`, as WSJT-X appends it.
+///
+/// `bits` may be just the message or a whole 91-bit payload, whose CRC
+/// field is ignored. Panics if it holds fewer than 77 bits.
+pub fn compute(bits: &BitSlice<u8, Msb0>) -> u16 {
+    assert!(bits.len() >= MESSAGE_BITS, "CRC input must hold 77 bits");
+    crc14(bits)
+}
+
+/// Whether bits 77..91 of a 91-bit payload (`message || crc14`) are the
+/// CRC of bits 0..77.
+///
+/// Panics if `message91` holds fewer than 91 bits.
+pub fn verify(message91: &BitSlice<u8, Msb0>) -> bool {
+    assert!(
+        message91.len() >= PAYLOAD_BITS,
+        "CRC payload must hold 91 bits"
+    );
+    message91[MESSAGE_BITS..PAYLOAD_BITS].load_be::<u16>() == crc14(message91)
+}
+
+/// Compute the 14-bit CRC of the first 77 bits of `bits`.
+pub(crate) fn crc14(bits: &BitSlice<u8, Msb0>) -> u16 {
+    let topbit: u16 = 1 << (CRC_WIDTH - 1);
+    let mut remainder: u16 = 0;
+
+    for i in 0..CRC_INPUT_BITS {
+        if i < MESSAGE_BITS && bits[i] {
+            remainder ^= topbit;
+        }
+        remainder = if remainder & topbit != 0 {
+            (remainder << 1) ^ CRC_POLYNOMIAL
+        } else {
+            remainder << 1
+        } & ((1 << CRC_WIDTH) - 1);
+    }
+
+    remainder
+}
+
+/// Build the 91-bit LDPC input from 77 message bits: `message || crc14`.
+pub(crate) fn append_crc(message77: &BitSlice<u8, Msb0>, message91: &mut BitSlice<u8, Msb0>) {
+    let crc = crc14(message77);
+    message91[..MESSAGE_BITS].copy_from_bitslice(&message77[..MESSAGE_BITS]);
+    message91[MESSAGE_BITS..PAYLOAD_BITS].store_be(crc);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc_of_zero_message_is_zero() {
+        let bits = bitarr![u8, Msb0; 0; 80];
+        assert_eq!(crc14(&bits[..77]), 0);
+    }
+
+    #[test]
+    fn test_crc_is_linear() {
+        let mut a = bitarr![u8, Msb0; 0; 80];
+        let mut b = bitarr![u8, Msb0; 0; 80];
+        a.set(0, true);
+        a.set(40, true);
+        b.set(76, true);
+        b.set(13, true);
+        let sum = a ^ b;
+        assert_ne!(crc14(&a[..77]), 0);
+        assert_eq!(crc14(&sum[..77]), crc14(&a[..77]) ^ crc14(&b[..77]));
+    }
+
+    #[test]
+    fn test_append_crc() {
+        let mut msg = bitarr![u8, Msb0; 0; 80];
+        for i in (0..77).step_by(3) {
+            msg.set(i, true);
+        }
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        append_crc(&msg[..77], &mut m91[..91]);
+        assert_eq!(&m91[..77], &msg[..77]);
+        assert_eq!(m91[77..91].load_be::<u16>(), crc14(&msg[..77]));
+        assert!(verify(&m91[..91]));
+
+        let flipped = !m91[5];
+        m91.set(5, flipped);
+        assert!(!verify(&m91[..91]));
+    }
+
+    #[test]
+    fn test_compute_single_bits() {
+        // Bit 76 is x^5 of the 82-bit field, so its CRC is x^19 mod
+        // x^14 + 0x2757: reducing x^14 → 0x2757 five more times gives 0x3874
+        let mut bits = bitarr![u8, Msb0; 0; 96];
+        bits.set(76, true);
+        assert_eq!(compute(&bits[..77]), 0x3874);
+        bits.set(76, false);
+        bits.set(0, true);
+        assert_eq!(compute(&bits[..77]), 0x2bf8);
+        // Only the first 77 bits count
+        bits[77..96].fill(true);
+        assert_eq!(compute(&bits[..91]), 0x2bf8);
+    }
+
+    #[test]
+    fn test_compute_is_remainder_of_polynomial_division() {
+        use crate::message::{encode, CallsignHashCache};
+
+        // The CRC is M(x)·x^19 mod x^14 + 0x2757, for the 77 message bits
+        // M(x) with the first bit highest, worked out here on integers
+        // rather than with the shift register
+        let generator = (1u128 << CRC_WIDTH) | CRC_POLYNOMIAL as u128;
+        let remainder = |mut value: u128| {
+            for shift in (CRC_WIDTH..128).rev() {
+                if value >> shift & 1 == 1 {
+                    value ^= generator << (shift - CRC_WIDTH);
+                }
+            }
+            value as u16
+        };
+        for text in ["CQ K1ABC FN42", "K1ABC W9XYZ R-09", "TNX BOB 73 GL"] {
+            let mut msg = bitarr![u8, Msb0; 0; 80];
+            encode(text, &mut msg[..77], &mut CallsignHashCache::new()).unwrap();
+            let value = msg[..77].load_be::<u128>() << 19;
+            assert_eq!(compute(&msg[..77]), remainder(value), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_verify_transmitted_payloads() {
+        use crate::message::{encode, CallsignHashCache};
+        use crate::symbol::{channel_symbols_from_bits, data_symbol_index, gray};
+
+        for text in ["CQ K1ABC FN42", "K1ABC W9XYZ RR73", "TNX BOB 73 GL"] {
+            let mut msg = bitarr![u8, Msb0; 0; 80];
+            encode(text, &mut msg[..77], &mut CallsignHashCache::new()).unwrap();
+
+            // The payload as carried by the first 91 bits of the tones
+            let tones = channel_symbols_from_bits(&msg[..77]);
+            let mut m91 = bitarr![u8, Msb0; 0; 96];
+            for k in 0..31 {
+                let tone = tones[data_symbol_index(k)];
+                m91[3 * k..3 * k + 3].store_be(gray::to_index(tone));
+            }
+            assert!(verify(&m91[..91]), "{}", text);
+            assert_eq!(m91[77..91].load_be::<u16>(), compute(&msg[..77]));
+
+            for bit in [0, 50, 80, 90] {
+                let mut bad = m91;
+                let flipped = !bad[bit];
+                bad.set(bit, flipped);
+                assert!(!verify(&bad[..91]), "{} bit {}", text, bit);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "91 bits")]
+    fn test_verify_needs_whole_payload() {
+        let bits = bitarr![u8, Msb0; 0; 80];
+        verify(&bits[..77]);
+    }
+}
@@ -0,0 +1,167 @@
+//! ADIF records for logging programs
+//!
+//! A decode addressed to the operator is turned into one ADIF record with
+//! the other station's callsign and whichever of grid or signal report the
+//! message carried, ready to append to a `.adi` log.
+
+use alloc::format;
+use alloc::string::String;
+
+use super::{base_call, DecodedMessage};
+use crate::message::{GridReport, MessageVariant};
+
+/// ADIF record for a decode addressed to `my_call`.
+///
+/// `dial_hz` is the receiver's dial frequency, to which the audio offset
+/// is added for `FREQ`, and `utc` the start of the slot in seconds since
+/// the Unix epoch, for `QSO_DATE` and `TIME_ON`. A grid becomes
+/// `GRIDSQUARE` and a report `RST_RCVD`. The other station's `/R` or `/P`
+/// is kept on its `CALL`.
+///
+/// Returns `None` for messages that are not part of a QSO with `my_call`:
+/// CQs, messages between other stations, free text, telemetry, and
+/// messages whose sender is an unresolved hash. Only the standard message
+/// types (i3 = 1 and 2) are logged.
+pub fn adif_record(m: &DecodedMessage, my_call: &str, dial_hz: f64, utc: u64) -> Option<String> {
+    let (call1, call2, call2_suffix, payload) = match &m.variant {
+        MessageVariant::Standard {
+            call1,
+            call2,
+            call2_suffix,
+            payload,
+            ..
+        } => (call1, call2, call2_suffix.then_some("/R"), payload),
+        MessageVariant::EuVhfContestType2 {
+            call1,
+            call2,
+            call2_suffix,
+            payload,
+            ..
+        } => (call1, call2, call2_suffix.then_some("/P"), payload),
+        _ => return None,
+    };
+    let directed = !call1.starts_with("CQ") && call1 != "QRZ" && call1 != "DE";
+    let mine = base_call(my_call);
+    if !directed || mine.is_empty() || base_call(call1) != mine {
+        return None;
+    }
+    let call = call2.trim_start_matches('<').trim_end_matches('>');
+    if call.is_empty() || call == "..." {
+        return None;
+    }
+
+    let mut record = String::new();
+    let mut field = |name: &str, value: &str| {
+        record += &format!("<{}:{}>{} ", name, value.len(), value);
+    };
+    field("CALL", &format!("{}{}", call, call2_suffix.unwrap_or("")));
+    match payload {
+        GridReport::Grid(grid) => field("GRIDSQUARE", grid),
+        GridReport::Report(report) => field("RST_RCVD", &format!("{:+03}", report)),
+        _ => {}
+    }
+    field("MODE", "FT8");
+    let (year, month, day) = civil_date(utc / 86_400);
+    field("QSO_DATE", &format!("{:04}{:02}{:02}", year, month, day));
+    let seconds = utc % 86_400;
+    let time = format!(
+        "{:02}{:02}{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    field("TIME_ON", &time);
+    let freq_mhz = (dial_hz + m.frequency as f64) / 1e6;
+    field("FREQ", &format!("{:.6}", freq_mhz));
+    field("STATION_CALLSIGN", my_call.trim());
+    record += "<EOR>";
+    Some(record)
+}
+
+/// Gregorian date of a day number counted from 1970-01-01.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    // Shift to eras starting 0000-03-01 so leap days fall at year end
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::tests::decoded;
+
+    #[test]
+    fn test_civil_date() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(19_675), (2023, 11, 14));
+        assert_eq!(civil_date(11_016), (2000, 2, 29));
+        assert_eq!(civil_date(11_017), (2000, 3, 1));
+        assert_eq!(civil_date(20_088), (2024, 12, 31));
+    }
+
+    #[test]
+    fn test_adif_record() {
+        // 2023-11-14 22:13:15 UTC on 20 m
+        let utc = 1_699_999_995;
+        let mut grid = decoded("K1ABC W9XYZ EN37");
+        grid.frequency = 1500.0;
+        assert_eq!(
+            adif_record(&grid, "K1ABC", 14_074_000.0, utc).unwrap(),
+            "<CALL:5>W9XYZ <GRIDSQUARE:4>EN37 <MODE:3>FT8 <QSO_DATE:8>20231114 \
+             <TIME_ON:6>221315 <FREQ:9>14.075500 <STATION_CALLSIGN:5>K1ABC <EOR>"
+        );
+
+        let report = decoded("W9XYZ K1ABC -11");
+        let record = adif_record(&report, "W9XYZ", 14_074_000.0, utc).unwrap();
+        assert!(record.starts_with("<CALL:5>K1ABC <RST_RCVD:3>-11 <MODE:3>FT8 "));
+        assert!(!record.contains("GRIDSQUARE"));
+        let record = adif_record(&decoded("W9XYZ K1ABC R+05"), "W9XYZ", 0.0, utc).unwrap();
+        assert!(record.contains("<RST_RCVD:3>+05 "), "{}", record);
+
+        // Acknowledgements log the call alone
+        let record = adif_record(&decoded("K1ABC W9XYZ RR73"), "K1ABC", 0.0, utc).unwrap();
+        assert!(
+            record.starts_with("<CALL:5>W9XYZ <MODE:3>FT8 "),
+            "{}",
+            record
+        );
+
+        // The other station's /R or /P stays on its call
+        let record = adif_record(&decoded("K1ABC W9XYZ/R EN37"), "K1ABC", 0.0, utc).unwrap();
+        assert!(
+            record.starts_with("<CALL:7>W9XYZ/R <GRIDSQUARE:4>EN37 "),
+            "{}",
+            record
+        );
+        let record = adif_record(&decoded("K1ABC/P G4ABC/P RR73"), "K1ABC", 0.0, utc).unwrap();
+        assert!(
+            record.starts_with("<CALL:7>G4ABC/P <MODE:3>FT8 "),
+            "{}",
+            record
+        );
+
+        // Not a QSO with me
+        assert_eq!(adif_record(&report, "K1ABC", 0.0, utc), None);
+        assert_eq!(
+            adif_record(&decoded("CQ K1ABC FN42"), "K1ABC", 0.0, utc),
+            None
+        );
+        assert_eq!(adif_record(&decoded("CQ K1ABC FN42"), "CQ", 0.0, utc), None);
+        assert_eq!(
+            adif_record(&decoded("TNX BOB 73 GL"), "K1ABC", 0.0, utc),
+            None
+        );
+        assert_eq!(
+            adif_record(&decoded("0123456789ABCDEF01"), "K1ABC", 0.0, utc),
+            None
+        );
+    }
+}
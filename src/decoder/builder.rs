@@ -0,0 +1,370 @@
+//! Checked construction of [`DecoderConfig`]
+
+use core::fmt;
+
+use super::{DecoderConfig, SnrMethod};
+use crate::ldpc::{DecodeAlgo, DecodeDepth};
+use crate::modulation::{SAMPLE_RATE, SYMBOL_DURATION, TONE_SPACING};
+
+/// Largest fine-search half-widths: eight tone spacings, the width of a
+/// whole signal, and one symbol. Searching further finds the neighbouring
+/// tone or symbol rather than refining the candidate
+const MAX_FINE_SEARCH_HZ: f32 = 8.0 * TONE_SPACING;
+const MAX_FINE_SEARCH_MS: f32 = SYMBOL_DURATION * 1000.0;
+
+/// A [`DecoderConfig`] setting outside the range the decoder can use.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// The search band is empty, negative or reaches past the 6 kHz
+    /// Nyquist frequency
+    FrequencyRange { min: f32, max: f32 },
+    /// A setting that must be finite and non-negative is not
+    Negative { setting: &'static str, value: f32 },
+    /// A count or width that must be positive is zero
+    Zero { setting: &'static str },
+    /// A fraction is outside 0 to 1
+    Fraction { setting: &'static str, value: f32 },
+    /// A setting is above the largest value the decoder can use
+    TooLarge {
+        setting: &'static str,
+        value: f32,
+        max: f32,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ConfigError::FrequencyRange { min, max } => write!(
+                f,
+                "Invalid frequency range {}-{} Hz: need 0 <= min < max and all tones below {} Hz",
+                min,
+                max,
+                SAMPLE_RATE / 2.0
+            ),
+            ConfigError::Negative { setting, value } => {
+                write!(
+                    f,
+                    "{} must be finite and non-negative, got {}",
+                    setting, value
+                )
+            }
+            ConfigError::Zero { setting } => write!(f, "{} must be greater than zero", setting),
+            ConfigError::Fraction { setting, value } => {
+                write!(f, "{} must be between 0 and 1, got {}", setting, value)
+            }
+            ConfigError::TooLarge {
+                setting,
+                value,
+                max,
+            } => write!(f, "{} must be at most {}, got {}", setting, max, value),
+        }
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+impl DecoderConfig {
+    /// A builder starting from [`DecoderConfig::default`].
+    pub fn builder() -> DecoderConfigBuilder {
+        DecoderConfigBuilder::default()
+    }
+
+    /// Check that every setting is in a range the decoder can use.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let (min, max) = (self.freq_min, self.freq_max);
+        let top_tone = max + 7.0 * TONE_SPACING;
+        if !(min >= 0.0 && min < max && top_tone < SAMPLE_RATE / 2.0) {
+            return Err(ConfigError::FrequencyRange { min, max });
+        }
+        for (setting, value) in [
+            ("sync_min", self.sync_min),
+            ("candidate_merge_hz", self.candidate_merge_hz),
+            ("candidate_merge_ms", self.candidate_merge_ms),
+            ("fine_search_hz", self.fine_search_hz),
+            ("fine_search_ms", self.fine_search_ms),
+            ("coarse_lag_steps", self.coarse_lag_steps as f32),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(ConfigError::Negative { setting, value });
+            }
+        }
+        for (setting, value, max) in [
+            ("fine_search_hz", self.fine_search_hz, MAX_FINE_SEARCH_HZ),
+            ("fine_search_ms", self.fine_search_ms, MAX_FINE_SEARCH_MS),
+        ] {
+            if value > max {
+                return Err(ConfigError::TooLarge {
+                    setting,
+                    value,
+                    max,
+                });
+            }
+        }
+        if let DecodeAlgo::MinSum { scale } = self.decode_algo {
+            if !(scale > 0.0 && scale <= 1.0) {
+                return Err(ConfigError::Fraction {
+                    setting: "min_sum_scale",
+                    value: scale,
+                });
+            }
+        }
+        let percentile = self.candidate_baseline_percentile;
+        if !(0.0..=1.0).contains(&percentile) {
+            return Err(ConfigError::Fraction {
+                setting: "candidate_baseline_percentile",
+                value: percentile,
+            });
+        }
+        if !(self.frequency_window > 0.0 && self.frequency_window.is_finite()) {
+            return Err(ConfigError::Zero {
+                setting: "frequency_window",
+            });
+        }
+        for (setting, value) in [
+            ("max_candidates", self.max_candidates),
+            ("threads", self.threads),
+            ("max_passes", self.max_passes),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::Zero { setting });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [`DecoderConfig`], checking the settings with
+/// [`DecoderConfig::validate`].
+#[derive(Debug, Clone, Default)]
+pub struct DecoderConfigBuilder {
+    config: DecoderConfig,
+}
+
+impl DecoderConfigBuilder {
+    /// Search tone-0 frequencies from `min` to `max` Hz.
+    pub fn freq_range(&mut self, min: f32, max: f32) -> &mut Self {
+        self.config.freq_min = min;
+        self.config.freq_max = max;
+        self
+    }
+
+    /// Minimum normalized coarse sync for a candidate.
+    pub fn sync_min(&mut self, sync_min: f32) -> &mut Self {
+        self.config.sync_min = sync_min;
+        self
+    }
+
+    /// Maximum number of candidates decoded per window.
+    pub fn max_candidates(&mut self, max_candidates: usize) -> &mut Self {
+        self.config.max_candidates = max_candidates;
+        self
+    }
+
+    /// LDPC effort per candidate.
+    pub fn depth(&mut self, depth: DecodeDepth) -> &mut Self {
+        self.config.decode_depth = depth;
+        self
+    }
+
+    /// Belief propagation check-node update.
+    pub fn algo(&mut self, algo: DecodeAlgo) -> &mut Self {
+        self.config.decode_algo = algo;
+        self
+    }
+
+    /// Drop decodes with more than `max` hard-decision errors.
+    pub fn max_hard_errors(&mut self, max: usize) -> &mut Self {
+        self.config.max_hard_errors = max;
+        self
+    }
+
+    /// The operator's callsign.
+    pub fn my_callsign(&mut self, call: &str) -> &mut Self {
+        self.config.my_callsign = Some(call.into());
+        self
+    }
+
+    /// Half-width in Hz of the search around each frequency given to
+    /// [`decode_ft8_at_frequencies`](super::decode_ft8_at_frequencies).
+    pub fn frequency_window(&mut self, hz: f32) -> &mut Self {
+        self.config.frequency_window = hz;
+        self
+    }
+
+    /// Round the refined frequency to a whole hertz.
+    pub fn round_downsample_freq(&mut self, on: bool) -> &mut Self {
+        self.config.round_downsample_freq = on;
+        self
+    }
+
+    /// Drop decodes whose callsigns or grids fail a sanity check.
+    pub fn plausibility_filter(&mut self, on: bool) -> &mut Self {
+        self.config.plausibility_filter = on;
+        self
+    }
+
+    /// Half-width of the primary coarse time search, in spectrogram steps.
+    pub fn coarse_lag_steps(&mut self, steps: i32) -> &mut Self {
+        self.config.coarse_lag_steps = steps;
+        self
+    }
+
+    /// Merge coarse candidates closer than `hz` and `ms`.
+    pub fn candidate_merge(&mut self, hz: f32, ms: f32) -> &mut Self {
+        self.config.candidate_merge_hz = hz;
+        self.config.candidate_merge_ms = ms;
+        self
+    }
+
+    /// Search `hz` and `ms` either side of each candidate when refining it,
+    /// up to eight tone spacings and one symbol.
+    pub fn fine_search(&mut self, hz: f32, ms: f32) -> &mut Self {
+        self.config.fine_search_hz = hz;
+        self.config.fine_search_ms = ms;
+        self
+    }
+
+    /// Normalize coarse sync by this percentile of the band, from 0 to 1.
+    pub fn candidate_baseline_percentile(&mut self, percentile: f32) -> &mut Self {
+        self.config.candidate_baseline_percentile = percentile;
+        self
+    }
+
+    /// Worker threads for the per-candidate work.
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        self.config.threads = threads;
+        self
+    }
+
+    /// Decode-and-subtract passes run by [`decode_ft8`](super::decode_ft8).
+    pub fn max_passes(&mut self, passes: usize) -> &mut Self {
+        self.config.max_passes = passes;
+        self
+    }
+
+    /// How the reported SNR is estimated.
+    pub fn snr_method(&mut self, method: SnrMethod) -> &mut Self {
+        self.config.snr_method = method;
+        self
+    }
+
+    /// The config, or the first setting out of range.
+    pub fn build(&self) -> Result<DecoderConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_are_valid() {
+        assert_eq!(
+            DecoderConfig::builder().build(),
+            Ok(DecoderConfig::default())
+        );
+        let config = DecoderConfig::builder()
+            .freq_range(200.0, 2900.0)
+            .sync_min(1.3)
+            .depth(DecodeDepth::BpOnly)
+            .algo(DecodeAlgo::MinSum { scale: 0.8 })
+            .max_hard_errors(30)
+            .my_callsign("K1ABC")
+            .threads(2)
+            .max_passes(3)
+            .snr_method(SnrMethod::Average)
+            .build()
+            .unwrap();
+        assert_eq!(
+            config,
+            DecoderConfig {
+                freq_min: 200.0,
+                freq_max: 2900.0,
+                sync_min: 1.3,
+                decode_depth: DecodeDepth::BpOnly,
+                decode_algo: DecodeAlgo::MinSum { scale: 0.8 },
+                max_hard_errors: 30,
+                my_callsign: Some("K1ABC".into()),
+                threads: 2,
+                max_passes: 3,
+                snr_method: SnrMethod::Average,
+                ..DecoderConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_rejects_impossible_settings() {
+        for (min, max) in [
+            (2900.0, 200.0),
+            (1000.0, 1000.0),
+            (-10.0, 3000.0),
+            (200.0, 5990.0),
+        ] {
+            assert_eq!(
+                DecoderConfig::builder().freq_range(min, max).build(),
+                Err(ConfigError::FrequencyRange { min, max })
+            );
+        }
+        assert!(DecoderConfig::builder()
+            .freq_range(f32::NAN, 3000.0)
+            .build()
+            .is_err());
+        assert_eq!(
+            DecoderConfig::builder().sync_min(-1.0).build(),
+            Err(ConfigError::Negative {
+                setting: "sync_min",
+                value: -1.0
+            })
+        );
+        assert!(DecoderConfig::builder().sync_min(f32::NAN).build().is_err());
+        assert!(DecoderConfig::builder()
+            .coarse_lag_steps(-1)
+            .build()
+            .is_err());
+        assert!(DecoderConfig::builder()
+            .frequency_window(0.0)
+            .build()
+            .is_err());
+        assert_eq!(
+            DecoderConfig::builder().max_passes(0).build(),
+            Err(ConfigError::Zero {
+                setting: "max_passes"
+            })
+        );
+        assert_eq!(
+            DecoderConfig::builder()
+                .candidate_baseline_percentile(1.5)
+                .build(),
+            Err(ConfigError::Fraction {
+                setting: "candidate_baseline_percentile",
+                value: 1.5
+            })
+        );
+        assert!(DecoderConfig::builder()
+            .algo(DecodeAlgo::MinSum { scale: 0.0 })
+            .build()
+            .is_err());
+        assert_eq!(
+            DecoderConfig::builder().fine_search(60.0, 50.0).build(),
+            Err(ConfigError::TooLarge {
+                setting: "fine_search_hz",
+                value: 60.0,
+                max: 50.0
+            })
+        );
+        assert!(DecoderConfig::builder()
+            .fine_search(3.5, 200.0)
+            .build()
+            .is_err());
+        assert!(DecoderConfig::builder()
+            .fine_search(50.0, 160.0)
+            .build()
+            .is_ok());
+        let err = DecoderConfig::builder().threads(0).build().unwrap_err();
+        assert_eq!(err.to_string(), "threads must be greater than zero");
+    }
+}
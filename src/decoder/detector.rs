@@ -0,0 +1,50 @@
+//! Candidate detection as a replaceable stage of the decoder
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::DecoderConfig;
+use crate::sync::{coarse_sync, coarse_sync_spectra, Candidate, NHSYM};
+
+/// Finds the candidates a window is decoded from.
+///
+/// [`decode_ft8`](super::decode_ft8) uses [`CoarseSyncDetector`] unless
+/// given another implementation, so a different detection method can be
+/// tried with the rest of the pipeline unchanged. Candidates need only be
+/// roughly placed: each is fine-synced before decoding.
+pub trait CandidateDetector {
+    /// Candidates in `signal`, a validated 15 s window at 12 kHz.
+    fn detect(&self, signal: &[f32], config: &DecoderConfig) -> Result<Vec<Candidate>, String>;
+
+    /// [`detect`](Self::detect) on a multipass residual whose spectrogram,
+    /// as from [`compute_spectra`](crate::sync::compute_spectra), is kept
+    /// up to date between passes. The default ignores `spectra`.
+    fn detect_spectra(
+        &self,
+        signal: &[f32],
+        spectra: &[[f32; NHSYM]],
+        config: &DecoderConfig,
+    ) -> Result<Vec<Candidate>, String> {
+        let _ = spectra;
+        self.detect(signal, config)
+    }
+}
+
+/// Spectrogram Costas correlation ([`coarse_sync`]), as in WSJT-X.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CoarseSyncDetector;
+
+impl CandidateDetector for CoarseSyncDetector {
+    fn detect(&self, signal: &[f32], config: &DecoderConfig) -> Result<Vec<Candidate>, String> {
+        coarse_sync(signal, &config.candidate_search())
+    }
+
+    fn detect_spectra(
+        &self,
+        _: &[f32],
+        spectra: &[[f32; NHSYM]],
+        config: &DecoderConfig,
+    ) -> Result<Vec<Candidate>, String> {
+        coarse_sync_spectra(spectra, &config.candidate_search())
+    }
+}
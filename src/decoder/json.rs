@@ -0,0 +1,118 @@
+//! JSON output for external tools
+
+use super::WindowResult;
+
+/// One window as a single-line JSON object, for writing one line per slot.
+pub fn window_to_json(window: &WindowResult) -> String {
+    serde_json::to_string(window).expect("decode results always serialize")
+}
+
+/// Serde adapter for the channel tones, which are longer than the arrays
+/// serde handles natively.
+pub(super) mod tones {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::symbol::NUM_SYMBOLS;
+
+    pub fn serialize<S: Serializer>(tones: &[u8; NUM_SYMBOLS], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(tones)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; NUM_SYMBOLS], D::Error> {
+        let tones = Vec::<u8>::deserialize(d)?;
+        let len = tones.len();
+        tones
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &"79 tones"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{decode_ft8, DecodedMessage, DecoderConfig};
+    use crate::ldpc::DecodeDepth;
+    use crate::message::tx_cq;
+    use crate::modulation::BandSynthesizer;
+    use crate::sync::{Candidate, SyncMetric};
+
+    #[test]
+    fn test_window_round_trip() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1200.0, 0.1, 1.0)
+            .synthesize();
+        let window = WindowResult {
+            utc: 1_700_000_010,
+            decodes: decode_ft8(&signal, &DecoderConfig::default(), None).unwrap(),
+        };
+        assert_eq!(window.decodes.len(), 1);
+
+        let json = window_to_json(&window);
+        assert!(!json.contains('\n'));
+        assert!(json.contains(r#""message":"CQ K1ABC FN42""#), "{}", json);
+
+        let parsed: WindowResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, window);
+
+        // A wrong number of tones is rejected
+        let decode = serde_json::to_string(&window.decodes[0]).unwrap();
+        let short = decode.replace(r#""tones":[3,1,4,"#, r#""tones":["#);
+        assert_ne!(short, decode);
+        assert!(serde_json::from_str::<DecodedMessage>(&short).is_err());
+    }
+
+    #[test]
+    fn test_decode_candidate_and_config_round_trip() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1500.0, -0.3, 1.0)
+            .synthesize();
+        let decode = decode_ft8(&signal, &DecoderConfig::default(), None)
+            .unwrap()
+            .remove(0);
+        let json = serde_json::to_string(&decode).unwrap();
+        for field in ["frequency", "time_offset", "snr_db", "sync_power"] {
+            // Floats are plain numbers, not strings
+            let value = json.split(&format!(r#""{}":"#, field)).nth(1).unwrap();
+            assert!(value.starts_with(|c: char| c == '-' || c.is_ascii_digit()));
+        }
+        assert_eq!(
+            serde_json::from_str::<DecodedMessage>(&json).unwrap(),
+            decode
+        );
+
+        let candidate = Candidate {
+            frequency: 1234.5,
+            time_offset: -0.25,
+            sync_power: 7.5,
+            sync_metric: SyncMetric::CostasBc,
+        };
+        let json = serde_json::to_string(&candidate).unwrap();
+        assert_eq!(
+            json,
+            r#"{"frequency":1234.5,"time_offset":-0.25,"sync_power":7.5,"sync_metric":"CostasBc"}"#
+        );
+        assert_eq!(serde_json::from_str::<Candidate>(&json).unwrap(), candidate);
+
+        let config = DecoderConfig {
+            decode_depth: DecodeDepth::BpOnly,
+            my_callsign: Some("K1ABC".into()),
+            threads: 4,
+            ..DecoderConfig::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            serde_json::from_str::<DecoderConfig>(&json).unwrap(),
+            config
+        );
+        // Missing fields take their defaults
+        let partial: DecoderConfig = serde_json::from_str(r#"{"threads":4}"#).unwrap();
+        assert_eq!(
+            partial,
+            DecoderConfig {
+                threads: 4,
+                ..DecoderConfig::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,2269 @@
+//! Complete FT8 receiver: audio window → decoded messages
+//!
+//! [`decode_ft8`] runs the [`sync`](crate::sync) chain on every coarse
+//! candidate, tries each of the four LLR variants with the LDPC decoder and
+//! unpacks the most reliable payload that passes the CRC. Candidates are
+//! tried best matched-filter score first, and of several yielding the same
+//! message text within 5 Hz only the most confident is kept. Decodes are
+//! returned sorted by frequency, then DT, so the result does not depend on
+//! [`DecoderConfig::threads`]. The detection stage is a [`CandidateDetector`],
+//! which the caller can replace.
+//!
+//! An attended station usually cares most about who is calling it;
+//! [`DecodedMessage::is_to`] and [`decode_ft8_directed`] separate those
+//! decodes from the rest of the band activity. [`decode_resampled`] takes
+//! audio at other sample rates, and [`decode_banded`] splits a capture
+//! wider than the usual 3 kHz into separately searched bands.
+//! [`decode_ft8_diagnostic`] also reports the candidates that did not
+//! decode and the stage that rejected each. With the `std` feature,
+//! `decode_wav_file` decodes a saved 15 s recording, and `spot_recording`
+//! turns a long multi-slot recording into timestamped reception reports.
+//! Everything else builds without `std`.
+//!
+//! With the `serde` feature, decodes, candidates and [`DecoderConfig`]
+//! serialize under their Rust field names, and `window_to_json` renders a
+//! [`WindowResult`] as one JSON object for external tools.
+
+mod adif;
+mod builder;
+mod detector;
+#[cfg(feature = "serde")]
+mod json;
+mod plausibility;
+#[cfg(feature = "std")]
+mod slot;
+#[cfg(feature = "std")]
+mod spot;
+#[cfg(feature = "std")]
+mod wav;
+mod wsjtx;
+
+pub use adif::adif_record;
+pub use builder::{ConfigError, DecoderConfigBuilder};
+pub use detector::{CandidateDetector, CoarseSyncDetector};
+#[cfg(feature = "serde")]
+pub use json::window_to_json;
+#[cfg(feature = "std")]
+pub use slot::{samples_for_slot, slot_start, SlotFrame};
+#[cfg(feature = "std")]
+pub use spot::{spot_recording, spot_samples, Spot};
+#[cfg(feature = "std")]
+pub use wav::decode_wav_file;
+pub use wsjtx::format_wsjtx;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+use core::fmt;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::ldpc::{self, DecodeAlgo, DecodeDepth};
+use crate::message::{self, CallsignHashCache, MessageVariant};
+use crate::modulation::{SLOT_DURATION, TONE_SPACING};
+use crate::subtract::subtract_ft8_signal;
+use crate::symbol::{channel_symbols_from_bits, NUM_SYMBOLS};
+use crate::sync::{
+    calculate_snr, calculate_snr_baseline, coarse_sync_spectra, compute_spectra,
+    compute_spectra_region, extract_symbols_all_llr_with, fine_sync_search, fine_sync_with,
+    locate_candidate, locate_candidate_near, matched_filter_score, noise_baseline, to_12khz,
+    wsjtx_off_tone_snr, Candidate, CandidateSearch, Downsampler, FineSearch, SymbolLlrs,
+    SyncMetric, NHSYM, NMAX,
+};
+
+/// Minimum number of the 21 Costas symbols that must be received
+/// correctly before a candidate is passed to the LDPC decoder
+const MIN_NSYNC: usize = 7;
+
+/// Minimum Costas count for the phase-drift frequency estimate to be
+/// trusted; below it the drift is mostly noise
+const PHASE_REFINE_NSYNC: usize = 12;
+
+/// Range of phase-drift frequency corrections acted on, in Hz. Smaller
+/// ones change nothing measurable; larger ones point to a drift estimate
+/// that has wrapped, as fine sync leaves at most a few tenths of a hertz
+const PHASE_REFINE_MIN_HZ: f32 = 0.01;
+const PHASE_REFINE_MAX_HZ: f32 = 1.0;
+
+/// Default for [`DecoderConfig::max_hard_errors`], the limit WSJT-X
+/// applies
+const MAX_HARD_ERRORS: usize = 36;
+
+/// As in WSJT-X's `ft8b`, a decode with at most this many Costas symbols
+/// in sync and an off-tone SNR, on WSJT-X's scale and before the reporting
+/// floor, below [`FALSE_DECODE_SNR_DB`] is dropped as a likely false
+/// decode. The SNR method chosen for reports does not affect it
+const FALSE_DECODE_NSYNC: usize = 10;
+const FALSE_DECODE_SNR_DB: f32 = -24.0;
+
+/// Samples within this fraction of the peak count as being at the peak
+const CLIP_LEVEL: f32 = 0.9999;
+
+/// Largest fraction of samples in flat runs at the peak level before the
+/// input is treated as clipped
+const MAX_CLIPPED_FRACTION: f32 = 0.005;
+
+/// Spectrogram rows recomputed either side of a subtracted signal, in Hz
+const SUBTRACT_MARGIN_HZ: f32 = 2.0 * TONE_SPACING;
+
+/// Total width of spectrogram rows, in Hz, past which recomputing the
+/// whole spectrogram between passes is cheaper than recomputing just those
+/// rows; the rows of one subtracted signal span 69 Hz
+/// (`cargo bench --bench spectra`)
+const MAX_REGION_HZ: f32 = 120.0;
+
+/// Decodes of the same text closer than this in frequency (Hz) are one
+/// signal found by several candidates
+const DUPLICATE_HZ: f32 = 5.0;
+
+/// Each band of [`decode_banded`] is widened by this much either side
+/// (Hz), one signal's bandwidth, so a signal on a boundary is searched
+/// in both neighbours
+const BAND_OVERLAP_HZ: f32 = 8.0 * TONE_SPACING;
+
+/// Search around the position given to [`decode_at`]: two tone spacings
+/// either side in frequency
+const DECODE_AT_WINDOW_HZ: f32 = 2.0 * TONE_SPACING;
+
+/// Search around the position given to [`decode_at`] in DT, in seconds
+const DECODE_AT_WINDOW_S: f32 = 0.25;
+
+/// Decoder settings.
+///
+/// With the `serde` feature, fields missing from a serialized config take
+/// their default values.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DecoderConfig {
+    /// Lowest tone-0 frequency searched, in Hz
+    pub freq_min: f32,
+    /// Highest tone-0 frequency searched, in Hz
+    pub freq_max: f32,
+    /// Minimum normalized coarse sync for a candidate
+    pub sync_min: f32,
+    /// Maximum number of candidates decoded per window
+    pub max_candidates: usize,
+    /// LDPC effort per candidate
+    pub decode_depth: DecodeDepth,
+    /// Check-node update of the belief propagation. Sum-product by
+    /// default; min-sum is faster for a small loss of weak decodes
+    pub decode_algo: DecodeAlgo,
+    /// Decodes with more received hard decisions than this disagreeing
+    /// with the decoded codeword are treated as false and dropped.
+    ///
+    /// Genuine decodes rarely come near the default of 36, while OSD run
+    /// on noise mostly finds codewords far from the received bits; lowering
+    /// the limit trades weak decodes for fewer false ones. The few noise
+    /// codewords within the limit are caught by their weak Costas sync and
+    /// SNR ([`FailureStage::LikelyFalse`]).
+    pub max_hard_errors: usize,
+    /// The operator's callsign. It is seeded into the hash cache so hashed
+    /// references to it resolve, and selects the decodes returned as
+    /// directed by [`decode_ft8_directed`].
+    pub my_callsign: Option<String>,
+    /// Half-width in Hz of the search around each frequency given to
+    /// [`decode_ft8_at_frequencies`]
+    pub frequency_window: f32,
+    /// Round the refined frequency to a whole hertz before extracting
+    /// symbols.
+    ///
+    /// Off by default: the downsampler centres on fractional frequencies
+    /// to within 0.05 Hz, and rounding adds up to 0.5 Hz of error that
+    /// spreads each tone into its neighbours. Only useful to reproduce the
+    /// behaviour of decoders that work on whole-hertz frequencies.
+    pub round_downsample_freq: bool,
+    /// Drop decodes whose callsigns or grids fail a sanity check.
+    ///
+    /// A last line of defence against false decodes that pass the CRC and
+    /// hard-error limit. Off by default because it also rejects genuine
+    /// oddities such as a special-event call sent in a standard message.
+    pub plausibility_filter: bool,
+    /// Half-width of the primary coarse time search, in quarter-symbol
+    /// spectrogram steps (40 ms).
+    ///
+    /// Peaks over the full ±2.48 s range are still considered, but compete
+    /// against a noisier baseline; widen this for recordings whose clock is
+    /// known to be off by more than the default ±0.4 s.
+    pub coarse_lag_steps: i32,
+    /// Coarse candidates closer than this in frequency (Hz) and in DT
+    /// ([`candidate_merge_ms`](Self::candidate_merge_ms)) are merged
+    /// before decoding, so one strong signal seen in adjacent bins is
+    /// decoded once
+    pub candidate_merge_hz: f32,
+    /// DT radius of the candidate merge, in milliseconds
+    pub candidate_merge_ms: f32,
+    /// Percentile of the band's coarse sync peaks, as a fraction from 0
+    /// to 1, that sync is normalized by before comparing with
+    /// [`sync_min`](Self::sync_min).
+    ///
+    /// The default 0.4 treats the quieter 40% of the band as noise; on a
+    /// quiet band a lower value lifts weak signals over the threshold.
+    pub candidate_baseline_percentile: f32,
+    /// Half-width in Hz of the fine frequency search around each
+    /// candidate, at most 50 Hz (eight tone spacings). The default 3.5 Hz
+    /// covers one coarse frequency bin
+    pub fine_search_hz: f32,
+    /// Half-width in milliseconds of the fine time search around each
+    /// candidate, at most 160 ms (one symbol). The default 50 ms covers
+    /// one coarse time step
+    pub fine_search_ms: f32,
+    /// Worker threads for the per-candidate work.
+    ///
+    /// With the `rayon` feature and more than one thread, candidates are
+    /// synchronized, demodulated and LDPC-decoded in parallel; otherwise
+    /// this is ignored. The subtraction in [`decode_ft8_multipass`] stays
+    /// serial. Results are identical for any thread count.
+    pub threads: usize,
+    /// Decode-and-subtract passes run by [`decode_ft8`]; more than one
+    /// makes it [`decode_ft8_multipass`] with this many passes
+    pub max_passes: usize,
+    /// How [`DecodedMessage::snr_db`] is estimated
+    pub snr_method: SnrMethod,
+}
+
+/// Noise reference for the reported SNR.
+///
+/// Both estimates compare the power on the transmitted tones with noise
+/// in the same bandwidth; they differ in where the noise is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SnrMethod {
+    /// The power on an untransmitted tone of each symbol
+    /// ([`calculate_snr`]). Reads high when another signal overlaps the
+    /// off tones.
+    #[default]
+    OffTone,
+    /// The noise floor of the spectrogram around the signal
+    /// ([`calculate_snr_baseline`]), as WSJT-X reports on its first pass
+    Baseline,
+    /// The mean of the two, in dB
+    Average,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        let search = CandidateSearch::default();
+        let fine = FineSearch::default();
+        DecoderConfig {
+            freq_min: search.freq_min,
+            freq_max: search.freq_max,
+            sync_min: search.sync_min,
+            max_candidates: search.max_candidates,
+            decode_depth: DecodeDepth::default(),
+            decode_algo: DecodeAlgo::default(),
+            max_hard_errors: MAX_HARD_ERRORS,
+            my_callsign: None,
+            frequency_window: 5.0,
+            round_downsample_freq: false,
+            plausibility_filter: false,
+            coarse_lag_steps: search.coarse_lag,
+            candidate_merge_hz: search.merge_hz,
+            candidate_merge_ms: search.merge_time * 1000.0,
+            candidate_baseline_percentile: search.baseline_percentile,
+            fine_search_hz: fine.freq_hz,
+            fine_search_ms: fine.time * 1000.0,
+            threads: 1,
+            max_passes: 1,
+            snr_method: SnrMethod::default(),
+        }
+    }
+}
+
+impl DecoderConfig {
+    /// The coarse-search part of the configuration.
+    fn candidate_search(&self) -> CandidateSearch {
+        CandidateSearch {
+            freq_min: self.freq_min,
+            freq_max: self.freq_max,
+            sync_min: self.sync_min,
+            max_candidates: self.max_candidates,
+            coarse_lag: self.coarse_lag_steps,
+            merge_hz: self.candidate_merge_hz,
+            merge_time: self.candidate_merge_ms / 1000.0,
+            baseline_percentile: self.candidate_baseline_percentile,
+        }
+    }
+
+    /// The fine-search part of the configuration.
+    fn fine_search(&self) -> FineSearch {
+        FineSearch {
+            freq_hz: self.fine_search_hz,
+            time: self.fine_search_ms / 1000.0,
+        }
+    }
+}
+
+/// A successfully decoded FT8 transmission.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodedMessage {
+    /// Message text as displayed by WSJT-X, with hashed callsigns shown
+    /// as `<CALL>` when the hash cache knows them
+    pub message: String,
+    /// Message text with every hashed callsign shown as `<...>`.
+    ///
+    /// Unlike [`message`](Self::message) this doesn't depend on which
+    /// callsigns the cache has seen, so it is the same for the same
+    /// transmission in every session and suits logs and deduplication.
+    pub canonical_message: String,
+    /// Message type `i3`, the last three of the 77 message bits
+    pub message_type: u8,
+    /// Subtype `n3` of `i3 = 0` messages, which tells free text (0),
+    /// DXpedition (1), Field Day (3, 4) and telemetry (5) apart; `None`
+    /// for other types
+    pub message_subtype: Option<u8>,
+    /// Frequency of tone 0 in Hz
+    pub frequency: f32,
+    /// DT in seconds relative to the nominal 0.5 s start
+    pub time_offset: f32,
+    /// SNR in dB in a 2500 Hz reference bandwidth
+    pub snr_db: f32,
+    /// Coarse sync strength of the candidate
+    pub sync_power: f32,
+    /// Which Costas arrays the candidate was found on; [`SyncMetric::CostasBc`]
+    /// marks a signal that started before the window
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sync_metric: SyncMetric,
+    /// BP iterations used, or 0 if OSD produced the decode
+    pub ldpc_iterations: usize,
+    /// Received hard decisions that disagree with the decoded codeword
+    pub nharderrors: usize,
+    /// How sure the decoder is that the decode is genuine, from 0 to 1.
+    ///
+    /// Combines the hard-error count, how quickly BP converged (OSD
+    /// decodes count as least sure) and how many Costas symbols were
+    /// received correctly; see [`decode_confidence`]. Thresholding it
+    /// filters out the marginal OSD decodes most likely to be false.
+    pub confidence: f32,
+    /// The soft-decision metric that produced the decode
+    pub llr_method: LlrMethod,
+    variant: MessageVariant,
+    #[cfg_attr(feature = "serde", serde(with = "json::tones"))]
+    tones: [u8; NUM_SYMBOLS],
+}
+
+/// Which of the four soft-decision metrics of
+/// [`SymbolLlrs`] a decode came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LlrMethod {
+    /// Single-symbol metrics
+    Nsym1,
+    /// Pairs of symbols combined coherently
+    Nsym2,
+    /// Triples of symbols combined coherently
+    Nsym3,
+    /// Single-symbol metrics normalized by the strongest tone
+    Normalized,
+}
+
+impl DecodedMessage {
+    /// Whether `mycall` appears in one of the message's callsign fields.
+    ///
+    /// Calls are compared on their base callsign, so `/P` and `/R`
+    /// suffixes and compound prefixes such as `KH6/` on either side are
+    /// ignored. Hashed references match when they resolved to `mycall`,
+    /// which [`decode_ft8`] arranges when `mycall` is the configured
+    /// [`DecoderConfig::my_callsign`].
+    pub fn is_to(&self, mycall: &str) -> bool {
+        let mine = base_call(mycall);
+        !mine.is_empty() && callsigns(&self.variant).any(|c| base_call(c) == mine)
+    }
+}
+
+/// Decodes split by whether they involve the operator's callsign.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DirectedDecodes {
+    /// Decodes with the operator's callsign in a callsign field
+    pub directed_to_me: Vec<DecodedMessage>,
+    /// Everything else
+    pub other: Vec<DecodedMessage>,
+}
+
+impl DirectedDecodes {
+    /// Partition `decodes` using [`DecodedMessage::is_to`].
+    pub fn partition(decodes: Vec<DecodedMessage>, mycall: &str) -> Self {
+        let (directed_to_me, other) = decodes.into_iter().partition(|d| d.is_to(mycall));
+        DirectedDecodes {
+            directed_to_me,
+            other,
+        }
+    }
+}
+
+/// Everything decoded in one 15 s window.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowResult {
+    /// Start of the slot in seconds since the Unix epoch (UTC)
+    pub utc: u64,
+    /// Decodes in the order the decoder returned them
+    pub decodes: Vec<DecodedMessage>,
+}
+
+/// Soft information for one candidate, as the LDPC decoder would see it.
+#[derive(Debug, Clone)]
+pub struct CandidateLlrs {
+    /// The candidate after fine time/frequency alignment
+    pub candidate: Candidate,
+    /// Per-symbol non-coherent LLRs (nsym = 1)
+    pub llra: Vec<f32>,
+    /// LLRs from coherently combined symbol pairs (nsym = 2)
+    pub llrb: Vec<f32>,
+    /// LLRs from coherently combined symbol triples (nsym = 3)
+    pub llrc: Vec<f32>,
+    /// nsym = 1 LLRs normalized by the strongest tone
+    pub llrd: Vec<f32>,
+    /// Tone amplitudes, `s8[symbol][tone]`
+    pub s8: Vec<[f32; 8]>,
+    /// Costas symbols whose strongest tone is the expected one (of 21)
+    pub nsync: usize,
+}
+
+/// Result of [`decode_ft8_multipass_report`].
+#[derive(Debug, Clone)]
+pub struct MultipassResult {
+    /// Every decode, sorted by frequency then DT
+    pub decodes: Vec<DecodedMessage>,
+    /// Passes run, including a final one that found nothing new
+    pub passes: usize,
+    /// New decodes found in each pass
+    pub new_per_pass: Vec<usize>,
+}
+
+/// Result of [`decode_ft8_diagnostic`].
+#[derive(Debug, Clone)]
+pub struct DiagnosticResult {
+    /// Every decode of a single pass, sorted by frequency then DT;
+    /// [`DecoderConfig::max_passes`] is ignored
+    pub decodes: Vec<DecodedMessage>,
+    /// Candidates that produced no decode, sorted by frequency then DT
+    pub failed: Vec<FailedCandidate>,
+}
+
+/// A candidate that did not decode, and why.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FailedCandidate {
+    /// The candidate after fine time/frequency alignment
+    pub candidate: Candidate,
+    /// Costas symbols whose strongest tone is the expected one (of 21)
+    pub nsync: usize,
+    /// The step that rejected it
+    pub stage: FailureStage,
+}
+
+/// Where a candidate dropped out of the decoder, in pipeline order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FailureStage {
+    /// Too few Costas symbols received correctly to try the LDPC decoder
+    Costas,
+    /// No LLR variant decoded to a codeword with a matching CRC within
+    /// [`DecoderConfig::max_hard_errors`]
+    Ldpc,
+    /// As `Ldpc`, but the hard decisions of some LLR variant already form
+    /// a codeword, whose CRC does not match: most likely a message sent
+    /// by a different mode or a corrupted copy
+    Crc,
+    /// Decoded, but too weak and too poorly synced to be believed: most
+    /// likely a codeword found in noise
+    LikelyFalse,
+    /// The payload is not a valid message
+    Unpack,
+    /// Dropped by [`DecoderConfig::plausibility_filter`]
+    Implausible,
+}
+
+/// The callsign fields of a message; CQ, QRZ and DE tokens are skipped.
+fn callsigns(variant: &MessageVariant) -> impl Iterator<Item = &str> {
+    let calls: [Option<&String>; 3] = match variant {
+        MessageVariant::FreeText { .. } | MessageVariant::Telemetry { .. } => [None; 3],
+        MessageVariant::Dxpedition {
+            call1,
+            call2,
+            call3,
+            ..
+        } => [Some(call1), Some(call2), Some(call3)],
+        MessageVariant::FieldDay { call1, call2, .. }
+        | MessageVariant::Standard { call1, call2, .. }
+        | MessageVariant::EuVhfContestType2 { call1, call2, .. }
+        | MessageVariant::RttyRoundup { call1, call2, .. }
+        | MessageVariant::EuVhfContestType5 { call1, call2, .. } => {
+            [Some(call1), Some(call2), None]
+        }
+        MessageVariant::NonStandardCall {
+            hashed_call,
+            full_call,
+            ..
+        } => [Some(hashed_call), Some(full_call), None],
+    };
+    calls
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .filter(|c| !c.starts_with("CQ") && *c != "QRZ" && *c != "DE")
+}
+
+/// Base callsign for comparison: brackets removed, uppercased, and of a
+/// compound call only the longest `/`-separated part kept.
+fn base_call(callsign: &str) -> String {
+    let call = callsign
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>');
+    call.split('/')
+        .fold(
+            "",
+            |best, part| if part.len() > best.len() { part } else { best },
+        )
+        .to_uppercase()
+}
+
+/// A receive buffer that cannot be decoded as given.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputError {
+    /// The buffer is not 15 s at 12 kHz
+    WrongLength { got: usize, expected: usize },
+    /// Every sample is zero
+    AllZero,
+    /// A large fraction of samples sit flat at the peak level
+    Clipped { fraction: f32 },
+    /// The buffer contains NaN or infinite samples
+    NonFinite,
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            InputError::WrongLength { got, expected } => {
+                write!(
+                    f,
+                    "Expected {} samples (15 s at 12 kHz), got {}",
+                    expected, got
+                )?;
+                // A whole-window buffer at another common rate
+                let rate = (got as f32 / SLOT_DURATION).round();
+                if got > expected && (rate / 1000.0).fract() == 0.0 {
+                    write!(f, "; this looks like {} Hz audio, resample to 12 kHz", rate)?;
+                }
+                Ok(())
+            }
+            InputError::AllZero => write!(f, "Signal is all zeros; check the audio source"),
+            InputError::Clipped { fraction } => write!(
+                f,
+                "Signal is clipped: {:.1}% of samples at peak level; reduce the input gain",
+                fraction * 100.0
+            ),
+            InputError::NonFinite => write!(f, "Signal contains NaN or infinite samples"),
+        }
+    }
+}
+
+/// Failure to decode a window with [`decode_ft8`] or the other entry
+/// points, a recording with `decode_wav_file`, or a tone array with
+/// [`decode_symbols`](crate::symbol::decode_symbols).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// The file cannot be opened or read as WAV
+    Io(String),
+    /// The WAV header describes audio that cannot be converted
+    UnsupportedFormat(String),
+    /// The audio fails [`validate_input`]
+    Input(InputError),
+    /// The decoder itself failed
+    Decode(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Io(msg)
+            | DecodeError::UnsupportedFormat(msg)
+            | DecodeError::Decode(msg) => {
+                write!(f, "{}", msg)
+            }
+            DecodeError::Input(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+impl From<InputError> for DecodeError {
+    fn from(e: InputError) -> Self {
+        DecodeError::Input(e)
+    }
+}
+
+impl From<String> for DecodeError {
+    fn from(msg: String) -> Self {
+        DecodeError::Decode(msg)
+    }
+}
+
+/// Check that `signal` is a usable 15 s, 12 kHz receive buffer.
+///
+/// [`decode_ft8`] and the other entry points run this first, so problems
+/// with the audio path show up as a specific error rather than failing
+/// deep inside the signal processing.
+pub fn validate_input(signal: &[f32]) -> Result<(), InputError> {
+    if signal.len() != NMAX {
+        return Err(InputError::WrongLength {
+            got: signal.len(),
+            expected: NMAX,
+        });
+    }
+    if signal.iter().any(|x| !x.is_finite()) {
+        return Err(InputError::NonFinite);
+    }
+
+    let peak = signal.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+    if peak == 0.0 {
+        return Err(InputError::AllZero);
+    }
+    // Clipping flattens the waveform, leaving runs of samples at the
+    // peak; an unclipped waveform only touches it briefly
+    let at_peak = signal
+        .windows(2)
+        .filter(|w| w.iter().all(|x| x.abs() >= peak * CLIP_LEVEL))
+        .count();
+    let fraction = at_peak as f32 / signal.len() as f32;
+    if fraction > MAX_CLIPPED_FRACTION {
+        return Err(InputError::Clipped { fraction });
+    }
+    Ok(())
+}
+
+/// Fine-sync `candidate` and extract its LLRs without decoding.
+///
+/// Runs the same steps as [`decode_ft8`] up to the LDPC decoder, whether
+/// or not the candidate would decode, so the soft information can be
+/// studied or compared with other decoders.
+pub fn candidate_llrs(signal: &[f32], candidate: &Candidate) -> Result<CandidateLlrs, String> {
+    let downsampler = Downsampler::new(signal)?;
+    let mut refined = fine_sync_with(&downsampler, candidate)?;
+    let SymbolLlrs {
+        llra,
+        llrb,
+        llrc,
+        llrd,
+        s8,
+        nsync,
+        ..
+    } = extract_phase_refined(&downsampler, &mut refined)?;
+    Ok(CandidateLlrs {
+        candidate: refined,
+        llra,
+        llrb,
+        llrc,
+        llrd,
+        s8,
+        nsync,
+    })
+}
+
+/// Decode every FT8 signal in a 15 s, 12 kHz window.
+///
+/// Candidates come from `detector`, or from the [`CoarseSyncDetector`]
+/// when it is `None`. Runs [`DecoderConfig::max_passes`] passes,
+/// subtracting decoded signals and asking the detector again between
+/// them when there is more than one.
+pub fn decode_ft8(
+    signal: &[f32],
+    config: &DecoderConfig,
+    detector: Option<&dyn CandidateDetector>,
+) -> Result<Vec<DecodedMessage>, DecodeError> {
+    let detector = detector.unwrap_or(&CoarseSyncDetector);
+    if config.max_passes > 1 {
+        return multipass(signal, config, config.max_passes, detector).map(|r| r.decodes);
+    }
+    validate_input(signal)?;
+    let cache = seeded_cache(config);
+    let candidates = detector.detect(signal, config)?;
+
+    let mut decodes = Vec::new();
+    decode_candidates(signal, &candidates, config, &cache, &mut decodes)?;
+    sort_decodes(&mut decodes);
+    Ok(decodes)
+}
+
+/// [`decode_ft8`] on a window of audio at another sample rate, such as
+/// the 48 or 24 kHz a sound card delivers.
+///
+/// The samples are resampled to 12 kHz with [`to_12khz`], cut or
+/// zero-padded to 15 s and checked with [`validate_input`], as
+/// `decode_wav_file` does with a file.
+pub fn decode_resampled(
+    samples: &[f32],
+    sample_rate: u32,
+    config: &DecoderConfig,
+) -> Result<Vec<DecodedMessage>, DecodeError> {
+    if sample_rate == 0 {
+        return Err(DecodeError::UnsupportedFormat(
+            "Sample rate must be positive".into(),
+        ));
+    }
+    decode_converted(to_12khz(samples, sample_rate), config)
+}
+
+/// Decode 12 kHz audio of about one window, padding or cutting it to
+/// exactly 15 s first.
+fn decode_converted(
+    mut signal: Vec<f32>,
+    config: &DecoderConfig,
+) -> Result<Vec<DecodedMessage>, DecodeError> {
+    signal.resize(NMAX, 0.0);
+    validate_input(&signal).map_err(DecodeError::Input)?;
+    decode_ft8(&signal, config, None)
+}
+
+/// [`decode_ft8`], also returning the candidates that failed to decode.
+///
+/// For telling an empty part of the band from a signal that is present
+/// but cannot be copied: each failed candidate carries its Costas count
+/// and the [`FailureStage`] that rejected it. Failed candidates within
+/// a signal's 50 Hz bandwidth of a decode are left out: nearly all are
+/// the decoded signal seen at a neighbouring tone or time. Always a
+/// single pass, whatever [`DecoderConfig::max_passes`] is.
+pub fn decode_ft8_diagnostic(
+    signal: &[f32],
+    config: &DecoderConfig,
+) -> Result<DiagnosticResult, DecodeError> {
+    validate_input(signal)?;
+    let cache = seeded_cache(config);
+    let candidates = CoarseSyncDetector.detect(signal, config)?;
+
+    let mut decodes = Vec::new();
+    let mut failed = decode_candidates(signal, &candidates, config, &cache, &mut decodes)?;
+    failed.retain(|f| {
+        !decodes
+            .iter()
+            .any(|d| (d.frequency - f.candidate.frequency).abs() < 8.0 * TONE_SPACING)
+    });
+    sort_decodes(&mut decodes);
+    failed.sort_by(|a, b| {
+        a.candidate
+            .frequency
+            .total_cmp(&b.candidate.frequency)
+            .then(a.candidate.time_offset.total_cmp(&b.candidate.time_offset))
+    });
+    Ok(DiagnosticResult { decodes, failed })
+}
+
+/// Decode only signals with tone 0 near the given frequencies.
+///
+/// Coarse sync is skipped: each frequency is searched within
+/// ±[`DecoderConfig::frequency_window`] and over the full DT range, which
+/// is much cheaper than a band-wide search when monitoring a few known
+/// frequencies.
+pub fn decode_ft8_at_frequencies(
+    signal: &[f32],
+    frequencies: &[f32],
+    config: &DecoderConfig,
+) -> Result<Vec<DecodedMessage>, DecodeError> {
+    validate_input(signal)?;
+    let cache = seeded_cache(config);
+    let candidates = frequencies
+        .iter()
+        .map(|&f| locate_candidate(signal, f, config.frequency_window))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut decodes = Vec::new();
+    decode_candidates(signal, &candidates, config, &cache, &mut decodes)?;
+    sort_decodes(&mut decodes);
+    Ok(decodes)
+}
+
+/// Decode a wide capture as separate frequency bands.
+///
+/// Each `(low, high)` band of tone-0 frequencies, in Hz, gets its own
+/// coarse sync over a shared spectrogram: its own sync baseline and up
+/// to [`DecoderConfig::max_candidates`] candidates, so a crowded band
+/// does not crowd out a quiet one and the candidate count grows with
+/// the span covered. Bands are widened by 50 Hz either side so a signal
+/// on a boundary is still found, and its decodes from both neighbours
+/// are merged like those of any two candidates. The frequency range of
+/// `config` is not used. Single pass; candidates from all bands share
+/// the [`DecoderConfig::threads`] workers.
+pub fn decode_banded(
+    signal: &[f32],
+    bands: &[(f32, f32)],
+    config: &DecoderConfig,
+) -> Result<Vec<DecodedMessage>, DecodeError> {
+    validate_input(signal)?;
+    let cache = seeded_cache(config);
+    let spectra = compute_spectra(signal)?;
+    let mut candidates = Vec::new();
+    for &(low, high) in bands {
+        let search = CandidateSearch {
+            freq_min: (low - BAND_OVERLAP_HZ).max(0.0),
+            freq_max: high + BAND_OVERLAP_HZ,
+            ..config.candidate_search()
+        };
+        candidates.extend(coarse_sync_spectra(&spectra, &search)?);
+    }
+
+    let mut decodes = Vec::new();
+    decode_candidates(signal, &candidates, config, &cache, &mut decodes)?;
+    sort_decodes(&mut decodes);
+    Ok(decodes)
+}
+
+/// Decode the one signal at a roughly known position, as picked by
+/// clicking on a waterfall.
+///
+/// Coarse sync is skipped: tone 0 is searched within ±12.5 Hz of `freq_hz`
+/// and DT within ±0.25 s of `dt_s`, and the best point refined with
+/// [`fine_sync`](crate::sync::fine_sync) before demodulating. `None` if nothing there decodes,
+/// including when too few Costas symbols line up or the input fails
+/// [`validate_input`].
+pub fn decode_at(
+    signal: &[f32],
+    freq_hz: f32,
+    dt_s: f32,
+    config: &DecoderConfig,
+) -> Option<DecodedMessage> {
+    validate_input(signal).ok()?;
+    let cache = seeded_cache(config);
+    let candidate = locate_candidate_near(
+        signal,
+        freq_hz,
+        dt_s,
+        DECODE_AT_WINDOW_HZ,
+        DECODE_AT_WINDOW_S,
+    )
+    .ok()?;
+    let downsampler = Downsampler::new(signal).ok()?;
+    decode_refined(signal, &downsampler, candidate, config, &cache).ok()?
+}
+
+/// Decode, subtract the decoded signals and search the residual again,
+/// for up to `passes` passes.
+///
+/// Signals hidden under stronger ones become decodable once those are
+/// removed. See [`decode_ft8_multipass_report`] for the per-pass counts.
+pub fn decode_ft8_multipass(
+    signal: &[f32],
+    config: &DecoderConfig,
+    passes: usize,
+) -> Result<Vec<DecodedMessage>, DecodeError> {
+    decode_ft8_multipass_report(signal, config, passes).map(|r| r.decodes)
+}
+
+/// [`decode_ft8_multipass`], also reporting how many passes ran and what
+/// each one added.
+///
+/// Within a pass candidates are tried best matched-filter score first,
+/// and each decode is subtracted before the next candidate is tried, so
+/// weaker signals are demodulated from a band already cleared of the
+/// stronger ones. That makes the decoding itself serial; only the fine
+/// sync and ranking of each pass's candidates use
+/// [`DecoderConfig::threads`]. Between passes the candidates are searched
+/// again; when a pass subtracted a single signal, or a few close together,
+/// just the spectrogram rows around them are recomputed.
+///
+/// Only messages that pass the CRC are subtracted, and a signal already
+/// reported is neither reported nor subtracted again. The loop stops
+/// after `passes` passes, or earlier after a pass with no new decodes.
+pub fn decode_ft8_multipass_report(
+    signal: &[f32],
+    config: &DecoderConfig,
+    passes: usize,
+) -> Result<MultipassResult, DecodeError> {
+    multipass(signal, config, passes, &CoarseSyncDetector)
+}
+
+/// [`decode_ft8_multipass_report`] with candidates from `detector`.
+fn multipass(
+    signal: &[f32],
+    config: &DecoderConfig,
+    passes: usize,
+    detector: &dyn CandidateDetector,
+) -> Result<MultipassResult, DecodeError> {
+    validate_input(signal)?;
+    let cache = seeded_cache(config);
+    let mut residual = signal.to_vec();
+    let mut spectra = compute_spectra(&residual)?;
+    let mut result = MultipassResult {
+        decodes: Vec::new(),
+        passes: 0,
+        new_per_pass: Vec::new(),
+    };
+
+    let mut stale = Vec::new();
+    while result.passes < passes {
+        refresh_spectra(&residual, &mut stale, &mut spectra)?;
+        let candidates = detector.detect_spectra(&residual, &spectra, config)?;
+        let mut found = 0;
+        let mut downsampler = Downsampler::new(&residual)?;
+        for refined in refine_and_rank(&downsampler, &candidates, config)? {
+            let Some(d) = decode_refined(&residual, &downsampler, refined, config, &cache)? else {
+                continue;
+            };
+            if result.decodes.iter().any(|e| same_signal(e, &d)) {
+                continue;
+            }
+            subtract_ft8_signal(&mut residual, &d.tones, d.frequency, d.time_offset);
+            stale.push((
+                d.frequency - SUBTRACT_MARGIN_HZ,
+                d.frequency + 7.0 * TONE_SPACING + SUBTRACT_MARGIN_HZ,
+            ));
+            downsampler = Downsampler::new(&residual)?;
+            result.decodes.push(d);
+            found += 1;
+        }
+        result.passes += 1;
+        result.new_per_pass.push(found);
+        if found == 0 {
+            break;
+        }
+    }
+
+    sort_decodes(&mut result.decodes);
+    Ok(result)
+}
+
+/// Bring `spectra` up to date with `residual` after signals were
+/// subtracted from the `(low, high)` frequency ranges in `stale`, which is
+/// emptied.
+///
+/// Only the rows covering those ranges are recomputed, unless together
+/// they span more than [`MAX_REGION_HZ`] and a full recompute is cheaper.
+fn refresh_spectra(
+    residual: &[f32],
+    stale: &mut Vec<(f32, f32)>,
+    spectra: &mut Vec<[f32; NHSYM]>,
+) -> Result<(), String> {
+    stale.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f32, f32)> = Vec::new();
+    for (low, high) in stale.drain(..) {
+        match merged.last_mut() {
+            Some(last) if low <= last.1 => last.1 = last.1.max(high),
+            _ => merged.push((low, high)),
+        }
+    }
+    if merged.iter().map(|(low, high)| high - low).sum::<f32>() > MAX_REGION_HZ {
+        *spectra = compute_spectra(residual)?;
+        return Ok(());
+    }
+    for (low, high) in merged {
+        compute_spectra_region(residual, low, high, spectra)?;
+    }
+    Ok(())
+}
+
+/// Order decodes by frequency, then DT.
+fn sort_decodes(decodes: &mut [DecodedMessage]) {
+    decodes.sort_by(|a, b| {
+        a.frequency
+            .total_cmp(&b.frequency)
+            .then(a.time_offset.total_cmp(&b.time_offset))
+    });
+}
+
+/// Hash cache for a decode run, seeded with the operator's callsign.
+fn seeded_cache(config: &DecoderConfig) -> CallsignHashCache {
+    let mut cache = CallsignHashCache::new();
+    if let Some(mycall) = &config.my_callsign {
+        cache.insert(mycall);
+    }
+    cache
+}
+
+/// Run the LDPC decoder on each LLR variant of one candidate and keep the
+/// most convincing result.
+///
+/// Variants are ranked by hard-error count, then BP before OSD, then fewer
+/// iterations. Once one variant has decoded the rest only get BP, since
+/// OSD on them would rarely improve on a result already in hand. Results
+/// with more than `max_hard_errors` hard errors are discarded.
+fn best_decode(
+    methods: &[(LlrMethod, &[f32])],
+    depth: DecodeDepth,
+    algo: DecodeAlgo,
+    max_hard_errors: usize,
+) -> Option<(LlrMethod, BitVec<u8, Msb0>, usize, usize)> {
+    let mut best: Option<(LlrMethod, BitVec<u8, Msb0>, usize, usize)> = None;
+    for &(method, llr) in methods {
+        let depth = if best.is_some() {
+            DecodeDepth::BpOnly
+        } else {
+            depth
+        };
+        let Some((message91, iterations, nhard)) =
+            ldpc::decode_hybrid_with_algo(llr, None, depth, algo)
+        else {
+            continue;
+        };
+        if nhard > max_hard_errors || message91[..message::MESSAGE_BITS].not_any() {
+            continue;
+        }
+        let rank = |iterations: usize, nhard: usize| (nhard, iterations == 0, iterations);
+        if best
+            .as_ref()
+            .is_none_or(|b| rank(iterations, nhard) < rank(b.2, b.3))
+        {
+            best = Some((method, message91, iterations, nhard));
+        }
+    }
+    best
+}
+
+/// Apply `f` to every candidate, on `threads` worker threads when the
+/// `rayon` feature is enabled. Results are in candidate order either way.
+fn map_candidates<T, F>(candidates: &[Candidate], threads: usize, f: F) -> Result<Vec<T>, String>
+where
+    T: Send,
+    F: Fn(&Candidate) -> Result<T, String> + Sync,
+{
+    #[cfg(feature = "rayon")]
+    if threads > 1 {
+        use rayon::prelude::*;
+        return thread_pool(threads)?.install(|| candidates.par_iter().map(&f).collect());
+    }
+    #[cfg(not(feature = "rayon"))]
+    let _ = threads;
+    candidates.iter().map(f).collect()
+}
+
+/// The worker pool for `threads` threads, built on first use and shared
+/// by every later decode asking for the same number.
+#[cfg(feature = "rayon")]
+fn thread_pool(threads: usize) -> Result<std::sync::Arc<rayon::ThreadPool>, String> {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+    static POOLS: OnceLock<Mutex<HashMap<usize, Arc<rayon::ThreadPool>>>> = OnceLock::new();
+    let mut pools = POOLS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(pool) = pools.get(&threads) {
+        return Ok(Arc::clone(pool));
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let pool = Arc::new(pool);
+    pools.insert(threads, Arc::clone(&pool));
+    Ok(pool)
+}
+
+/// Fine-sync `candidates` and order them by [`matched_filter_score`],
+/// best first.
+///
+/// The coarse `sync_power` is a power-spectrum measure taken before the
+/// time and frequency are refined; the matched filter, measured at the
+/// refined position, is a better guide to which candidates will decode.
+/// Only multipass decoding needs the order, as each decode is subtracted
+/// before the next candidate is tried, and it costs a downsample per
+/// candidate.
+fn refine_and_rank(
+    downsampler: &Downsampler,
+    candidates: &[Candidate],
+    config: &DecoderConfig,
+) -> Result<Vec<Candidate>, String> {
+    let search = config.fine_search();
+    let mut scored = map_candidates(candidates, config.threads, |candidate| {
+        let refined = fine_sync_search(downsampler, candidate, &search)?;
+        let (cd, _) = downsampler.downsample(refined.frequency)?;
+        Ok((matched_filter_score(&cd, &refined), refined))
+    })?;
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    Ok(scored.into_iter().map(|(_, c)| c).collect())
+}
+
+/// Refine and decode `candidates`, appending them to `decodes` with
+/// duplicates merged by [`merge_decode`]. Returns the candidates that
+/// failed.
+fn decode_candidates(
+    signal: &[f32],
+    candidates: &[Candidate],
+    config: &DecoderConfig,
+    cache: &CallsignHashCache,
+    decodes: &mut Vec<DecodedMessage>,
+) -> Result<Vec<FailedCandidate>, String> {
+    let downsampler = Downsampler::new(signal)?;
+    let search = config.fine_search();
+    let results = map_candidates(candidates, config.threads, |candidate| {
+        let refined = fine_sync_search(&downsampler, candidate, &search)?;
+        try_decode_refined(signal, &downsampler, refined, config, cache)
+    })?;
+    let mut failed = Vec::new();
+    for result in results {
+        match result {
+            Ok(d) => merge_decode(decodes, d),
+            Err(f) => failed.push(f),
+        }
+    }
+    Ok(failed)
+}
+
+/// Whether two decodes are the same signal: the same text within
+/// [`DUPLICATE_HZ`]. Stations sending the same text on different
+/// frequencies are kept apart.
+///
+/// The texts compared are the canonical ones: a hashed callsign can
+/// resolve in one decode and not in another, depending on what the
+/// cache had seen by then.
+fn same_signal(a: &DecodedMessage, b: &DecodedMessage) -> bool {
+    a.canonical_message == b.canonical_message && (a.frequency - b.frequency).abs() <= DUPLICATE_HZ
+}
+
+/// Add `d` to `decodes`, or if it duplicates a decode already there keep
+/// whichever of the two has the higher confidence.
+fn merge_decode(decodes: &mut Vec<DecodedMessage>, d: DecodedMessage) {
+    match decodes.iter_mut().find(|e| same_signal(e, &d)) {
+        Some(e) if d.confidence > e.confidence => *e = d,
+        Some(_) => {}
+        None => decodes.push(d),
+    }
+}
+
+/// Extract and decode one fine-synced candidate from the window
+/// `downsampler` was built from, which must be `signal`.
+fn decode_refined(
+    signal: &[f32],
+    downsampler: &Downsampler,
+    refined: Candidate,
+    config: &DecoderConfig,
+    cache: &CallsignHashCache,
+) -> Result<Option<DecodedMessage>, String> {
+    Ok(try_decode_refined(signal, downsampler, refined, config, cache)?.ok())
+}
+
+/// [`decode_refined`], saying where a candidate that does not decode
+/// failed.
+fn try_decode_refined(
+    signal: &[f32],
+    downsampler: &Downsampler,
+    mut refined: Candidate,
+    config: &DecoderConfig,
+    cache: &CallsignHashCache,
+) -> Result<Result<DecodedMessage, FailedCandidate>, String> {
+    let llrs = if config.round_downsample_freq {
+        refined.frequency = refined.frequency.round();
+        extract_symbols_all_llr_with(downsampler, &refined)?
+    } else {
+        extract_phase_refined(downsampler, &mut refined)?
+    };
+    let fail = |stage| {
+        Ok(Err(FailedCandidate {
+            candidate: refined,
+            nsync: llrs.nsync,
+            stage,
+        }))
+    };
+    if llrs.nsync < MIN_NSYNC {
+        return fail(FailureStage::Costas);
+    }
+
+    let methods = [
+        (LlrMethod::Nsym1, &llrs.llra[..]),
+        (LlrMethod::Nsym2, &llrs.llrb[..]),
+        (LlrMethod::Nsym3, &llrs.llrc[..]),
+        (LlrMethod::Normalized, &llrs.llrd[..]),
+    ];
+    let Some((llr_method, message91, iterations, nharderrors)) = best_decode(
+        &methods,
+        config.decode_depth.for_nsync(llrs.nsync),
+        config.decode_algo,
+        config.max_hard_errors,
+    ) else {
+        let crc_only = methods.iter().any(|(_, llr)| {
+            let mut cw = bitarr![u8, Msb0; 0; 176];
+            for (i, &l) in llr.iter().enumerate() {
+                cw.set(i, l > 0.0);
+            }
+            ldpc::syndrome_weight(&cw[..ldpc::N]) == Some(0) && !crate::crc::verify(&cw[..ldpc::K])
+        });
+        return fail(if crc_only {
+            FailureStage::Crc
+        } else {
+            FailureStage::Ldpc
+        });
+    };
+
+    let message77 = &message91[..message::MESSAGE_BITS];
+    let tones = channel_symbols_from_bits(message77);
+    if llrs.nsync <= FALSE_DECODE_NSYNC
+        && wsjtx_off_tone_snr(&llrs.s8, &tones) < FALSE_DECODE_SNR_DB
+    {
+        return fail(FailureStage::LikelyFalse);
+    }
+    let Ok(variant) = message::decode_variant(message77, Some(cache)) else {
+        return fail(FailureStage::Unpack);
+    };
+    if config.plausibility_filter && !plausibility::is_plausible(&variant) {
+        return fail(FailureStage::Implausible);
+    }
+
+    let text = variant.to_string();
+    let snr_db = signal_snr(
+        signal,
+        &llrs.s8,
+        &tones,
+        refined.frequency,
+        config.snr_method,
+    )?;
+    let canonical_message = message::decode(message77, None).unwrap_or_else(|_| text.clone());
+    Ok(Ok(DecodedMessage {
+        message: text,
+        canonical_message,
+        message_type: variant.i3(),
+        message_subtype: variant.n3(),
+        frequency: refined.frequency,
+        time_offset: refined.time_offset,
+        snr_db,
+        sync_power: refined.sync_power,
+        sync_metric: refined.sync_metric,
+        ldpc_iterations: iterations,
+        nharderrors,
+        confidence: decode_confidence(nharderrors, config.max_hard_errors, iterations, llrs.nsync),
+        llr_method,
+        variant,
+        tones,
+    }))
+}
+
+/// Extract a candidate's symbols, re-extracting once at a corrected
+/// frequency when the phase drift across the Costas arrays shows a
+/// residual frequency error.
+///
+/// Fine sync's 0.5 Hz grid leaves a few tenths of a hertz of error even
+/// after interpolation, which spreads each tone into its neighbours. The
+/// drift estimate is finer, so a candidate in sync well enough to trust
+/// it is moved to the corrected frequency, updating `candidate`.
+fn extract_phase_refined(
+    downsampler: &Downsampler,
+    candidate: &mut Candidate,
+) -> Result<SymbolLlrs, String> {
+    let llrs = extract_symbols_all_llr_with(downsampler, candidate)?;
+    let correction = llrs.freq_error;
+    if llrs.nsync < PHASE_REFINE_NSYNC
+        || !(PHASE_REFINE_MIN_HZ..PHASE_REFINE_MAX_HZ).contains(&correction.abs())
+    {
+        return Ok(llrs);
+    }
+    candidate.frequency += correction;
+    extract_symbols_all_llr_with(downsampler, candidate)
+}
+
+/// Confidence score of a decode, from 0 to 1.
+///
+/// The product of three factors: 1 falling by `1 / (max_hard_errors + 1)`
+/// per hard error, so a decode at the limit
+/// ([`DecoderConfig::max_hard_errors`]) still scores above 0; 1 for BP
+/// converging at once falling to ½ at the last iteration (and ½ for OSD,
+/// `iterations == 0`); and the fraction of the 21 Costas symbols received
+/// correctly.
+pub fn decode_confidence(
+    nharderrors: usize,
+    max_hard_errors: usize,
+    iterations: usize,
+    nsync: usize,
+) -> f32 {
+    let hard = 1.0 - (nharderrors as f32 / (max_hard_errors + 1) as f32).min(1.0);
+    let ldpc = if iterations == 0 {
+        0.5
+    } else {
+        1.0 - 0.5 * (iterations - 1) as f32 / ldpc::MAX_BP_ITERATIONS as f32
+    };
+    let sync = nsync.min(21) as f32 / 21.0;
+    hard * ldpc * sync
+}
+
+/// SNR of a decode by `method`.
+fn signal_snr(
+    signal: &[f32],
+    s8: &[[f32; 8]],
+    tones: &[u8],
+    frequency: f32,
+    method: SnrMethod,
+) -> Result<f32, String> {
+    let baseline = || {
+        let center = frequency + 3.5 * TONE_SPACING;
+        noise_baseline(signal, center).map(|b| calculate_snr_baseline(s8, tones, b))
+    };
+    Ok(match method {
+        SnrMethod::OffTone => calculate_snr(s8, tones),
+        SnrMethod::Baseline => baseline()?,
+        SnrMethod::Average => (calculate_snr(s8, tones) + baseline()?) / 2.0,
+    })
+}
+
+/// [`decode_ft8`], with the decodes split by
+/// [`DecoderConfig::my_callsign`]. Without a callsign every decode is in
+/// [`DirectedDecodes::other`].
+pub fn decode_ft8_directed(
+    signal: &[f32],
+    config: &DecoderConfig,
+) -> Result<DirectedDecodes, DecodeError> {
+    let decodes = decode_ft8(signal, config, None)?;
+    Ok(match &config.my_callsign {
+        Some(mycall) => DirectedDecodes::partition(decodes, mycall),
+        None => DirectedDecodes {
+            directed_to_me: Vec::new(),
+            other: decodes,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{Awgn, ChannelModel};
+    use crate::message::{tx_cq, tx_report};
+    use crate::modulation::BandSynthesizer;
+    use crate::symbol::{channel_symbols, channel_symbols_from_bits};
+    use crate::sync::{estimate_frequency_from_phase, extract_symbols_all_llr, COARSE_LAG};
+
+    fn tones(text: &str) -> [u8; 79] {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        message::encode(text, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+        channel_symbols_from_bits(&bits[..77])
+    }
+
+    pub(super) fn decoded(text: &str) -> DecodedMessage {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        let mut cache = CallsignHashCache::new();
+        cache.insert("N0YPR");
+        message::encode(text, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+        let variant = message::decode_variant(&bits[..77], Some(&cache)).unwrap();
+        DecodedMessage {
+            message: variant.to_string(),
+            canonical_message: message::decode(&bits[..77], None).unwrap(),
+            message_type: variant.i3(),
+            message_subtype: variant.n3(),
+            frequency: 1000.0,
+            time_offset: 0.0,
+            snr_db: 0.0,
+            sync_power: 10.0,
+            sync_metric: SyncMetric::AllCostas,
+            ldpc_iterations: 1,
+            nharderrors: 0,
+            confidence: 1.0,
+            llr_method: LlrMethod::Nsym1,
+            variant,
+            tones: channel_symbols_from_bits(&bits[..77]),
+        }
+    }
+
+    #[test]
+    fn test_duplicates_merge_whatever_the_cache_resolved() {
+        // The same signal, decoded once before and once after the cache
+        // learned the hashed callsign
+        let resolved = decoded("<N0YPR> PJ4/K1ABC");
+        assert_eq!(resolved.message, "<N0YPR> PJ4/K1ABC");
+        let unresolved = DecodedMessage {
+            message: resolved.canonical_message.clone(),
+            frequency: 1001.0,
+            confidence: 2.0,
+            ..resolved.clone()
+        };
+        assert_ne!(unresolved.message, resolved.message);
+        assert!(same_signal(&resolved, &unresolved));
+
+        let mut decodes = vec![resolved.clone()];
+        merge_decode(&mut decodes, unresolved);
+        assert_eq!(decodes.len(), 1);
+        assert_eq!(decodes[0].confidence, 2.0);
+
+        // The same text from another station stays separate
+        let elsewhere = DecodedMessage {
+            frequency: 1200.0,
+            ..resolved
+        };
+        merge_decode(&mut decodes, elsewhere);
+        assert_eq!(decodes.len(), 2);
+    }
+
+    #[test]
+    fn test_is_to() {
+        assert!(decoded("N0YPR K1ABC FN42").is_to("N0YPR"));
+        assert!(decoded("K1ABC N0YPR -10").is_to("n0ypr"));
+        assert!(!decoded("K1ABC W9XYZ -10").is_to("N0YPR"));
+        assert!(!decoded("CQ K1ABC FN42").is_to("N0YPR"));
+        assert!(!decoded("N0YPR IS QRV").is_to("N0YPR"));
+        assert!(!decoded("N0YPR K1ABC FN42").is_to(""));
+
+        // Portable, rover and compound forms on either side
+        assert!(decoded("N0YPR/R K1ABC FN42").is_to("N0YPR"));
+        assert!(decoded("G4ABC/P N0YPR/P IO91").is_to("N0YPR"));
+        assert!(decoded("N0YPR K1ABC FN42").is_to("N0YPR/P"));
+        assert!(decoded("<K1ABC> KH6/N0YPR").is_to("N0YPR"));
+        assert!(decoded("N0YPR K1ABC FN42").is_to("KH6/N0YPR"));
+    }
+
+    #[test]
+    fn test_is_to_hashed_reference() {
+        // The non-standard call is sent in full; mine only as a hash,
+        // which resolves because the cache was seeded with it
+        let msg = decoded("<N0YPR> PJ4/K1ABC");
+        assert_eq!(msg.message, "<N0YPR> PJ4/K1ABC");
+        assert_eq!(msg.canonical_message, "<...> PJ4/K1ABC");
+        assert!(msg.is_to("N0YPR"));
+        assert!(!decoded("<W9XYZ> PJ4/K1ABC").is_to("N0YPR"));
+    }
+
+    #[test]
+    fn test_partition() {
+        let decodes = vec![
+            decoded("N0YPR K1ABC FN42"),
+            decoded("CQ W9XYZ EN37"),
+            decoded("K1ABC N0YPR R-12"),
+        ];
+        let split = DirectedDecodes::partition(decodes, "N0YPR");
+        assert_eq!(split.directed_to_me.len(), 2);
+        assert_eq!(split.other.len(), 1);
+        assert_eq!(split.other[0].message, "CQ W9XYZ EN37");
+    }
+
+    #[test]
+    fn test_decode_reports_message_type() {
+        // DXpedition messages have no text form to parse
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        let dxpedition = MessageVariant::Dxpedition {
+            call1: "K1ABC".into(),
+            call2: "W9XYZ".into(),
+            call3: "KH1/KH7Z".into(),
+            report: -8,
+        };
+        message::encode_variant(&dxpedition, &mut bits[..77], &mut CallsignHashCache::new())
+            .unwrap();
+        let messages = [
+            (tones("TNX BOB 73 GL"), 0, Some(0)),
+            (channel_symbols_from_bits(&bits[..77]), 0, Some(1)),
+            (tones("K1ABC W9XYZ 6A WI"), 0, Some(3)),
+            (tones("K1ABC W9XYZ 17B EMA"), 0, Some(4)),
+            (tones("123456789ABCDEF012"), 0, Some(5)),
+            (tones("CQ K1ABC FN42"), 1, None),
+            (tones("CQ G4ABC/P IO91"), 2, None),
+            (tones("K1ABC W9XYZ 579 WI"), 3, None),
+            (tones("CQ PJ4/K1ABC"), 4, None),
+            (tones("<PA3XYZ> <G4ABC> 570123 JO22AB"), 5, None),
+        ];
+        let mut synth = BandSynthesizer::default();
+        for (k, (tones, _, _)) in messages.iter().enumerate() {
+            synth.add_signal(tones, 300.0 + 250.0 * k as f32, 0.0, 1.0);
+        }
+        let decodes = decode_ft8(&synth.synthesize(), &DecoderConfig::default(), None).unwrap();
+        assert_eq!(decodes.len(), messages.len());
+
+        for (k, (_, i3, n3)) in messages.iter().enumerate() {
+            let f0 = 300.0 + 250.0 * k as f32;
+            let decode = decodes
+                .iter()
+                .find(|d| (d.frequency - f0).abs() < 5.0)
+                .unwrap();
+            assert_eq!(
+                (decode.message_type, decode.message_subtype),
+                (*i3, *n3),
+                "{}",
+                decode.message
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_band() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 700.0, 0.2, 1.0)
+            .add_signal(
+                &tx_report("K1ABC", "N0YPR", -15, false).unwrap(),
+                1350.0,
+                -0.1,
+                1.0,
+            )
+            .add_signal(&tones("<N0YPR> PJ4/K1ABC"), 2100.0, 0.0, 1.0)
+            .add_channel(Box::new(Awgn::new(-12.0, 1)))
+            .synthesize();
+
+        let config = DecoderConfig {
+            my_callsign: Some("N0YPR".into()),
+            ..DecoderConfig::default()
+        };
+        let split = decode_ft8_directed(&signal, &config).unwrap();
+
+        let mut mine: Vec<&str> = split
+            .directed_to_me
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        mine.sort();
+        assert_eq!(mine, ["<N0YPR> PJ4/K1ABC", "N0YPR K1ABC -15"]);
+        assert_eq!(split.other.len(), 1);
+        let hashed = split
+            .directed_to_me
+            .iter()
+            .find(|d| d.message.starts_with('<'))
+            .unwrap();
+        assert_eq!(hashed.canonical_message, "<...> PJ4/K1ABC");
+
+        let cq = &split.other[0];
+        assert_eq!(cq.message, "CQ W9XYZ EN37");
+        assert_eq!(cq.canonical_message, cq.message);
+        assert!((cq.frequency - 700.0).abs() < 1.0, "{:?}", cq);
+        assert!((cq.time_offset - 0.2).abs() < 0.02, "{:?}", cq);
+        assert!((cq.snr_db + 12.0).abs() < 3.0, "{:?}", cq);
+    }
+
+    #[test]
+    fn test_multipass_reports_each_signal_once() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1000.0, 0.0, 1.0)
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1600.0, 0.4, 0.5)
+            .add_channel(Box::new(Awgn::new(-10.0, 2)))
+            .synthesize();
+        let decodes = decode_ft8_multipass(&signal, &DecoderConfig::default(), 3).unwrap();
+        let mut texts: Vec<&str> = decodes.iter().map(|d| d.message.as_str()).collect();
+        texts.sort();
+        assert_eq!(texts, ["CQ K1ABC FN42", "CQ W9XYZ EN37"]);
+    }
+
+    #[test]
+    fn test_multipass_uncovers_signal_between_stronger_neighbours() {
+        // The weak signal's tones overlap both neighbours'
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1480.0, 0.0, 1.0)
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1520.0, 0.1, 1.0)
+            .add_signal(&tx_cq("N0YPR", "DM42").unwrap(), 1500.0, 0.05, 0.3)
+            .add_channel(Box::new(Awgn::new(10.0, 5)))
+            .synthesize();
+        let config = DecoderConfig::default();
+
+        let single = decode_ft8(&signal, &config, None).unwrap();
+        assert!(single.iter().all(|d| d.message != "CQ N0YPR DM42"));
+
+        let result = decode_ft8_multipass_report(&signal, &config, 4).unwrap();
+        assert_eq!(result.passes, 3);
+        assert_eq!(result.new_per_pass, [2, 1, 0]);
+        assert_eq!(result.decodes[1].message, "CQ N0YPR DM42");
+
+        // The same passes when configured rather than asked for
+        let config = DecoderConfig {
+            max_passes: 4,
+            ..config
+        };
+        assert_eq!(decode_ft8(&signal, &config, None).unwrap(), result.decodes);
+    }
+
+    #[test]
+    fn test_multipass_uncovers_overlapping_weaker_signal() {
+        // 3 Hz apart, the weaker signal shares every tone bin of the stronger
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1500.0, 0.0, 1.0)
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1503.0, 0.08, 0.3)
+            .add_channel(Box::new(Awgn::new(0.0, 5)))
+            .synthesize();
+        let config = DecoderConfig::default();
+
+        let single = decode_ft8(&signal, &config, None).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].message, "CQ W9XYZ EN37");
+
+        let result = decode_ft8_multipass_report(&signal, &config, 5).unwrap();
+        assert_eq!(result.new_per_pass, [1, 1, 0]);
+        let weak = &result.decodes[1];
+        assert_eq!(weak.message, "CQ K1ABC FN42");
+        assert!((weak.frequency - 1503.0).abs() < 1.0, "{:?}", weak);
+        assert!((weak.time_offset - 0.08).abs() < 0.02, "{:?}", weak);
+    }
+
+    #[test]
+    fn test_custom_detector() {
+        struct Fixed(Vec<f32>);
+        impl CandidateDetector for Fixed {
+            fn detect(&self, signal: &[f32], _: &DecoderConfig) -> Result<Vec<Candidate>, String> {
+                self.0
+                    .iter()
+                    .map(|&f| locate_candidate(signal, f, 3.0))
+                    .collect()
+            }
+        }
+
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 800.0, 0.3, 1.0)
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1900.0, -0.2, 1.0)
+            .synthesize();
+        let config = DecoderConfig::default();
+
+        let texts = |decodes: Vec<DecodedMessage>| {
+            let mut texts: Vec<String> = decodes.into_iter().map(|d| d.message).collect();
+            texts.sort();
+            texts
+        };
+        let default = texts(decode_ft8(&signal, &config, None).unwrap());
+        assert_eq!(default, ["CQ K1ABC FN42", "CQ W9XYZ EN37"]);
+        let explicit = decode_ft8(&signal, &config, Some(&CoarseSyncDetector)).unwrap();
+        assert_eq!(texts(explicit), default);
+
+        let one = decode_ft8(&signal, &config, Some(&Fixed(vec![1901.0]))).unwrap();
+        assert_eq!(texts(one), ["CQ K1ABC FN42"]);
+        let none = decode_ft8(&signal, &config, Some(&Fixed(vec![]))).unwrap();
+        assert!(none.is_empty());
+
+        // Every pass of a multipass decode asks the same detector
+        let multipass = DecoderConfig {
+            max_passes: 3,
+            ..config
+        };
+        let one = decode_ft8(&signal, &multipass, Some(&Fixed(vec![1901.0]))).unwrap();
+        assert_eq!(texts(one), ["CQ K1ABC FN42"]);
+        let none = decode_ft8(&signal, &multipass, Some(&Fixed(vec![]))).unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_decode_resampled() {
+        let tones = tx_cq("K1ABC", "FN42").unwrap();
+        for rate in [48_000, 24_000] {
+            let signal = BandSynthesizer::new(rate as f32)
+                .add_signal(&tones, 1200.0, 0.1, 1.0)
+                .add_channel(Box::new(Awgn::new(-12.0, 8)))
+                .synthesize();
+            let decodes = decode_resampled(&signal, rate, &DecoderConfig::default()).unwrap();
+            assert_eq!(decodes.len(), 1, "{} Hz", rate);
+            assert_eq!(decodes[0].message, "CQ K1ABC FN42");
+            assert!((decodes[0].frequency - 1200.0).abs() < 0.5);
+            assert!((decodes[0].time_offset - 0.1).abs() < 0.02);
+        }
+        assert!(matches!(
+            decode_resampled(&[0.0; 1000], 0, &DecoderConfig::default()),
+            Err(DecodeError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_banded() {
+        // One signal in each band, and one across the boundary at 2 kHz
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 900.0, 0.1, 1.0)
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1980.0, -0.2, 1.0)
+            .add_signal(&tx_cq("N0YPR", "DM79").unwrap(), 3600.0, 0.3, 1.0)
+            .add_channel(Box::new(Awgn::new(-12.0, 4)))
+            .synthesize();
+        let config = DecoderConfig::default();
+
+        let decodes =
+            decode_banded(&signal, &[(200.0, 2000.0), (2000.0, 4500.0)], &config).unwrap();
+        let found: Vec<(&str, f32)> = decodes
+            .iter()
+            .map(|d| (d.message.as_str(), d.frequency))
+            .collect();
+        assert_eq!(found.len(), 3, "{:?}", found);
+        for ((text, freq), (expected, f0)) in found.into_iter().zip([
+            ("CQ K1ABC FN42", 900.0),
+            ("CQ W9XYZ EN37", 1980.0),
+            ("CQ N0YPR DM79", 3600.0),
+        ]) {
+            assert_eq!(text, expected);
+            assert!((freq - f0).abs() < 1.0, "{}: {}", text, freq);
+        }
+
+        // The default range stops short of the upper band
+        assert_eq!(decode_ft8(&signal, &config, None).unwrap().len(), 2);
+        assert!(decode_banded(&signal, &[(1000.0, 500.0)], &config).is_err());
+    }
+
+    #[test]
+    fn test_diagnostic_reports_failed_candidates() {
+        // A codeword whose CRC does not match its message
+        let mut m91 = message::payload(
+            &message::encode_to_vec("CQ W9XYZ EN37", &mut CallsignHashCache::new()).unwrap(),
+        );
+        let bit = !m91[80];
+        m91.set(80, bit);
+        let bad_crc = crate::symbol::codeword_to_symbols(&ldpc::encode_detailed(&m91).codeword);
+
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 800.0, 0.0, 1.0)
+            .add_signal(&tx_cq("N0YPR", "DM79").unwrap(), 1400.0, 0.2, 0.2)
+            .add_signal(&bad_crc, 2000.0, -0.1, 1.0)
+            .add_channel(Box::new(Awgn::new(-10.0, 12)))
+            .synthesize();
+        let config = DecoderConfig::default();
+        let result = decode_ft8_diagnostic(&signal, &config).unwrap();
+        assert_eq!(result.decodes, decode_ft8(&signal, &config, None).unwrap());
+        assert_eq!(result.decodes.len(), 1);
+        let near = |freq: f32| {
+            result
+                .failed
+                .iter()
+                .filter(|f| (f.candidate.frequency - freq).abs() < 3.0)
+                .max_by_key(|f| f.nsync)
+                .unwrap_or_else(|| panic!("nothing near {} Hz", freq))
+        };
+        // Nor are the decoded signal's sidelobes
+        assert!(result
+            .failed
+            .iter()
+            .all(|f| (f.candidate.frequency - 800.0).abs() >= 50.0));
+        // Present but too weak to copy, and a clean codeword that fails
+        // the CRC
+        let weak = near(1400.0);
+        assert_eq!(weak.stage, FailureStage::Ldpc);
+        assert!(weak.nsync >= MIN_NSYNC);
+        let crc = near(2000.0);
+        assert_eq!(crc.stage, FailureStage::Crc);
+        assert_eq!(crc.nsync, 21);
+        assert!(result
+            .failed
+            .iter()
+            .any(|f| f.stage == FailureStage::Costas));
+    }
+
+    #[test]
+    fn test_duplicate_candidates_merged() {
+        struct Twice;
+        impl CandidateDetector for Twice {
+            fn detect(&self, _: &[f32], _: &DecoderConfig) -> Result<Vec<Candidate>, String> {
+                let at = |frequency| Candidate {
+                    frequency,
+                    time_offset: 0.1,
+                    sync_power: 5.0,
+                    sync_metric: SyncMetric::AllCostas,
+                };
+                Ok(vec![at(998.5), at(1002.0)])
+            }
+        }
+
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1000.0, 0.1, 1.0)
+            .add_channel(Box::new(Awgn::new(-5.0, 3)))
+            .synthesize();
+        let config = DecoderConfig::default();
+        let decodes = decode_ft8(&signal, &config, Some(&Twice)).unwrap();
+        assert_eq!(decodes.len(), 1);
+        assert!((decodes[0].frequency - 1000.0).abs() < 1.0);
+
+        // The same text from two stations is two decodes
+        let tnx = channel_symbols("TNX BOB 73 GL", &mut CallsignHashCache::new()).unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tnx, 800.0, 0.1, 1.0)
+            .add_signal(&tnx, 1600.0, -0.2, 1.0)
+            .synthesize();
+        let decodes = decode_ft8(&signal, &config, None).unwrap();
+        assert_eq!(decodes.len(), 2, "{:?}", decodes);
+        assert!(decodes.iter().all(|d| d.message == "TNX BOB 73 GL"));
+        let multipass = decode_ft8_multipass(&signal, &config, 2).unwrap();
+        assert_eq!(multipass.len(), 2);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_threads_give_identical_decodes() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1900.0, 0.3, 1.0)
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 800.0, -0.2, 1.0)
+            .add_signal(&tx_cq("N0YPR", "DM42").unwrap(), 1300.0, 0.1, 0.5)
+            .add_channel(Box::new(Awgn::new(-10.0, 11)))
+            .synthesize();
+        let key = |decodes: Vec<DecodedMessage>| -> Vec<(String, u32, u32)> {
+            decodes
+                .into_iter()
+                .map(|d| (d.message, d.frequency.to_bits(), d.time_offset.to_bits()))
+                .collect()
+        };
+
+        let serial = DecoderConfig::default();
+        let parallel = DecoderConfig {
+            threads: 4,
+            ..DecoderConfig::default()
+        };
+        let one = key(decode_ft8(&signal, &serial, None).unwrap());
+        assert_eq!(one.len(), 3);
+        assert!(one
+            .windows(2)
+            .all(|w| f32::from_bits(w[0].1) <= f32::from_bits(w[1].1)));
+        assert_eq!(key(decode_ft8(&signal, &parallel, None).unwrap()), one);
+        assert_eq!(
+            key(decode_ft8_multipass(&signal, &parallel, 2).unwrap()),
+            key(decode_ft8_multipass(&signal, &serial, 2).unwrap())
+        );
+
+        // Every decode with four threads ran on the same pool
+        assert!(std::sync::Arc::ptr_eq(
+            &thread_pool(4).unwrap(),
+            &thread_pool(4).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_decode_at_click() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 900.0, 0.0, 1.0)
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1502.0, 0.8, 1.0)
+            .add_channel(Box::new(Awgn::new(-12.0, 3)))
+            .synthesize();
+        let config = DecoderConfig::default();
+
+        // A click 9 Hz and 0.2 s off still locks on
+        let d = decode_at(&signal, 1511.0, 0.6, &config).unwrap();
+        assert_eq!(d.message, "CQ K1ABC FN42");
+        assert!((d.frequency - 1502.0).abs() < 0.5, "{}", d.frequency);
+        assert!((d.time_offset - 0.8).abs() < 0.02, "{}", d.time_offset);
+        let d = decode_at(&signal, 892.0, -0.15, &config).unwrap();
+        assert_eq!(d.message, "CQ W9XYZ EN37");
+
+        // Empty band, or a position well away from either signal
+        assert_eq!(decode_at(&signal, 2200.0, 0.0, &config), None);
+        assert_eq!(decode_at(&signal, 1502.0, -1.5, &config), None);
+        assert_eq!(decode_at(&signal, 9000.0, 0.0, &config), None);
+        assert_eq!(decode_at(&signal[..1000], 1502.0, 0.8, &config), None);
+    }
+
+    #[test]
+    fn test_decode_at_frequencies() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 900.0, 0.0, 1.0)
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1502.0, 0.8, 1.0)
+            .add_channel(Box::new(Awgn::new(-12.0, 3)))
+            .synthesize();
+        let config = DecoderConfig::default();
+
+        let decodes = decode_ft8_at_frequencies(&signal, &[1500.0], &config).unwrap();
+        assert_eq!(decodes.len(), 1);
+        assert_eq!(decodes[0].message, "CQ K1ABC FN42");
+        assert!((decodes[0].frequency - 1502.0).abs() < 1.0);
+
+        // Nothing transmitted within the window
+        let decodes = decode_ft8_at_frequencies(&signal, &[2000.0], &config).unwrap();
+        assert!(decodes.is_empty());
+    }
+
+    #[test]
+    fn test_best_decode_keeps_cleanest_method() {
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        message::encode(
+            "CQ N0YPR DM42",
+            &mut bits[..77],
+            &mut CallsignHashCache::new(),
+        )
+        .unwrap();
+        crate::crc::append_crc(&bits[..77], &mut m91[..ldpc::K]);
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        ldpc::encode(&m91[..ldpc::K], &mut cw[..ldpc::N]);
+
+        let noisy = |errors: &[usize]| {
+            let mut llr: Vec<f32> = cw[..ldpc::N]
+                .iter()
+                .map(|b| if *b { 2.5 } else { -2.5 })
+                .collect();
+            for &i in errors {
+                llr[i] *= -0.5;
+            }
+            llr
+        };
+        let worse = noisy(&[3, 60, 100, 150]);
+        let better = noisy(&[70]);
+        let garbage = vec![0.0; ldpc::N];
+        let methods = [
+            (LlrMethod::Nsym1, &worse[..]),
+            (LlrMethod::Nsym2, &garbage[..]),
+            (LlrMethod::Nsym3, &better[..]),
+        ];
+
+        let (method, decoded, iterations, nhard) = best_decode(
+            &methods,
+            DecodeDepth::BpOsdHybrid,
+            DecodeAlgo::SumProduct,
+            MAX_HARD_ERRORS,
+        )
+        .unwrap();
+        assert_eq!(method, LlrMethod::Nsym3);
+        assert_eq!(decoded, m91[..ldpc::K].to_bitvec());
+        assert!(iterations >= 1);
+        assert_eq!(nhard, 1);
+    }
+
+    #[test]
+    fn test_fractional_downsample_frequency() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1500.4, 0.0, 1.0)
+            .add_channel(Box::new(Awgn::new(-5.0, 4)))
+            .synthesize();
+        let snr_at = |frequency: f32| {
+            let candidate = Candidate {
+                frequency,
+                time_offset: 0.0,
+                sync_power: 1.0,
+                sync_metric: SyncMetric::AllCostas,
+            };
+            let llrs = extract_symbols_all_llr(&signal, &candidate).unwrap();
+            calculate_snr(&llrs.s8, &tones)
+        };
+        let exact = snr_at(1500.4);
+        let rounded = snr_at(1500.4f32.round());
+        assert!(exact > rounded + 0.5, "exact {} rounded {}", exact, rounded);
+
+        let config = DecoderConfig {
+            round_downsample_freq: true,
+            ..DecoderConfig::default()
+        };
+        let decodes = decode_ft8(&signal, &config, None).unwrap();
+        assert_eq!(decodes[0].frequency.fract(), 0.0);
+    }
+
+    #[test]
+    fn test_snr_methods_at_known_snr() {
+        let tones = tx_cq("K1ABC", "FN42").unwrap();
+        for snr in [-18.0, -10.0, 0.0] {
+            let signal = BandSynthesizer::default()
+                .add_signal(&tones, 1200.0, 0.0, 1.0)
+                .add_channel(Box::new(Awgn::new(snr, 21)))
+                .synthesize();
+            let reported: Vec<f32> = [SnrMethod::OffTone, SnrMethod::Baseline, SnrMethod::Average]
+                .into_iter()
+                .map(|snr_method| {
+                    let config = DecoderConfig {
+                        snr_method,
+                        ..DecoderConfig::default()
+                    };
+                    decode_ft8(&signal, &config, None).unwrap()[0].snr_db
+                })
+                .collect();
+            for (method, r) in ["off-tone", "baseline", "average"].iter().zip(&reported) {
+                assert!((r - snr).abs() < 2.0, "{} at {} dB: {}", method, snr, r);
+            }
+            assert!((reported[2] - (reported[0] + reported[1]) / 2.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_early_signal_decodes() {
+        // Starting 0.2-0.3 s before the window, part of the first Costas
+        // array is lost; arrays 2 and 3 and the truncated symbols suffice
+        for (dt, freq) in [(-0.7, 1200.0), (-0.8, 1650.0)] {
+            let signal = BandSynthesizer::default()
+                .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), freq, dt, 1.0)
+                .add_channel(Box::new(Awgn::new(-14.0, 12)))
+                .synthesize();
+            let decodes = decode_ft8(&signal, &DecoderConfig::default(), None).unwrap();
+            assert_eq!(decodes.len(), 1, "DT {}", dt);
+            assert_eq!(decodes[0].message, "CQ K1ABC FN42");
+            assert!(
+                (decodes[0].time_offset - dt).abs() < 0.01,
+                "{:?}",
+                decodes[0]
+            );
+            assert!(
+                (decodes[0].frequency - freq).abs() < 1.0,
+                "{:?}",
+                decodes[0]
+            );
+        }
+    }
+
+    #[test]
+    fn test_late_signal_with_default_and_widened_coarse_lag() {
+        // 1 s late: outside the default ±0.4 s primary search, found by the
+        // full-range pass or directly by a widened primary search
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1200.0, 1.0, 1.0)
+            .add_channel(Box::new(Awgn::new(-16.0, 8)))
+            .synthesize();
+        for coarse_lag_steps in [COARSE_LAG, 30, 1000] {
+            let config = DecoderConfig {
+                coarse_lag_steps,
+                ..DecoderConfig::default()
+            };
+            let decodes = decode_ft8(&signal, &config, None).unwrap();
+            assert_eq!(decodes.len(), 1, "lag {}", coarse_lag_steps);
+            assert_eq!(decodes[0].message, "CQ K1ABC FN42");
+            assert!((decodes[0].time_offset - 1.0).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn test_candidate_llrs_without_decoding() {
+        let tones = tx_cq("K1ABC", "FN42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1000.0, 0.2, 1.0)
+            .add_channel(Box::new(Awgn::new(-10.0, 6)))
+            .synthesize();
+        let rough = Candidate {
+            frequency: 1001.5,
+            time_offset: 0.24,
+            sync_power: 5.0,
+            sync_metric: SyncMetric::AllCostas,
+        };
+        let llrs = candidate_llrs(&signal, &rough).unwrap();
+        assert!((llrs.candidate.frequency - 1000.0).abs() < 0.5);
+        assert!((llrs.candidate.time_offset - 0.2).abs() < 0.02);
+        assert!(llrs.nsync >= MIN_NSYNC);
+        assert_eq!(llrs.s8.len(), NUM_SYMBOLS);
+        for llr in [&llrs.llra, &llrs.llrb, &llrs.llrc, &llrs.llrd] {
+            assert_eq!(llr.len(), ldpc::N);
+        }
+        assert!(ldpc::decode_hybrid(&llrs.llra, DecodeDepth::BpOnly).is_some());
+
+        // Also returned where nothing was transmitted
+        let empty = Candidate {
+            frequency: 2000.0,
+            ..rough
+        };
+        let noise = candidate_llrs(&signal, &empty).unwrap();
+        assert_eq!(noise.llra.len(), ldpc::N);
+        assert!(noise.nsync < MIN_NSYNC);
+    }
+
+    #[test]
+    fn test_strong_signal_stops_bp_early() {
+        // BP returns at the first iteration whose hard decisions pass
+        // every parity check and the CRC, far short of MAX_BP_ITERATIONS
+        for (snr, seed) in [(0.0, 1), (-8.0, 2), (-12.0, 3)] {
+            let signal = BandSynthesizer::default()
+                .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1000.0, 0.0, 1.0)
+                .add_channel(Box::new(Awgn::new(snr, seed)))
+                .synthesize();
+            let decodes = decode_ft8(&signal, &DecoderConfig::default(), None).unwrap();
+            assert_eq!(decodes.len(), 1, "{} dB", snr);
+            // 1 is the check of the channel hard decisions, before any
+            // message passing
+            assert_eq!(decodes[0].ldpc_iterations, 1, "{} dB", snr);
+        }
+    }
+
+    #[test]
+    fn test_confidence_bp_above_osd() {
+        let tones = tx_cq("K1ABC", "FN42").unwrap();
+        let decode = |channel: Option<Awgn>| {
+            let mut synth = BandSynthesizer::default();
+            synth.add_signal(&tones, 1000.0, 0.2, 1.0);
+            if let Some(channel) = channel {
+                synth.add_channel(Box::new(channel));
+            }
+            let decodes = decode_ft8(&synth.synthesize(), &DecoderConfig::default(), None).unwrap();
+            assert_eq!(decodes.len(), 1);
+            decodes[0].clone()
+        };
+
+        let clean = decode(None);
+        assert!(clean.ldpc_iterations > 0);
+        assert!(clean.confidence > 0.9, "{}", clean.confidence);
+        // A noise draw that only OSD decodes
+        let osd = decode(Some(Awgn::new(-20.0, 5)));
+        assert_eq!(osd.ldpc_iterations, 0);
+        assert!(
+            osd.confidence < 0.5 * clean.confidence,
+            "{}",
+            osd.confidence
+        );
+        assert!(osd.confidence > 0.0);
+
+        let max = MAX_HARD_ERRORS;
+        assert_eq!(decode_confidence(0, max, 1, 21), 1.0);
+        // Even a decode at the limit scores above 0
+        assert!((decode_confidence(max, max, 1, 21) - 1.0 / 37.0).abs() < 1e-6);
+        assert_eq!(decode_confidence(max + 1, max, 1, 21), 0.0);
+        assert!(decode_confidence(3, max, 2, 20) > decode_confidence(3, max, 0, 20));
+        assert!(decode_confidence(3, max, 2, 20) > decode_confidence(3, max, 2, 12));
+
+        // A tightened limit scales the hard-error factor to it
+        assert!((decode_confidence(10, 20, 1, 21) - 11.0 / 21.0).abs() < 1e-6);
+        assert!((decode_confidence(20, 20, 1, 21) - 1.0 / 21.0).abs() < 1e-6);
+        assert_eq!(decode_confidence(0, 0, 1, 21), 1.0);
+        assert_eq!(decode_confidence(1, 0, 1, 21), 0.0);
+    }
+
+    #[test]
+    fn test_phase_refinement_rescues_offset_candidates() {
+        // Candidates 0.9 Hz off, as fine sync is bypassed, at -21 dB
+        let tones = tx_cq("K1ABC", "FN42").unwrap();
+        let config = DecoderConfig::default();
+        let cache = CallsignHashCache::new();
+        let (mut plain, mut refined) = (Vec::new(), Vec::new());
+        for seed in 0..16 {
+            let f0 = 1000.0 + 7.3 * seed as f32;
+            let signal = BandSynthesizer::default()
+                .add_signal(&tones, f0, 0.0, 1.0)
+                .add_channel(Box::new(Awgn::new(-21.0, 500 + seed)))
+                .synthesize();
+            let downsampler = Downsampler::new(&signal).unwrap();
+            let candidate = Candidate {
+                frequency: f0 + 0.9,
+                time_offset: 0.0,
+                sync_power: 1.0,
+                sync_metric: SyncMetric::AllCostas,
+            };
+
+            let llrs = extract_symbols_all_llr_with(&downsampler, &candidate).unwrap();
+            let methods = [
+                (LlrMethod::Nsym1, &llrs.llra[..]),
+                (LlrMethod::Nsym2, &llrs.llrb[..]),
+                (LlrMethod::Nsym3, &llrs.llrc[..]),
+                (LlrMethod::Normalized, &llrs.llrd[..]),
+            ];
+            let decoded = best_decode(
+                &methods,
+                config.decode_depth,
+                config.decode_algo,
+                config.max_hard_errors,
+            );
+            if decoded.is_some() {
+                plain.push(seed);
+            }
+            let result = try_decode_refined(&signal, &downsampler, candidate, &config, &cache);
+            if let Ok(decode) = result.unwrap() {
+                // Rescued decodes were moved onto the signal
+                if decoded.is_none() {
+                    assert!((decode.frequency - f0).abs() < 0.2, "{}", decode.frequency);
+                }
+                refined.push(seed);
+            }
+        }
+        assert!(
+            plain.iter().all(|s| refined.contains(s)),
+            "{:?} {:?}",
+            plain,
+            refined
+        );
+        assert!(
+            refined.len() >= plain.len() + 3,
+            "{:?} {:?}",
+            plain,
+            refined
+        );
+
+        // Noise-free, the estimate lands on the signal
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1500.0, 0.0, 1.0)
+            .synthesize();
+        let candidate = Candidate {
+            frequency: 1500.6,
+            time_offset: 0.0,
+            sync_power: 1.0,
+            sync_metric: SyncMetric::AllCostas,
+        };
+        let error = estimate_frequency_from_phase(&signal, &candidate).unwrap();
+        assert!((error + 0.6).abs() < 0.05, "{}", error);
+    }
+
+    #[test]
+    fn test_auto_depth_matches_hybrid() {
+        let hybrid = DecoderConfig::default();
+        let auto = DecoderConfig {
+            decode_depth: DecodeDepth::Auto,
+            ..hybrid.clone()
+        };
+
+        // Six signals at -20 dB, one of them only decoded by OSD
+        let calls = ["K1ABC", "W9XYZ", "N0YPR", "G4ABC", "JA1XYZ", "VK2ABC"];
+        let mut synth = BandSynthesizer::default();
+        for (i, call) in calls.iter().enumerate() {
+            let tones = tx_cq(call, "FN42").unwrap();
+            synth.add_signal(&tones, 500.0 + 350.0 * i as f32, 0.1 * i as f32, 1.0);
+        }
+        let signal = synth
+            .add_channel(Box::new(Awgn::new(-20.0, 77)))
+            .synthesize();
+        let full = decode_ft8(&signal, &hybrid, None).unwrap();
+        assert_eq!(full.len(), 6);
+        assert!(full.iter().any(|d| d.ldpc_iterations == 0));
+        assert_eq!(decode_ft8(&signal, &auto, None).unwrap(), full);
+
+        // In the noise window where OSD finds a false decode, Auto leaves
+        // the weakly synced candidate to BP alone and never gets that far
+        let mut noise = vec![0.0; NMAX];
+        Awgn::new(0.0, 131).apply(&mut noise, 12000.0);
+        let likely_false = |config: &DecoderConfig| {
+            let result = decode_ft8_diagnostic(&noise, config).unwrap();
+            assert_eq!(result.decodes, vec![]);
+            result
+                .failed
+                .iter()
+                .filter(|f| f.stage == FailureStage::LikelyFalse)
+                .count()
+        };
+        assert!(likely_false(&hybrid) > 0);
+        assert_eq!(likely_false(&auto), 0);
+    }
+
+    #[test]
+    fn test_decodes_report_sync_metric() {
+        // The first signal starts 1.4 s before the window, losing its
+        // first Costas array
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1200.0, -1.9, 1.0)
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1600.0, 0.1, 1.0)
+            .add_channel(Box::new(Awgn::new(-8.0, 7)))
+            .synthesize();
+        let decodes = decode_ft8(&signal, &DecoderConfig::default(), None).unwrap();
+        let metric = |message: &str| {
+            decodes
+                .iter()
+                .find(|d| d.message == message)
+                .map(|d| d.sync_metric)
+        };
+        assert_eq!(metric("CQ K1ABC FN42"), Some(SyncMetric::CostasBc));
+        assert_eq!(metric("CQ W9XYZ EN37"), Some(SyncMetric::AllCostas));
+    }
+
+    #[test]
+    fn test_noise_only_decodes_nothing() {
+        let config = DecoderConfig::default();
+        assert_eq!(config.decode_depth, DecodeDepth::BpOsdHybrid);
+        let noise = |seed: u64| {
+            let mut noise = vec![0.0; NMAX];
+            Awgn::new(0.0, seed).apply(&mut noise, 12000.0);
+            noise
+        };
+        // Including windows where OSD finds a codeword with a valid CRC,
+        // which is then dropped for its weak sync and SNR
+        for seed in 100..160 {
+            assert_eq!(decode_ft8(&noise(seed), &config, None).unwrap(), vec![]);
+        }
+        let failed = decode_ft8_diagnostic(&noise(131), &config).unwrap().failed;
+        assert!(failed.iter().any(|f| f.stage == FailureStage::LikelyFalse));
+    }
+
+    #[test]
+    fn test_validate_input() {
+        let noise = |len: usize| -> Vec<f32> {
+            let mut signal = vec![0.0; len];
+            Awgn::new(0.0, 5).apply(&mut signal, 12000.0);
+            signal
+        };
+        assert_eq!(validate_input(&noise(NMAX)), Ok(()));
+
+        let err = validate_input(&noise(4 * NMAX)).unwrap_err();
+        assert_eq!(
+            err,
+            InputError::WrongLength {
+                got: 720_000,
+                expected: NMAX
+            }
+        );
+        assert!(err.to_string().contains("48000 Hz"), "{}", err);
+        assert!(matches!(
+            validate_input(&noise(NMAX - 100)),
+            Err(InputError::WrongLength { .. })
+        ));
+
+        assert_eq!(validate_input(&vec![0.0; NMAX]), Err(InputError::AllZero));
+
+        let mut bad = noise(NMAX);
+        bad[1234] = f32::NAN;
+        assert_eq!(validate_input(&bad), Err(InputError::NonFinite));
+
+        let clipped: Vec<f32> = noise(NMAX).iter().map(|x| x.clamp(-0.5, 0.5)).collect();
+        match validate_input(&clipped) {
+            Err(InputError::Clipped { fraction }) => assert!(fraction > 0.1),
+            other => panic!("{:?}", other),
+        }
+
+        let err = decode_ft8(&vec![0.0; NMAX], &DecoderConfig::default(), None).unwrap_err();
+        assert_eq!(err, DecodeError::Input(InputError::AllZero));
+        let err = decode_banded(&clipped, &[(200.0, 3000.0)], &DecoderConfig::default());
+        assert!(matches!(
+            err,
+            Err(DecodeError::Input(InputError::Clipped { .. }))
+        ));
+    }
+}
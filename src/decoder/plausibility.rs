@@ -0,0 +1,93 @@
+//! Field sanity checks for the optional false-decode filter
+
+use super::callsigns;
+use crate::message::grid::{validate_grid6, validate_grid_basic};
+use crate::message::{classify_callsign, CallsignKind, GridReport, MessageVariant};
+
+/// Whether every callsign and grid in a decoded message looks like one.
+///
+/// Hashed callsigns are taken on trust. Other callsigns must fit the
+/// standard encoding or be compound; a plain non-standard call is only
+/// accepted in the full-call field of an i3=4 message, the one field meant
+/// for it. Grids must be valid Maidenhead squares, with subsquare letters
+/// `A`–`X` for 6-character locators.
+pub(crate) fn is_plausible(variant: &MessageVariant) -> bool {
+    let full_call = match variant {
+        MessageVariant::NonStandardCall { full_call, .. } => Some(full_call.as_str()),
+        _ => None,
+    };
+    let calls_ok = callsigns(variant)
+        .filter(|call| !call.starts_with('<'))
+        .all(|call| match classify_callsign(call) {
+            CallsignKind::Standard | CallsignKind::Compound => true,
+            CallsignKind::NonStandard => Some(call) == full_call,
+            CallsignKind::Token | CallsignKind::Invalid => false,
+        });
+
+    let grid_ok = match variant {
+        MessageVariant::Standard { payload, .. }
+        | MessageVariant::EuVhfContestType2 { payload, .. }
+        | MessageVariant::NonStandardCall { payload, .. } => match payload {
+            GridReport::Grid(grid) => validate_grid_basic(grid),
+            _ => true,
+        },
+        MessageVariant::EuVhfContestType5 { grid6, .. } => validate_grid6(grid6),
+        _ => true,
+    };
+
+    calls_ok && grid_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::parse_message_variant;
+
+    fn standard(call1: &str, call2: &str, payload: GridReport) -> MessageVariant {
+        MessageVariant::Standard {
+            call1: call1.into(),
+            call1_suffix: false,
+            call2: call2.into(),
+            call2_suffix: false,
+            r_flag: false,
+            payload,
+        }
+    }
+
+    #[test]
+    fn test_plausibility() {
+        for text in [
+            "CQ K1ABC FN42",
+            "K1ABC W9XYZ R-09",
+            "CQ DX G4ABC/P IO91",
+            "<W9XYZ> PJ4/K1ABC RRR",
+            "W9XYZ YW18FIFA",
+            "TNX BOB 73 GL",
+            "123456789ABCDEF012",
+        ] {
+            let variant = parse_message_variant(text).unwrap();
+            assert!(is_plausible(&variant), "{}", text);
+        }
+
+        let grid = |g: &str| GridReport::Grid(g.into());
+        assert!(!is_plausible(&standard("CQ", "QQQQ", grid("FN42"))));
+        assert!(!is_plausible(&standard("K1ABC", "W9XYZ", grid("ZZ99"))));
+        assert!(!is_plausible(&standard(
+            "K1ABC",
+            "YW18FIFA",
+            GridReport::Blank
+        )));
+        assert!(is_plausible(&standard("<...>", "W9XYZ", GridReport::Rr73)));
+
+        let type5 = |grid6: &str| MessageVariant::EuVhfContestType5 {
+            call1: "PA3XYZ".into(),
+            call2: "G4ABC".into(),
+            r_flag: false,
+            rst: 59,
+            serial: 1,
+            grid6: grid6.into(),
+        };
+        assert!(is_plausible(&type5("IO91NP")));
+        assert!(!is_plausible(&type5("IO91ZZ")));
+    }
+}
@@ -0,0 +1,136 @@
+//! Alignment of audio to the 15 s UTC slots
+//!
+//! FT8 transmissions start 0.5 s after every quarter minute. A continuous
+//! receiver captures audio from a sound card with a timestamp for its
+//! first sample, and has to cut the stream into 15 s frames that start on
+//! slot boundaries before handing them to [`decode_ft8`](super::decode_ft8).
+
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::modulation::{SAMPLE_RATE, SLOT_DURATION};
+use crate::sync::NMAX;
+
+/// One 15 s frame of a continuous stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotFrame {
+    /// Slot boundary the frame belongs to
+    pub start: SystemTime,
+    /// Indices of the frame's [`NMAX`] samples in the stream
+    pub samples: Range<usize>,
+}
+
+/// The slot boundary nearest to `utc`.
+///
+/// Times before the Unix epoch are treated as the epoch.
+pub fn slot_start(utc: SystemTime) -> SystemTime {
+    let slot_ns = (SLOT_DURATION as u128) * 1_000_000_000;
+    let ns = utc
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let nearest = (ns + slot_ns / 2) / slot_ns * slot_ns;
+    UNIX_EPOCH + Duration::from_nanos(nearest as u64)
+}
+
+/// Every complete slot in a 12 kHz stream of `len` samples whose first
+/// sample was captured at `start`.
+///
+/// Frames begin at the sample nearest to each boundary. A slot that the
+/// stream misses by no more than `guard` at its beginning or end, as when
+/// capture started a moment late or a sound card runs slow, is still
+/// returned, moved to fit inside the stream; decodes in such a frame show
+/// a DT off by the amount it was moved.
+pub fn samples_for_slot(start: SystemTime, len: usize, guard: Duration) -> Vec<SlotFrame> {
+    let slot = Duration::from_secs(SLOT_DURATION as u64);
+    let guard = (guard.as_secs_f64() * SAMPLE_RATE as f64).round() as i64;
+    let mut boundary = slot_start(start);
+    if boundary > start {
+        boundary -= slot;
+    }
+
+    let mut frames = Vec::new();
+    if len < NMAX {
+        return frames;
+    }
+    loop {
+        let offset = match boundary.duration_since(start) {
+            Ok(after) => after.as_secs_f64(),
+            Err(before) => -before.duration().as_secs_f64(),
+        };
+        let first = (offset * SAMPLE_RATE as f64).round() as i64;
+        if first - guard > len as i64 - NMAX as i64 {
+            break;
+        }
+        let shifted = first.clamp(0, len as i64 - NMAX as i64);
+        if (shifted - first).abs() <= guard {
+            let shifted = shifted as usize;
+            frames.push(SlotFrame {
+                start: boundary,
+                samples: shifted..shifted + NMAX,
+            });
+        }
+        boundary += slot;
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: f64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs_f64(seconds)
+    }
+
+    #[test]
+    fn test_slot_start() {
+        let t0 = 1_700_000_010.0;
+        assert_eq!(slot_start(at(t0)), at(t0));
+        assert_eq!(slot_start(at(t0 + 7.4)), at(t0));
+        assert_eq!(slot_start(at(t0 + 7.6)), at(t0 + 15.0));
+        assert_eq!(slot_start(at(t0 - 0.2)), at(t0));
+        assert_eq!(slot_start(UNIX_EPOCH - Duration::from_secs(20)), UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_samples_for_slot() {
+        let t0 = 1_700_000_010.0;
+        let frames = |start: f64, seconds: f64, guard_ms: u64| {
+            let len = (seconds * 12_000.0).round() as usize;
+            samples_for_slot(at(start), len, Duration::from_millis(guard_ms))
+        };
+
+        // A stream starting 4 s before a boundary and running 40 s holds
+        // two slots, the first 48000 samples in
+        let two = frames(t0 - 4.0, 40.0, 0);
+        assert_eq!(two.len(), 2);
+        assert_eq!(two[0].start, at(t0));
+        assert_eq!(two[0].samples, 48_000..48_000 + NMAX);
+        assert_eq!(two[1].start, at(t0 + 15.0));
+        assert_eq!(two[1].samples, 228_000..228_000 + NMAX);
+
+        // Sub-sample offsets round to the nearest sample
+        let third = 1.0 / 36_000.0;
+        assert_eq!(frames(t0 - 1.0 - third, 20.0, 0)[0].samples.start, 12_000);
+        assert_eq!(
+            frames(t0 - 1.0 - 2.0 * third, 20.0, 0)[0].samples.start,
+            12_001
+        );
+
+        // Capture starting 50 ms late loses the slot unless the guard
+        // allows it, and then the frame starts at sample 0
+        assert!(frames(t0 + 0.05, 16.0, 0).is_empty());
+        let late = frames(t0 + 0.05, 16.0, 100);
+        assert_eq!(late.len(), 1);
+        assert_eq!(late[0].start, at(t0));
+        assert_eq!(late[0].samples, 0..NMAX);
+
+        // A stream ending 50 ms early
+        assert!(frames(t0 - 1.0, 15.95, 0).is_empty());
+        let early = frames(t0 - 1.0, 15.95, 100);
+        assert_eq!(early.len(), 1);
+        assert_eq!(early[0].samples, 11_400..11_400 + NMAX);
+        assert!(frames(t0, 14.95, 100).is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+//! Batch decoding of long recordings into reception reports
+//!
+//! A recording made over many FT8 periods is cut into 15 s windows aligned
+//! to the UTC slot boundaries, each window is decoded, and every decode
+//! that names its sender becomes a [`Spot`] of the kind uploaded to
+//! PSKReporter.
+
+use std::path::Path;
+
+use super::wav::read_wav;
+use super::{decode_ft8, DecodeError, DecodedMessage, DecoderConfig};
+use crate::message::{GridReport, MessageVariant};
+use crate::modulation::{SAMPLE_RATE, SLOT_DURATION};
+use crate::sync::NMAX;
+
+/// One station heard in one time slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spot {
+    /// Start of the 15 s slot, in seconds since the Unix epoch (UTC)
+    pub utc: u64,
+    /// Radio frequency of tone 0 in Hz: dial frequency plus audio offset
+    pub freq_rf: f64,
+    /// SNR in dB, rounded as WSJT-X reports it
+    pub snr: i32,
+    /// DT in seconds
+    pub dt: f32,
+    /// Callsign of the transmitting station
+    pub call: String,
+    /// Grid locator, when the message carried one
+    pub grid: Option<String>,
+}
+
+/// Decode a WAV recording that started at `start_utc` (seconds since the
+/// Unix epoch) with the receiver dialled to `dial_hz`.
+///
+/// The file is converted to 12 kHz mono as by [`decode_wav_file`](super::decode_wav_file).
+pub fn spot_recording(
+    path: impl AsRef<Path>,
+    start_utc: u64,
+    dial_hz: f64,
+    config: &DecoderConfig,
+) -> Result<Vec<Spot>, DecodeError> {
+    let samples = read_wav(path.as_ref())?;
+    spot_samples(&samples, start_utc, dial_hz, config)
+}
+
+/// [`spot_recording`] on samples already in memory.
+///
+/// Decoding starts at the first slot boundary at or after `start_utc`;
+/// a partial slot at the end of the recording is skipped.
+pub fn spot_samples(
+    samples: &[f32],
+    start_utc: u64,
+    dial_hz: f64,
+    config: &DecoderConfig,
+) -> Result<Vec<Spot>, DecodeError> {
+    let slot = SLOT_DURATION as u64;
+    let first_slot = start_utc.div_ceil(slot) * slot;
+    let mut offset = ((first_slot - start_utc) as f32 * SAMPLE_RATE) as usize;
+    let mut utc = first_slot;
+
+    let mut spots = Vec::new();
+    while offset + NMAX <= samples.len() {
+        let window = &samples[offset..offset + NMAX];
+        let silent = window.iter().all(|&x| x == 0.0);
+        if !silent {
+            for decode in decode_ft8(window, config, None)? {
+                if let Some(spot) = to_spot(&decode, utc, dial_hz) {
+                    spots.push(spot);
+                }
+            }
+        }
+        offset += NMAX;
+        utc += slot;
+    }
+    Ok(spots)
+}
+
+fn to_spot(decode: &DecodedMessage, utc: u64, dial_hz: f64) -> Option<Spot> {
+    let (call, grid) = sender(&decode.variant)?;
+    Some(Spot {
+        utc,
+        freq_rf: dial_hz + decode.frequency as f64,
+        snr: decode.snr_db.round() as i32,
+        dt: decode.time_offset,
+        call,
+        grid,
+    })
+}
+
+/// The transmitting station's callsign and grid, if the message names
+/// them. Unresolved hashed callsigns are not reportable.
+fn sender(variant: &MessageVariant) -> Option<(String, Option<String>)> {
+    let (call, grid) = match variant {
+        MessageVariant::FreeText { .. } | MessageVariant::Telemetry { .. } => return None,
+        MessageVariant::Standard { call2, payload, .. }
+        | MessageVariant::EuVhfContestType2 { call2, payload, .. } => {
+            let grid = match payload {
+                GridReport::Grid(grid) => Some(grid.clone()),
+                _ => None,
+            };
+            (call2, grid)
+        }
+        MessageVariant::EuVhfContestType5 { call2, grid6, .. } => (call2, Some(grid6.clone())),
+        MessageVariant::FieldDay { call2, .. } | MessageVariant::RttyRoundup { call2, .. } => {
+            (call2, None)
+        }
+        MessageVariant::Dxpedition { call3, .. } => (call3, None),
+        MessageVariant::NonStandardCall {
+            hashed_call,
+            full_call,
+            hashed_first,
+            cq,
+            ..
+        } => {
+            let call = if *cq || *hashed_first {
+                full_call
+            } else {
+                hashed_call
+            };
+            (call, None)
+        }
+    };
+
+    let call = call.trim_start_matches('<').trim_end_matches('>');
+    let reportable = !call.is_empty() && call != "..." && !call.starts_with("CQ");
+    reportable.then(|| (call.to_string(), grid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::Awgn;
+    use crate::message::{self, tx_cq, tx_report, CallsignHashCache};
+    use crate::modulation::BandSynthesizer;
+    use bitvec::prelude::*;
+
+    fn variant(text: &str) -> MessageVariant {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        let mut cache = CallsignHashCache::new();
+        message::encode(text, &mut bits[..77], &mut cache).unwrap();
+        message::decode_variant(&bits[..77], Some(&cache)).unwrap()
+    }
+
+    #[test]
+    fn test_sender() {
+        let cases = [
+            ("CQ K1ABC FN42", Some(("K1ABC", Some("FN42")))),
+            ("K1ABC W9XYZ EN37", Some(("W9XYZ", Some("EN37")))),
+            ("K1ABC W9XYZ R-09", Some(("W9XYZ", None))),
+            ("CQ G4ABC/P IO91", Some(("G4ABC", Some("IO91")))),
+            ("W9XYZ K1ABC R 17B EMA", Some(("K1ABC", None))),
+            ("CQ PJ4/K1ABC", Some(("PJ4/K1ABC", None))),
+            ("<W9XYZ> PJ4/K1ABC RRR", Some(("PJ4/K1ABC", None))),
+            ("PJ4/K1ABC <W9XYZ> 73", Some(("W9XYZ", None))),
+            ("TNX BOB 73 GL", None),
+        ];
+        for (text, expected) in cases {
+            let got = sender(&variant(text));
+            let expected = expected.map(|(c, g)| (c.to_string(), g.map(String::from)));
+            assert_eq!(got, expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_spot_samples_across_slots() {
+        let slot = |tones: &[u8], freq: f32| {
+            BandSynthesizer::default()
+                .add_signal(tones, freq, 0.1, 1.0)
+                .add_channel(Box::new(Awgn::new(-10.0, freq as u64)))
+                .synthesize()
+        };
+        // Recording starts 3 s before a slot boundary; the leading partial
+        // slot and the trailing 5 s are not decoded
+        let start_utc = 1_700_000_007;
+        let mut samples = vec![0.0f32; 3 * 12_000];
+        samples.extend(slot(&tx_cq("K1ABC", "FN42").unwrap(), 1200.0));
+        samples.extend(slot(
+            &tx_report("N0YPR", "K1ABC", -12, false).unwrap(),
+            800.0,
+        ));
+        samples.extend(vec![0.0f32; 5 * 12_000]);
+
+        let spots =
+            spot_samples(&samples, start_utc, 14_074_000.0, &DecoderConfig::default()).unwrap();
+        assert_eq!(spots.len(), 2, "{:?}", spots);
+
+        assert_eq!(spots[0].utc, 1_700_000_010);
+        assert_eq!(spots[0].call, "K1ABC");
+        assert_eq!(spots[0].grid.as_deref(), Some("FN42"));
+        assert!((spots[0].freq_rf - 14_075_200.0).abs() < 1.0);
+        assert!((spots[0].dt - 0.1).abs() < 0.02);
+
+        assert_eq!(spots[1].utc, 1_700_000_025);
+        assert_eq!(spots[1].call, "N0YPR");
+        assert_eq!(spots[1].grid, None);
+    }
+
+    #[test]
+    fn test_spot_recording_reads_wav() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1500.0, 0.0, 0.5)
+            .synthesize();
+        let path = std::env::temp_dir().join("rustyft8_spot_recording.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 12_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for &x in &signal {
+            writer.write_sample((x * 32767.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let spots = spot_recording(&path, 1_700_000_010, 7_074_000.0, &DecoderConfig::default());
+        std::fs::remove_file(&path).unwrap();
+        let spots = spots.unwrap();
+        assert_eq!(spots.len(), 1);
+        assert_eq!(spots[0].call, "W9XYZ");
+        assert_eq!(spots[0].utc, 1_700_000_010);
+    }
+}
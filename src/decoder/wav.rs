@@ -0,0 +1,141 @@
+//! WAV file input
+
+use std::path::Path;
+
+use super::{decode_converted, DecodeError, DecodedMessage, DecoderConfig};
+use crate::sync::to_12khz;
+
+/// Read a WAV file as 12 kHz mono samples scaled to ±1.
+///
+/// Integer formats of any width are divided by their full-scale value;
+/// float files are taken unchanged. Multi-channel files are mixed down by
+/// averaging the channels, and other sample rates are resampled to 12 kHz
+/// with [`to_12khz`].
+pub(crate) fn read_wav(path: &Path) -> Result<Vec<f32>, DecodeError> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| DecodeError::Io(format!("Cannot open {}: {}", path.display(), e)))?;
+    let spec = reader.spec();
+    if spec.channels == 0 || spec.sample_rate == 0 {
+        return Err(DecodeError::UnsupportedFormat(format!(
+            "{} has {} channels at {} Hz",
+            path.display(),
+            spec.channels,
+            spec.sample_rate
+        )));
+    }
+
+    let samples: Result<Vec<f32>, hound::Error> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect(),
+        hound::SampleFormat::Int => {
+            let full_scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / full_scale))
+                .collect()
+        }
+    };
+    let samples =
+        samples.map_err(|e| DecodeError::Io(format!("Cannot read {}: {}", path.display(), e)))?;
+
+    let channels = spec.channels as usize;
+    let mono: Vec<f32> = if channels == 1 {
+        samples
+    } else {
+        samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    };
+
+    Ok(to_12khz(&mono, spec.sample_rate))
+}
+
+/// Decode a WAV recording of one 15 s FT8 window.
+///
+/// The file may be mono or multi-channel, at any sample rate (WSJT-X
+/// saves 12 kHz; sound cards commonly record at 48 or 24 kHz), and in
+/// 8- to 32-bit integer or float PCM. It is converted to 12 kHz mono, cut
+/// or zero-padded to 15 s and checked with
+/// [`validate_input`](super::validate_input) before decoding, so an
+/// unusable file is reported rather than decoded as noise.
+pub fn decode_wav_file(
+    path: &Path,
+    config: &DecoderConfig,
+) -> Result<Vec<DecodedMessage>, DecodeError> {
+    let signal = read_wav(path)?;
+    decode_converted(signal, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::tx_cq;
+    use crate::modulation::BandSynthesizer;
+
+    fn write_wav(name: &str, spec: hound::WavSpec, samples: &[f32]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        let full_scale = (1i64 << (spec.bits_per_sample - 1)) as f32 - 1.0;
+        for &x in samples {
+            for _ in 0..spec.channels {
+                let v = (x * full_scale) as i32;
+                match spec.bits_per_sample {
+                    8 => writer.write_sample(v as i8).unwrap(),
+                    16 => writer.write_sample(v as i16).unwrap(),
+                    _ => writer.write_sample(v).unwrap(),
+                }
+            }
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    fn spec(channels: u16, sample_rate: u32, bits_per_sample: u16) -> hound::WavSpec {
+        hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample,
+            sample_format: hound::SampleFormat::Int,
+        }
+    }
+
+    #[test]
+    fn test_decode_wav_file_formats() {
+        let tones = tx_cq("W9XYZ", "EN37").unwrap();
+        for (name, rate, channels, bits) in [
+            ("rustyft8_48k_stereo_24bit.wav", 48_000, 2, 24),
+            ("rustyft8_24k_mono_8bit.wav", 24_000, 1, 8),
+        ] {
+            let mut signal = BandSynthesizer::new(rate as f32)
+                .add_signal(&tones, 1500.0, 0.0, 0.5)
+                .synthesize();
+            // A little short of 15 s, as a recording started late may be
+            signal.truncate(signal.len() * 29 / 30);
+            let path = write_wav(name, spec(channels, rate, bits), &signal);
+
+            let decodes = decode_wav_file(&path, &DecoderConfig::default());
+            std::fs::remove_file(&path).unwrap();
+            let decodes = decodes.unwrap();
+            assert_eq!(decodes.len(), 1, "{}", name);
+            assert_eq!(decodes[0].message, "CQ W9XYZ EN37", "{}", name);
+            assert!((decodes[0].frequency - 1500.0).abs() < 1.0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_decode_wav_file_errors() {
+        let missing = std::env::temp_dir().join("rustyft8_no_such_file.wav");
+        assert!(matches!(
+            decode_wav_file(&missing, &DecoderConfig::default()),
+            Err(DecodeError::Io(_))
+        ));
+
+        let path = write_wav("rustyft8_silent.wav", spec(1, 12_000, 16), &[0.0; 1000]);
+        let result = decode_wav_file(&path, &DecoderConfig::default());
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(DecodeError::Input(super::super::InputError::AllZero))
+        ));
+    }
+}
@@ -0,0 +1,68 @@
+//! Decode lines in the format WSJT-X shows in its Band Activity window
+//!
+//! Tools that tail WSJT-X output parse lines like
+//! `123015 -10  0.2 1500 ~  CQ N0YPR DM42`: UTC, SNR, DT, audio frequency
+//! and message in fixed-width columns.
+
+use alloc::format;
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::DecodedMessage;
+
+/// Format a decode as a WSJT-X decode line.
+///
+/// `utc` is the start of the slot in seconds since the Unix epoch and
+/// becomes the `HHMMSS` column; without it the column is `000000`. SNR and
+/// frequency are rounded to whole numbers, DT to a tenth of a second.
+pub fn format_wsjtx(m: &DecodedMessage, utc: Option<u64>) -> String {
+    let seconds = utc.map_or(0, |t| t % 86_400);
+    format!(
+        "{:02}{:02}{:02} {:3} {:4.1} {:4} ~  {}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        m.snr_db.round() as i32,
+        m.time_offset,
+        m.frequency.round() as i32,
+        m.message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::tests::decoded;
+
+    #[test]
+    fn test_format_wsjtx_columns() {
+        let mut m = decoded("CQ N0YPR DM42");
+        m.snr_db = -10.2;
+        m.time_offset = 0.24;
+        m.frequency = 1500.4;
+        assert_eq!(
+            format_wsjtx(&m, None),
+            "000000 -10  0.2 1500 ~  CQ N0YPR DM42"
+        );
+
+        // 2023-11-14 22:13:15 UTC
+        let mut m = decoded("K1ABC W9XYZ R-12");
+        m.snr_db = 3.0;
+        m.time_offset = -0.06;
+        m.frequency = 987.0;
+        assert_eq!(
+            format_wsjtx(&m, Some(1_700_000_000 - 5)),
+            "221315   3 -0.1  987 ~  K1ABC W9XYZ R-12"
+        );
+
+        let mut m = decoded("W9XYZ K1ABC -21");
+        m.snr_db = -24.0;
+        m.time_offset = 1.5;
+        m.frequency = 2875.0;
+        assert_eq!(
+            format_wsjtx(&m, Some(1_700_000_000 - 1_700_000_000 % 86_400)),
+            "000000 -24  1.5 2875 ~  W9XYZ K1ABC -21"
+        );
+    }
+}
@@ -0,0 +1,410 @@
+//! FFTs used by the signal-processing layers
+//!
+//! With `std`, transforms are computed with RustFFT, which handles any
+//! length (mixed radix), so the spectrogram can use the same 3840-point
+//! FFT as WSJT-X. Plans are kept in a per-thread [`FftCache`] and reused
+//! by every later transform of the same length.
+//!
+//! Without `std` a built-in transform is used instead: radix-2 for
+//! power-of-two lengths and Bluestein's algorithm for the rest. Its plans
+//! are made for each transform, as there is no thread-local storage to
+//! keep them in.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use num_complex::Complex32;
+#[cfg(feature = "std")]
+use rustfft::{Fft, FftDirection, FftPlanner};
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+/// FFT plans keyed by length and direction.
+#[cfg(feature = "std")]
+pub struct FftCache {
+    planner: FftPlanner<f32>,
+    plans: HashMap<(usize, bool), Arc<dyn Fft<f32>>>,
+}
+
+#[cfg(feature = "std")]
+impl Default for FftCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl FftCache {
+    /// An empty cache; plans are made as lengths are first used.
+    pub fn new() -> Self {
+        Self {
+            planner: FftPlanner::new(),
+            plans: HashMap::new(),
+        }
+    }
+
+    /// The plan for a transform of `len` points, planned on first use.
+    pub fn plan(&mut self, len: usize, inverse: bool) -> Arc<dyn Fft<f32>> {
+        let planner = &mut self.planner;
+        self.plans
+            .entry((len, inverse))
+            .or_insert_with(|| {
+                let direction = if inverse {
+                    FftDirection::Inverse
+                } else {
+                    FftDirection::Forward
+                };
+                planner.plan_fft(len, direction)
+            })
+            .clone()
+    }
+
+    /// Number of distinct plans made so far.
+    pub fn len(&self) -> usize {
+        self.plans.len()
+    }
+
+    /// Whether no plan has been made yet.
+    pub fn is_empty(&self) -> bool {
+        self.plans.is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static FFT_CACHE: RefCell<FftCache> = RefCell::new(FftCache::new());
+}
+
+/// A transform of one length and direction, ready to run.
+#[derive(Clone)]
+pub(crate) struct Plan {
+    #[cfg(feature = "std")]
+    inner: Arc<dyn Fft<f32>>,
+    #[cfg(not(feature = "std"))]
+    inner: builtin::Plan,
+}
+
+impl Plan {
+    /// Plan a transform of `len` points; from this thread's cache with
+    /// `std`.
+    pub(crate) fn new(len: usize, inverse: bool) -> Self {
+        #[cfg(feature = "std")]
+        let inner = FFT_CACHE.with(|cache| cache.borrow_mut().plan(len, inverse));
+        #[cfg(not(feature = "std"))]
+        let inner = builtin::Plan::new(len, inverse);
+        Self { inner }
+    }
+
+    /// Transform `data` in place; its length must be the planned one.
+    pub(crate) fn process(&self, data: &mut [Complex32]) {
+        self.inner.process(data);
+    }
+}
+
+/// Run `data` through a plan for its length.
+fn transform(data: &mut [Complex32], inverse: bool) {
+    if data.len() <= 1 {
+        return;
+    }
+    Plan::new(data.len(), inverse).process(data);
+}
+
+/// In-place forward FFT (`e^{-j2πkn/N}` kernel, unnormalized).
+pub(crate) fn fft(data: &mut [Complex32]) {
+    transform(data, false);
+}
+
+/// In-place inverse FFT (unnormalized: the result is scaled by `N`).
+pub(crate) fn ifft(data: &mut [Complex32]) {
+    transform(data, true);
+}
+
+/// The transform used without `std`.
+#[cfg(any(test, not(feature = "std")))]
+mod builtin {
+    use alloc::boxed::Box;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::f64::consts::PI;
+    use num_complex::Complex32;
+    #[cfg(not(feature = "std"))]
+    use num_traits::Float;
+
+    /// `e^{-j2πk/n}` for `k < n/2`, computed in double precision so the
+    /// large transforms stay accurate.
+    fn twiddles(n: usize) -> Vec<Complex32> {
+        (0..n / 2)
+            .map(|k| {
+                let (s, c) = (-2.0 * PI * k as f64 / n as f64).sin_cos();
+                Complex32::new(c as f32, s as f32)
+            })
+            .collect()
+    }
+
+    /// Iterative radix-2 Cooley–Tukey forward transform, with the
+    /// twiddles of `data.len()`.
+    fn radix2(data: &mut [Complex32], twiddles: &[Complex32]) {
+        let n = data.len();
+        let shift = usize::BITS - n.trailing_zeros();
+        for i in 0..n {
+            let j = i.reverse_bits() >> shift;
+            if j > i {
+                data.swap(i, j);
+            }
+        }
+        let mut len = 2;
+        while len <= n {
+            let half = len / 2;
+            let stride = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..half {
+                    let a = data[start + k];
+                    let b = data[start + k + half] * twiddles[k * stride];
+                    data[start + k] = a + b;
+                    data[start + k + half] = a - b;
+                }
+            }
+            len <<= 1;
+        }
+    }
+
+    /// Bluestein's algorithm: a length-`n` transform as a convolution of
+    /// power-of-two length.
+    #[derive(Clone)]
+    struct Bluestein {
+        /// `e^{-jπk²/n}`
+        chirp: Vec<Complex32>,
+        /// Transform of the conjugate chirp, wrapped to the convolution
+        /// length
+        kernel: Vec<Complex32>,
+        /// Twiddles of the convolution length
+        twiddles: Vec<Complex32>,
+    }
+
+    impl Bluestein {
+        fn new(n: usize) -> Self {
+            let m = (2 * n - 1).next_power_of_two();
+            let chirp: Vec<Complex32> = (0..n as u64)
+                .map(|k| {
+                    // k² mod 2n keeps the angle small
+                    let phase = (k * k % (2 * n as u64)) as f64;
+                    let (s, c) = (-PI * phase / n as f64).sin_cos();
+                    Complex32::new(c as f32, s as f32)
+                })
+                .collect();
+            let twiddles = twiddles(m);
+            let mut kernel = vec![Complex32::new(0.0, 0.0); m];
+            kernel[0] = chirp[0].conj();
+            for k in 1..n {
+                kernel[k] = chirp[k].conj();
+                kernel[m - k] = chirp[k].conj();
+            }
+            radix2(&mut kernel, &twiddles);
+            Self {
+                chirp,
+                kernel,
+                twiddles,
+            }
+        }
+
+        fn forward(&self, data: &mut [Complex32]) {
+            let m = self.kernel.len();
+            let mut work = vec![Complex32::new(0.0, 0.0); m];
+            for ((w, x), c) in work.iter_mut().zip(data.iter()).zip(&self.chirp) {
+                *w = x * c;
+            }
+            radix2(&mut work, &self.twiddles);
+            // Inverse transform of the product by conjugating around a
+            // forward one
+            for (w, k) in work.iter_mut().zip(&self.kernel) {
+                *w = (*w * k).conj();
+            }
+            radix2(&mut work, &self.twiddles);
+            let scale = 1.0 / m as f32;
+            for ((x, w), c) in data.iter_mut().zip(&work).zip(&self.chirp) {
+                *x = w.conj() * c * scale;
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    enum Kind {
+        Radix2(Vec<Complex32>),
+        Bluestein(Box<Bluestein>),
+    }
+
+    /// A built-in transform of one length and direction.
+    #[derive(Clone)]
+    pub(super) struct Plan {
+        len: usize,
+        inverse: bool,
+        kind: Kind,
+    }
+
+    impl Plan {
+        pub(super) fn new(len: usize, inverse: bool) -> Self {
+            let kind = if len.is_power_of_two() {
+                Kind::Radix2(twiddles(len))
+            } else {
+                Kind::Bluestein(Box::new(Bluestein::new(len)))
+            };
+            Self { len, inverse, kind }
+        }
+
+        pub(super) fn process(&self, data: &mut [Complex32]) {
+            assert_eq!(data.len(), self.len, "FFT length does not match its plan");
+            if self.len <= 1 {
+                return;
+            }
+            // The inverse is the conjugate of the forward transform of the
+            // conjugate
+            if self.inverse {
+                data.iter_mut().for_each(|x| *x = x.conj());
+            }
+            match &self.kind {
+                Kind::Radix2(twiddles) => radix2(data, twiddles),
+                Kind::Bluestein(b) => b.forward(data),
+            }
+            if self.inverse {
+                data.iter_mut().for_each(|x| *x = x.conj());
+            }
+        }
+    }
+}
+
+/// Analytic signal `x + j·H{x}` of a real signal via the FFT.
+pub(crate) fn analytic_signal(signal: &[f32]) -> Vec<Complex32> {
+    let nfft = signal.len().next_power_of_two();
+    let mut buf = vec![Complex32::new(0.0, 0.0); nfft];
+    for (b, &s) in buf.iter_mut().zip(signal.iter()) {
+        b.re = s;
+    }
+    fft(&mut buf);
+    // Keep DC and Nyquist, double positive and zero negative frequencies
+    for c in buf[1..nfft / 2].iter_mut() {
+        *c *= 2.0;
+    }
+    for c in buf[nfft / 2 + 1..].iter_mut() {
+        *c = Complex32::new(0.0, 0.0);
+    }
+    ifft(&mut buf);
+    buf.truncate(signal.len());
+    let scale = 1.0 / nfft as f32;
+    buf.iter_mut().for_each(|c| *c *= scale);
+    buf
+}
+
+/// Power spectrum `|X[k]|²` of a real signal, zero-padded to `nfft`.
+///
+/// Returns the `nfft / 2 + 1` non-negative frequency bins.
+pub(crate) fn real_power_spectrum(signal: &[f32], nfft: usize) -> Vec<f32> {
+    let mut buf = vec![Complex32::new(0.0, 0.0); nfft];
+    for (b, &s) in buf.iter_mut().zip(signal.iter()) {
+        b.re = s;
+    }
+    fft(&mut buf);
+    buf[..nfft / 2 + 1].iter().map(|c| c.norm_sqr()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_builtin_matches_rustfft() {
+        // Radix-2 and Bluestein lengths, both directions
+        for n in [4096, 3840, 1152, 100] {
+            let input: Vec<Complex32> = (0..n)
+                .map(|i| {
+                    let phase = 2.0 * PI * 437.3 * i as f32 / n as f32;
+                    Complex32::new(phase.sin(), (0.3 * phase).cos())
+                })
+                .collect();
+            for inverse in [false, true] {
+                let mut expected = input.clone();
+                transform(&mut expected, inverse);
+                let mut built_in = input.clone();
+                builtin::Plan::new(n, inverse).process(&mut built_in);
+                let peak = expected.iter().fold(0.0f32, |m, c| m.max(c.norm()));
+                for (k, (a, b)) in built_in.iter().zip(&expected).enumerate() {
+                    assert!((a - b).norm() < 1e-4 * peak, "n {} bin {}", n, k);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_mixed_radix_length_and_plan_reuse() {
+        // 3840 = 2^8 · 3 · 5: a 3.125 Hz bin spacing at 12 kHz
+        let n = 3840;
+        let signal: Vec<f32> = (0..n)
+            .map(|i| (2.0 * PI * 1500.0 * i as f32 / 12000.0).cos())
+            .collect();
+        let power = real_power_spectrum(&signal, n);
+        let peak = (0..power.len())
+            .max_by(|&a, &b| power[a].total_cmp(&power[b]))
+            .unwrap();
+        assert_eq!(peak, 480);
+        assert!((power[479] / power[480]) < 1e-6);
+
+        // Public as sync::fft::FftCache
+        let mut cache = crate::sync::fft::FftCache::new();
+        let a = cache.plan(n, false);
+        let b = cache.plan(n, false);
+        assert!(Arc::ptr_eq(&a, &b));
+        cache.plan(n, true);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_single_tone_bin() {
+        let n = 256;
+        let signal: Vec<f32> = (0..n)
+            .map(|i| (2.0 * PI * 10.0 * i as f32 / n as f32).cos())
+            .collect();
+        let power = real_power_spectrum(&signal, n);
+        let peak = power
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .unwrap()
+            .0;
+        assert_eq!(peak, 10);
+        assert!((power[10] - (n as f32 / 2.0).powi(2)).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_matches_dft() {
+        let n = 64;
+        let input: Vec<Complex32> = (0..n)
+            .map(|i| Complex32::new((i as f32 * 0.37).sin(), (i as f32 * 0.11).cos()))
+            .collect();
+        let mut data = input.clone();
+        fft(&mut data);
+        for (k, x) in data.iter().enumerate() {
+            let dft: Complex32 = input
+                .iter()
+                .enumerate()
+                .map(|(i, v)| v * Complex32::from_polar(1.0, -2.0 * PI * (i * k) as f32 / n as f32))
+                .sum();
+            assert!((x - dft).norm() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_analytic_signal() {
+        let n = 512;
+        let signal: Vec<f32> = (0..n)
+            .map(|i| (2.0 * PI * 20.0 * i as f32 / n as f32).cos())
+            .collect();
+        let analytic = analytic_signal(&signal);
+        for (i, z) in analytic.iter().enumerate() {
+            let expected = Complex32::from_polar(1.0, 2.0 * PI * 20.0 * i as f32 / n as f32);
+            assert!((z - expected).norm() < 1e-3);
+        }
+    }
+}
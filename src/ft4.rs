@@ -0,0 +1,300 @@
+//! FT4: the faster contest mode, sharing FT8's message layers
+//!
+//! FT4 carries the same 77-bit messages with the same CRC and LDPC(174,91)
+//! code as FT8, but sends them as 4-GFSK at 20.833 baud in 7.5 s periods.
+//! Before the CRC is added, the message bits are XORed with a fixed
+//! scrambling vector so that common messages do not produce long runs of
+//! one tone. The 174 codeword bits then go two per symbol (Gray coded) into
+//! 87 data symbols, split into three blocks of 29 by four 4-symbol Costas
+//! arrays, for 103 channel symbols; the modulator adds a ramp symbol at
+//! each end.
+
+use bitvec::prelude::*;
+
+use crate::message::{self, CallsignHashCache, MessageError, MESSAGE_BITS};
+use crate::{crc, ldpc};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The four Costas synchronization arrays, in transmission order
+pub const COSTAS_ARRAYS: [[u8; 4]; 4] = [[0, 1, 3, 2], [1, 0, 2, 3], [2, 3, 1, 0], [3, 2, 0, 1]];
+
+/// Symbol index of each of the four Costas arrays
+pub const COSTAS_POSITIONS: [usize; 4] = [0, 33, 66, 99];
+
+/// Gray code: 2-bit value → tone number
+pub const GRAY_MAP: [u8; 4] = [0, 1, 3, 2];
+
+/// Total number of channel symbols, excluding the two ramp symbols
+pub const NUM_SYMBOLS: usize = 103;
+
+/// Number of data (non-sync) symbols
+pub const NUM_DATA_SYMBOLS: usize = 87;
+
+/// Scrambling vector XORed with the 77 message bits before the CRC
+pub const SCRAMBLE_BITS: [u8; MESSAGE_BITS] = [
+    0, 1, 0, 0, 1, 0, 1, 0, 0, 1, 0, 1, 1, 1, 1, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 0, 1, 0, 0,
+    1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1,
+    1, 0, 1, 1, 1, 1, 1, 0, 0, 0, 1, 0, 1,
+];
+
+/// Smallest number of the 16 Costas symbols whose strongest tone must be
+/// the expected one before a candidate is decoded
+const MIN_NSYNC: usize = 6;
+
+/// Channel symbol index of data symbol `k` (0..87).
+pub const fn data_symbol_index(k: usize) -> usize {
+    k + 4 * (k / 29 + 1)
+}
+
+/// XOR 77 message bits with [`SCRAMBLE_BITS`]; the same call undoes it.
+fn scramble(message77: &BitSlice<u8, Msb0>, out: &mut BitSlice<u8, Msb0>) {
+    for (i, &r) in SCRAMBLE_BITS.iter().enumerate() {
+        out.set(i, message77[i] ^ (r == 1));
+    }
+}
+
+/// Map a 174-bit codeword onto the 103 channel tones.
+fn codeword_to_symbols(codeword: &BitSlice<u8, Msb0>) -> [u8; NUM_SYMBOLS] {
+    let mut tones = [0u8; NUM_SYMBOLS];
+
+    for (&pos, costas) in COSTAS_POSITIONS.iter().zip(&COSTAS_ARRAYS) {
+        tones[pos..pos + 4].copy_from_slice(costas);
+    }
+
+    for k in 0..NUM_DATA_SYMBOLS {
+        let value: u8 = codeword[2 * k..2 * k + 2].load_be();
+        tones[data_symbol_index(k)] = GRAY_MAP[value as usize];
+    }
+
+    tones
+}
+
+/// Generate the 103 FT4 channel tones for a packed 77-bit message.
+///
+/// Runs scrambling → CRC → LDPC → Gray mapping → Costas insertion.
+pub(crate) fn channel_symbols_from_bits(message77: &BitSlice<u8, Msb0>) -> [u8; NUM_SYMBOLS] {
+    let mut scrambled = bitarr![u8, Msb0; 0; 80];
+    scramble(message77, &mut scrambled[..MESSAGE_BITS]);
+
+    let mut message91 = bitarr![u8, Msb0; 0; 96];
+    crc::append_crc(&scrambled[..MESSAGE_BITS], &mut message91[..ldpc::K]);
+
+    let mut codeword = bitarr![u8, Msb0; 0; 176];
+    ldpc::encode(&message91[..ldpc::K], &mut codeword[..ldpc::N]);
+
+    codeword_to_symbols(&codeword[..ldpc::N])
+}
+
+/// The 103 FT4 channel tones for message text, the FT4 counterpart of
+/// [`symbol::channel_symbols`](crate::symbol::channel_symbols).
+pub fn channel_symbols(
+    text: &str,
+    cache: &mut CallsignHashCache,
+) -> Result<[u8; NUM_SYMBOLS], MessageError> {
+    let mut bits = bitarr![u8, Msb0; 0; 80];
+    message::encode(text, &mut bits[..MESSAGE_BITS], cache)?;
+    Ok(channel_symbols_from_bits(&bits[..MESSAGE_BITS]))
+}
+
+/// Encode message text as an FT4 waveform at 12 kHz with tone 0 at `f0`
+/// Hz.
+///
+/// Returns the 105 symbols (5.04 s) of unit-amplitude audio, ramp symbols
+/// included, to be transmitted from 0.5 s into a 7.5 s period.
+pub fn encode_waveform(text: &str, f0: f32) -> Result<Vec<f32>, MessageError> {
+    use crate::modulation::{Modulator, SAMPLE_RATE};
+
+    let tones = channel_symbols(text, &mut CallsignHashCache::new())?;
+    Ok(Modulator::ft4(SAMPLE_RATE).modulate(&tones, f0))
+}
+
+/// A message decoded by [`decode`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ft4Message {
+    /// Message text, with hashed callsigns shown as `<...>`
+    pub message: String,
+    /// Frequency of tone 0 in Hz
+    pub frequency: f32,
+    /// DT in seconds relative to the nominal 0.5 s start
+    pub time_offset: f32,
+    /// SNR in dB in a 2500 Hz reference bandwidth
+    pub snr_db: f32,
+    /// Received hard decisions that disagree with the decoded codeword
+    pub nharderrors: usize,
+}
+
+/// Decode a 7.5 s FT4 receive period of 12 kHz audio.
+///
+/// Candidates from a Costas search between 100 and 3000 Hz and DT −0.5
+/// to +1.0 s are refined, demodulated with single-symbol soft metrics and
+/// decoded with BP and OSD. Duplicates keep the first decode; the result
+/// is ordered by frequency.
+pub fn decode(signal: &[f32]) -> Vec<Ft4Message> {
+    use crate::sync::ft4::{ft4_candidates, ft4_llrs, ft4_refine, ft4_snr};
+
+    let mut decodes: Vec<Ft4Message> = Vec::new();
+    for candidate in ft4_candidates(signal) {
+        let refined = ft4_refine(signal, &candidate);
+        let (llr, s4) = ft4_llrs(signal, &refined);
+        if count_sync(&s4) < MIN_NSYNC {
+            continue;
+        }
+        let Some((message91, _, nharderrors)) =
+            ldpc::decode_hybrid(&llr, ldpc::DecodeDepth::BpOsdHybrid)
+        else {
+            continue;
+        };
+        if message91.not_any() {
+            continue;
+        }
+
+        let mut message77 = bitarr![u8, Msb0; 0; 80];
+        scramble(&message91[..MESSAGE_BITS], &mut message77[..MESSAGE_BITS]);
+        let Ok(text) = message::decode(&message77[..MESSAGE_BITS], None) else {
+            continue;
+        };
+        if decodes.iter().any(|d| d.message == text) {
+            continue;
+        }
+        let tones = channel_symbols_from_bits(&message77[..MESSAGE_BITS]);
+        decodes.push(Ft4Message {
+            message: text,
+            frequency: refined.frequency,
+            time_offset: refined.time_offset,
+            snr_db: ft4_snr(&s4, &tones),
+            nharderrors,
+        });
+    }
+    decodes.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+    decodes
+}
+
+/// Count Costas symbols whose strongest tone is the expected one.
+fn count_sync(s4: &[[f32; 4]]) -> usize {
+    COSTAS_POSITIONS
+        .iter()
+        .zip(&COSTAS_ARRAYS)
+        .flat_map(|(&pos, costas)| {
+            costas.iter().enumerate().map(move |(k, &tone)| {
+                let row = &s4[pos + k];
+                let best = (0..4).fold(0, |b, t| if row[t] > row[b] { t } else { b });
+                best == tone as usize
+            })
+        })
+        .filter(|&ok| ok)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_symbol_index_skips_sync() {
+        assert_eq!(data_symbol_index(0), 4);
+        assert_eq!(data_symbol_index(28), 32);
+        assert_eq!(data_symbol_index(29), 37);
+        assert_eq!(data_symbol_index(58), 70);
+        assert_eq!(data_symbol_index(86), 98);
+    }
+
+    #[test]
+    fn test_scrambled_zero_codeword() {
+        // A message equal to the scrambling vector scrambles to zero, so
+        // its CRC and codeword are zero and every data tone is 0
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        for (i, &r) in SCRAMBLE_BITS.iter().enumerate() {
+            bits.set(i, r == 1);
+        }
+        let tones = channel_symbols_from_bits(&bits[..MESSAGE_BITS]);
+        let expected: Vec<u8> = (0..NUM_SYMBOLS)
+            .map(|i| {
+                match COSTAS_POSITIONS
+                    .iter()
+                    .position(|&p| (p..p + 4).contains(&i))
+                {
+                    Some(a) => COSTAS_ARRAYS[a][i - COSTAS_POSITIONS[a]],
+                    None => 0,
+                }
+            })
+            .collect();
+        assert_eq!(tones.to_vec(), expected);
+
+        // The all-zero message is not sent as all-zero tones
+        let zero = channel_symbols_from_bits(&bitarr![u8, Msb0; 0; 80][..MESSAGE_BITS]);
+        assert!((0..NUM_DATA_SYMBOLS).any(|k| zero[data_symbol_index(k)] != 0));
+    }
+
+    #[test]
+    fn test_channel_symbols_share_ft8_codeword() {
+        // FT4 and FT8 carry the same codeword for the same scrambled bits
+        let text = "CQ K1ABC FN42";
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        message::encode(text, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+        let mut scrambled = bitarr![u8, Msb0; 0; 80];
+        scramble(&bits[..77], &mut scrambled[..77]);
+        let ft8 = crate::symbol::channel_symbols_from_bits(&scrambled[..77]);
+        let ft4 = channel_symbols(text, &mut CallsignHashCache::new()).unwrap();
+
+        let mut ft8_bits = bitvec![u8, Msb0;];
+        for k in 0..crate::symbol::NUM_DATA_SYMBOLS {
+            let tone = ft8[crate::symbol::data_symbol_index(k)];
+            let value = crate::symbol::gray::to_index(tone);
+            ft8_bits.extend_from_bitslice(&value.view_bits::<Msb0>()[5..]);
+        }
+        for k in 0..NUM_DATA_SYMBOLS {
+            let tone = ft4[data_symbol_index(k)];
+            let value = GRAY_MAP.iter().position(|&t| t == tone).unwrap();
+            assert_eq!(value >> 1 == 1, ft8_bits[2 * k], "symbol {}", k);
+            assert_eq!(value & 1 == 1, ft8_bits[2 * k + 1], "symbol {}", k);
+        }
+    }
+
+    #[test]
+    fn test_decode_band() {
+        use crate::channel::{Awgn, ChannelModel};
+        use crate::modulation::{FT4_SLOT_DURATION, NOMINAL_START, SAMPLE_RATE};
+
+        let len = (FT4_SLOT_DURATION * SAMPLE_RATE) as usize;
+        let mut band = vec![0.0f32; len];
+        for (text, f0, dt, amplitude) in [
+            ("CQ K1ABC FN42", 1000.0, 0.0, 0.5),
+            ("W9XYZ K1ABC R-12", 1517.0, 0.3, 0.3),
+            ("<PJ4/K1ABC> W9XYZ RR73", 2200.0, -0.2, 0.2),
+        ] {
+            let wave = encode_waveform(text, f0).unwrap();
+            assert_eq!(wave.len(), 105 * 576);
+            let start = ((NOMINAL_START + dt) * SAMPLE_RATE) as usize;
+            for (b, w) in band[start..].iter_mut().zip(&wave) {
+                *b += amplitude * w;
+            }
+        }
+        Awgn::new(5.0, 5).apply(&mut band, SAMPLE_RATE);
+
+        let decodes = decode(&band);
+        let found: Vec<&str> = decodes.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            found,
+            ["CQ K1ABC FN42", "W9XYZ K1ABC R-12", "<...> W9XYZ RR73"]
+        );
+        for (d, (f0, dt)) in decodes
+            .iter()
+            .zip([(1000.0, 0.0), (1517.0, 0.3), (2200.0, -0.2)])
+        {
+            assert!((d.frequency - f0).abs() < 2.0, "{:?}", d);
+            assert!((d.time_offset - dt).abs() < 0.01, "{:?}", d);
+        }
+    }
+
+    #[test]
+    fn test_decode_noise() {
+        use crate::channel::{Awgn, ChannelModel};
+
+        let mut band = vec![0.0f32; 90_000];
+        Awgn::new(0.0, 9).apply(&mut band, 12_000.0);
+        assert!(decode(&band).is_empty());
+        assert!(decode(&[]).is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+//! Belief propagation decoder
+//!
+//! Port of WSJT-X `bpdecode174_91`, with a min-sum check-node update as
+//! an alternative to its sum-product one. LLRs are positive for a `1` bit.
+
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::parity::{MN, NM, NRW};
+use super::{syndrome_weight, DecodeAlgo, K, M, N};
+use crate::crc;
+
+/// Hard decisions of the a-posteriori LLRs
+fn hard_decision(zn: &[f32; N]) -> BitArray<[u8; 22], Msb0> {
+    let mut cw = BitArray::<[u8; 22], Msb0>::ZERO;
+    for (i, &z) in zn.iter().enumerate() {
+        cw.set(i, z > 0.0);
+    }
+    cw
+}
+
+/// Clamped inverse hyperbolic tangent, as in WSJT-X `platanh`.
+fn platanh(x: f32) -> f32 {
+    let z = x.abs();
+    if z <= 0.664 {
+        x / 0.83
+    } else if z <= 0.9217 {
+        x.signum() * (z - 0.4064) / 0.322
+    } else if z <= 0.9951 {
+        x.signum() * (z - 0.8378) / 0.0524
+    } else if z <= 0.9998 {
+        x.signum() * (z - 0.9914) / 0.0012
+    } else {
+        x.signum() * 7.0
+    }
+}
+
+/// Number of BP iterations whose accumulated LLRs are kept for OSD
+pub(crate) const SNAPSHOT_ITERATIONS: usize = 3;
+
+/// Run belief propagation on `llr` with the check-node update of `algo`.
+///
+/// Bits with `apmask` set are treated as known and never updated. When
+/// `snapshots` is given, the running sum of the a-posteriori LLRs after
+/// each of the first [`SNAPSHOT_ITERATIONS`] iterations is pushed to it for
+/// later OSD reprocessing.
+///
+/// Returns the 91 payload bits and the 1-based iteration at which a valid
+/// codeword with a correct CRC was found.
+pub(crate) fn bp_decode(
+    llr: &[f32],
+    apmask: Option<&[bool]>,
+    max_iterations: usize,
+    algo: DecodeAlgo,
+    mut snapshots: Option<&mut Vec<[f32; N]>>,
+) -> Option<(BitVec<u8, Msb0>, usize)> {
+    let masked = |i: usize| apmask.is_some_and(|m| m[i]);
+
+    // Check-to-bit messages (tov) and the bit-to-check messages, kept as
+    // tanh(-toc / 2) for sum-product and unchanged for min-sum
+    let mut tov = [[0.0f32; 3]; N];
+    let mut tanhtoc = [[0.0f32; 7]; M];
+    let mut zn = [0.0f32; N];
+    let mut zsum = [0.0f32; N];
+
+    for iter in 0..=max_iterations {
+        for i in 0..N {
+            zn[i] = if masked(i) {
+                llr[i]
+            } else {
+                llr[i] + tov[i].iter().sum::<f32>()
+            };
+            zsum[i] += zn[i];
+        }
+        if let Some(snaps) = snapshots.as_deref_mut() {
+            if iter >= 1 && snaps.len() < SNAPSHOT_ITERATIONS {
+                snaps.push(zsum);
+            }
+        }
+
+        let cw = hard_decision(&zn);
+        if syndrome_weight(&cw[..N]) == Some(0) && crc::verify(&cw[..K]) {
+            return Some((cw[..K].to_bitvec(), iter + 1));
+        }
+
+        // Check-node update
+        for (m, row) in NM.iter().enumerate() {
+            let bits = &row[..NRW[m] as usize];
+            for (j, &n) in bits.iter().enumerate() {
+                let n = n as usize;
+                let k = MN[n].iter().position(|&c| c as usize == m).unwrap();
+                let toc = zn[n] - tov[n][k];
+                tanhtoc[m][j] = match algo {
+                    DecodeAlgo::SumProduct => (-toc / 2.0).tanh(),
+                    DecodeAlgo::MinSum { .. } => toc,
+                };
+            }
+        }
+
+        // Bit-node update
+        for (n, checks) in MN.iter().enumerate() {
+            for (k, &m) in checks.iter().enumerate() {
+                let m = m as usize;
+                let row = &NM[m][..NRW[m] as usize];
+                let others = row
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &b)| b as usize != n)
+                    .map(|(j, _)| tanhtoc[m][j]);
+                tov[n][k] = match algo {
+                    DecodeAlgo::SumProduct => 2.0 * platanh(-others.product::<f32>()),
+                    // The sign sum-product would give, and the least
+                    // reliable incoming message as its magnitude
+                    DecodeAlgo::MinSum { scale } => {
+                        let (sign, min) = others.fold((-1.0f32, f32::MAX), |(s, min), toc| {
+                            (s * -toc.signum(), min.min(toc.abs()))
+                        });
+                        scale * sign * min
+                    }
+                };
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crc::append_crc;
+    use crate::ldpc::encode;
+
+    fn test_codeword() -> BitArray<[u8; 22], Msb0> {
+        let mut msg = bitarr![u8, Msb0; 0; 80];
+        for i in (0..77).step_by(7) {
+            msg.set(i, true);
+        }
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        append_crc(&msg[..77], &mut m91[..K]);
+        let mut cw = BitArray::<[u8; 22], Msb0>::ZERO;
+        encode(&m91[..K], &mut cw[..N]);
+        cw
+    }
+
+    #[test]
+    fn test_bp_corrects_errors() {
+        let cw = test_codeword();
+        let mut llr: Vec<f32> = cw[..N]
+            .iter()
+            .map(|b| if *b { 2.0 } else { -2.0 })
+            .collect();
+        for i in [3, 50, 91, 120, 170] {
+            llr[i] = -llr[i] * 0.5;
+        }
+        for algo in [DecodeAlgo::SumProduct, DecodeAlgo::MinSum { scale: 0.8 }] {
+            let (decoded, iters) = bp_decode(&llr, None, 30, algo, None).expect("should decode");
+            assert_eq!(decoded, cw[..K].to_bitvec(), "{:?}", algo);
+            assert!(iters > 1);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Gallager bit-flipping decoder on hard decisions
+
+use bitvec::prelude::*;
+
+use super::parity::MN;
+use super::{parity_check, syndrome_weight, K, M, N};
+use crate::crc;
+
+/// Whether parity check `m` fails on `cw`
+fn check_fails(cw: &BitSlice<u8, Msb0>, m: usize) -> bool {
+    parity_check(m)
+        .iter()
+        .fold(false, |acc, &n| acc ^ cw[n as usize])
+}
+
+/// Correct `codeword` by flipping bits until every parity check passes.
+///
+/// Each iteration counts, for every bit, how many of its three checks
+/// fail, and flips together all the bits with the highest count. Flipping
+/// one bit at a time instead can cycle between the same few words when
+/// three bits are wrong. Returns the 91 payload bits once all checks pass
+/// and the CRC matches, or `None` if that has not happened after
+/// `max_iterations` rounds of flips.
+pub(crate) fn flip_decode(
+    codeword: &BitSlice<u8, Msb0>,
+    max_iterations: usize,
+) -> Option<BitVec<u8, Msb0>> {
+    let mut cw = codeword[..N].to_bitvec();
+    let mut failed = [false; M];
+    for (m, f) in failed.iter_mut().enumerate() {
+        *f = check_fails(&cw, m);
+    }
+
+    for _ in 0..max_iterations {
+        if !failed.contains(&true) {
+            break;
+        }
+        let mut counts = [0u8; N];
+        for (count, checks) in counts.iter_mut().zip(&MN) {
+            *count = checks.iter().filter(|&&m| failed[m as usize]).count() as u8;
+        }
+        let most = counts.iter().copied().max().unwrap_or(0);
+        for n in (0..N).filter(|&n| counts[n] == most) {
+            let flipped = !cw[n];
+            cw.set(n, flipped);
+            for &m in &MN[n] {
+                failed[m as usize] = !failed[m as usize];
+            }
+        }
+    }
+
+    (syndrome_weight(&cw) == Some(0) && crc::verify(&cw[..K])).then(|| cw[..K].to_bitvec())
+}
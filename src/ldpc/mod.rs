@@ -0,0 +1,597 @@
+//! LDPC(174,91) forward error correction
+//!
+//! FT8 protects the 91-bit payload (77 message bits + 14-bit CRC) with a
+//! systematic (174,91) low-density parity-check code. The codeword is the
+//! 91 payload bits followed by 83 parity bits. [`encode_detailed`] returns
+//! the codeword split into those segments, and [`parity_check`] lists the
+//! bits of each check, for following the encoder by hand.
+//!
+//! Decoding takes 174 log-likelihood ratios, positive for a `1` bit, and
+//! runs belief propagation optionally followed by ordered-statistics
+//! decoding. BP uses sum-product by default or min-sum ([`DecodeAlgo`]).
+//! [`decode_hard`] instead bit-flips a hard-decision word, for
+//! loopback tests and clean channels.
+
+mod bp;
+mod flip;
+mod generator;
+mod osd;
+mod parity;
+
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+
+use generator::GENERATOR;
+use parity::{NM, NRW};
+
+use crate::crc::CRC_WIDTH;
+
+/// Codeword length in bits
+pub const N: usize = 174;
+
+/// Payload length in bits (77 message + 14 CRC)
+pub const K: usize = 91;
+
+/// Number of parity checks
+pub const M: usize = N - K;
+
+/// Encode a 91-bit payload into a 174-bit codeword.
+///
+/// The first 91 bits of `codeword` receive the payload unchanged; the
+/// remaining 83 bits are the parity bits computed from the WSJT-X generator
+/// matrix.
+pub fn encode(message91: &BitSlice<u8, Msb0>, codeword: &mut BitSlice<u8, Msb0>) {
+    assert!(message91.len() >= K, "LDPC input must hold 91 bits");
+    assert!(codeword.len() >= N, "LDPC output must hold 174 bits");
+
+    codeword[..K].copy_from_bitslice(&message91[..K]);
+
+    for (i, row) in GENERATOR.iter().enumerate() {
+        let row_bits = row.view_bits::<Msb0>();
+        let parity = row_bits[..K]
+            .iter_ones()
+            .fold(false, |acc, j| acc ^ message91[j]);
+        codeword.set(K + i, parity);
+    }
+}
+
+/// The segments of an encoded codeword, for inspecting each stage of the
+/// encoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodewordParts {
+    /// The 77 message bits
+    pub message: BitVec<u8, Msb0>,
+    /// The 14 CRC bits
+    pub crc: BitVec<u8, Msb0>,
+    /// The 83 parity bits
+    pub parity: BitVec<u8, Msb0>,
+    /// The whole 174-bit codeword: message, CRC, then parity
+    pub codeword: BitVec<u8, Msb0>,
+}
+
+/// [`encode`] a 91-bit payload, returning the codeword split into its
+/// message, CRC and parity bits.
+///
+/// The payload is usually built with
+/// [`message::payload`](crate::message::payload); each parity bit can be
+/// checked by hand against [`parity_check`].
+pub fn encode_detailed(message91: &BitSlice<u8, Msb0>) -> CodewordParts {
+    let mut codeword = bitvec![u8, Msb0; 0; N];
+    encode(message91, &mut codeword);
+    CodewordParts {
+        message: codeword[..K - CRC_WIDTH].to_bitvec(),
+        crc: codeword[K - CRC_WIDTH..K].to_bitvec(),
+        parity: codeword[K..].to_bitvec(),
+        codeword,
+    }
+}
+
+/// The codeword bits taking part in parity check `m`, which every valid
+/// codeword satisfies with an even number of ones.
+///
+/// Panics if `m` is not below [`M`].
+pub fn parity_check(m: usize) -> &'static [u8] {
+    &NM[m][..NRW[m] as usize]
+}
+
+/// Number of parity checks a 174-bit word fails, or `None` if `cw` holds
+/// fewer than 174 bits.
+///
+/// Zero for every valid codeword; each flipped bit changes the result of
+/// the three checks it participates in, so the weight is a cheap measure of
+/// how far a hard-decision word is from the code before running BP.
+pub fn syndrome_weight(cw: &BitSlice<u8, Msb0>) -> Option<usize> {
+    if cw.len() < N {
+        return None;
+    }
+    Some(
+        NM.iter()
+            .zip(NRW)
+            .filter(|(row, nrw)| {
+                row[..*nrw as usize]
+                    .iter()
+                    .fold(false, |acc, &n| acc ^ cw[n as usize])
+            })
+            .count(),
+    )
+}
+
+/// Maximum belief-propagation iterations
+pub const MAX_BP_ITERATIONS: usize = 30;
+
+/// Rounds of flips [`decode_hard`] makes before giving up
+const MAX_FLIP_ITERATIONS: usize = 20;
+
+/// OSD search order used after BP fails
+const OSD_ORDER: usize = 2;
+
+/// How hard the LDPC decoder tries before giving up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DecodeDepth {
+    /// Belief propagation only
+    BpOnly,
+    /// Belief propagation, then OSD on the channel LLRs
+    BpOsdUncoupled,
+    /// Belief propagation, then OSD on the channel LLRs and on the
+    /// accumulated BP LLRs of the first few iterations
+    #[default]
+    BpOsdHybrid,
+    /// One of the above per candidate, by how many of its Costas symbols
+    /// were received correctly ([`for_nsync`](Self::for_nsync)): OSD is
+    /// only spent on candidates in sync well enough to be worth it. Where
+    /// no candidate is known, as in [`decode_hybrid`], the same as
+    /// `BpOsdHybrid`
+    Auto,
+}
+
+/// [`DecodeDepth::Auto`] runs OSD on candidates with at least this many
+/// of the 21 Costas symbols correct
+const AUTO_OSD_NSYNC: usize = 10;
+
+/// ... and hybrid OSD on candidates with at least this many
+const AUTO_HYBRID_NSYNC: usize = 13;
+
+impl DecodeDepth {
+    /// The depth to decode a candidate at, given the number of its 21
+    /// Costas symbols received on the expected tone. `Auto` picks
+    /// `BpOnly` below 10, `BpOsdUncoupled` below 13 and `BpOsdHybrid`
+    /// from there; the other depths are returned unchanged.
+    pub fn for_nsync(self, nsync: usize) -> DecodeDepth {
+        match self {
+            DecodeDepth::Auto if nsync < AUTO_OSD_NSYNC => DecodeDepth::BpOnly,
+            DecodeDepth::Auto if nsync < AUTO_HYBRID_NSYNC => DecodeDepth::BpOsdUncoupled,
+            DecodeDepth::Auto => DecodeDepth::BpOsdHybrid,
+            depth => depth,
+        }
+    }
+}
+
+/// Check-node update used by belief propagation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DecodeAlgo {
+    /// Sum-product, as WSJT-X: exact message combination through `tanh`
+    /// and its inverse
+    #[default]
+    SumProduct,
+    /// Normalized min-sum: each check-to-bit message is the least
+    /// reliable of the other incoming messages, times `scale` (about 0.8)
+    /// to offset the overestimate. No transcendental functions, so faster
+    /// and suited to fixed point, for a small loss in sensitivity
+    MinSum { scale: f32 },
+}
+
+/// Count received hard decisions that disagree with the codeword of a
+/// decoded payload.
+///
+/// A genuine decode typically has few such errors; a large count marks a
+/// likely false decode.
+pub fn compute_nharderrors(message91: &BitSlice<u8, Msb0>, llr: &[f32]) -> usize {
+    let mut cw = bitarr![u8, Msb0; 0; 176];
+    encode(message91, &mut cw[..N]);
+    cw[..N]
+        .iter()
+        .zip(llr)
+        .filter(|(bit, &l)| **bit != (l > 0.0))
+        .count()
+}
+
+/// Decode a 174-bit hard-decision word with Gallager bit-flipping.
+///
+/// Much cheaper than [`decode_hybrid`] and needs no LLRs, but corrects
+/// only a few bit errors: enough for loopback tests and clean channels.
+/// Returns the 91-bit payload once every parity check passes and the CRC
+/// matches, or `None` if that takes more than 20 rounds of flips or
+/// `codeword174` holds fewer than 174 bits.
+pub fn decode_hard(codeword174: &BitSlice<u8, Msb0>) -> Option<BitVec<u8, Msb0>> {
+    if codeword174.len() < N {
+        return None;
+    }
+    flip::flip_decode(codeword174, MAX_FLIP_ITERATIONS)
+}
+
+/// Magnitude of the LLRs built by [`corrupt_and_decode`]
+const SATURATED_LLR: f32 = 4.0;
+
+/// Encode `message91`, flip `bit_errors` distinct codeword bits chosen
+/// by `seed`, and decode the result as LLRs of equal magnitude at
+/// [`DecodeDepth::BpOsdHybrid`].
+///
+/// True only if the original payload comes back. The LLRs carry no
+/// reliability information, so this measures what the code itself can
+/// correct from hard decisions; run it over many seeds and error counts
+/// to chart decode probability.
+pub fn corrupt_and_decode(message91: &BitSlice<u8, Msb0>, bit_errors: usize, seed: u64) -> bool {
+    assert!(bit_errors <= N, "cannot flip more than 174 bits");
+    let mut cw = bitarr![u8, Msb0; 0; 176];
+    encode(message91, &mut cw[..N]);
+    let mut llr: Vec<f32> = cw[..N]
+        .iter()
+        .map(|b| if *b { SATURATED_LLR } else { -SATURATED_LLR })
+        .collect();
+
+    let mut rng = crate::channel::Rng::new(seed);
+    let mut flipped = 0;
+    while flipped < bit_errors {
+        let i = (rng.next_u64() % N as u64) as usize;
+        if (llr[i] > 0.0) == cw[i] {
+            llr[i] = -llr[i];
+            flipped += 1;
+        }
+    }
+
+    decode_hybrid(&llr, DecodeDepth::BpOsdHybrid)
+        .is_some_and(|(decoded, _, _)| decoded[..] == message91[..K])
+}
+
+/// Decode 174 LLRs at the given depth.
+///
+/// Returns the 91-bit payload (CRC verified), the number of BP iterations
+/// (0 when OSD produced the result) and the hard-error count.
+pub fn decode_hybrid(llr: &[f32], depth: DecodeDepth) -> Option<(BitVec<u8, Msb0>, usize, usize)> {
+    decode_hybrid_with_ap(llr, None, depth)
+}
+
+/// [`decode_hybrid`] with a-priori bits: positions where `apmask` is set
+/// carry known values in `llr`. They are held fixed during BP, and OSD
+/// takes them as known when choosing and searching its information set.
+/// At [`DecodeDepth::BpOsdHybrid`] the BP snapshots are reprocessed just
+/// as without a mask.
+pub fn decode_hybrid_with_ap(
+    llr: &[f32],
+    apmask: Option<&[bool]>,
+    depth: DecodeDepth,
+) -> Option<(BitVec<u8, Msb0>, usize, usize)> {
+    decode_hybrid_with_algo(llr, apmask, depth, DecodeAlgo::SumProduct)
+}
+
+/// [`decode_hybrid_with_ap`] with the belief propagation run by `algo`.
+/// OSD is unaffected.
+pub fn decode_hybrid_with_algo(
+    llr: &[f32],
+    apmask: Option<&[bool]>,
+    depth: DecodeDepth,
+    algo: DecodeAlgo,
+) -> Option<(BitVec<u8, Msb0>, usize, usize)> {
+    assert_eq!(llr.len(), N, "LDPC decoder needs 174 LLRs");
+
+    let mut snapshots = Vec::new();
+    let want_snapshots = matches!(depth, DecodeDepth::BpOsdHybrid | DecodeDepth::Auto);
+    let bp = bp::bp_decode(
+        llr,
+        apmask,
+        MAX_BP_ITERATIONS,
+        algo,
+        want_snapshots.then_some(&mut snapshots),
+    );
+    if let Some((message91, iters)) = bp {
+        let nhard = compute_nharderrors(&message91, llr);
+        return Some((message91, iters, nhard));
+    }
+
+    if depth == DecodeDepth::BpOnly {
+        return None;
+    }
+
+    core::iter::once(llr)
+        .chain(snapshots.iter().map(|s| &s[..]))
+        .find_map(|z| osd::osd_decode(z, apmask, OSD_ORDER))
+        .map(|message91| {
+            let nhard = compute_nharderrors(&message91, llr);
+            (message91, 0, nhard)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_message_encodes_to_zero_codeword() {
+        let msg = bitarr![u8, Msb0; 0; 96];
+        let mut cw = bitarr![u8, Msb0; 1; 176];
+        encode(&msg[..K], &mut cw[..N]);
+        assert!(cw[..N].not_any());
+    }
+
+    #[test]
+    fn test_encode_is_systematic() {
+        let mut msg = bitarr![u8, Msb0; 0; 96];
+        for i in (0..K).step_by(5) {
+            msg.set(i, true);
+        }
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        encode(&msg[..K], &mut cw[..N]);
+        assert_eq!(&cw[..K], &msg[..K]);
+    }
+
+    #[test]
+    fn test_first_message_bit_selects_generator_column() {
+        // Message bit 0 alone produces parity equal to column 0 of the generator
+        let mut msg = bitarr![u8, Msb0; 0; 96];
+        msg.set(0, true);
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        encode(&msg[..K], &mut cw[..N]);
+        for (i, row) in GENERATOR.iter().enumerate() {
+            assert_eq!(cw[K + i], row[0] & 0x80 != 0, "parity bit {}", i);
+        }
+    }
+
+    #[test]
+    fn test_auto_depth_for_nsync() {
+        assert_eq!(DecodeDepth::Auto.for_nsync(9), DecodeDepth::BpOnly);
+        assert_eq!(DecodeDepth::Auto.for_nsync(10), DecodeDepth::BpOsdUncoupled);
+        assert_eq!(DecodeDepth::Auto.for_nsync(13), DecodeDepth::BpOsdHybrid);
+        assert_eq!(DecodeDepth::BpOnly.for_nsync(21), DecodeDepth::BpOnly);
+        assert_eq!(
+            DecodeDepth::BpOsdHybrid.for_nsync(0),
+            DecodeDepth::BpOsdHybrid
+        );
+    }
+
+    #[test]
+    fn test_syndrome_weight() {
+        let mut msg = bitarr![u8, Msb0; 0; 96];
+        for i in (0..77).step_by(7) {
+            msg.set(i, true);
+        }
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        crate::crc::append_crc(&msg[..77], &mut m91[..K]);
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        encode(&m91[..K], &mut cw[..N]);
+        assert_eq!(syndrome_weight(&cw[..N]), Some(0));
+
+        // A single error fails the three checks on that bit
+        let flipped = !cw[100];
+        cw.set(100, flipped);
+        assert_eq!(syndrome_weight(&cw[..N]), Some(3));
+        assert_eq!(syndrome_weight(&cw[..N - 1]), None);
+    }
+
+    #[test]
+    fn test_random_payloads_encode_to_codewords() {
+        // Arbitrary payloads, CRC not applied: this checks the generator
+        // matrix against the parity checks, independent of the message layer
+        let mut rng = crate::channel::Rng::new(91);
+        for _ in 0..200 {
+            let mut msg = bitarr![u8, Msb0; 0; 96];
+            for i in 0..K {
+                msg.set(i, rng.next_u64() & 1 == 1);
+            }
+            let mut cw = bitarr![u8, Msb0; 0; 176];
+            encode(&msg[..K], &mut cw[..N]);
+            assert_eq!(syndrome_weight(&cw[..N]), Some(0), "{}", msg);
+
+            for i in K..N {
+                let flipped = !cw[i];
+                cw.set(i, flipped);
+                assert_ne!(syndrome_weight(&cw[..N]), Some(0), "parity bit {}", i);
+                cw.set(i, !flipped);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_detailed_satisfies_every_check() {
+        use crate::message::{encode_to_vec, payload, CallsignHashCache};
+
+        let bits = encode_to_vec("K1ABC W9XYZ R-09", &mut CallsignHashCache::new()).unwrap();
+        let parts = encode_detailed(&payload(&bits));
+        assert_eq!(parts.message, bits);
+        assert_eq!(parts.crc.load_be::<u16>(), crate::crc::compute(&bits));
+        assert_eq!(parts.parity.len(), M);
+        let mut joined = parts.message.clone();
+        joined.extend_from_bitslice(&parts.crc);
+        joined.extend_from_bitslice(&parts.parity);
+        assert_eq!(joined, parts.codeword);
+
+        let violations = (0..M)
+            .filter(|&m| {
+                parity_check(m)
+                    .iter()
+                    .fold(false, |acc, &n| acc ^ parts.codeword[n as usize])
+            })
+            .count();
+        assert_eq!(violations, 0);
+    }
+
+    /// The payload of a random 77-bit message drawn from `seed`.
+    fn random_payload(seed: u64) -> BitVec<u8, Msb0> {
+        let mut rng = crate::channel::Rng::new(seed);
+        let msg: BitVec<u8, Msb0> = (0..77).map(|_| rng.next_u64() & 1 == 1).collect();
+        crate::message::payload(&msg)
+    }
+
+    #[test]
+    fn test_decode_hard() {
+        let m91 = random_payload(174);
+        let mut rng = crate::channel::Rng::new(175);
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        encode(&m91[..K], &mut cw[..N]);
+        assert_eq!(decode_hard(&cw[..N]), Some(m91[..K].to_bitvec()));
+        assert_eq!(decode_hard(&cw[..N - 1]), None);
+
+        let with_flips = |count: usize, rng: &mut crate::channel::Rng| {
+            let mut received = cw;
+            let mut flipped = 0;
+            while flipped < count {
+                let i = (rng.next_u64() % N as u64) as usize;
+                if received[i] == cw[i] {
+                    let bit = !received[i];
+                    received.set(i, bit);
+                    flipped += 1;
+                }
+            }
+            decode_hard(&received[..N])
+        };
+        for count in 1..=3 {
+            for _ in 0..100 {
+                assert_eq!(
+                    with_flips(count, &mut rng),
+                    Some(m91[..K].to_bitvec()),
+                    "{} flips",
+                    count
+                );
+            }
+        }
+        for _ in 0..100 {
+            assert_eq!(with_flips(40, &mut rng), None);
+        }
+    }
+
+    #[test]
+    fn test_corrupt_and_decode_margin() {
+        let m91 = random_payload(1548);
+        let recovered = |errors: usize| {
+            (0..200)
+                .filter(|&seed| corrupt_and_decode(&m91[..K], errors, seed))
+                .count()
+        };
+        // Measured: every seed up to 12 errors, 142 at 15, 5 at 20, none at 25
+        assert_eq!(recovered(12), 200);
+        let at_15 = recovered(15);
+        assert!(at_15 >= 130, "{}", at_15);
+        let at_20 = recovered(20);
+        assert!(at_20 < 10, "{}", at_20);
+    }
+
+    fn noisy_llrs(m91: &BitSlice<u8, Msb0>, flips: &[usize]) -> Vec<f32> {
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        encode(m91, &mut cw[..N]);
+        let mut llr: Vec<f32> = cw[..N]
+            .iter()
+            .map(|b| if *b { 2.5 } else { -2.5 })
+            .collect();
+        for &i in flips {
+            llr[i] = -llr[i] * 0.3;
+        }
+        llr
+    }
+
+    #[test]
+    fn test_min_sum_close_to_sum_product() {
+        use crate::message::{encode_to_vec, payload, CallsignHashCache};
+
+        // BPSK codewords through AWGN near the BP threshold
+        let texts = ["CQ K1ABC FN42", "K1ABC W9XYZ -12", "W9XYZ K1ABC RR73"];
+        let mut corpus = Vec::new();
+        let mut rng = crate::channel::Rng::new(1550);
+        for (i, sigma) in [0.75f32, 0.8, 0.85].into_iter().enumerate() {
+            for _ in 0..100 {
+                let bits = encode_to_vec(texts[i], &mut CallsignHashCache::new()).unwrap();
+                let m91 = payload(&bits);
+                let parts = encode_detailed(&m91);
+                let llr: Vec<f32> = parts
+                    .codeword
+                    .iter()
+                    .map(|b| {
+                        let x = if *b { 1.0 } else { -1.0 };
+                        2.0 * (x + sigma * rng.gaussian()) / (sigma * sigma)
+                    })
+                    .collect();
+                corpus.push((m91, llr));
+            }
+        }
+        let decoded = |algo| {
+            corpus
+                .iter()
+                .filter(|(m91, llr)| {
+                    decode_hybrid_with_algo(llr, None, DecodeDepth::BpOnly, algo)
+                        .is_some_and(|d| d.0 == *m91)
+                })
+                .count() as i32
+        };
+
+        // Measured 179 and 184 of 300; min-sum ran in a third of the time
+        let sum_product = decoded(DecodeAlgo::SumProduct);
+        let min_sum = decoded(DecodeAlgo::MinSum { scale: 0.8 });
+        assert!(sum_product > 150 && sum_product < 250, "{}", sum_product);
+        assert!(
+            (min_sum - sum_product).abs() <= 15,
+            "{} vs {}",
+            min_sum,
+            sum_product
+        );
+        // Unscaled min-sum overestimates its messages and loses decodes
+        assert!(decoded(DecodeAlgo::MinSum { scale: 1.0 }) < min_sum);
+    }
+
+    #[test]
+    fn test_decode_hybrid_depths() {
+        let mut msg = bitarr![u8, Msb0; 0; 96];
+        for i in (2..77).step_by(3) {
+            msg.set(i, true);
+        }
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        crate::crc::append_crc(&msg[..77], &mut m91[..K]);
+
+        let llr = noisy_llrs(&m91[..K], &[1, 40, 99, 150]);
+        let (decoded, iters, nhard) = decode_hybrid(&llr, DecodeDepth::BpOnly).unwrap();
+        assert_eq!(decoded, m91[..K].to_bitvec());
+        assert!(iters >= 1);
+        assert_eq!(nhard, 4);
+
+        assert_eq!(compute_nharderrors(&m91[..K], &llr), 4);
+    }
+
+    #[test]
+    fn test_hybrid_snapshots_with_ap() {
+        use crate::message::{encode as encode_message, CallsignHashCache};
+
+        let mut msg = bitarr![u8, Msb0; 0; 80];
+        encode_message(
+            "CQ K1ABC FN42",
+            &mut msg[..77],
+            &mut CallsignHashCache::new(),
+        )
+        .unwrap();
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        crate::crc::append_crc(&msg[..77], &mut m91[..K]);
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        encode(&m91[..K], &mut cw[..N]);
+        let sign = |i: usize| if cw[i] { 1.0 } else { -1.0 };
+
+        // A noise draw that only the snapshot OSD passes close
+        let sigma = 0.9f32;
+        let mut rng = crate::channel::Rng::new(66);
+        let llr: Vec<f32> = (0..N)
+            .map(|i| 2.0 * (sign(i) + sigma * rng.gaussian()) / (sigma * sigma))
+            .collect();
+        assert!(decode_hybrid(&llr, DecodeDepth::BpOnly).is_none());
+        assert!(decode_hybrid(&llr, DecodeDepth::BpOsdUncoupled).is_none());
+        let (decoded, iters, _) = decode_hybrid(&llr, DecodeDepth::BpOsdHybrid).unwrap();
+        assert_eq!((decoded, iters), (m91[..K].to_bitvec(), 0));
+
+        // The CQ field and i3 known, as a-priori decoding supplies them
+        let apmask: Vec<bool> = (0..N).map(|i| i < 29 || (74..77).contains(&i)).collect();
+        let apmag = 1.01 * llr.iter().fold(0.0f32, |m, l| m.max(l.abs()));
+        let ap_llr: Vec<f32> = (0..N)
+            .map(|i| if apmask[i] { apmag * sign(i) } else { llr[i] })
+            .collect();
+        let ap = |depth| decode_hybrid_with_ap(&ap_llr, Some(&apmask), depth);
+        assert!(ap(DecodeDepth::BpOsdUncoupled).is_none());
+        let (decoded, iters, _) = ap(DecodeDepth::BpOsdHybrid).unwrap();
+        assert_eq!((decoded, iters), (m91[..K].to_bitvec(), 0));
+    }
+}
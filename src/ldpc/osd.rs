@@ -0,0 +1,251 @@
+//! Ordered-statistics decoding
+//!
+//! When belief propagation fails, OSD re-encodes the most reliable
+//! independent set of 91 hard decisions and searches low-weight error
+//! patterns on them, keeping the codeword closest (in LLR-weighted
+//! distance) to the received word. Modelled on WSJT-X `osd174_91`.
+
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+
+use super::generator::GENERATOR;
+use super::{K, N};
+use crate::crc;
+
+/// 174-bit row packed into three words
+type Row = [u64; 3];
+
+/// Number of least-reliable information positions searched at order 2
+const ORDER2_WIDTH: usize = 30;
+
+fn get(row: &Row, i: usize) -> bool {
+    row[i / 64] >> (i % 64) & 1 == 1
+}
+
+fn flip(row: &mut Row, i: usize) {
+    row[i / 64] ^= 1 << (i % 64);
+}
+
+fn xor(a: &Row, b: &Row) -> Row {
+    [a[0] ^ b[0], a[1] ^ b[1], a[2] ^ b[2]]
+}
+
+/// Row `i` of the systematic generator matrix: unit vector `i` followed by
+/// the parity bits that message bit `i` contributes to.
+fn generator_row(i: usize) -> [bool; N] {
+    let mut row = [false; N];
+    row[i] = true;
+    for (j, g) in GENERATOR.iter().enumerate() {
+        row[K + j] = g.view_bits::<Msb0>()[i];
+    }
+    row
+}
+
+/// Sum of `|llr|` over the positions where `cw` disagrees with `hard`.
+fn distance(cw: &Row, hard: &Row, reliability: &[f32; N]) -> f32 {
+    let diff = xor(cw, hard);
+    let mut d = 0.0;
+    for (w, &word) in diff.iter().enumerate() {
+        let mut bits = word;
+        while bits != 0 {
+            let b = bits.trailing_zeros() as usize;
+            d += reliability[w * 64 + b];
+            bits &= bits - 1;
+        }
+    }
+    d
+}
+
+/// Decode `llr` by ordered-statistics reprocessing up to `order` (0–2).
+///
+/// Bits with `apmask` set are known: they are taken into the information
+/// set ahead of every received bit, whatever their LLR magnitude, and are
+/// never flipped by the error-pattern search.
+///
+/// Returns the 91 payload bits when the closest codeword found also passes
+/// the CRC.
+pub(crate) fn osd_decode(
+    llr: &[f32],
+    apmask: Option<&[bool]>,
+    order: usize,
+) -> Option<BitVec<u8, Msb0>> {
+    let masked = |i: usize| apmask.is_some_and(|m| m[i]);
+
+    // Sort positions by decreasing reliability, known bits first
+    let mut perm: Vec<usize> = (0..N).collect();
+    perm.sort_by(|&a, &b| {
+        masked(b)
+            .cmp(&masked(a))
+            .then(llr[b].abs().total_cmp(&llr[a].abs()))
+    });
+
+    let mut reliability = [0.0f32; N];
+    let mut hard: Row = [0; 3];
+    for (c, &p) in perm.iter().enumerate() {
+        reliability[c] = llr[p].abs();
+        if llr[p] > 0.0 {
+            flip(&mut hard, c);
+        }
+    }
+
+    // Generator matrix with permuted columns
+    let mut rows: Vec<Row> = (0..K)
+        .map(|i| {
+            let g = generator_row(i);
+            let mut row = [0; 3];
+            for (c, &p) in perm.iter().enumerate() {
+                if g[p] {
+                    flip(&mut row, c);
+                }
+            }
+            row
+        })
+        .collect();
+
+    // Gauss-Jordan elimination, taking pivots from the most reliable
+    // columns; dependent columns are skipped
+    let mut pivots = Vec::with_capacity(K);
+    let mut col = 0;
+    for k in 0..K {
+        loop {
+            if col >= N {
+                return None;
+            }
+            if let Some(r) = (k..K).find(|&r| get(&rows[r], col)) {
+                rows.swap(k, r);
+                let pivot_row = rows[k];
+                for (r, row) in rows.iter_mut().enumerate() {
+                    if r != k && get(row, col) {
+                        *row = xor(row, &pivot_row);
+                    }
+                }
+                pivots.push(col);
+                col += 1;
+                break;
+            }
+            col += 1;
+        }
+    }
+
+    // Order-0 codeword from the hard decisions on the information set
+    let mut c0: Row = [0; 3];
+    for (k, &p) in pivots.iter().enumerate() {
+        if get(&hard, p) {
+            c0 = xor(&c0, &rows[k]);
+        }
+    }
+
+    let mut best = c0;
+    let mut best_d = distance(&c0, &hard, &reliability);
+
+    // Rows whose information bit may be flipped
+    let known = |k: usize| masked(perm[pivots[k]]);
+    let free: Vec<usize> = (0..K).filter(|&k| !known(k)).collect();
+
+    if order >= 1 {
+        for row in free.iter().map(|&k| &rows[k]) {
+            let c = xor(&c0, row);
+            let d = distance(&c, &hard, &reliability);
+            if d < best_d {
+                best = c;
+                best_d = d;
+            }
+        }
+    }
+
+    if order >= 2 {
+        let least = &free[free.len().saturating_sub(ORDER2_WIDTH)..];
+        for (n, &i) in least.iter().enumerate() {
+            let ci = xor(&c0, &rows[i]);
+            for row in least[n + 1..].iter().map(|&k| &rows[k]) {
+                let c = xor(&ci, row);
+                let d = distance(&c, &hard, &reliability);
+                if d < best_d {
+                    best = c;
+                    best_d = d;
+                }
+            }
+        }
+    }
+
+    let mut cw = BitArray::<[u8; 22], Msb0>::ZERO;
+    for (c, &p) in perm.iter().enumerate() {
+        cw.set(p, get(&best, c));
+    }
+    crc::verify(&cw[..K]).then(|| cw[..K].to_bitvec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crc::append_crc;
+    use crate::ldpc::encode;
+
+    #[test]
+    fn test_osd_recovers_heavily_corrupted_word() {
+        let mut msg = bitarr![u8, Msb0; 0; 80];
+        for i in (1..77).step_by(5) {
+            msg.set(i, true);
+        }
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        append_crc(&msg[..77], &mut m91[..K]);
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        encode(&m91[..K], &mut cw[..N]);
+
+        // Strong correct bits, with a dozen weak wrong decisions
+        let mut llr: Vec<f32> = cw[..N]
+            .iter()
+            .map(|b| if *b { 3.0 } else { -3.0 })
+            .collect();
+        for i in (0..N).step_by(14) {
+            llr[i] = -llr[i] * 0.1;
+        }
+        let decoded = osd_decode(&llr, None, 1).expect("OSD should decode");
+        assert_eq!(decoded, m91[..K].to_bitvec());
+    }
+
+    #[test]
+    fn test_osd_honors_ap_mask() {
+        use crate::message::{encode as encode_message, CallsignHashCache};
+
+        let mut msg = bitarr![u8, Msb0; 0; 80];
+        encode_message(
+            "CQ K1ABC FN42",
+            &mut msg[..77],
+            &mut CallsignHashCache::new(),
+        )
+        .unwrap();
+        let mut m91 = bitarr![u8, Msb0; 0; 96];
+        append_crc(&msg[..77], &mut m91[..K]);
+        let mut cw = bitarr![u8, Msb0; 0; 176];
+        encode(&m91[..K], &mut cw[..N]);
+        let sign = |i: usize| if cw[i] { 1.0 } else { -1.0 };
+
+        // A priori: the CQ field, its flag and i3, correct but no more
+        // confident than the weaker channel bits
+        let apmask: Vec<bool> = (0..N).map(|i| i < 29 || (74..77).contains(&i)).collect();
+        let mut others: Vec<usize> = (0..N).filter(|&i| !apmask[i]).collect();
+        let mut rng = crate::channel::Rng::new(13);
+        for i in (1..others.len()).rev() {
+            others.swap(i, rng.next_u64() as usize % (i + 1));
+        }
+        let mut llr = vec![0.0f32; N];
+        for (n, &i) in others.iter().enumerate() {
+            llr[i] = match n {
+                // Enough strong bits to complete an information set with
+                // the known ones, but not one of their own
+                0..70 => 3.0 * sign(i),
+                // Confident errors that then fall into it
+                70..80 => -2.0 * sign(i),
+                _ => 0.5 * sign(i),
+            };
+        }
+        for i in (0..N).filter(|&i| apmask[i]) {
+            llr[i] = sign(i);
+        }
+
+        assert_eq!(osd_decode(&llr, None, 2), None);
+        let decoded = osd_decode(&llr, Some(&apmask), 2).expect("AP + OSD should decode");
+        assert_eq!(decoded, m91[..K].to_bitvec());
+    }
+}
@@ -0,0 +1,53 @@
+//! RustyFt8: an implementation of the FT8 digital mode
+//!
+//! The crate is layered the same way as the FT8 signal chain:
+//!
+//! - [`message`]: text ↔ 77-bit source encoding
+//! - [`crc`]: 14-bit CRC appended to form the 91-bit payload
+//! - [`ldpc`]: LDPC(174,91) forward error correction
+//! - [`ap`]: a-priori hints that help the LDPC decoder with expected messages
+//! - [`symbol`]: codeword ↔ 79 Gray-coded 8-FSK tones with Costas sync
+//! - [`modulation`]: tones → GFSK audio waveform
+//! - [`channel`]: noise, fading and multipath models for testing
+//! - [`sync`]: candidate search and soft-bit extraction
+//! - [`decoder`]: the complete receiver, audio → messages
+//! - [`subtract`]: removal of decoded signals for multi-pass decoding
+//!
+//! [`ft4`] adds the FT4 mode on top of the same message, CRC and LDPC
+//! layers, with its own symbol mapping, 4-GFSK waveform and receiver.
+//!
+//! [`self_test`] runs a known message through the whole chain as a quick
+//! check that transmit and receive agree.
+//!
+//! The whole receive chain, up to [`decoder::decode_ft8`], only requires
+//! `alloc` and builds without the `std` feature, for receivers on embedded
+//! targets: floating-point functions then come from `libm`, and a built-in
+//! FFT replaces RustFFT. WAV input, recording spots, slot timing, JSON
+//! output and threading need `std`. The `no-std-check` crate builds and
+//! runs the decoder this way.
+//!
+//! The library prints nothing. With the `tracing` feature the sync stages
+//! emit [`tracing`](https://docs.rs/tracing) events: spectrogram and LLR
+//! summaries at debug level, and the per-symbol tone amplitudes and
+//! Costas checks of every candidate at trace level, so a subscriber
+//! filtering on `rustyft8=trace` shows the full demodulation.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod ap;
+pub mod channel;
+pub mod crc;
+pub mod decoder;
+mod fft;
+pub mod ft4;
+pub mod ldpc;
+pub mod message;
+pub mod modulation;
+mod self_test;
+pub mod subtract;
+pub mod symbol;
+pub mod sync;
+
+pub use self_test::self_test;
@@ -0,0 +1,37 @@
+//! Fixed-width big-endian field access on the 77-bit message
+
+use bitvec::prelude::*;
+
+/// Write the low `width` bits of `value` MSB-first at `offset`.
+pub(crate) fn put(bits: &mut BitSlice<u8, Msb0>, offset: usize, width: usize, value: u128) {
+    for i in 0..width {
+        let bit = (value >> (width - 1 - i)) & 1 == 1;
+        bits.set(offset + i, bit);
+    }
+}
+
+/// Read `width` bits MSB-first starting at `offset`.
+pub(crate) fn get(bits: &BitSlice<u8, Msb0>, offset: usize, width: usize) -> u128 {
+    (0..width).fold(0u128, |acc, i| (acc << 1) | bits[offset + i] as u128)
+}
+
+/// Read a field of at most 32 bits.
+pub(crate) fn get_u32(bits: &BitSlice<u8, Msb0>, offset: usize, width: usize) -> u32 {
+    debug_assert!(width <= 32);
+    get(bits, offset, width) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_get_roundtrip() {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        put(&mut bits, 3, 28, 0x0ABC_DEF1);
+        assert_eq!(get(&bits, 3, 28), 0x0ABC_DEF1);
+        assert!(!bits[0] && !bits[1] && !bits[2]);
+        put(&mut bits, 71, 3, 5);
+        assert_eq!(get_u32(&bits, 71, 3), 5);
+    }
+}
@@ -0,0 +1,391 @@
+//! 28-bit callsign field (`c28`) packing
+//!
+//! The `c28` field carries, in increasing numeric order:
+//!
+//! | range                          | meaning                                   |
+//! |--------------------------------|-------------------------------------------|
+//! | 0, 1, 2                        | `DE`, `QRZ`, `CQ`                         |
+//! | 3 ..= 1002                     | `CQ nnn` (directed CQ, three digits)      |
+//! | 1003 .. `NTOKENS`              | `CQ A` … `CQ ZZZZ` (directed CQ, letters) |
+//! | `NTOKENS` .. `NTOKENS + MAX22` | 22-bit hash of a non-standard callsign    |
+//! | above                          | standard callsign                         |
+
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use super::error::MessageError;
+use super::hash::{ihashcall, render_hashed, strip_brackets, CallsignHashCache};
+
+/// Number of special tokens at the bottom of the `c28` range
+pub const NTOKENS: u32 = 2_063_592;
+
+/// Size of the 22-bit hash range
+pub const MAX22: u32 = 4_194_304;
+
+const A1: &[u8] = b" 0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const A2: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const A3: &[u8] = b"0123456789";
+const A4: &[u8] = b" ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Characters allowed in a 58-bit (`c58`) non-standard callsign
+pub(crate) const C58_CHARS: &[u8] = b" 0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ/";
+
+/// How a callsign string can be carried in a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallsignKind {
+    /// `CQ`, `QRZ`, `DE` or a directed `CQ xxx`
+    Token,
+    /// Fits the 28-bit standard encoding (optionally with `/R` or `/P`)
+    Standard,
+    /// Contains a `/` prefix or suffix other than `/R` `/P`; must be hashed
+    Compound,
+    /// Plain callsign that does not fit the standard encoding; must be hashed
+    NonStandard,
+    /// Not a usable callsign
+    Invalid,
+}
+
+/// Classify a callsign by how it can be encoded.
+pub fn classify_callsign(callsign: &str) -> CallsignKind {
+    let call = callsign.trim().to_uppercase();
+    if call == "CQ" || call == "QRZ" || call == "DE" || directed_cq_token(&call).is_some() {
+        return CallsignKind::Token;
+    }
+    if pack_standard(strip_rover_portable(&call)).is_some() {
+        return CallsignKind::Standard;
+    }
+    if !validate_callsign_basic(&call) {
+        return CallsignKind::Invalid;
+    }
+    if call.contains('/') {
+        CallsignKind::Compound
+    } else {
+        CallsignKind::NonStandard
+    }
+}
+
+/// Loose sanity check for something that looks like a callsign.
+///
+/// Accepts 3–11 characters from `A-Z 0-9 /` containing at least one letter
+/// and one digit. This is deliberately permissive; the packers perform the
+/// exact checks.
+pub fn validate_callsign_basic(callsign: &str) -> bool {
+    let call = callsign.as_bytes();
+    (3..=11).contains(&call.len())
+        && call
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || *c == b'/')
+        && call.iter().any(u8::is_ascii_digit)
+        && call.iter().any(u8::is_ascii_uppercase)
+        && !call.starts_with(b"/")
+        && !call.ends_with(b"/")
+}
+
+/// Strip a trailing `/R` or `/P` (either case).
+pub(crate) fn strip_rover_portable(callsign: &str) -> &str {
+    let upper_suffix = callsign
+        .len()
+        .checked_sub(2)
+        .and_then(|i| callsign.get(i..))
+        .map(|s| s.to_ascii_uppercase());
+    match upper_suffix.as_deref() {
+        Some("/R") | Some("/P") => &callsign[..callsign.len() - 2],
+        _ => callsign,
+    }
+}
+
+/// Whether `modifier` fits the directed-CQ range of `c28`: three digits
+/// (`005`) or one to four letters (`DX`, `POTA`).
+pub(crate) fn is_cq_modifier(modifier: &str) -> bool {
+    let is_num = modifier.len() == 3 && modifier.bytes().all(|c| c.is_ascii_digit());
+    let is_alpha =
+        (1..=4).contains(&modifier.len()) && modifier.bytes().all(|c| c.is_ascii_uppercase());
+    is_num || is_alpha
+}
+
+/// Parse the modifier of a directed CQ (`CQ DX`, `CQ_DX`, `CQ 001`).
+fn directed_cq_token(call: &str) -> Option<&str> {
+    let rest = call
+        .strip_prefix("CQ_")
+        .or_else(|| call.strip_prefix("CQ "))?;
+    is_cq_modifier(rest).then_some(rest)
+}
+
+/// Pack a standard callsign into its 0-based index (before the token and
+/// hash offsets), or `None` if it does not fit the standard encoding.
+pub(crate) fn pack_standard(callsign: &str) -> Option<u32> {
+    let mut call = callsign.to_string();
+
+    // WSJT-X special cases for Swaziland (3DA0) and Guinea (3X)
+    if call.starts_with("3DA0") && call.len() <= 7 {
+        call = format!("3D0{}", &call[4..]);
+    } else if call.starts_with("3X") && call.as_bytes().get(2).is_some_and(u8::is_ascii_uppercase) {
+        call = format!("Q{}", &call[2..]);
+    }
+
+    let bytes = call.as_bytes();
+    if bytes.is_empty()
+        || bytes.len() > 6
+        || !bytes
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    // The call area is the last digit, which must be the 2nd or 3rd character
+    let iarea = bytes.iter().rposition(u8::is_ascii_digit)?;
+    if !(1..=2).contains(&iarea) {
+        return None;
+    }
+    let prefix_letters = bytes[..iarea]
+        .iter()
+        .filter(|c| c.is_ascii_uppercase())
+        .count();
+    let prefix_digits = bytes[..iarea].iter().filter(|c| c.is_ascii_digit()).count();
+    let suffix_letters = bytes.len() - iarea - 1;
+    if prefix_letters == 0 || prefix_digits >= iarea || suffix_letters > 3 {
+        return None;
+    }
+
+    // Right-align so the call area digit sits in the third position
+    let mut c6 = [b' '; 6];
+    let start = 2 - iarea;
+    c6[start..start + bytes.len()].copy_from_slice(bytes);
+
+    let idx = |table: &[u8], c: u8| table.iter().position(|&t| t == c).map(|i| i as u32);
+    let i1 = idx(A1, c6[0])?;
+    let i2 = idx(A2, c6[1])?;
+    let i3 = idx(A3, c6[2])?;
+    let i4 = idx(A4, c6[3])?;
+    let i5 = idx(A4, c6[4])?;
+    let i6 = idx(A4, c6[5])?;
+
+    Some(((((i1 * 36 + i2) * 10 + i3) * 27 + i4) * 27 + i5) * 27 + i6)
+}
+
+/// Pack a callsign or token into the 28-bit `c28` field.
+///
+/// Accepts `DE`, `QRZ`, `CQ`, directed CQ (`CQ DX` or `CQ_DX`), a bracketed
+/// hashed callsign (`<PJ4/K1ABC>`), or a standard callsign. A trailing `/R`
+/// or `/P` is ignored; the caller carries it in its own flag bit.
+pub fn pack_callsign(callsign: &str) -> Result<u32, MessageError> {
+    let call = callsign.trim().to_uppercase();
+
+    match call.as_str() {
+        "DE" => return Ok(0),
+        "QRZ" => return Ok(1),
+        "CQ" => return Ok(2),
+        _ => {}
+    }
+
+    if let Some(modifier) = directed_cq_token(&call) {
+        let bytes = modifier.as_bytes();
+        if bytes[0].is_ascii_digit() {
+            let n: u32 = modifier
+                .parse()
+                .map_err(|_| MessageError::Encode("Invalid CQ number".into()))?;
+            return Ok(3 + n);
+        }
+        // Letters are right-aligned in a 4-character base-27 field
+        let m = (0..4).fold(0u32, |m, i| {
+            let pad = 4 - bytes.len();
+            let j = if i < pad {
+                0
+            } else {
+                (bytes[i - pad] - b'A' + 1) as u32
+            };
+            27 * m + j
+        });
+        return Ok(3 + 1000 + m);
+    }
+
+    if call.starts_with('<') && call.ends_with('>') {
+        let inner = strip_brackets(&call);
+        if inner.is_empty() || !inner.bytes().all(|c| C58_CHARS.contains(&c)) {
+            return Err(MessageError::Encode(format!(
+                "Invalid hashed callsign: {}",
+                callsign
+            )));
+        }
+        return Ok(NTOKENS + ihashcall(inner, 22));
+    }
+
+    let base = strip_rover_portable(&call);
+    pack_standard(base)
+        .map(|n| NTOKENS + MAX22 + n)
+        .ok_or_else(|| {
+            MessageError::Encode(if base.len() > 6 {
+                format!("Callsign too long for standard encoding: {}", callsign)
+            } else {
+                format!("Invalid standard callsign: {}", callsign)
+            })
+        })
+}
+
+/// Unpack a 28-bit `c28` field into a callsign or token.
+///
+/// Hashed callsigns are rendered as `<CALL>` when `cache` knows them and as
+/// `<...>` otherwise.
+pub fn unpack_callsign(
+    n28: u32,
+    cache: Option<&CallsignHashCache>,
+) -> Result<String, MessageError> {
+    match n28 {
+        0 => return Ok("DE".to_string()),
+        1 => return Ok("QRZ".to_string()),
+        2 => return Ok("CQ".to_string()),
+        3..=1002 => return Ok(format!("CQ {:03}", n28 - 3)),
+        _ => {}
+    }
+
+    if n28 < NTOKENS {
+        let mut m = n28 - 1003;
+        if m >= 27 * 27 * 27 * 27 {
+            return Err(MessageError::Decode(format!("Invalid c28 token: {}", n28)));
+        }
+        let mut c4 = [b' '; 4];
+        for i in (0..4).rev() {
+            c4[i] = A4[(m % 27) as usize];
+            m /= 27;
+        }
+        let modifier = core::str::from_utf8(&c4).unwrap_or("").trim();
+        if modifier.is_empty() || modifier.contains(' ') {
+            return Err(MessageError::Decode(format!(
+                "Invalid directed CQ token: {}",
+                n28
+            )));
+        }
+        return Ok(format!("CQ {}", modifier));
+    }
+
+    if n28 < NTOKENS + MAX22 {
+        let hash = n28 - NTOKENS;
+        return Ok(render_hashed(cache.and_then(|c| c.lookup22(hash))));
+    }
+
+    let mut n = n28 - NTOKENS - MAX22;
+    let i6 = (n % 27) as usize;
+    n /= 27;
+    let i5 = (n % 27) as usize;
+    n /= 27;
+    let i4 = (n % 27) as usize;
+    n /= 27;
+    let i3 = (n % 10) as usize;
+    n /= 10;
+    let i2 = (n % 36) as usize;
+    n /= 36;
+    let i1 = n as usize;
+    if i1 >= A1.len() {
+        return Err(MessageError::Decode(format!(
+            "Invalid standard callsign value: {}",
+            n28
+        )));
+    }
+
+    let c6 = [A1[i1], A2[i2], A3[i3], A4[i4], A4[i5], A4[i6]];
+    let call = core::str::from_utf8(&c6).unwrap_or("").trim();
+    if call.is_empty() || call.contains(' ') {
+        return Err(MessageError::Decode(format!(
+            "Invalid standard callsign value: {}",
+            n28
+        )));
+    }
+
+    let call = if let Some(rest) = call.strip_prefix("3D0") {
+        format!("3DA0{}", rest)
+    } else if call.starts_with('Q') && call.as_bytes().get(1).is_some_and(u8::is_ascii_uppercase) {
+        format!("3X{}", &call[1..])
+    } else {
+        call.to_string()
+    };
+
+    Ok(call)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(pack_callsign("DE"), Ok(0));
+        assert_eq!(pack_callsign("QRZ"), Ok(1));
+        assert_eq!(pack_callsign("CQ"), Ok(2));
+        assert_eq!(pack_callsign("CQ 000"), Ok(3));
+        assert_eq!(pack_callsign("CQ_999"), Ok(1002));
+        assert_eq!(unpack_callsign(0, None).unwrap(), "DE");
+        assert_eq!(unpack_callsign(1, None).unwrap(), "QRZ");
+        assert_eq!(unpack_callsign(2, None).unwrap(), "CQ");
+        assert_eq!(unpack_callsign(8, None).unwrap(), "CQ 005");
+    }
+
+    #[test]
+    fn test_directed_cq_letters() {
+        // Right-aligned: "DX" → (0, 0, D, X)
+        let dx = 1003 + 4 * 27 + 24;
+        assert_eq!(pack_callsign("CQ DX"), Ok(dx));
+        assert_eq!(unpack_callsign(dx, None).unwrap(), "CQ DX");
+        for modifier in ["A", "FD", "POTA", "SOTA", "TEST", "ZZZZ"] {
+            let n = pack_callsign(&format!("CQ {}", modifier)).unwrap();
+            assert_eq!(
+                unpack_callsign(n, None).unwrap(),
+                format!("CQ {}", modifier)
+            );
+        }
+    }
+
+    #[test]
+    fn test_standard_callsign_value() {
+        // " K1ABC": i1=0 (space), i2=20 (K), i3=1, i4..i6 = A, B, C
+        let expected = 20 * 10 * 27 * 27 * 27 + 27 * 27 * 27 + 27 * 27 + 27 * 2 + 3;
+        assert_eq!(pack_callsign("K1ABC"), Ok(NTOKENS + MAX22 + expected));
+    }
+
+    #[test]
+    fn test_standard_roundtrip() {
+        for call in [
+            "K1ABC", "W9XYZ", "N0YPR", "KA1ABC", "G4ABC", "PA9XYZ", "K1A", "VK2XYZ", "3DA0XYZ",
+            "3XY1ABC",
+        ] {
+            let n = pack_callsign(call).unwrap_or_else(|e| panic!("{}: {}", call, e));
+            assert_eq!(unpack_callsign(n, None).unwrap(), call);
+        }
+    }
+
+    #[test]
+    fn test_rover_portable_suffix_ignored() {
+        assert_eq!(pack_callsign("K1ABC/R"), pack_callsign("K1ABC"));
+        assert_eq!(pack_callsign("K1ABC/P"), pack_callsign("K1ABC"));
+    }
+
+    #[test]
+    fn test_invalid_standard() {
+        assert!(pack_callsign("ABCDEFG").is_err());
+        assert!(pack_callsign("PJ4/K1ABC").is_err());
+        assert!(pack_callsign("123").is_err());
+        assert!(pack_callsign("K1ABCD").is_err());
+    }
+
+    #[test]
+    fn test_hashed_callsign() {
+        let n = pack_callsign("<PJ4/K1ABC>").unwrap();
+        assert_eq!(n, NTOKENS + ihashcall("PJ4/K1ABC", 22));
+        assert_eq!(unpack_callsign(n, None).unwrap(), "<...>");
+
+        let mut cache = CallsignHashCache::new();
+        cache.insert("PJ4/K1ABC");
+        assert_eq!(unpack_callsign(n, Some(&cache)).unwrap(), "<PJ4/K1ABC>");
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify_callsign("CQ"), CallsignKind::Token);
+        assert_eq!(classify_callsign("CQ DX"), CallsignKind::Token);
+        assert_eq!(classify_callsign("K1ABC"), CallsignKind::Standard);
+        assert_eq!(classify_callsign("K1ABC/R"), CallsignKind::Standard);
+        assert_eq!(classify_callsign("PJ4/K1ABC"), CallsignKind::Compound);
+        assert_eq!(classify_callsign("YW18FIFA"), CallsignKind::NonStandard);
+        assert_eq!(classify_callsign("HELLO"), CallsignKind::Invalid);
+    }
+}
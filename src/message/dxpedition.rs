@@ -0,0 +1,105 @@
+//! DXpedition mode messages (i3=0 n3=1)
+//!
+//! `K1ABC RR73; W9XYZ <KH1/KH7Z> -08`: acknowledges one station and sends a
+//! report to the next in a single transmission. Layout: `c28 c28 h10 r5`.
+
+use alloc::format;
+use bitvec::prelude::*;
+
+use super::bits::{get_u32, put};
+use super::callsign::{pack_callsign, unpack_callsign};
+use super::error::MessageError;
+use super::hash::{ihashcall, render_hashed, strip_brackets, CallsignHashCache};
+use super::types::MessageVariant;
+
+/// Encode a DXpedition message.
+pub(crate) fn encode(
+    call1: &str,
+    call2: &str,
+    call3: &str,
+    report: i8,
+    bits: &mut BitSlice<u8, Msb0>,
+    cache: &mut CallsignHashCache,
+) -> Result<(), MessageError> {
+    if !(-30..=32).contains(&report) || report % 2 != 0 {
+        return Err(MessageError::Encode(format!(
+            "DXpedition report must be even, -30..+32: {}",
+            report
+        )));
+    }
+    let n28a = pack_callsign(call1)?;
+    let n28b = pack_callsign(call2)?;
+    let dx = strip_brackets(call3);
+    let h10 = ihashcall(dx, 10);
+    let n5 = ((report as i32 + 30) / 2) as u32;
+
+    put(bits, 0, 28, n28a as u128);
+    put(bits, 28, 28, n28b as u128);
+    put(bits, 56, 10, h10 as u128);
+    put(bits, 66, 5, n5 as u128);
+    put(bits, 71, 3, 1);
+    put(bits, 74, 3, 0);
+
+    cache.insert(dx);
+    Ok(())
+}
+
+/// Decode an i3=0 n3=1 message.
+pub(crate) fn decode(
+    bits: &BitSlice<u8, Msb0>,
+    cache: Option<&CallsignHashCache>,
+) -> Result<MessageVariant, MessageError> {
+    let call1 = unpack_callsign(get_u32(bits, 0, 28), cache)?;
+    let call2 = unpack_callsign(get_u32(bits, 28, 28), cache)?;
+    let h10 = get_u32(bits, 56, 10);
+    let call3 = render_hashed(cache.and_then(|c| c.lookup10(h10)));
+    let report = (2 * get_u32(bits, 66, 5) as i32 - 30) as i8;
+
+    Ok(MessageVariant::Dxpedition {
+        call1,
+        call2,
+        call3,
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_roundtrip_with_cache() {
+        let mut cache = CallsignHashCache::new();
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode(
+            "K1ABC",
+            "W9XYZ",
+            "KH1/KH7Z",
+            -8,
+            &mut bits[..77],
+            &mut cache,
+        )
+        .unwrap();
+        let decoded = decode(&bits[..77], Some(&cache)).unwrap();
+        assert_eq!(decoded.to_string(), "K1ABC RR73; W9XYZ <KH1/KH7Z> -08");
+
+        let unresolved = decode(&bits[..77], None).unwrap();
+        assert_eq!(unresolved.to_string(), "K1ABC RR73; W9XYZ <...> -08");
+    }
+
+    #[test]
+    fn test_odd_report_rejected() {
+        let mut cache = CallsignHashCache::new();
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        assert!(encode(
+            "K1ABC",
+            "W9XYZ",
+            "KH1/KH7Z",
+            -7,
+            &mut bits[..77],
+            &mut cache
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Errors returned when parsing, packing or unpacking messages
+
+use alloc::string::String;
+use core::fmt;
+
+/// Error parsing, encoding or decoding a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageError {
+    /// Callsign cannot be carried in the requested message type
+    InvalidCallsign(String),
+    /// Callsign longer than the 11 characters the non-standard encoding
+    /// carries
+    CallsignTooLong { call: String, max: usize },
+    /// Grid is not a 4-character Maidenhead square, or not a valid
+    /// 6-character locator where one is needed
+    InvalidGrid(String),
+    /// Directed-CQ modifier that is neither three digits nor one to four
+    /// letters
+    InvalidCqModifier(String),
+    /// Signal report outside the encodable range
+    ReportOutOfRange(i8),
+    /// Free text contains a character outside the FT8 free-text alphabet
+    InvalidChar { ch: char },
+    /// Free text longer than the 13 characters a message can carry
+    TooLong { max: usize, got: usize },
+    /// Output buffer is not exactly 77 bits
+    BufferWrongSize { expected: usize, got: usize },
+    /// Input holds fewer than 77 bits
+    InputTooShort { expected: usize, got: usize },
+    /// Telemetry hex whose value needs more than the 71 bits a message
+    /// carries
+    TelemetryOverflow(String),
+    /// Nothing to send
+    Empty,
+    /// `i3`/`n3` select a message type this crate does not unpack;
+    /// `n3` is only given for `i3 = 0`
+    UnsupportedType { i3: u8, n3: Option<u8> },
+    /// A parsing rule does not apply to a message of this many words;
+    /// `expected` describes the counts it takes
+    WordCount { expected: &'static str, got: usize },
+    /// The last word of a two-callsign message is not a grid, report,
+    /// `RRR`, `RR73` or `73`
+    InvalidPayload(String),
+    /// Expected `R` before the grid of a four-word message
+    ExpectedR(String),
+    /// A message with both a `/R` and a `/P` callsign
+    MixedSuffixes,
+    /// Both callsigns need the non-standard encoding, which carries only
+    /// one
+    TwoNonStandardCalls,
+    /// A non-standard callsign sent with something other than nothing,
+    /// `RRR`, `RR73` or `73`
+    NonStandardPayload,
+    /// A non-standard callsign after a token other than a plain `CQ`
+    NonStandardCq(String),
+    /// The unusual-callsign form needs exactly one callsign that no other
+    /// rule accepts
+    ExpectedOneUnusualCall,
+    /// RTTY Roundup report that is not a three-digit RST
+    InvalidRst(String),
+    /// Contest exchange that is not a valid serial number, state or
+    /// report
+    InvalidExchange(String),
+    /// Field Day class that is not 1-32 transmitters and a class A-F
+    InvalidFieldDayClass(String),
+    /// Not an ARRL/RAC section
+    UnknownSection(String),
+    /// Telemetry that is not 1 to 18 hex digits
+    InvalidTelemetry(String),
+    /// Text with a callsign that matches no structured rule, refused
+    /// as free text by strict parsing
+    NotStructured(String),
+    /// Any other encoding failure
+    Encode(String),
+    /// Bits that do not unpack to a valid message
+    Decode(String),
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::InvalidCallsign(call) => write!(f, "Invalid callsign: {}", call),
+            MessageError::CallsignTooLong { call, max } => write!(
+                f,
+                "Callsign too long: {} ({} characters, max {})",
+                call,
+                call.len(),
+                max
+            ),
+            MessageError::InvalidGrid(grid) => write!(f, "Invalid grid: {}", grid),
+            MessageError::InvalidCqModifier(modifier) => {
+                write!(f, "Invalid CQ modifier: {}", modifier)
+            }
+            MessageError::ReportOutOfRange(db) => write!(f, "Report out of range: {}", db),
+            MessageError::InvalidChar { ch } => {
+                write!(f, "Invalid character in free text: '{}'", ch)
+            }
+            MessageError::TooLong { max, got } => {
+                write!(f, "Free text too long: {} characters (max {})", got, max)
+            }
+            MessageError::BufferWrongSize { expected, got } => {
+                write!(f, "Output buffer must be {} bits, got {}", expected, got)
+            }
+            MessageError::InputTooShort { expected, got } => {
+                write!(f, "Input must be at least {} bits, got {}", expected, got)
+            }
+            MessageError::TelemetryOverflow(hex) => {
+                write!(f, "Telemetry exceeds 71 bits: {}", hex)
+            }
+            MessageError::Empty => write!(f, "Empty message"),
+            MessageError::UnsupportedType { i3, n3: Some(n3) } => {
+                write!(f, "Unsupported message subtype: i3={} n3={}", i3, n3)
+            }
+            MessageError::UnsupportedType { i3, n3: None } => {
+                write!(f, "Unsupported message type: i3={}", i3)
+            }
+            MessageError::WordCount { expected, got } => {
+                write!(f, "Expected {} words, got {}", expected, got)
+            }
+            MessageError::InvalidPayload(word) => {
+                write!(f, "Not a grid, report, RRR, RR73 or 73: {}", word)
+            }
+            MessageError::ExpectedR(word) => write!(f, "Expected R before the grid, got {}", word),
+            MessageError::MixedSuffixes => {
+                write!(f, "/R and /P cannot be combined in one message")
+            }
+            MessageError::TwoNonStandardCalls => {
+                write!(f, "Two non-standard callsigns cannot be sent together")
+            }
+            MessageError::NonStandardPayload => write!(
+                f,
+                "A non-standard callsign can only be sent with RRR, RR73 or 73"
+            ),
+            MessageError::NonStandardCq(call) => write!(
+                f,
+                "A non-standard callsign can only follow a plain CQ: {}",
+                call
+            ),
+            MessageError::ExpectedOneUnusualCall => {
+                write!(f, "Expected exactly one unusual callsign")
+            }
+            MessageError::InvalidRst(rst) => write!(f, "Invalid RST: {}", rst),
+            MessageError::InvalidExchange(exchange) => {
+                write!(f, "Invalid contest exchange: {}", exchange)
+            }
+            MessageError::InvalidFieldDayClass(class) => {
+                write!(f, "Invalid Field Day class: {}", class)
+            }
+            MessageError::UnknownSection(section) => {
+                write!(f, "Unknown ARRL section: {}", section)
+            }
+            MessageError::InvalidTelemetry(hex) => {
+                write!(f, "Telemetry must be 1-18 hex digits: {}", hex)
+            }
+            MessageError::NotStructured(text) => write!(f, "Not a structured message: {}", text),
+            MessageError::Encode(msg) | MessageError::Decode(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl core::error::Error for MessageError {}
+
+impl From<String> for MessageError {
+    fn from(msg: String) -> Self {
+        MessageError::Encode(msg)
+    }
+}
@@ -0,0 +1,102 @@
+//! EU VHF contest messages with serial number and 6-character locator (i3=5)
+//!
+//! `<PA3XYZ> <G4ABC/P> R 590003 IO91NP`. Layout: `h12 h22 R1 r3 s11 g25`.
+//! Both callsigns are hashed; `r3` is the report 52–59, `s11` the serial
+//! number 0–2047 and `g25` the 6-character locator.
+
+use alloc::format;
+use bitvec::prelude::*;
+
+use super::bits::{get_u32, put};
+use super::error::MessageError;
+use super::grid::{decode_grid6, encode_grid6};
+use super::hash::{ihashcall, render_hashed, strip_brackets, CallsignHashCache};
+use super::types::MessageVariant;
+
+/// Encode an i3=5 EU VHF contest message.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn encode(
+    call1: &str,
+    call2: &str,
+    r_flag: bool,
+    rst: u8,
+    serial: u16,
+    grid6: &str,
+    bits: &mut BitSlice<u8, Msb0>,
+    cache: &mut CallsignHashCache,
+) -> Result<(), MessageError> {
+    if !(52..=59).contains(&rst) {
+        return Err(MessageError::Encode(format!(
+            "EU VHF report must be 52-59: {}",
+            rst
+        )));
+    }
+    if serial > 2047 {
+        return Err(MessageError::Encode(format!(
+            "EU VHF serial must be 0-2047: {}",
+            serial
+        )));
+    }
+    let c1 = strip_brackets(call1);
+    let c2 = strip_brackets(call2);
+    let g25 = encode_grid6(grid6)?;
+
+    put(bits, 0, 12, ihashcall(c1, 12) as u128);
+    put(bits, 12, 22, ihashcall(c2, 22) as u128);
+    put(bits, 34, 1, r_flag as u128);
+    put(bits, 35, 3, (rst - 52) as u128);
+    put(bits, 38, 11, serial as u128);
+    put(bits, 49, 25, g25 as u128);
+    put(bits, 74, 3, 5);
+
+    cache.insert(c1);
+    cache.insert(c2);
+    Ok(())
+}
+
+/// Decode an i3=5 message.
+pub(crate) fn decode(
+    bits: &BitSlice<u8, Msb0>,
+    cache: Option<&CallsignHashCache>,
+) -> Result<MessageVariant, MessageError> {
+    let h12 = get_u32(bits, 0, 12);
+    let h22 = get_u32(bits, 12, 22);
+    let call1 = render_hashed(cache.and_then(|c| c.lookup12(h12)));
+    let call2 = render_hashed(cache.and_then(|c| c.lookup22(h22)));
+
+    Ok(MessageVariant::EuVhfContestType5 {
+        call1,
+        call2,
+        r_flag: bits[34],
+        rst: 52 + get_u32(bits, 35, 3) as u8,
+        serial: get_u32(bits, 38, 11) as u16,
+        grid6: decode_grid6(get_u32(bits, 49, 25))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_roundtrip_with_cache() {
+        let mut cache = CallsignHashCache::new();
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode(
+            "PA3XYZ",
+            "G4ABC",
+            true,
+            59,
+            3,
+            "IO91NP",
+            &mut bits[..77],
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(
+            decode(&bits[..77], Some(&cache)).unwrap().to_string(),
+            "<PA3XYZ> <G4ABC> R 590003 IO91NP"
+        );
+    }
+}
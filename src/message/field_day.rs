@@ -0,0 +1,138 @@
+//! ARRL Field Day messages (i3=0 n3=3 and n3=4)
+//!
+//! `WA9XYZ KA1ABC R 16A EMA`. Layout: `c28 c28 R1 n4 k3 s7`, where `n4` is
+//! the transmitter count minus one (n3=3 covers 1–16, n3=4 covers 17–32),
+//! `k3` the class letter and `s7` the 1-based ARRL/RAC section index.
+
+use alloc::format;
+use alloc::string::ToString;
+use bitvec::prelude::*;
+
+use super::bits::{get_u32, put};
+use super::callsign::{pack_callsign, unpack_callsign};
+use super::error::MessageError;
+use super::hash::CallsignHashCache;
+use super::standard::remember_call;
+use super::types::MessageVariant;
+
+/// ARRL/RAC sections in WSJT-X order
+pub const ARRL_SECTIONS: [&str; 86] = [
+    "AB", "AK", "AL", "AR", "AZ", "BC", "CO", "CT", "DE", "EB", "EMA", "ENY", "EPA", "EWA", "GA",
+    "GTA", "IA", "ID", "IL", "IN", "KS", "KY", "LA", "LAX", "MAR", "MB", "MDC", "ME", "MI", "MN",
+    "MO", "MS", "MT", "NC", "ND", "NE", "NFL", "NH", "NL", "NLI", "NM", "NNJ", "NNY", "NT", "NTX",
+    "NV", "OH", "OK", "ONE", "ONN", "ONS", "OR", "ORG", "PAC", "PR", "QC", "RI", "SB", "SC", "SCV",
+    "SD", "SDG", "SF", "SFL", "SJV", "SK", "SNJ", "STX", "SV", "TN", "UT", "VA", "VI", "VT", "WCF",
+    "WI", "WMA", "WNY", "WPA", "WTX", "WV", "WWA", "WY", "DX", "PE", "NB",
+];
+
+/// Encode a Field Day message.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn encode(
+    call1: &str,
+    call2: &str,
+    r_flag: bool,
+    transmitters: u8,
+    class: char,
+    section: &str,
+    bits: &mut BitSlice<u8, Msb0>,
+    cache: &mut CallsignHashCache,
+) -> Result<(), MessageError> {
+    if !(1..=32).contains(&transmitters) || !('A'..='F').contains(&class) {
+        return Err(MessageError::InvalidFieldDayClass(format!(
+            "{}{}",
+            transmitters, class
+        )));
+    }
+    let isec = ARRL_SECTIONS
+        .iter()
+        .position(|&s| s == section)
+        .ok_or_else(|| MessageError::UnknownSection(section.to_string()))?
+        + 1;
+
+    let n28a = pack_callsign(call1)?;
+    let n28b = pack_callsign(call2)?;
+    let (n3, intx) = if transmitters > 16 {
+        (4, transmitters - 17)
+    } else {
+        (3, transmitters - 1)
+    };
+
+    put(bits, 0, 28, n28a as u128);
+    put(bits, 28, 28, n28b as u128);
+    put(bits, 56, 1, r_flag as u128);
+    put(bits, 57, 4, intx as u128);
+    put(bits, 61, 3, (class as u8 - b'A') as u128);
+    put(bits, 64, 7, isec as u128);
+    put(bits, 71, 3, n3);
+    put(bits, 74, 3, 0);
+
+    remember_call(cache, call1);
+    remember_call(cache, call2);
+    Ok(())
+}
+
+/// Decode an i3=0 n3=3/4 message.
+pub(crate) fn decode(
+    bits: &BitSlice<u8, Msb0>,
+    cache: Option<&CallsignHashCache>,
+) -> Result<MessageVariant, MessageError> {
+    let call1 = unpack_callsign(get_u32(bits, 0, 28), cache)?;
+    let call2 = unpack_callsign(get_u32(bits, 28, 28), cache)?;
+    let r_flag = bits[56];
+    let intx = get_u32(bits, 57, 4) as u8;
+    let nclass = get_u32(bits, 61, 3) as u8;
+    let isec = get_u32(bits, 64, 7) as usize;
+    let n3 = get_u32(bits, 71, 3);
+
+    if nclass > 5 {
+        return Err(MessageError::Decode(format!(
+            "Invalid Field Day class index: {}",
+            nclass
+        )));
+    }
+    if isec == 0 || isec > ARRL_SECTIONS.len() {
+        return Err(MessageError::Decode(format!(
+            "Invalid ARRL section index: {}",
+            isec
+        )));
+    }
+
+    Ok(MessageVariant::FieldDay {
+        call1,
+        call2,
+        r_flag,
+        transmitters: intx + if n3 == 4 { 17 } else { 1 },
+        class: (b'A' + nclass) as char,
+        section: ARRL_SECTIONS[isec - 1].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let cases = [
+            (
+                "WA9XYZ",
+                "KA1ABC",
+                true,
+                16,
+                'A',
+                "EMA",
+                "WA9XYZ KA1ABC R 16A EMA",
+            ),
+            ("W9XYZ", "K1ABC", false, 1, 'F', "NB", "W9XYZ K1ABC 1F NB"),
+            ("W9XYZ", "K1ABC", false, 32, 'C', "AB", "W9XYZ K1ABC 32C AB"),
+        ];
+        for (c1, c2, r, ntx, class, sec, text) in cases {
+            let mut cache = CallsignHashCache::new();
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            encode(c1, c2, r, ntx, class, sec, &mut bits[..77], &mut cache).unwrap();
+            let decoded = decode(&bits[..77], None).unwrap();
+            assert_eq!(decoded.to_string(), text);
+            assert_eq!(decoded.n3(), Some(if ntx > 16 { 4 } else { 3 }));
+        }
+    }
+}
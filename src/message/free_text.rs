@@ -0,0 +1,100 @@
+//! Free text messages (i3=0 n3=0)
+//!
+//! Up to 13 characters from a 42-character alphabet, packed as a base-42
+//! number into 71 bits. The text is right-justified in a 13-character field.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+
+use super::bits::{get, put};
+use super::error::MessageError;
+use super::types::MessageVariant;
+
+/// Free text alphabet
+pub(crate) const FREE_TEXT_CHARS: &[u8] = b" 0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ+-./?";
+
+/// Maximum free text length
+pub const MAX_FREE_TEXT_LEN: usize = 13;
+
+/// Check that `text` (already uppercased) can be sent as free text.
+pub(crate) fn validate(text: &str) -> Result<(), MessageError> {
+    if text.len() > MAX_FREE_TEXT_LEN {
+        return Err(MessageError::TooLong {
+            max: MAX_FREE_TEXT_LEN,
+            got: text.len(),
+        });
+    }
+    if let Some(ch) = text
+        .chars()
+        .find(|c| !c.is_ascii() || !FREE_TEXT_CHARS.contains(&(*c as u8)))
+    {
+        return Err(MessageError::InvalidChar { ch });
+    }
+    Ok(())
+}
+
+/// Encode free text into the 77-bit message.
+pub(crate) fn encode(text: &str, bits: &mut BitSlice<u8, Msb0>) -> Result<(), MessageError> {
+    let text = text.trim();
+    validate(text)?;
+
+    // Right-justifying spaces are leading zero digits, so only the text itself
+    // contributes to the value
+    let value = text.bytes().fold(0u128, |acc, c| {
+        let j = FREE_TEXT_CHARS.iter().position(|&t| t == c).unwrap_or(0) as u128;
+        acc * 42 + j
+    });
+
+    put(bits, 0, 71, value);
+    put(bits, 71, 3, 0);
+    put(bits, 74, 3, 0);
+    Ok(())
+}
+
+/// Decode an i3=0 n3=0 message.
+pub(crate) fn decode(bits: &BitSlice<u8, Msb0>) -> Result<MessageVariant, MessageError> {
+    let mut value = get(bits, 0, 71);
+    let mut chars = Vec::with_capacity(MAX_FREE_TEXT_LEN);
+    for _ in 0..MAX_FREE_TEXT_LEN {
+        chars.push(FREE_TEXT_CHARS[(value % 42) as usize]);
+        value /= 42;
+    }
+    chars.reverse();
+    let text = String::from_utf8(chars).map_err(|_| "Invalid free text".to_string())?;
+    Ok(MessageVariant::FreeText {
+        text: text.trim().into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for text in ["TNX BOB 73 GL", "HELLO", "A", "123/+-.?", "CQ"] {
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            encode(text, &mut bits[..77]).unwrap();
+            match decode(&bits[..77]).unwrap() {
+                MessageVariant::FreeText { text: t } => assert_eq!(t, text),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_right_justified_value() {
+        // "A" right-justified is the single base-42 digit 11
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode("A", &mut bits[..77]).unwrap();
+        assert_eq!(get(&bits, 0, 71), 11);
+    }
+
+    #[test]
+    fn test_rejects_invalid() {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        assert!(encode("THIS IS TOO LONG", &mut bits[..77]).is_err());
+        assert!(encode("HI@THERE", &mut bits[..77]).is_err());
+    }
+}
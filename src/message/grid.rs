@@ -0,0 +1,298 @@
+//! Maidenhead grid locators
+//!
+//! Standard messages carry a 4-character grid square (`FN42`) in the low
+//! 32400 values of the 15-bit `g15` field. EU VHF contest messages (i3=5)
+//! carry a 6-character locator (`FN31PR`), whose subsquare letters run
+//! `A`–`X`, in the 25-bit `g25` field.
+//!
+//! [`grid_to_latlon`] locates either form on the map, and
+//! [`grid_distance_km`] and [`grid_bearing_deg`] give the great-circle
+//! distance and beam heading between two grids.
+
+use alloc::format;
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::error::MessageError;
+
+/// Number of 4-character grid squares (18 × 18 × 10 × 10)
+pub const MAXGRID4: u32 = 32_400;
+
+/// Error converting a grid to a position: always
+/// [`MessageError::InvalidGrid`]
+pub type GridError = MessageError;
+
+/// Mean Earth radius used for great-circle distances, in km
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Number of 6-character locators (18 × 18 × 10 × 10 × 24 × 24)
+pub const MAXGRID6: u32 = MAXGRID4 * 24 * 24;
+
+/// Check that `grid` is a 4-character Maidenhead square (`AA00`–`RR99`).
+pub fn validate_grid_basic(grid: &str) -> bool {
+    let g = grid.as_bytes();
+    g.len() == 4
+        && (b'A'..=b'R').contains(&g[0])
+        && (b'A'..=b'R').contains(&g[1])
+        && g[2].is_ascii_digit()
+        && g[3].is_ascii_digit()
+}
+
+/// Encode a 4-character grid square to its index (0..32400).
+pub fn encode_grid4(grid: &str) -> Result<u32, MessageError> {
+    let upper = grid.to_uppercase();
+    if !validate_grid_basic(&upper) {
+        return Err(MessageError::InvalidGrid(grid.into()));
+    }
+    let g = upper.as_bytes();
+    let j1 = (g[0] - b'A') as u32;
+    let j2 = (g[1] - b'A') as u32;
+    let j3 = (g[2] - b'0') as u32;
+    let j4 = (g[3] - b'0') as u32;
+    Ok(((j1 * 18 + j2) * 10 + j3) * 10 + j4)
+}
+
+/// Decode a grid square index (0..32400) to its 4-character form.
+pub fn decode_grid4(value: u32) -> Result<String, MessageError> {
+    if value >= MAXGRID4 {
+        return Err(MessageError::Decode(format!(
+            "Grid value out of range: {}",
+            value
+        )));
+    }
+    let j4 = value % 10;
+    let j3 = (value / 10) % 10;
+    let j2 = (value / 100) % 18;
+    let j1 = value / 1800;
+    Ok(format!(
+        "{}{}{}{}",
+        (b'A' + j1 as u8) as char,
+        (b'A' + j2 as u8) as char,
+        j3,
+        j4
+    ))
+}
+
+/// Check that `grid` is a 6-character locator (`AA00AA`–`RR99XX`).
+pub fn validate_grid6(grid: &str) -> bool {
+    let g = grid.as_bytes();
+    g.len() == 6
+        && grid.is_ascii()
+        && validate_grid_basic(&grid[..4])
+        && (b'A'..=b'X').contains(&g[4])
+        && (b'A'..=b'X').contains(&g[5])
+}
+
+/// Encode a 6-character locator to its index (0..[`MAXGRID6`]).
+///
+/// The subsquare letters may be either case. A locator whose subsquare is
+/// out of range is an error, not truncated to its square.
+pub fn encode_grid6(grid: &str) -> Result<u32, MessageError> {
+    let upper = grid.to_uppercase();
+    if !validate_grid6(&upper) {
+        return Err(MessageError::InvalidGrid(grid.into()));
+    }
+    let g = upper.as_bytes();
+    let j5 = (g[4] - b'A') as u32;
+    let j6 = (g[5] - b'A') as u32;
+    Ok((encode_grid4(&upper[..4])? * 24 + j5) * 24 + j6)
+}
+
+/// Decode a locator index (0..[`MAXGRID6`]) to its 6-character form
+/// (uppercase).
+pub fn decode_grid6(value: u32) -> Result<String, MessageError> {
+    if value >= MAXGRID6 {
+        return Err(MessageError::Decode(format!(
+            "Grid6 value out of range: {}",
+            value
+        )));
+    }
+    let j6 = value % 24;
+    let j5 = (value / 24) % 24;
+    Ok(format!(
+        "{}{}{}",
+        decode_grid4(value / 576)?,
+        (b'A' + j5 as u8) as char,
+        (b'A' + j6 as u8) as char
+    ))
+}
+
+/// Latitude and longitude in degrees (north and east positive) of the
+/// center of a 4-character square or 6-character subsquare.
+///
+/// A square spans 2° of longitude by 1° of latitude, so at the equator
+/// its corners are about 124 km from the center; a subsquare spans 5′ by
+/// 2.5′, about 5 km. Either case is accepted.
+pub fn grid_to_latlon(grid: &str) -> Result<(f64, f64), GridError> {
+    let upper = grid.to_uppercase();
+    let g = upper.as_bytes();
+    let (lon_step, lat_step) = match g.len() {
+        4 if validate_grid_basic(&upper) => (2.0, 1.0),
+        6 if validate_grid6(&upper) => (2.0 / 24.0, 1.0 / 24.0),
+        _ => return Err(MessageError::InvalidGrid(grid.into())),
+    };
+
+    let mut lon = -180.0 + (g[0] - b'A') as f64 * 20.0 + (g[2] - b'0') as f64 * 2.0;
+    let mut lat = -90.0 + (g[1] - b'A') as f64 * 10.0 + (g[3] - b'0') as f64;
+    if g.len() == 6 {
+        lon += (g[4] - b'A') as f64 * lon_step;
+        lat += (g[5] - b'A') as f64 * lat_step;
+    }
+    Ok((lat + lat_step / 2.0, lon + lon_step / 2.0))
+}
+
+/// [`grid_to_latlon`] in radians.
+fn latlon_radians(grid: &str) -> Result<(f64, f64), GridError> {
+    let (lat, lon) = grid_to_latlon(grid)?;
+    Ok((lat.to_radians(), lon.to_radians()))
+}
+
+/// Great-circle distance in km between the centers of two grids.
+///
+/// Uses the haversine formula on a spherical Earth, which is accurate to
+/// about 0.5%, well within the size of a grid square.
+pub fn grid_distance_km(a: &str, b: &str) -> Result<f64, GridError> {
+    let (lat1, lon1) = latlon_radians(a)?;
+    let (lat2, lon2) = latlon_radians(b)?;
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    Ok(2.0 * EARTH_RADIUS_KM * h.sqrt().atan2((1.0 - h).sqrt()))
+}
+
+/// Initial great-circle bearing in degrees (0–360, clockwise from true
+/// north) from the center of grid `a` towards grid `b`: the beam heading
+/// at `a`. Zero when the grids are the same.
+pub fn grid_bearing_deg(a: &str, b: &str) -> Result<f64, GridError> {
+    let (lat1, lon1) = latlon_radians(a)?;
+    let (lat2, lon2) = latlon_radians(b)?;
+    let dlon = lon2 - lon1;
+    let y = dlon.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+    // atan2 gives -180..=180°; fold the western half onto 180..360°
+    Ok((y.atan2(x).to_degrees() + 360.0) % 360.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_values() {
+        assert_eq!(encode_grid4("AA00"), Ok(0));
+        assert_eq!(encode_grid4("RR99"), Ok(MAXGRID4 - 1));
+        // F=5, N=13 → (5*18+13)*100 + 42
+        assert_eq!(encode_grid4("FN42"), Ok(10342));
+        assert_eq!(encode_grid4("fn42"), Ok(10342));
+    }
+
+    #[test]
+    fn test_grid_roundtrip() {
+        for grid in ["FN42", "DM42", "EN37", "JO22", "IO91", "AA00", "RR99"] {
+            assert_eq!(decode_grid4(encode_grid4(grid).unwrap()).unwrap(), grid);
+        }
+    }
+
+    #[test]
+    fn test_invalid_grids() {
+        assert!(!validate_grid_basic("SS00"));
+        assert!(!validate_grid_basic("FN4"));
+        assert!(!validate_grid_basic("FNAB"));
+        assert!(encode_grid4("ZZ99").is_err());
+        assert!(decode_grid4(MAXGRID4).is_err());
+    }
+
+    #[test]
+    fn test_grid6_values() {
+        assert_eq!(encode_grid6("AA00AA"), Ok(0));
+        assert_eq!(encode_grid6("RR99XX"), Ok(MAXGRID6 - 1));
+        // FN31 = 10331, then P=15, R=17 in base 24
+        assert_eq!(encode_grid6("FN31PR"), Ok((10331 * 24 + 15) * 24 + 17));
+        assert_eq!(encode_grid6("FN31pr"), encode_grid6("FN31PR"));
+        // IO91 = 15891, N=13, P=15
+        assert_eq!(encode_grid6("IO91NP"), Ok(9_153_543));
+    }
+
+    #[test]
+    fn test_grid6_roundtrip() {
+        for grid in ["FN31PR", "IO91NP", "JO22AA", "DM42XX", "AA00AA", "RR99XX"] {
+            assert_eq!(decode_grid6(encode_grid6(grid).unwrap()).unwrap(), grid);
+        }
+        assert_eq!(
+            decode_grid6(encode_grid6("fn31pr").unwrap()).unwrap(),
+            "FN31PR"
+        );
+    }
+
+    #[test]
+    fn test_invalid_grid6() {
+        assert_eq!(
+            encode_grid6("FN31zz"),
+            Err(MessageError::InvalidGrid("FN31zz".into()))
+        );
+        assert!(encode_grid6("FN31").is_err());
+        assert!(encode_grid6("SN31AA").is_err());
+        assert!(encode_grid6("FN3XAA").is_err());
+        assert!(encode_grid6("FN31PRX").is_err());
+        assert!(!validate_grid6("FN31YA"));
+        assert!(decode_grid6(MAXGRID6).is_err());
+    }
+
+    #[test]
+    fn test_grid_to_latlon() {
+        let close = |grid: &str, lat: f64, lon: f64| {
+            let (la, lo) = grid_to_latlon(grid).unwrap();
+            assert!(
+                (la - lat).abs() < 1e-3 && (lo - lon).abs() < 1e-3,
+                "{}: {}, {}",
+                grid,
+                la,
+                lo
+            );
+        };
+        // W1AW, Newington CT, and central London
+        close("FN31pr", 41.729, -72.708);
+        close("IO91wm", 51.521, -0.125);
+        close("FN31", 41.5, -73.0);
+        // Corners of the map
+        close("AA00", -89.5, -179.0);
+        close("RR99", 89.5, 179.0);
+        close("RR99XX", 89.979, 179.958);
+
+        for bad in ["FN3", "FN31P", "SN31", "FN31ZZ", "FN31PRX", ""] {
+            assert_eq!(
+                grid_to_latlon(bad),
+                Err(MessageError::InvalidGrid(bad.into())),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_grid_distance_and_bearing() {
+        // W1AW to London: about 5400 km, heading north-east
+        let d = grid_distance_km("FN31PR", "IO91WM").unwrap();
+        assert!((d - 5415.0).abs() < 5.0, "{}", d);
+        let b = grid_bearing_deg("FN31PR", "IO91WM").unwrap();
+        assert!((b - 52.2).abs() < 0.5, "{}", b);
+        let back = grid_bearing_deg("IO91WM", "FN31PR").unwrap();
+        assert!((back - 288.6).abs() < 0.5, "{}", back);
+
+        // Across the antimeridian the short way: 2° of longitude apart
+        let d = grid_distance_km("RJ90", "AJ00").unwrap();
+        assert!((d - 222.4).abs() < 0.5, "{}", d);
+        assert!((grid_bearing_deg("RJ90", "AJ00").unwrap() - 90.0).abs() < 0.1);
+        assert!((grid_bearing_deg("AJ00", "RJ90").unwrap() - 270.0).abs() < 0.1);
+
+        // Over the pole: 180° of longitude apart at 89.5° N is 1° of arc
+        let d = grid_distance_km("AR09", "JR09").unwrap();
+        assert!((d - 111.2).abs() < 0.5, "{}", d);
+        assert!(grid_bearing_deg("AR09", "JR09").unwrap() < 0.1);
+
+        assert_eq!(grid_distance_km("FN31", "FN31").unwrap(), 0.0);
+        assert_eq!(grid_bearing_deg("FN31", "FN31").unwrap(), 0.0);
+        assert!(grid_distance_km("FN31", "ZZ00").is_err());
+        assert!(grid_bearing_deg("XX", "FN31").is_err());
+    }
+}
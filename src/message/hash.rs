@@ -0,0 +1,537 @@
+//! Callsign hashing
+//!
+//! Non-standard and compound callsigns that do not fit the 28-bit standard
+//! encoding are sent as 10-, 12- or 22-bit hashes. A receiver can only turn a
+//! hash back into a callsign if it has seen the full callsign before, so both
+//! the encoder and the decoder record callsigns in a [`CallsignHashCache`].
+//! The cache can be exported with [`CallsignHashCache::to_entries`] and
+//! reloaded with [`CallsignHashCache::from_entries`], or with the `serde`
+//! feature saved directly, so callsigns seen in one session still resolve
+//! in the next. A long-running decoder can bound the cache with
+//! [`CallsignHashCache::with_capacity`], which forgets the callsigns heard
+//! longest ago. With `std` the hash tables are hash maps, so recording and
+//! looking up a callsign take constant time; without it they are B-trees.
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as Map;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap as Map;
+
+/// Character set used by the WSJT-X `ihashcall` routine
+const HASH_CHARS: &[u8] = b" 0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ/";
+
+/// Multiplier from WSJT-X `ihashcall`
+const HASH_MULTIPLIER: u64 = 47_055_833_459;
+
+/// Compute the WSJT-X `ihashcall` hash of `callsign` with `bits` bits (10, 12 or 22).
+///
+/// The callsign is left-justified in an 11-character field padded with spaces.
+/// Angle brackets around the callsign are ignored.
+pub fn ihashcall(callsign: &str, bits: u32) -> u32 {
+    debug_assert!(bits > 0 && bits <= 32);
+    let call = strip_brackets(callsign).as_bytes();
+
+    let mut n8: u64 = 0;
+    for i in 0..11 {
+        let c = call.get(i).map(|c| c.to_ascii_uppercase()).unwrap_or(b' ');
+        let j = HASH_CHARS.iter().position(|&h| h == c).unwrap_or(0) as u64;
+        n8 = 38 * n8 + j;
+    }
+
+    (HASH_MULTIPLIER.wrapping_mul(n8) >> (64 - bits)) as u32
+}
+
+/// Remove surrounding `<` `>` from a hashed callsign reference.
+pub(crate) fn strip_brackets(callsign: &str) -> &str {
+    callsign.trim_start_matches('<').trim_end_matches('>')
+}
+
+/// Lookup table from callsign hashes back to callsigns
+///
+/// Each callsign is kept once, under its 22-bit hash, and listed under its
+/// 10- and 12-bit hashes along with any other callsigns sharing them; a
+/// short hash resolves to the one of those inserted last. The callsigns are
+/// also linked in the order they were last inserted, which a bounded cache
+/// evicts from.
+///
+/// With the `serde` feature the cache serializes as its
+/// [`to_entries`](Self::to_entries) list; the capacity is not saved.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        into = "Vec<(String, u32, u32, u32)>",
+        from = "Vec<(String, u32, u32, u32)>"
+    )
+)]
+pub struct CallsignHashCache {
+    /// Callsigns and their hashes, indexed by the tables below
+    slots: Vec<Slot>,
+    /// Slots freed by eviction, for reuse
+    free: Vec<usize>,
+    /// Least recently inserted callsign
+    oldest: Option<usize>,
+    /// Most recently inserted callsign
+    newest: Option<usize>,
+    hash10: Map<u32, Vec<usize>>,
+    hash12: Map<u32, Vec<usize>>,
+    hash22: Map<u32, usize>,
+    /// Most callsigns kept, or `None` for no limit
+    capacity: Option<usize>,
+}
+
+/// A cached callsign, linked into the insertion order
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    call: String,
+    h10: u32,
+    h12: u32,
+    h22: u32,
+    /// Callsign inserted just before this one
+    older: Option<usize>,
+    /// Callsign inserted just after this one
+    newer: Option<usize>,
+}
+
+impl CallsignHashCache {
+    /// Create an empty cache with no limit on its size.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty cache that holds at most `capacity` callsigns.
+    ///
+    /// Once full, each new callsign evicts the one inserted longest ago.
+    /// Inserting a callsign again counts as hearing it again, so stations
+    /// still on the air stay resolvable while stale ones drop out.
+    /// Lookups do not refresh a callsign.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// The most callsigns the cache keeps, or `None` if it is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Record a callsign under its 10-, 12- and 22-bit hashes.
+    ///
+    /// Brackets are stripped and the callsign is uppercased. Placeholders
+    /// such as `...` are ignored. A full bounded cache first forgets its
+    /// least recently inserted callsign.
+    pub fn insert(&mut self, callsign: &str) {
+        let call = strip_brackets(callsign.trim()).to_uppercase();
+        if call.is_empty() || call == "..." {
+            return;
+        }
+        let (h22, h12, h10) = (
+            ihashcall(&call, 22),
+            ihashcall(&call, 12),
+            ihashcall(&call, 10),
+        );
+        self.record(call, h22, h12, h10);
+
+        let Some(capacity) = self.capacity else {
+            return;
+        };
+        while self.hash22.len() > capacity {
+            let Some(oldest) = self.oldest else {
+                break;
+            };
+            self.evict(oldest);
+        }
+    }
+
+    /// Store `call` under the given hashes as the newest callsign. A
+    /// callsign already under `h22` is replaced and its slot reused.
+    fn record(&mut self, call: String, h22: u32, h12: u32, h10: u32) {
+        let slot = match self.hash22.get(&h22) {
+            Some(&slot) => {
+                self.unlink(slot);
+                slot
+            }
+            None => {
+                let slot = self.free.pop().unwrap_or_else(|| {
+                    self.slots.push(Slot::default());
+                    self.slots.len() - 1
+                });
+                self.hash22.insert(h22, slot);
+                slot
+            }
+        };
+        self.slots[slot] = Slot {
+            call,
+            h10,
+            h12,
+            h22,
+            older: self.newest,
+            newer: None,
+        };
+        match self.newest {
+            Some(newest) => self.slots[newest].newer = Some(slot),
+            None => self.oldest = Some(slot),
+        }
+        self.newest = Some(slot);
+        // Lookups take the end of a short-hash list
+        self.hash10.entry(h10).or_default().push(slot);
+        self.hash12.entry(h12).or_default().push(slot);
+    }
+
+    /// Take `slot` out of the insertion order and its short-hash lists.
+    fn unlink(&mut self, slot: usize) {
+        let Slot {
+            h10,
+            h12,
+            older,
+            newer,
+            ..
+        } = self.slots[slot];
+        match older {
+            Some(older) => self.slots[older].newer = newer,
+            None => self.oldest = newer,
+        }
+        match newer {
+            Some(newer) => self.slots[newer].older = older,
+            None => self.newest = older,
+        }
+        remove_from(&mut self.hash10, h10, slot);
+        remove_from(&mut self.hash12, h12, slot);
+    }
+
+    /// Forget the callsign in `slot`. Any other callsign sharing one of
+    /// its shorter hashes stays resolvable under it.
+    fn evict(&mut self, slot: usize) {
+        self.unlink(slot);
+        self.hash22.remove(&self.slots[slot].h22);
+        self.slots[slot] = Slot::default();
+        self.free.push(slot);
+    }
+
+    /// The callsign last inserted among those listed under a short hash.
+    fn last_in(&self, table: &Map<u32, Vec<usize>>, hash: u32) -> Option<&str> {
+        let &slot = table.get(&hash)?.last()?;
+        Some(&self.slots[slot].call)
+    }
+
+    /// Look up a 10-bit hash.
+    pub fn lookup10(&self, hash: u32) -> Option<&str> {
+        self.last_in(&self.hash10, hash)
+    }
+
+    /// Look up a 12-bit hash.
+    pub fn lookup12(&self, hash: u32) -> Option<&str> {
+        self.last_in(&self.hash12, hash)
+    }
+
+    /// Look up a 22-bit hash.
+    pub fn lookup22(&self, hash: u32) -> Option<&str> {
+        let &slot = self.hash22.get(&hash)?;
+        Some(&self.slots[slot].call)
+    }
+
+    /// Every known callsign whose `width`-bit hash is `hash`, in 22-bit
+    /// hash order.
+    ///
+    /// The `lookup` methods return only the last callsign inserted under a
+    /// 10- or 12-bit hash, and 10-bit DXpedition hashes collide often. More
+    /// than one candidate means the hash is ambiguous. `width` is 10, 12 or
+    /// 22; any other width gives no candidates.
+    pub fn candidates_for_hash(&self, hash: u32, width: u32) -> Vec<&str> {
+        let mut slots: Vec<&Slot> = match width {
+            10 | 12 => {
+                let table = if width == 10 {
+                    &self.hash10
+                } else {
+                    &self.hash12
+                };
+                table
+                    .get(&hash)
+                    .into_iter()
+                    .flatten()
+                    .map(|&slot| &self.slots[slot])
+                    .collect()
+            }
+            22 => self
+                .hash22
+                .get(&hash)
+                .map(|&slot| &self.slots[slot])
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        };
+        slots.sort_unstable_by_key(|slot| slot.h22);
+        slots.into_iter().map(|slot| slot.call.as_str()).collect()
+    }
+
+    /// Number of distinct callsigns known to the cache.
+    pub fn len(&self) -> usize {
+        self.hash22.len()
+    }
+
+    /// Whether the cache holds no callsigns.
+    pub fn is_empty(&self) -> bool {
+        self.hash22.is_empty()
+    }
+
+    /// Whether `callsign` has been recorded.
+    pub fn contains(&self, callsign: &str) -> bool {
+        let call = strip_brackets(callsign.trim()).to_uppercase();
+        self.lookup22(ihashcall(&call, 22)) == Some(call.as_str())
+    }
+
+    /// Every known callsign with its 22-, 12- and 10-bit hashes, in
+    /// 22-bit hash order.
+    pub fn to_entries(&self) -> Vec<(String, u32, u32, u32)> {
+        let mut entries: Vec<_> = self
+            .hash22
+            .values()
+            .map(|&slot| {
+                let s = &self.slots[slot];
+                (s.call.clone(), s.h22, s.h12, s.h10)
+            })
+            .collect();
+        entries.sort_unstable_by_key(|e| e.1);
+        entries
+    }
+
+    /// Rebuild a cache from [`to_entries`](Self::to_entries).
+    ///
+    /// Each callsign is recorded under the hashes given with it. Where two
+    /// entries share a 10- or 12-bit hash, the later one wins.
+    pub fn from_entries(entries: impl IntoIterator<Item = (String, u32, u32, u32)>) -> Self {
+        let mut cache = Self::new();
+        for (call, h22, h12, h10) in entries {
+            cache.record(call, h22, h12, h10);
+        }
+        cache
+    }
+}
+
+/// Drop `slot` from the list under `hash`, and the list once empty.
+fn remove_from(table: &mut Map<u32, Vec<usize>>, hash: u32, slot: usize) {
+    if let Some(list) = table.get_mut(&hash) {
+        list.retain(|&s| s != slot);
+        if list.is_empty() {
+            table.remove(&hash);
+        }
+    }
+}
+
+impl From<Vec<(String, u32, u32, u32)>> for CallsignHashCache {
+    fn from(entries: Vec<(String, u32, u32, u32)>) -> Self {
+        Self::from_entries(entries)
+    }
+}
+
+impl From<CallsignHashCache> for Vec<(String, u32, u32, u32)> {
+    fn from(cache: CallsignHashCache) -> Self {
+        cache.to_entries()
+    }
+}
+
+/// Render a hashed callsign for display: `<CALL>` if known, `<...>` otherwise.
+pub(crate) fn render_hashed(found: Option<&str>) -> String {
+    match found {
+        Some(call) => ["<", call, ">"].concat(),
+        None => "<...>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A K1 callsign with the same 10-bit hash as `call`
+    fn sharing_hash10(call: &str) -> String {
+        let h10 = ihashcall(call, 10);
+        (0..26 * 26 * 26)
+            .map(|n| {
+                let letter = |i: u32| (b'A' + (n / 26u32.pow(i) % 26) as u8) as char;
+                alloc::format!("K1{}{}{}", letter(2), letter(1), letter(0))
+            })
+            .find(|other| ihashcall(other, 10) == h10)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hash_widths_are_prefixes() {
+        // All widths come from the top bits of the same 64-bit product
+        let h22 = ihashcall("PJ4/K1ABC", 22);
+        let h12 = ihashcall("PJ4/K1ABC", 12);
+        let h10 = ihashcall("PJ4/K1ABC", 10);
+        assert!(h22 < (1 << 22));
+        assert_eq!(h22 >> 10, h12);
+        assert_eq!(h22 >> 12, h10);
+    }
+
+    #[test]
+    fn test_hash_ignores_brackets_and_case() {
+        assert_eq!(ihashcall("<yw18fifa>", 22), ihashcall("YW18FIFA", 22));
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let mut cache = CallsignHashCache::new();
+        assert!(cache.is_empty());
+        cache.insert("KH1/KH7Z");
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains("<KH1/KH7Z>"));
+        assert_eq!(cache.lookup22(ihashcall("KH1/KH7Z", 22)), Some("KH1/KH7Z"));
+        assert_eq!(cache.lookup12(ihashcall("KH1/KH7Z", 12)), Some("KH1/KH7Z"));
+        assert_eq!(cache.lookup10(ihashcall("KH1/KH7Z", 10)), Some("KH1/KH7Z"));
+        assert_eq!(cache.lookup22(ihashcall("W9XYZ", 22)), None);
+    }
+
+    #[test]
+    fn test_candidates_for_colliding_hash() {
+        let h10 = ihashcall("KH1/KH7Z", 10);
+        let other = sharing_hash10("KH1/KH7Z");
+        assert_ne!(ihashcall(&other, 22), ihashcall("KH1/KH7Z", 22));
+
+        let mut cache = CallsignHashCache::new();
+        cache.insert("KH1/KH7Z");
+        cache.insert(&other);
+        cache.insert("W9XYZ");
+        let mut candidates = cache.candidates_for_hash(h10, 10);
+        candidates.sort_unstable();
+        let mut expected = [other.as_str(), "KH1/KH7Z"];
+        expected.sort_unstable();
+        assert_eq!(candidates, expected);
+        // The plain lookup only knows the later one
+        assert_eq!(cache.lookup10(h10), Some(other.as_str()));
+
+        // The longer hashes tell them apart
+        let h12 = ihashcall("KH1/KH7Z", 12);
+        assert_eq!(cache.candidates_for_hash(h12, 12), ["KH1/KH7Z"]);
+        assert_eq!(
+            cache.candidates_for_hash(ihashcall("W9XYZ", 22), 22),
+            ["W9XYZ"]
+        );
+        assert!(cache.candidates_for_hash(h10, 11).is_empty());
+    }
+
+    #[test]
+    fn test_entries_roundtrip() {
+        let mut cache = CallsignHashCache::new();
+        cache.insert("KH1/KH7Z");
+        cache.insert("YW18FIFA");
+        let entries = cache.to_entries();
+        assert_eq!(entries.len(), 2);
+        let kh1 = entries.iter().find(|e| e.0 == "KH1/KH7Z").unwrap();
+        assert_eq!(
+            (kh1.1, kh1.2, kh1.3),
+            (
+                ihashcall("KH1/KH7Z", 22),
+                ihashcall("KH1/KH7Z", 12),
+                ihashcall("KH1/KH7Z", 10)
+            )
+        );
+
+        let reloaded = CallsignHashCache::from_entries(entries.clone());
+        assert_eq!(reloaded.to_entries(), entries);
+        assert_eq!(reloaded.lookup10(kh1.3), Some("KH1/KH7Z"));
+        assert!(reloaded.contains("YW18FIFA"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_saved_cache_resolves_dxpedition_hash() {
+        use crate::message::{decode, encode_variant, MessageVariant};
+        use bitvec::prelude::*;
+
+        // Yesterday's session saw the DXpedition's full callsign
+        let mut yesterday = CallsignHashCache::new();
+        yesterday.insert("KH1/KH7Z");
+        let saved = serde_json::to_string(&yesterday).unwrap();
+
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        let message = MessageVariant::Dxpedition {
+            call1: "K1ABC".into(),
+            call2: "W9XYZ".into(),
+            call3: "KH1/KH7Z".into(),
+            report: -8,
+        };
+        encode_variant(&message, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+        let fresh = CallsignHashCache::new();
+        assert_eq!(
+            decode(&bits[..77], Some(&fresh)).unwrap(),
+            "K1ABC RR73; W9XYZ <...> -08"
+        );
+
+        let today: CallsignHashCache = serde_json::from_str(&saved).unwrap();
+        assert_eq!(
+            decode(&bits[..77], Some(&today)).unwrap(),
+            "K1ABC RR73; W9XYZ <KH1/KH7Z> -08"
+        );
+    }
+
+    #[test]
+    fn test_bounded_cache_evicts_least_recently_heard() {
+        let mut cache = CallsignHashCache::with_capacity(2);
+        assert_eq!(cache.capacity(), Some(2));
+        assert_eq!(CallsignHashCache::new().capacity(), None);
+
+        cache.insert("KH1/KH7Z");
+        cache.insert("YW18FIFA");
+        cache.insert("PJ4/K1ABC");
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains("KH1/KH7Z"));
+        assert_eq!(cache.lookup10(ihashcall("KH1/KH7Z", 10)), None);
+        assert_eq!(cache.lookup12(ihashcall("KH1/KH7Z", 12)), None);
+        assert_eq!(
+            cache.lookup10(ihashcall("PJ4/K1ABC", 10)),
+            Some("PJ4/K1ABC")
+        );
+
+        // Hearing a callsign again keeps it; the other one goes next
+        cache.insert("YW18FIFA");
+        cache.insert("KH1/KH7Z");
+        assert!(cache.contains("YW18FIFA"));
+        assert!(cache.contains("KH1/KH7Z"));
+        assert!(!cache.contains("PJ4/K1ABC"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.to_entries().len(), 2);
+    }
+
+    #[test]
+    fn test_eviction_keeps_colliding_callsign_resolvable() {
+        let h10 = ihashcall("KH1/KH7Z", 10);
+        let other = sharing_hash10("KH1/KH7Z");
+
+        let mut cache = CallsignHashCache::with_capacity(2);
+        cache.insert(&other);
+        cache.insert("KH1/KH7Z");
+        cache.insert("W9XYZ");
+        // The evicted callsign shared the survivor's 10-bit hash
+        assert!(!cache.contains(&other));
+        assert_eq!(cache.lookup10(h10), Some("KH1/KH7Z"));
+        assert_eq!(cache.candidates_for_hash(h10, 10), ["KH1/KH7Z"]);
+
+        // Whichever of two colliding callsigns is evicted, the other
+        // still resolves
+        let mut cache = CallsignHashCache::with_capacity(3);
+        cache.insert("KH1/KH7Z");
+        cache.insert(&other);
+        cache.insert("KH1/KH7Z");
+        cache.insert("W9XYZ");
+        cache.insert("YW18FIFA");
+        assert!(!cache.contains(&other));
+        assert_eq!(cache.lookup10(h10), Some("KH1/KH7Z"));
+        cache.insert(&other);
+        assert!(!cache.contains("KH1/KH7Z"));
+        assert_eq!(cache.lookup10(h10), Some(other.as_str()));
+    }
+
+    #[test]
+    fn test_placeholder_not_cached() {
+        let mut cache = CallsignHashCache::new();
+        cache.insert("<...>");
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,1097 @@
+//! FT8 message packing
+//!
+//! Converts between human-readable message text and the 77-bit source
+//! encoding defined by WSJT-X 2.x. The last three bits hold the message type
+//! `i3`; for `i3 == 0` the three bits before them hold the subtype `n3`.
+//!
+//! | i3 | n3 | type                          | module          |
+//! |----|----|-------------------------------|-----------------|
+//! | 0  | 0  | free text                     | `free_text`     |
+//! | 0  | 1  | DXpedition                    | `dxpedition`    |
+//! | 0  | 3,4| ARRL Field Day                | `field_day`     |
+//! | 0  | 5  | telemetry                     | `telemetry`     |
+//! | 1  |    | standard                      | `standard`      |
+//! | 2  |    | EU VHF (`/P`)                 | `standard`      |
+//! | 3  |    | ARRL RTTY Roundup             | `rtty`          |
+//! | 4  |    | non-standard callsign         | `nonstandard`   |
+//! | 5  |    | EU VHF with serial and grid6  | `eu_vhf`        |
+//!
+//! This module only needs `alloc` and is usable in `no_std` builds.
+//!
+//! Decoding cannot always recover the exact input text: hashed callsigns
+//! only resolve when the [`CallsignHashCache`] has seen them, and redundant
+//! spacing or case in the input is normalized away.
+
+pub(crate) mod bits;
+pub mod callsign;
+mod dxpedition;
+mod error;
+mod eu_vhf;
+mod field_day;
+mod free_text;
+pub mod grid;
+mod hash;
+mod nonstandard;
+mod parser;
+mod rtty;
+mod standard;
+mod telemetry;
+mod tx;
+mod types;
+
+use alloc::string::{String, ToString};
+use bitvec::prelude::*;
+
+pub use callsign::{classify_callsign, pack_callsign, unpack_callsign, CallsignKind};
+pub use error::MessageError;
+pub use hash::{ihashcall, CallsignHashCache};
+pub use parser::{
+    explain_parse, parse_message_variant, parse_message_variant_strict, ParseAttempt, ParseRule,
+    ParseTrace,
+};
+pub use tx::{tx_cq, tx_grid, tx_report, tx_rr73};
+pub use types::{GridReport, MessageVariant, RttyExchange};
+
+/// Number of bits in a packed message
+pub const MESSAGE_BITS: usize = 77;
+
+/// Encode a parsed message variant into 77 bits.
+///
+/// Text fields may be in any case: the variant is encoded as
+/// [`MessageVariant::to_uppercase`] gives it, so lowercase callsigns,
+/// grids, sections and free text encode exactly as uppercase ones do.
+pub fn encode_variant(
+    variant: &MessageVariant,
+    bits: &mut BitSlice<u8, Msb0>,
+    cache: &mut CallsignHashCache,
+) -> Result<(), MessageError> {
+    if bits.len() != MESSAGE_BITS {
+        return Err(MessageError::BufferWrongSize {
+            expected: MESSAGE_BITS,
+            got: bits.len(),
+        });
+    }
+    bits.fill(false);
+
+    match &variant.to_uppercase() {
+        MessageVariant::FreeText { text } => free_text::encode(text, bits),
+        MessageVariant::Dxpedition {
+            call1,
+            call2,
+            call3,
+            report,
+        } => dxpedition::encode(call1, call2, call3, *report, bits, cache),
+        MessageVariant::FieldDay {
+            call1,
+            call2,
+            r_flag,
+            transmitters,
+            class,
+            section,
+        } => field_day::encode(
+            call1,
+            call2,
+            *r_flag,
+            *transmitters,
+            *class,
+            section,
+            bits,
+            cache,
+        ),
+        MessageVariant::Telemetry { hex } => telemetry::encode(hex, bits),
+        MessageVariant::Standard {
+            call1,
+            call1_suffix,
+            call2,
+            call2_suffix,
+            r_flag,
+            payload,
+        } => standard::encode(
+            1,
+            call1,
+            *call1_suffix,
+            call2,
+            *call2_suffix,
+            *r_flag,
+            payload,
+            bits,
+            cache,
+        ),
+        MessageVariant::EuVhfContestType2 {
+            call1,
+            call1_suffix,
+            call2,
+            call2_suffix,
+            r_flag,
+            payload,
+        } => standard::encode(
+            2,
+            call1,
+            *call1_suffix,
+            call2,
+            *call2_suffix,
+            *r_flag,
+            payload,
+            bits,
+            cache,
+        ),
+        MessageVariant::RttyRoundup {
+            tu,
+            call1,
+            call2,
+            r_flag,
+            rst,
+            exchange,
+        } => rtty::encode(*tu, call1, call2, *r_flag, *rst, exchange, bits, cache),
+        MessageVariant::NonStandardCall {
+            hashed_call,
+            full_call,
+            hashed_first,
+            payload,
+            cq,
+        } => nonstandard::encode(
+            hashed_call,
+            full_call,
+            *hashed_first,
+            payload,
+            *cq,
+            bits,
+            cache,
+        ),
+        MessageVariant::EuVhfContestType5 {
+            call1,
+            call2,
+            r_flag,
+            rst,
+            serial,
+            grid6,
+        } => eu_vhf::encode(call1, call2, *r_flag, *rst, *serial, grid6, bits, cache),
+    }
+}
+
+/// Encode message text into 77 bits.
+///
+/// `bits` must be exactly 77 bits long. Callsigns in the message are
+/// recorded in `cache` so that later hashed references to them resolve.
+///
+/// Case does not matter: the text is uppercased before parsing, and free
+/// text is always sent in uppercase.
+pub fn encode(
+    text: &str,
+    bits: &mut BitSlice<u8, Msb0>,
+    cache: &mut CallsignHashCache,
+) -> Result<(), MessageError> {
+    let variant = parse_message_variant(text)?;
+    encode_variant(&variant, bits, cache)
+}
+
+/// [`encode`] into a new vector of exactly 77 bits.
+pub fn encode_to_vec(
+    text: &str,
+    cache: &mut CallsignHashCache,
+) -> Result<BitVec<u8, Msb0>, MessageError> {
+    encode_variant_to_vec(&parse_message_variant(text)?, cache)
+}
+
+/// [`encode`] into 10 bytes, most significant bit first, with the 77 bits
+/// left-aligned and the last three bits zero.
+pub fn encode_bytes(text: &str, cache: &mut CallsignHashCache) -> Result<[u8; 10], MessageError> {
+    let mut bits = BitArray::<[u8; 10], Msb0>::ZERO;
+    encode(text, &mut bits[..MESSAGE_BITS], cache)?;
+    Ok(bits.into_inner())
+}
+
+/// [`encode_variant`] into a new vector of exactly 77 bits.
+fn encode_variant_to_vec(
+    variant: &MessageVariant,
+    cache: &mut CallsignHashCache,
+) -> Result<BitVec<u8, Msb0>, MessageError> {
+    let mut bits = bitvec![u8, Msb0; 0; MESSAGE_BITS];
+    encode_variant(variant, &mut bits, cache)?;
+    Ok(bits)
+}
+
+/// Encode `text` as free text (i3=0, n3=0), even when the parser would
+/// read it as another message type.
+///
+/// Case and runs of spaces are normalized as [`encode`] does.
+pub fn encode_free_text(text: &str) -> Result<BitVec<u8, Msb0>, MessageError> {
+    let variant = MessageVariant::FreeText {
+        text: parser::normalize(text),
+    };
+    encode_variant_to_vec(&variant, &mut CallsignHashCache::new())
+}
+
+/// Encode up to 71 bits of hexadecimal telemetry (i3=0, n3=5).
+pub fn encode_telemetry(hex: &str) -> Result<BitVec<u8, Msb0>, MessageError> {
+    let variant = MessageVariant::Telemetry {
+        hex: hex.trim().to_string(),
+    };
+    encode_variant_to_vec(&variant, &mut CallsignHashCache::new())
+}
+
+/// Encode a standard message (i3=1), `CALL1 CALL2 [R]REPORT`, without
+/// going through the text parser.
+///
+/// Either callsign may end in `/R`, which is sent as the rover flag, and
+/// `call1` may be `CQ`, `QRZ`, `DE` or a directed CQ such as `CQ DX`.
+/// The callsigns are recorded in `cache` as [`encode`] does.
+pub fn encode_standard(
+    call1: &str,
+    call2: &str,
+    r_flag: bool,
+    report: GridReport,
+    cache: &mut CallsignHashCache,
+) -> Result<BitVec<u8, Msb0>, MessageError> {
+    let rover = |call: &str| {
+        let call = call.trim().to_uppercase();
+        match call.strip_suffix("/R") {
+            Some(base) => (base.to_string(), true),
+            None => (call, false),
+        }
+    };
+    let (call1, call1_suffix) = rover(call1);
+    let (call2, call2_suffix) = rover(call2);
+    let variant = MessageVariant::Standard {
+        call1,
+        call1_suffix,
+        call2,
+        call2_suffix,
+        r_flag,
+        payload: report,
+    };
+    encode_variant_to_vec(&variant, cache)
+}
+
+/// The 91-bit LDPC input for 77 message bits: the message followed by
+/// its CRC ([`crc::compute`](crate::crc::compute)), as [`ldpc::encode`](crate::ldpc::encode) takes it.
+pub fn payload(bits77: &BitSlice<u8, Msb0>) -> BitVec<u8, Msb0> {
+    let mut message91 = bitvec![u8, Msb0; 0; crate::ldpc::K];
+    crate::crc::append_crc(bits77, &mut message91);
+    message91
+}
+
+/// Decode 77 bits into a message variant.
+pub fn decode_variant(
+    bits: &BitSlice<u8, Msb0>,
+    cache: Option<&CallsignHashCache>,
+) -> Result<MessageVariant, MessageError> {
+    if bits.len() < MESSAGE_BITS {
+        return Err(MessageError::InputTooShort {
+            expected: MESSAGE_BITS,
+            got: bits.len(),
+        });
+    }
+    let i3 = bits::get_u32(bits, 74, 3);
+    let n3 = bits::get_u32(bits, 71, 3);
+
+    match (i3, n3) {
+        (0, 0) => free_text::decode(bits),
+        (0, 1) => dxpedition::decode(bits, cache),
+        (0, 3) | (0, 4) => field_day::decode(bits, cache),
+        (0, 5) => telemetry::decode(bits),
+        (0, n3) => Err(MessageError::UnsupportedType {
+            i3: 0,
+            n3: Some(n3 as u8),
+        }),
+        (1, _) | (2, _) => standard::decode(bits, cache),
+        (3, _) => rtty::decode(bits, cache),
+        (4, _) => nonstandard::decode(bits, cache),
+        (5, _) => eu_vhf::decode(bits, cache),
+        (i3, _) => Err(MessageError::UnsupportedType {
+            i3: i3 as u8,
+            n3: None,
+        }),
+    }
+}
+
+/// Decode 77 bits into message text.
+///
+/// Hashed callsigns are shown as `<CALL>` when `cache` knows them and as
+/// `<...>` otherwise.
+pub fn decode(
+    bits: &BitSlice<u8, Msb0>,
+    cache: Option<&CallsignHashCache>,
+) -> Result<String, MessageError> {
+    decode_variant(bits, cache).map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(text: &str, cache: &mut CallsignHashCache) -> String {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode(text, &mut bits[..77], cache).unwrap_or_else(|e| panic!("{}: {}", text, e));
+        decode(&bits[..77], Some(cache)).unwrap_or_else(|e| panic!("{}: {}", text, e))
+    }
+
+    #[test]
+    fn test_payload_appends_crc() {
+        let bits = encode_to_vec("CQ K1ABC FN42", &mut CallsignHashCache::new()).unwrap();
+        let message91 = payload(&bits);
+        assert_eq!(message91.len(), 91);
+        assert_eq!(message91[..77], bits[..]);
+        assert_eq!(message91[77..].load_be::<u16>(), crate::crc::compute(&bits));
+        assert!(crate::crc::verify(&message91));
+    }
+
+    #[test]
+    fn test_roundtrip_all_types() {
+        let cases = [
+            "CQ K1ABC FN42",
+            "K1ABC W9XYZ EN37",
+            "W9XYZ K1ABC -11",
+            "K1ABC W9XYZ R-09",
+            "W9XYZ K1ABC RRR",
+            "K1ABC W9XYZ 73",
+            "K1ABC W9XYZ RR73",
+            "CQ FD K1ABC FN42",
+            "CQ TEST K1ABC/R FN42",
+            "K1ABC/R W9XYZ EN37",
+            "W9XYZ K1ABC/R R FN42",
+            "K1ABC W9XYZ R EN37",
+            "K1ABC W9XYZ",
+            "CQ G4ABC/P IO91",
+            "G4ABC/P PA9XYZ JO22",
+            "G4ABC/P PA9XYZ/P JO22",
+            "PA9XYZ G4ABC/P RR73",
+            "PA9XYZ G4ABC/P R IO91",
+            "CQ 005 K1ABC FN42",
+            "QRZ K1ABC FN42",
+            "QRZ K1ABC",
+            "QRZ K1ABC/R FN42",
+            "QRZ G4ABC/P IO91",
+            "DE K1ABC FN42",
+            "<W9XYZ> PJ4/K1ABC RRR",
+            "PJ4/K1ABC <W9XYZ> 73",
+            "CQ PJ4/K1ABC",
+            "K1ABC W9XYZ 6A WI",
+            "W9XYZ K1ABC R 17B EMA",
+            "TU; W9XYZ K1ABC R 579 MA",
+            "K1ABC W9XYZ 579 0013",
+            "<PA3XYZ> <DL1ABC/P> R 590003 IO91NP",
+            "<K1ABC> <W9XYZ> 522047 FN31PR",
+            "123456789ABCDEF012",
+            "TNX BOB 73 GL",
+        ];
+        for text in cases {
+            let mut cache = CallsignHashCache::new();
+            assert_eq!(roundtrip(text, &mut cache), text);
+        }
+    }
+
+    #[test]
+    fn test_hashed_callsign_needs_cache() {
+        let mut cache = CallsignHashCache::new();
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode("W9XYZ <YW18FIFA> R-09", &mut bits[..77], &mut cache).unwrap();
+        assert_eq!(
+            decode(&bits[..77], Some(&cache)).unwrap(),
+            "W9XYZ <YW18FIFA> R-09"
+        );
+        assert_eq!(decode(&bits[..77], None).unwrap(), "W9XYZ <...> R-09");
+    }
+
+    #[test]
+    fn test_allocating_encoders_match_slice_api() {
+        for text in [
+            "CQ K1ABC FN42",
+            "<W9XYZ> PJ4/K1ABC RRR",
+            "K1ABC W9XYZ 6A WI",
+            "123456789ABCDEF012",
+            "TNX BOB 73 GL",
+        ] {
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            encode(text, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+
+            let vec = encode_to_vec(text, &mut CallsignHashCache::new()).unwrap();
+            assert_eq!(vec.len(), MESSAGE_BITS);
+            assert_eq!(vec, bits[..77], "{}", text);
+
+            let bytes = encode_bytes(text, &mut CallsignHashCache::new()).unwrap();
+            assert_eq!(bytes, bits.into_inner(), "{}", text);
+            assert_eq!(bytes[9] & 0b111, 0);
+        }
+
+        let mut cache = CallsignHashCache::new();
+        assert!(encode_to_vec("THIS MESSAGE IS FAR TOO LONG", &mut cache).is_err());
+        encode_bytes("CQ PJ4/K1ABC", &mut cache).unwrap();
+        assert!(cache.contains("PJ4/K1ABC"));
+    }
+
+    #[test]
+    fn test_compound_calls_resolve_at_every_hash_width() {
+        // The cache learns each compound call from an earlier message
+        // that carried it in full; PA3XYZ is never heard and stays <...>
+        let mut heard = CallsignHashCache::new();
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        for text in ["CQ PJ4/K1ABC", "CQ KH1/KH7Z", "CQ DL1ABC/P"] {
+            encode(text, &mut bits[..77], &mut heard).unwrap();
+        }
+
+        let mut hashed = Vec::new();
+        for (text, i3) in [
+            // 22-bit hash in a standard message
+            ("W9XYZ <PJ4/K1ABC> -10", 1),
+            // 12-bit hash in a non-standard-call message
+            ("<PJ4/K1ABC> YW18FIFA RRR", 4),
+            // 12- and 22-bit hashes in an EU VHF message
+            ("<PA3XYZ> <DL1ABC/P> R 590003 IO91NP", 5),
+        ] {
+            encode(text, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+            assert_eq!(decode_variant(&bits[..77], None).unwrap().i3(), i3);
+            hashed.push(bits);
+        }
+        // 10-bit hash in a DXpedition message
+        let dxpedition = MessageVariant::Dxpedition {
+            call1: "K1ABC".into(),
+            call2: "W9XYZ".into(),
+            call3: "KH1/KH7Z".into(),
+            report: -8,
+        };
+        encode_variant(&dxpedition, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+        hashed.push(bits);
+
+        let decoded: Vec<(String, String)> = hashed
+            .iter()
+            .map(|bits| {
+                (
+                    decode(&bits[..77], Some(&heard)).unwrap(),
+                    decode(&bits[..77], None).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            decoded,
+            [
+                ("W9XYZ <PJ4/K1ABC> -10", "W9XYZ <...> -10"),
+                ("<PJ4/K1ABC> YW18FIFA RRR", "<...> YW18FIFA RRR"),
+                (
+                    "<...> <DL1ABC/P> R 590003 IO91NP",
+                    "<...> <...> R 590003 IO91NP"
+                ),
+                (
+                    "K1ABC RR73; W9XYZ <KH1/KH7Z> -08",
+                    "K1ABC RR73; W9XYZ <...> -08"
+                ),
+            ]
+            .map(|(with, without)| (with.to_string(), without.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unhashed_nonstandard_call_is_bracketed_on_decode() {
+        let mut cache = CallsignHashCache::new();
+        assert_eq!(roundtrip("W9XYZ YW18FIFA", &mut cache), "<W9XYZ> YW18FIFA");
+        assert_eq!(roundtrip("W1ABC KH6ABCDE", &mut cache), "<W1ABC> KH6ABCDE");
+        assert_eq!(
+            roundtrip("W1ABC NONSTANDARD", &mut cache),
+            "<W1ABC> NONSTANDARD"
+        );
+        assert_eq!(roundtrip("CQ NONSTANDARD", &mut cache), "CQ NONSTANDARD");
+    }
+
+    #[test]
+    fn test_message_types() {
+        let mut cache = CallsignHashCache::new();
+        for (text, i3, n3) in [
+            ("TNX BOB 73 GL", 0, Some(0)),
+            ("K1ABC W9XYZ 6A WI", 0, Some(3)),
+            ("123456789ABCDEF012", 0, Some(5)),
+            ("CQ K1ABC FN42", 1, None),
+            ("CQ G4ABC/P IO91", 2, None),
+            ("K1ABC W9XYZ 579 WI", 3, None),
+            ("CQ PJ4/K1ABC", 4, None),
+            ("<PA3XYZ> <G4ABC> 570123 JO22AB", 5, None),
+            ("QRZ K1ABC FN42", 1, None),
+            ("DE K1ABC", 1, None),
+            // Only CQ has a non-standard-call form
+            ("QRZ PJ4/K1ABC", 0, Some(0)),
+        ] {
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            encode(text, &mut bits[..77], &mut cache).unwrap();
+            let variant = decode_variant(&bits[..77], None).unwrap();
+            assert_eq!((variant.i3(), variant.n3()), (i3, n3), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_r_flag_with_grid() {
+        let mut cache = CallsignHashCache::new();
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode("K1ABC W9XYZ R EN37", &mut bits[..77], &mut cache).unwrap();
+        // ir sits between the second call's suffix flag and g15
+        assert!(bits[58]);
+        assert_eq!(
+            decode_variant(&bits[..77], None).unwrap(),
+            MessageVariant::Standard {
+                call1: "K1ABC".into(),
+                call1_suffix: false,
+                call2: "W9XYZ".into(),
+                call2_suffix: false,
+                r_flag: true,
+                payload: GridReport::Grid("EN37".into()),
+            }
+        );
+
+        encode("K1ABC W9XYZ EN37", &mut bits[..77], &mut cache).unwrap();
+        assert!(!bits[58]);
+    }
+
+    #[test]
+    fn test_wrong_buffer_size() {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        let mut cache = CallsignHashCache::new();
+        assert_eq!(
+            encode("CQ K1ABC FN42", &mut bits[..], &mut cache),
+            Err(MessageError::BufferWrongSize {
+                expected: 77,
+                got: 80
+            })
+        );
+        assert_eq!(
+            decode(&bits[..70], None),
+            Err(MessageError::InputTooShort {
+                expected: 77,
+                got: 70
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        let mut cache = CallsignHashCache::new();
+        let mut encode_err = |text: &str| encode(text, &mut bits[..77], &mut cache).unwrap_err();
+
+        let err = encode_err("THIS MESSAGE IS FAR TOO LONG");
+        assert_eq!(err, MessageError::TooLong { max: 13, got: 28 });
+        assert_eq!(
+            err.to_string(),
+            "Free text too long: 28 characters (max 13)"
+        );
+        let err = encode_err("HI@THERE");
+        assert_eq!(err, MessageError::InvalidChar { ch: '@' });
+        assert_eq!(err.to_string(), "Invalid character in free text: '@'");
+        assert_eq!(encode_err("  "), MessageError::Empty);
+        assert!(matches!(
+            encode_err("W1ABC VERYLONGCALL"),
+            MessageError::CallsignTooLong { .. }
+        ));
+
+        // i3=0 n3=6 and i3=6 are not assigned
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        bits[71..74].store_be(6u8);
+        let err = decode(&bits[..77], None).unwrap_err();
+        assert_eq!(err, MessageError::UnsupportedType { i3: 0, n3: Some(6) });
+        assert_eq!(err.to_string(), "Unsupported message subtype: i3=0 n3=6");
+        bits[74..77].store_be(6u8);
+        assert_eq!(
+            decode(&bits[..77], None),
+            Err(MessageError::UnsupportedType { i3: 6, n3: None })
+        );
+    }
+
+    #[test]
+    fn test_case_and_spacing_normalized() {
+        let mut cache = CallsignHashCache::new();
+        assert_eq!(roundtrip("  cq   k1abc fn42 ", &mut cache), "CQ K1ABC FN42");
+    }
+
+    #[test]
+    fn test_mixed_case_encodes_identically() {
+        let bits_of = |text: &str| encode_to_vec(text, &mut CallsignHashCache::new()).unwrap();
+        for spellings in [
+            ["CQ N0YPR DM42", "cq n0ypr dm42", "Cq N0ypr Dm42"],
+            [
+                "K1ABC/R W9XYZ R-09",
+                "k1abc/r w9xyz r-09",
+                "K1abc/R w9XYZ R-09",
+            ],
+            [
+                "G4ABC/P PA9XYZ JO22",
+                "g4abc/p pa9xyz jo22",
+                "G4abc/p Pa9xyz jO22",
+            ],
+            [
+                "<W9XYZ> PJ4/K1ABC RRR",
+                "<w9xyz> pj4/k1abc rrr",
+                "<W9xyz> Pj4/K1abc Rrr",
+            ],
+            [
+                "K1ABC W9XYZ 6A WI",
+                "k1abc w9xyz 6a wi",
+                "K1ABC W9xyz 6a Wi",
+            ],
+            [
+                "TU; W9XYZ K1ABC R 579 MA",
+                "tu; w9xyz k1abc r 579 ma",
+                "Tu; W9XYZ k1abc R 579 Ma",
+            ],
+            [
+                "<PA3XYZ> <DL1ABC/P> R 590003 IO91NP",
+                "<pa3xyz> <dl1abc/p> r 590003 io91np",
+                "<Pa3xyz> <DL1ABC/p> R 590003 Io91nP",
+            ],
+            [
+                "123456789ABCDEF012",
+                "123456789abcdef012",
+                "123456789AbCdEf012",
+            ],
+            ["TNX BOB 73 GL", "tnx bob 73 gl", "Tnx Bob 73 Gl"],
+        ] {
+            let bits = bits_of(spellings[0]);
+            for text in &spellings[1..] {
+                assert_eq!(bits_of(text), bits, "{}", text);
+            }
+        }
+
+        // Variants built by hand in lowercase encode as the parsed text
+        let rtty = MessageVariant::RttyRoundup {
+            tu: false,
+            call1: "k1abc".into(),
+            call2: "w9xyz".into(),
+            r_flag: true,
+            rst: 579,
+            exchange: RttyExchange::State("ma".into()),
+        };
+        let field_day = MessageVariant::FieldDay {
+            call1: "k1abc".into(),
+            call2: "w9xyz".into(),
+            r_flag: false,
+            transmitters: 6,
+            class: 'a',
+            section: "wi".into(),
+        };
+        let nonstandard = MessageVariant::NonStandardCall {
+            hashed_call: "<w9xyz>".into(),
+            full_call: "pj4/k1abc".into(),
+            hashed_first: true,
+            payload: GridReport::Rrr,
+            cq: false,
+        };
+        let free_text = MessageVariant::FreeText {
+            text: "tnx bob".into(),
+        };
+        for (variant, text) in [
+            (rtty, "K1ABC W9XYZ R 579 MA"),
+            (field_day, "K1ABC W9XYZ 6A WI"),
+            (nonstandard, "<W9XYZ> PJ4/K1ABC RRR"),
+            (free_text, "TNX BOB"),
+        ] {
+            assert_eq!(variant.to_uppercase(), parse_message_variant(text).unwrap());
+            let bits = encode_variant_to_vec(&variant, &mut CallsignHashCache::new());
+            assert_eq!(bits, Ok(bits_of(text)), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_typed_encoders_bypass_parser() {
+        let i3_n3 =
+            |bits: &BitSlice<u8, Msb0>| (bits::get_u32(bits, 74, 3), bits::get_u32(bits, 71, 3));
+
+        // "CQ" and a callsign pair are sent as free text when asked to be
+        for text in ["CQ", "K1ABC W9XYZ"] {
+            let bits = encode_free_text(text).unwrap();
+            assert_eq!(bits.len(), MESSAGE_BITS);
+            assert_eq!(i3_n3(&bits), (0, 0));
+            assert_eq!(
+                decode_variant(&bits, None),
+                Ok(MessageVariant::FreeText { text: text.into() })
+            );
+        }
+        let parsed = encode_to_vec("K1ABC W9XYZ", &mut CallsignHashCache::new()).unwrap();
+        assert_eq!(i3_n3(&parsed).0, 1);
+
+        let bits = encode_telemetry("123456789ABCDEF01").unwrap();
+        assert_eq!(i3_n3(&bits), (0, 5));
+        assert_eq!(decode(&bits, None).unwrap(), "123456789ABCDEF01");
+
+        let mut cache = CallsignHashCache::new();
+        let bits = encode_standard(
+            "k1abc/r",
+            "W9XYZ",
+            true,
+            GridReport::Report(-10),
+            &mut cache,
+        )
+        .unwrap();
+        let parsed = encode_to_vec("K1ABC/R W9XYZ R-10", &mut CallsignHashCache::new()).unwrap();
+        assert_eq!(bits, parsed);
+        assert!(cache.contains("K1ABC/R"));
+        assert!(encode_standard(
+            "CQ",
+            "W9XYZ",
+            false,
+            GridReport::Grid("EN37".into()),
+            &mut cache
+        )
+        .is_ok());
+        assert!(encode_free_text("HI@THERE").is_err());
+        assert!(encode_telemetry("XYZ").is_err());
+    }
+
+    /// Random valid messages of every type, written the way `decode`
+    /// prints them, for the round-trip properties below.
+    mod strategies {
+        use super::*;
+        use alloc::format;
+        use proptest::prelude::*;
+
+        /// A callsign that packs into 28 bits. Calls starting with Q or
+        /// 3D0 are left out: those are how the 3X and 3DA0 prefixes are
+        /// sent, so they would decode as 3X and 3DA0 calls.
+        fn standard_call() -> impl Strategy<Value = String> {
+            prop_oneof![
+                8 => "([A-PR-Z][A-Z0-9]?|[0-9][A-Z])[0-9][A-Z]{1,3}"
+                    .prop_filter("3DA0 alias", |c| !c.starts_with("3D0")),
+                1 => "3X[A-Z][0-9][A-Z]{1,3}",
+                1 => "3DA0[A-Z]{1,3}",
+            ]
+        }
+
+        fn grid4() -> impl Strategy<Value = String> {
+            // RR73 is a square too, but always means the acknowledgement
+            "[A-R]{2}[0-9]{2}".prop_filter("RR73", |g| g != "RR73")
+        }
+
+        fn hashed(call: String) -> String {
+            format!("<{}>", call)
+        }
+
+        /// An ending that works after two callsigns, with the R flag only
+        /// where it is sent: in front of a grid or a report
+        fn ending() -> impl Strategy<Value = (bool, GridReport)> {
+            prop_oneof![
+                Just((false, GridReport::Blank)),
+                (any::<bool>(), grid4()).prop_map(|(r, g)| (r, GridReport::Grid(g))),
+                (any::<bool>(), -50i8..=49).prop_map(|(r, db)| (r, GridReport::Report(db))),
+                Just((false, GridReport::Rrr)),
+                Just((false, GridReport::Rr73)),
+                Just((false, GridReport::SeventyThree)),
+            ]
+        }
+
+        fn standard() -> impl Strategy<Value = MessageVariant> {
+            let to_call = (
+                standard_call(),
+                any::<bool>(),
+                standard_call(),
+                any::<bool>(),
+                ending(),
+            )
+                .prop_map(
+                    |(call1, call1_suffix, call2, call2_suffix, (r_flag, payload))| {
+                        MessageVariant::Standard {
+                            call1,
+                            call1_suffix,
+                            call2,
+                            call2_suffix,
+                            r_flag,
+                            payload,
+                        }
+                    },
+                );
+            let cq = prop_oneof![
+                Just("CQ".to_string()),
+                Just("QRZ".to_string()),
+                Just("DE".to_string()),
+                (0u16..=999).prop_map(|n| format!("CQ {:03}", n)),
+                "[A-Z]{1,4}".prop_map(|m| format!("CQ {}", m)),
+            ];
+            let payload = prop_oneof![Just(GridReport::Blank), grid4().prop_map(GridReport::Grid)];
+            let to_all = (cq, standard_call(), any::<bool>(), payload).prop_map(
+                |(call1, call2, call2_suffix, payload)| MessageVariant::Standard {
+                    call1,
+                    call1_suffix: false,
+                    call2,
+                    call2_suffix,
+                    r_flag: false,
+                    payload,
+                },
+            );
+            prop_oneof![3 => to_call, 1 => to_all]
+        }
+
+        fn eu_vhf_type2() -> impl Strategy<Value = MessageVariant> {
+            // At least one /P, or it is an ordinary standard message
+            let suffixes =
+                prop_oneof![Just((true, false)), Just((false, true)), Just((true, true))];
+            (standard_call(), standard_call(), suffixes, ending()).prop_map(
+                |(call1, call2, (call1_suffix, call2_suffix), (r_flag, payload))| {
+                    MessageVariant::EuVhfContestType2 {
+                        call1,
+                        call1_suffix,
+                        call2,
+                        call2_suffix,
+                        r_flag,
+                        payload,
+                    }
+                },
+            )
+        }
+
+        fn nonstandard_call() -> impl Strategy<Value = String> {
+            ("[A-Z0-9]{1,4}", standard_call())
+                .prop_map(|(prefix, call)| format!("{}/{}", prefix, call))
+                .prop_filter("fits 58 bits", |c| c.len() <= nonstandard::MAX_C58_LEN)
+        }
+
+        fn nonstandard() -> impl Strategy<Value = MessageVariant> {
+            let payload = prop_oneof![
+                Just(GridReport::Blank),
+                Just(GridReport::Rrr),
+                Just(GridReport::Rr73),
+                Just(GridReport::SeventyThree),
+            ];
+            let qso = (standard_call(), nonstandard_call(), any::<bool>(), payload).prop_map(
+                |(hashed_call, full_call, hashed_first, payload)| MessageVariant::NonStandardCall {
+                    hashed_call: hashed(hashed_call),
+                    full_call,
+                    hashed_first,
+                    payload,
+                    cq: false,
+                },
+            );
+            let cq = nonstandard_call().prop_map(|full_call| MessageVariant::NonStandardCall {
+                hashed_call: String::new(),
+                full_call,
+                hashed_first: false,
+                payload: GridReport::Blank,
+                cq: true,
+            });
+            prop_oneof![3 => qso, 1 => cq]
+        }
+
+        fn field_day() -> impl Strategy<Value = MessageVariant> {
+            (
+                standard_call(),
+                standard_call(),
+                any::<bool>(),
+                1u8..=32,
+                prop::char::range('A', 'F'),
+                prop::sample::select(&field_day::ARRL_SECTIONS[..]),
+            )
+                .prop_map(|(call1, call2, r_flag, transmitters, class, section)| {
+                    MessageVariant::FieldDay {
+                        call1,
+                        call2,
+                        r_flag,
+                        transmitters,
+                        class,
+                        section: section.to_string(),
+                    }
+                })
+        }
+
+        fn rtty() -> impl Strategy<Value = MessageVariant> {
+            let exchange = prop_oneof![
+                (0u16..=7999).prop_map(RttyExchange::Serial),
+                prop::sample::select(&rtty::RTTY_MULTIPLIERS[..])
+                    .prop_map(|s| RttyExchange::State(s.to_string())),
+            ];
+            (
+                any::<bool>(),
+                standard_call(),
+                standard_call(),
+                any::<bool>(),
+                (2u16..=9).prop_map(|s| 509 + 10 * s),
+                exchange,
+            )
+                .prop_map(|(tu, call1, call2, r_flag, rst, exchange)| {
+                    MessageVariant::RttyRoundup {
+                        tu,
+                        call1,
+                        call2,
+                        r_flag,
+                        rst,
+                        exchange,
+                    }
+                })
+        }
+
+        fn portable_hashed_call() -> impl Strategy<Value = String> {
+            (standard_call(), any::<bool>())
+                .prop_map(|(c, portable)| hashed(if portable { c + "/P" } else { c }))
+        }
+
+        fn eu_vhf_type5() -> impl Strategy<Value = MessageVariant> {
+            (
+                portable_hashed_call(),
+                portable_hashed_call(),
+                any::<bool>(),
+                52u8..=59,
+                0u16..=2047,
+                "[A-R]{2}[0-9]{2}[A-X]{2}",
+            )
+                .prop_map(|(call1, call2, r_flag, rst, serial, grid6)| {
+                    MessageVariant::EuVhfContestType5 {
+                        call1,
+                        call2,
+                        r_flag,
+                        rst,
+                        serial,
+                        grid6,
+                    }
+                })
+        }
+
+        /// DXpedition messages, which the text parser does not read
+        pub fn dxpedition() -> impl Strategy<Value = MessageVariant> {
+            (
+                standard_call(),
+                standard_call(),
+                standard_call(),
+                (-15i8..=16).prop_map(|n| 2 * n),
+            )
+                .prop_map(|(call1, call2, call3, report)| MessageVariant::Dxpedition {
+                    call1,
+                    call2,
+                    call3: hashed(call3),
+                    report,
+                })
+        }
+
+        /// Every type the parser reaches from text
+        fn parsed_message() -> impl Strategy<Value = MessageVariant> {
+            prop_oneof![
+                4 => standard(),
+                1 => eu_vhf_type2(),
+                2 => nonstandard(),
+                1 => field_day(),
+                1 => rtty(),
+                1 => eu_vhf_type5(),
+            ]
+        }
+
+        /// Canonical text of a random message, and the same text with
+        /// random case and extra spaces to encode
+        pub fn message_text() -> impl Strategy<Value = (String, String)> {
+            parsed_message()
+                .prop_map(|v| v.to_string())
+                .prop_flat_map(|text| (Just(text.clone()), respelled(text)))
+        }
+
+        fn respelled(text: String) -> impl Strategy<Value = String> {
+            let words = text.split(' ').count();
+            (
+                prop::collection::vec((any::<bool>(), any::<bool>(), 1usize..=3), words),
+                0usize..=2,
+            )
+                .prop_map(move |(spelling, lead)| {
+                    let mut out = " ".repeat(lead);
+                    for (word, &(lower, alias, spaces)) in text.split(' ').zip(&spelling) {
+                        let word = if alias {
+                            packed_alias(word)
+                        } else {
+                            word.to_string()
+                        };
+                        if lower {
+                            out.push_str(&word.to_lowercase());
+                        } else {
+                            out.push_str(&word);
+                        }
+                        out.push_str(&" ".repeat(spaces));
+                    }
+                    out
+                })
+        }
+
+        /// The spelling WSJT-X packs a 3X or 3DA0 callsign as, which
+        /// decodes back to the full prefix. Callsigns sent in full as
+        /// non-standard calls have no such alias.
+        fn packed_alias(word: &str) -> String {
+            if strip_rover_portable(word).contains('/') {
+                return word.to_string();
+            }
+            if let Some(rest) = word.strip_prefix("3X") {
+                if rest.starts_with(|c: char| c.is_ascii_uppercase()) {
+                    return format!("Q{}", rest);
+                }
+            }
+            if let Some(rest) = word.strip_prefix("3DA0") {
+                return format!("3D0{}", rest);
+            }
+            word.to_string()
+        }
+    }
+
+    use callsign::strip_rover_portable;
+    use proptest::prelude::*;
+
+    /// `canonical` with every bracketed callsign unresolved, as decoding
+    /// without a cache prints it
+    fn without_cache(canonical: &str) -> String {
+        canonical
+            .split(' ')
+            .map(|word| if word.starts_with('<') { "<...>" } else { word })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Whether two different words of `text`, callsigns among them,
+    /// share a 10- or 12-bit hash. The cache then cannot tell which one a
+    /// hashed callsign was.
+    fn hashes_collide(text: &str) -> bool {
+        let mut words: Vec<&str> = text
+            .split(' ')
+            .map(|w| w.trim_matches(|c| c == '<' || c == '>'))
+            .flat_map(|w| [w, strip_rover_portable(w)])
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+        words.iter().enumerate().any(|(i, a)| {
+            words[i + 1..]
+                .iter()
+                .any(|b| [10, 12].iter().any(|&m| ihashcall(a, m) == ihashcall(b, m)))
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn prop_text_roundtrip((canonical, input) in strategies::message_text()) {
+            prop_assume!(!hashes_collide(&canonical));
+
+            // Case and spacing are normalized away
+            let mut cache = CallsignHashCache::new();
+            let bits = encode_to_vec(&input, &mut cache)
+                .map_err(|e| TestCaseError::fail(format!("{:?}: {}", input, e)))?;
+            prop_assert_eq!(decode(&bits, Some(&cache)).unwrap(), canonical.as_str());
+
+            // Hashed callsigns only resolve through the cache
+            prop_assert_eq!(decode(&bits, None).unwrap(), without_cache(&canonical));
+
+            // Decoded text encodes back to the same bits
+            let again = encode_to_vec(&canonical, &mut CallsignHashCache::new()).unwrap();
+            prop_assert_eq!(again, bits);
+        }
+
+        #[test]
+        fn prop_dxpedition_roundtrip(variant in strategies::dxpedition()) {
+            let canonical = variant.to_string();
+            prop_assume!(!hashes_collide(&canonical));
+            let mut cache = CallsignHashCache::new();
+            let bits = encode_variant_to_vec(&variant, &mut cache).unwrap();
+            prop_assert_eq!(decode(&bits, Some(&cache)).unwrap(), canonical.as_str());
+            prop_assert_eq!(decode(&bits, None).unwrap(), without_cache(&canonical));
+        }
+
+        #[test]
+        fn prop_free_text_roundtrip(text in "[0-9A-Z+\\-./?]{1,6}( [0-9A-Z+\\-./?]{1,5})?") {
+            let bits = encode_free_text(&text.to_lowercase()).unwrap();
+            prop_assert_eq!(decode(&bits, None).unwrap(), text);
+        }
+
+        #[test]
+        fn prop_telemetry_roundtrip(hex in "[0-7][0-9A-F]{0,17}") {
+            // Leading zeros are not sent
+            let trimmed = hex.trim_start_matches('0');
+            let canonical = if trimmed.is_empty() { "0" } else { trimmed };
+            let bits = encode_telemetry(&hex.to_lowercase()).unwrap();
+            prop_assert_eq!(decode(&bits, None).unwrap(), canonical);
+        }
+    }
+}
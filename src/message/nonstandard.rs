@@ -0,0 +1,187 @@
+//! Non-standard callsign messages (i3=4)
+//!
+//! `<W9XYZ> PJ4/K1ABC RRR` or `CQ PJ4/K1ABC`. Layout: `h12 c58 h1 r2 c1`.
+//! One callsign is sent in full as up to 11 base-38 characters (`c58`); the
+//! other is sent as a 12-bit hash. `h1` is set when the hashed callsign is
+//! the second one, `r2` carries blank/RRR/RR73/73 and `c1` marks a CQ.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+
+use super::bits::{get, get_u32, put};
+use super::callsign::C58_CHARS;
+use super::error::MessageError;
+use super::hash::{ihashcall, render_hashed, strip_brackets, CallsignHashCache};
+use super::types::{GridReport, MessageVariant};
+
+/// Maximum length of the full callsign
+pub const MAX_C58_LEN: usize = 11;
+
+/// Pack a callsign into the 58-bit base-38 field (right-justified).
+pub(crate) fn pack_c58(callsign: &str) -> Result<u64, MessageError> {
+    if callsign.is_empty() || callsign.len() > MAX_C58_LEN {
+        return Err(MessageError::Encode(format!(
+            "Callsign must be 1-11 characters: {}",
+            callsign
+        )));
+    }
+    callsign.bytes().try_fold(0u64, |acc, c| {
+        let j = C58_CHARS.iter().position(|&t| t == c).ok_or_else(|| {
+            MessageError::Encode(format!("Invalid character in callsign: {}", callsign))
+        })?;
+        Ok(acc * 38 + j as u64)
+    })
+}
+
+/// Unpack the 58-bit base-38 field.
+pub(crate) fn unpack_c58(mut n58: u64) -> Result<String, MessageError> {
+    let mut chars = Vec::with_capacity(MAX_C58_LEN);
+    for _ in 0..MAX_C58_LEN {
+        chars.push(C58_CHARS[(n58 % 38) as usize]);
+        n58 /= 38;
+    }
+    if n58 != 0 {
+        return Err(MessageError::Decode("Invalid c58 callsign".into()));
+    }
+    chars.reverse();
+    let call = String::from_utf8(chars)
+        .map_err(|_| MessageError::Decode("Invalid c58 callsign".into()))?;
+    let call = call.trim();
+    if call.is_empty() || call.contains(' ') {
+        return Err(MessageError::Decode(format!(
+            "Invalid c58 callsign: '{}'",
+            call
+        )));
+    }
+    Ok(call.into())
+}
+
+/// Encode a non-standard callsign message.
+pub(crate) fn encode(
+    hashed_call: &str,
+    full_call: &str,
+    hashed_first: bool,
+    payload: &GridReport,
+    cq: bool,
+    bits: &mut BitSlice<u8, Msb0>,
+    cache: &mut CallsignHashCache,
+) -> Result<(), MessageError> {
+    let n58 = pack_c58(full_call)?;
+    let hashed = strip_brackets(hashed_call);
+    let h12 = if cq { 0 } else { ihashcall(hashed, 12) };
+    let r2 = match payload {
+        GridReport::Blank => 0,
+        GridReport::Rrr => 1,
+        GridReport::Rr73 => 2,
+        GridReport::SeventyThree => 3,
+        other => {
+            return Err(MessageError::Encode(format!(
+                "Unsupported field for non-standard message: {:?}",
+                other
+            )))
+        }
+    };
+
+    put(bits, 0, 12, h12 as u128);
+    put(bits, 12, 58, n58 as u128);
+    put(bits, 70, 1, !hashed_first as u128);
+    put(bits, 71, 2, r2);
+    put(bits, 73, 1, cq as u128);
+    put(bits, 74, 3, 4);
+
+    cache.insert(full_call);
+    if !cq {
+        cache.insert(hashed);
+    }
+    Ok(())
+}
+
+/// Decode an i3=4 message.
+pub(crate) fn decode(
+    bits: &BitSlice<u8, Msb0>,
+    cache: Option<&CallsignHashCache>,
+) -> Result<MessageVariant, MessageError> {
+    let h12 = get_u32(bits, 0, 12);
+    let full_call = unpack_c58(get(bits, 12, 58) as u64)?;
+    let hashed_first = !bits[70];
+    let payload = match get_u32(bits, 71, 2) {
+        0 => GridReport::Blank,
+        1 => GridReport::Rrr,
+        2 => GridReport::Rr73,
+        _ => GridReport::SeventyThree,
+    };
+    let cq = bits[73];
+    let hashed_call = if cq {
+        String::new()
+    } else {
+        render_hashed(cache.and_then(|c| c.lookup12(h12)))
+    };
+
+    Ok(MessageVariant::NonStandardCall {
+        hashed_call,
+        full_call,
+        hashed_first,
+        payload: if cq { GridReport::Blank } else { payload },
+        cq,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_c58_roundtrip() {
+        for call in ["PJ4/K1ABC", "YW18FIFA", "KH1/KH7Z", "A", "VERYLONGCAL"] {
+            assert_eq!(unpack_c58(pack_c58(call).unwrap()).unwrap(), call);
+        }
+        assert!(pack_c58("TOOLONGCALLS").is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_with_cache() {
+        let mut cache = CallsignHashCache::new();
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode(
+            "<W9XYZ>",
+            "PJ4/K1ABC",
+            true,
+            &GridReport::Rrr,
+            false,
+            &mut bits[..77],
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(
+            decode(&bits[..77], Some(&cache)).unwrap().to_string(),
+            "<W9XYZ> PJ4/K1ABC RRR"
+        );
+        assert_eq!(
+            decode(&bits[..77], None).unwrap().to_string(),
+            "<...> PJ4/K1ABC RRR"
+        );
+    }
+
+    #[test]
+    fn test_cq() {
+        let mut cache = CallsignHashCache::new();
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode(
+            "",
+            "PJ4/K1ABC",
+            false,
+            &GridReport::Blank,
+            true,
+            &mut bits[..77],
+            &mut cache,
+        )
+        .unwrap();
+        assert_eq!(
+            decode(&bits[..77], None).unwrap().to_string(),
+            "CQ PJ4/K1ABC"
+        );
+    }
+}
@@ -0,0 +1,1191 @@
+//! Text → [`MessageVariant`] parser
+//!
+//! Rules are tried in order: standard messages (including CQ and the EU VHF
+//! `/P` variant), non-standard callsign messages, RTTY Roundup, Field Day,
+//! telemetry, EU VHF contest exchanges with a 6-character locator, and
+//! finally free text. Only the EU VHF exchange carries a 6-character
+//! locator; everywhere else a grid is the 4-character square.
+//! [`explain_parse`] runs the same rules and reports why each one that
+//! didn't match was rejected.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::callsign::{
+    is_cq_modifier, pack_standard, strip_rover_portable, validate_callsign_basic, C58_CHARS,
+};
+use super::error::MessageError;
+use super::field_day::ARRL_SECTIONS;
+use super::free_text;
+use super::grid::{encode_grid6, validate_grid_basic};
+use super::hash::strip_brackets;
+use super::nonstandard::MAX_C58_LEN;
+use super::rtty::{is_valid_rst, RTTY_MULTIPLIERS};
+use super::telemetry;
+use super::types::{GridReport, MessageVariant, RttyExchange};
+
+/// How a callsign word can be encoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CallKind {
+    /// Fits `c28`; `suffix` is `Some('R')` or `Some('P')` for `/R` `/P`
+    Standard { suffix: Option<char> },
+    /// Bracketed `<CALL>`, sent as a hash
+    Hashed,
+    /// Needs the i3=4 non-standard encoding
+    NonStandard,
+}
+
+#[derive(Debug, Clone)]
+struct CallWord {
+    /// Callsign without `/R` `/P` suffix (brackets kept for hashed calls)
+    call: String,
+    kind: CallKind,
+}
+
+impl CallWord {
+    /// The callsign as written, including any `/R` `/P` suffix.
+    fn full(&self) -> String {
+        match self.kind {
+            CallKind::Standard { suffix: Some(s) } => format!("{}/{}", self.call, s),
+            _ => self.call.clone(),
+        }
+    }
+}
+
+/// Classify a single word as a callsign.
+fn parse_call(word: &str) -> Option<CallWord> {
+    if word.starts_with('<') && word.ends_with('>') {
+        let inner = strip_brackets(word);
+        let valid = !inner.is_empty()
+            && inner.len() <= MAX_C58_LEN
+            && inner.bytes().all(|c| C58_CHARS.contains(&c) && c != b' ');
+        return valid.then(|| CallWord {
+            call: word.to_string(),
+            kind: CallKind::Hashed,
+        });
+    }
+
+    let base = strip_rover_portable(word);
+    if pack_standard(base).is_some() {
+        let suffix = (base.len() != word.len()).then(|| word.as_bytes()[word.len() - 1] as char);
+        return Some(CallWord {
+            call: base.to_string(),
+            kind: CallKind::Standard { suffix },
+        });
+    }
+
+    validate_callsign_basic(word).then(|| CallWord {
+        call: word.to_string(),
+        kind: CallKind::NonStandard,
+    })
+}
+
+/// A parsing rule: the message, or why the rule doesn't apply
+type Rule = fn(&[&str]) -> Result<MessageVariant, MessageError>;
+
+/// Outcome of a rule that can also reject a message outright: `Err` if the
+/// message doesn't have the rule's shape, `Ok(Err)` if it does but can't
+/// be sent
+type Shaped = Result<Result<MessageVariant, MessageError>, MessageError>;
+
+/// A rule returning [`Shaped`]
+type ShapedRule = fn(&[&str]) -> Shaped;
+
+/// [`parse_call`], with the word as the rejection reason.
+fn call_word(word: &str) -> Result<CallWord, MessageError> {
+    parse_call(word).ok_or_else(|| MessageError::InvalidCallsign(word.to_string()))
+}
+
+/// Parse a directed-CQ modifier (`DX`, `POTA`, `005`).
+fn parse_cq_modifier(word: &str) -> Result<&str, MessageError> {
+    if is_cq_modifier(word) {
+        Ok(word)
+    } else {
+        Err(MessageError::InvalidCqModifier(word.to_string()))
+    }
+}
+
+/// Parse a signal report (`-10`, `+05`) into dB.
+fn parse_report(word: &str) -> Option<i8> {
+    let digits = word.strip_prefix('+').or_else(|| word.strip_prefix('-'))?;
+    if digits.is_empty() || digits.len() > 2 || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let value: i8 = digits.parse().ok()?;
+    let db = if word.starts_with('-') { -value } else { value };
+    (-50..=49).contains(&db).then_some(db)
+}
+
+/// Parse the final word of a standard message into `(r_flag, payload)`.
+fn parse_payload(word: &str) -> Result<(bool, GridReport), MessageError> {
+    match word {
+        "RRR" => return Ok((false, GridReport::Rrr)),
+        "RR73" => return Ok((false, GridReport::Rr73)),
+        "73" => return Ok((false, GridReport::SeventyThree)),
+        _ => {}
+    }
+    if let Some(db) = parse_report(word) {
+        return Ok((false, GridReport::Report(db)));
+    }
+    if let Some(db) = word.strip_prefix('R').and_then(parse_report) {
+        return Ok((true, GridReport::Report(db)));
+    }
+    if validate_grid_basic(word) {
+        Ok((false, GridReport::Grid(word.to_string())))
+    } else {
+        Err(MessageError::InvalidPayload(word.to_string()))
+    }
+}
+
+/// The last word as a 4-character grid, for `CQ CALL GRID` forms.
+fn parse_grid(word: &str) -> Result<GridReport, MessageError> {
+    if validate_grid_basic(word) && word != "RR73" {
+        Ok(GridReport::Grid(word.to_string()))
+    } else {
+        Err(MessageError::InvalidGrid(word.to_string()))
+    }
+}
+
+/// Build a standard (i3=1) or EU VHF (i3=2) message from its parts.
+///
+/// `call1` is either a token (`CQ`, `CQ DX`) or a callsign word.
+fn build_standard(
+    call1: &str,
+    call1_suffix: Option<char>,
+    call2: &str,
+    call2_suffix: Option<char>,
+    r_flag: bool,
+    payload: GridReport,
+) -> Result<MessageVariant, MessageError> {
+    let is_p_suffix1 = call1_suffix == Some('P');
+    let is_p_suffix2 = call2_suffix == Some('P');
+    let is_r_suffix = call1_suffix == Some('R') || call2_suffix == Some('R');
+
+    if is_p_suffix1 || is_p_suffix2 {
+        if is_r_suffix {
+            return Err(MessageError::MixedSuffixes);
+        }
+        return Ok(MessageVariant::EuVhfContestType2 {
+            call1: call1.to_string(),
+            call1_suffix: is_p_suffix1,
+            call2: call2.to_string(),
+            call2_suffix: is_p_suffix2,
+            r_flag,
+            payload,
+        });
+    }
+
+    Ok(MessageVariant::Standard {
+        call1: call1.to_string(),
+        call1_suffix: call1_suffix.is_some(),
+        call2: call2.to_string(),
+        call2_suffix: call2_suffix.is_some(),
+        r_flag,
+        payload,
+    })
+}
+
+fn suffix_of(word: &CallWord) -> Option<char> {
+    match word.kind {
+        CallKind::Standard { suffix } => suffix,
+        _ => None,
+    }
+}
+
+/// Build a message addressed between two callsigns.
+///
+/// Two standard (or bracketed) callsigns give a standard message; one
+/// non-standard callsign routes to the i3=4 encoding with the other hashed.
+fn build_pair(
+    c1: &CallWord,
+    c2: &CallWord,
+    r_flag: bool,
+    payload: GridReport,
+) -> Result<MessageVariant, MessageError> {
+    match (&c1.kind, &c2.kind) {
+        (CallKind::NonStandard, CallKind::NonStandard) => Err(MessageError::TwoNonStandardCalls),
+        (CallKind::NonStandard, _) | (_, CallKind::NonStandard) => {
+            let short_reply = matches!(
+                payload,
+                GridReport::Blank | GridReport::Rrr | GridReport::Rr73 | GridReport::SeventyThree
+            );
+            if !short_reply || r_flag {
+                return Err(MessageError::NonStandardPayload);
+            }
+            let hashed_first = c2.kind == CallKind::NonStandard;
+            let (hashed, full) = if hashed_first { (c1, c2) } else { (c2, c1) };
+            if full.call.len() > MAX_C58_LEN {
+                return Err(MessageError::InvalidCallsign(full.call.clone()));
+            }
+            Ok(MessageVariant::NonStandardCall {
+                hashed_call: format!("<{}>", strip_brackets(&hashed.full())),
+                full_call: full.call.clone(),
+                hashed_first,
+                payload,
+                cq: false,
+            })
+        }
+        _ => build_standard(
+            &c1.call,
+            suffix_of(c1),
+            &c2.call,
+            suffix_of(c2),
+            r_flag,
+            payload,
+        ),
+    }
+}
+
+/// Tokens that can stand in place of the first callsign
+fn is_call_token(word: &str) -> bool {
+    matches!(word, "CQ" | "QRZ" | "DE")
+}
+
+/// `CQ <call>` (or `QRZ`, `DE`) with an optional grid.
+fn build_cq(
+    cq: &str,
+    call: &CallWord,
+    payload: GridReport,
+) -> Result<MessageVariant, MessageError> {
+    match call.kind {
+        CallKind::NonStandard if cq == "CQ" && payload == GridReport::Blank => {
+            Ok(MessageVariant::NonStandardCall {
+                hashed_call: String::new(),
+                full_call: call.call.clone(),
+                hashed_first: false,
+                payload,
+                cq: true,
+            })
+        }
+        CallKind::NonStandard => Err(MessageError::NonStandardCq(call.call.clone())),
+        _ => build_standard(cq, None, &call.call, suffix_of(call), false, payload),
+    }
+}
+
+/// `CALL1 CALL2` or `CQ CALL` (also `QRZ CALL`, `DE CALL`)
+pub(crate) fn parse_two_word_message(words: &[&str]) -> Result<MessageVariant, MessageError> {
+    let call2 = call_word(words[1])?;
+    if is_call_token(words[0]) {
+        return build_cq(words[0], &call2, GridReport::Blank);
+    }
+    let call1 = call_word(words[0])?;
+    build_pair(&call1, &call2, false, GridReport::Blank)
+}
+
+/// `CALL1 CALL2 <grid|report|RRR|RR73|73>`, `CQ CALL GRID` (also with
+/// `QRZ` or `DE`) or `CQ MOD CALL`
+pub(crate) fn parse_three_word_message(words: &[&str]) -> Result<MessageVariant, MessageError> {
+    if is_call_token(words[0]) {
+        let not_grid = match parse_grid(words[2]) {
+            Ok(grid) => {
+                let call = call_word(words[1])?;
+                return build_cq(words[0], &call, grid);
+            }
+            Err(e) => e,
+        };
+        if words[0] != "CQ" {
+            return Err(not_grid);
+        }
+        // Unless the last word is a callsign a directed CQ can carry, it
+        // was meant as the grid
+        let call = call_word(words[2])
+            .ok()
+            .filter(|call| call.kind != CallKind::NonStandard)
+            .ok_or(not_grid)?;
+        let modifier = parse_cq_modifier(words[1])?;
+        return build_cq(&format!("CQ {}", modifier), &call, GridReport::Blank);
+    }
+
+    let call1 = call_word(words[0])?;
+    let call2 = call_word(words[1])?;
+    let (r_flag, payload) = parse_payload(words[2])?;
+    build_pair(&call1, &call2, r_flag, payload)
+}
+
+/// `CALL1 CALL2 R GRID` or `CQ MOD CALL GRID`
+pub(crate) fn parse_four_word_message(words: &[&str]) -> Result<MessageVariant, MessageError> {
+    let grid = parse_grid(words[3])?;
+
+    if words[0] == "CQ" {
+        let modifier = parse_cq_modifier(words[1])?;
+        let call = call_word(words[2])?;
+        return build_cq(&format!("CQ {}", modifier), &call, grid);
+    }
+
+    if words[2] != "R" {
+        return Err(MessageError::ExpectedR(words[2].to_string()));
+    }
+    let call1 = call_word(words[0])?;
+    let call2 = call_word(words[1])?;
+    build_pair(&call1, &call2, true, grid)
+}
+
+/// Standard, CQ and non-standard callsign messages of two to four words.
+fn parse_standard_message(words: &[&str]) -> Result<MessageVariant, MessageError> {
+    match words.len() {
+        2 => parse_two_word_message(words),
+        3 => parse_three_word_message(words),
+        4 => parse_four_word_message(words),
+        n => Err(MessageError::WordCount {
+            expected: "2 to 4",
+            got: n,
+        }),
+    }
+}
+
+/// A message whose callsign fails the loose callsign check (no digit, say)
+/// but fits the 58-bit field: `CALL WORD [RRR|RR73|73]` or `CQ WORD`.
+///
+/// Only tried once the text has proved too long for free text, so short
+/// words are still sent as text. `Err` if the message doesn't have that
+/// shape; `Ok(Err)` if the odd word can't be sent as a callsign either.
+fn parse_unusual_call_message(words: &[&str]) -> Shaped {
+    let payload = match words.len() {
+        2 => GridReport::Blank,
+        3 => match parse_payload(words[2])? {
+            (false, payload @ (GridReport::Rrr | GridReport::Rr73 | GridReport::SeventyThree)) => {
+                payload
+            }
+            _ => return Err(MessageError::NonStandardPayload),
+        },
+        n => {
+            return Err(MessageError::WordCount {
+                expected: "2 or 3",
+                got: n,
+            })
+        }
+    };
+    let unusual = |word: &str| {
+        if word.len() > MAX_C58_LEN {
+            return Err(MessageError::CallsignTooLong {
+                call: word.to_string(),
+                max: MAX_C58_LEN,
+            });
+        }
+        if !word.bytes().all(|c| C58_CHARS.contains(&c) && c != b' ') {
+            return Err(MessageError::InvalidCallsign(word.into()));
+        }
+        Ok(CallWord {
+            call: word.to_string(),
+            kind: CallKind::NonStandard,
+        })
+    };
+
+    if words[0] == "CQ" {
+        if words.len() != 2 {
+            return Err(MessageError::WordCount {
+                expected: "2",
+                got: words.len(),
+            });
+        }
+        if parse_call(words[1]).is_some() {
+            return Err(MessageError::ExpectedOneUnusualCall);
+        }
+        return Ok(unusual(words[1]).and_then(|call| {
+            build_cq("CQ", &call, payload)
+                .map_err(|_| MessageError::InvalidCallsign(words[1].into()))
+        }));
+    }
+
+    let (c1, c2) = match (parse_call(words[0]), parse_call(words[1])) {
+        (Some(c1), None) => (Ok(c1), unusual(words[1])),
+        (None, Some(c2)) => (unusual(words[0]), Ok(c2)),
+        _ => return Err(MessageError::ExpectedOneUnusualCall),
+    };
+    Ok(c1.and_then(|c1| build_pair(&c1, &c2?, false, payload)))
+}
+
+/// A callsign usable in a `c28` field without suffix (RTTY, Field Day).
+fn plain_c28_call(word: &str) -> Result<String, MessageError> {
+    let call = call_word(word)?;
+    match call.kind {
+        CallKind::Standard { suffix: None } | CallKind::Hashed => Ok(call.call),
+        _ => Err(MessageError::InvalidCallsign(word.to_string())),
+    }
+}
+
+/// `[TU;] CALL1 CALL2 [R] 5x9 <serial|state>`
+pub(crate) fn parse_rtty_message(words: &[&str]) -> Result<MessageVariant, MessageError> {
+    let (tu, rest) = match words.first() {
+        Some(&"TU;") => (true, &words[1..]),
+        _ => (false, words),
+    };
+    let (r_flag, fields) = match rest.len() {
+        4 => (false, rest),
+        5 if rest[2] == "R" => (true, rest),
+        n => {
+            return Err(MessageError::WordCount {
+                expected: "4, or 5 with R,",
+                got: n,
+            })
+        }
+    };
+    let call1 = plain_c28_call(fields[0])?;
+    let call2 = plain_c28_call(fields[1])?;
+    let rst_word = fields[fields.len() - 2];
+    let exch_word = fields[fields.len() - 1];
+
+    let rst = rst_word
+        .parse()
+        .ok()
+        .filter(|&rst| rst_word.len() == 3 && is_valid_rst(rst))
+        .ok_or_else(|| MessageError::InvalidRst(rst_word.to_string()))?;
+
+    let exchange = if exch_word.bytes().all(|c| c.is_ascii_digit()) && exch_word.len() <= 4 {
+        exch_word
+            .parse()
+            .ok()
+            .filter(|&serial| serial <= 7999)
+            .map(RttyExchange::Serial)
+    } else if RTTY_MULTIPLIERS.contains(&exch_word) {
+        Some(RttyExchange::State(exch_word.to_string()))
+    } else {
+        None
+    }
+    .ok_or_else(|| MessageError::InvalidExchange(exch_word.to_string()))?;
+
+    Ok(MessageVariant::RttyRoundup {
+        tu,
+        call1,
+        call2,
+        r_flag,
+        rst,
+        exchange,
+    })
+}
+
+/// `CALL1 CALL2 [R] <n><class> <section>`
+pub(crate) fn parse_field_day_message(words: &[&str]) -> Result<MessageVariant, MessageError> {
+    let r_flag = match words.len() {
+        4 => false,
+        5 if words[2] == "R" => true,
+        n => {
+            return Err(MessageError::WordCount {
+                expected: "4, or 5 with R,",
+                got: n,
+            })
+        }
+    };
+    let call1 = plain_c28_call(words[0])?;
+    let call2 = plain_c28_call(words[1])?;
+    let exch = words[words.len() - 2];
+    let section = words[words.len() - 1];
+
+    let bad_class = || MessageError::InvalidFieldDayClass(exch.to_string());
+    let (count, class) = exch.split_at(exch.len().checked_sub(1).ok_or_else(bad_class)?);
+    let class = class.chars().next().ok_or_else(bad_class)?;
+    if count.is_empty() || count.len() > 2 || !count.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(bad_class());
+    }
+    let transmitters: u8 = count.parse().map_err(|_| bad_class())?;
+    if !(1..=32).contains(&transmitters) || !('A'..='F').contains(&class) {
+        return Err(bad_class());
+    }
+    if !ARRL_SECTIONS.contains(&section) {
+        return Err(MessageError::UnknownSection(section.to_string()));
+    }
+
+    Ok(MessageVariant::FieldDay {
+        call1,
+        call2,
+        r_flag,
+        transmitters,
+        class,
+        section: section.to_string(),
+    })
+}
+
+/// A single word of up to 18 hex digits
+///
+/// `Err` if the word isn't hex of that length; `Ok(Err)` if it is but the
+/// value overflows the 71-bit field.
+fn parse_telemetry_message(words: &[&str]) -> Shaped {
+    if words.len() != 1 {
+        return Err(MessageError::WordCount {
+            expected: "1",
+            got: words.len(),
+        });
+    }
+    match telemetry::parse_hex(words[0]) {
+        Ok(_) => Ok(Ok(MessageVariant::Telemetry {
+            hex: words[0].to_string(),
+        })),
+        Err(e @ MessageError::TelemetryOverflow(_)) => Ok(Err(e)),
+        Err(e) => Err(e),
+    }
+}
+
+/// `CALL1 CALL2 [R] <rst><serial> <grid6>`, e.g.
+/// `<PA3XYZ> <G4ABC> R 590003 IO91NP`
+///
+/// The exchange is a 52–59 report followed by a four-digit serial number
+/// up to 2047. `Err` if the message doesn't have that shape; `Ok(Err)` if
+/// it does but the locator is not a valid 6-character one, so `FN31ZZ` is
+/// rejected rather than sent as `FN31`.
+fn parse_eu_vhf_message(words: &[&str]) -> Shaped {
+    let r_flag = match words.len() {
+        4 => false,
+        5 if words[2] == "R" => true,
+        n => {
+            return Err(MessageError::WordCount {
+                expected: "4, or 5 with R,",
+                got: n,
+            })
+        }
+    };
+    let call1 = call_word(words[0])?;
+    let call2 = call_word(words[1])?;
+    for call in [&call1, &call2] {
+        if call.kind == CallKind::NonStandard {
+            return Err(MessageError::InvalidCallsign(call.call.clone()));
+        }
+    }
+    let exch = words[words.len() - 2];
+    let grid6 = words[words.len() - 1];
+
+    let bad_exchange = || MessageError::InvalidExchange(exch.to_string());
+    if exch.len() != 6 || !exch.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(bad_exchange());
+    }
+    let rst: u8 = exch[..2].parse().map_err(|_| bad_exchange())?;
+    let serial: u16 = exch[2..].parse().map_err(|_| bad_exchange())?;
+    if !(52..=59).contains(&rst) || serial > 2047 {
+        return Err(bad_exchange());
+    }
+    if grid6.len() != 6 {
+        return Err(MessageError::InvalidGrid(grid6.to_string()));
+    }
+    if let Err(e) = encode_grid6(grid6) {
+        return Ok(Err(e));
+    }
+
+    Ok(Ok(MessageVariant::EuVhfContestType5 {
+        call1: call1.full(),
+        call2: call2.full(),
+        r_flag,
+        rst,
+        serial,
+        grid6: grid6.to_string(),
+    }))
+}
+
+/// Uppercase and collapse runs of whitespace.
+pub(crate) fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(str::to_uppercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The parser's rules, in the order they are tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseRule {
+    /// Two callsigns or a CQ, with an optional grid or report; also the
+    /// forms with a non-standard callsign
+    Standard,
+    /// ARRL RTTY Roundup exchange
+    RttyRoundup,
+    /// ARRL Field Day exchange
+    FieldDay,
+    /// Up to 18 hex digits of telemetry
+    Telemetry,
+    /// EU VHF contest exchange with a 6-character locator
+    EuVhf,
+    /// Free text of up to 13 characters
+    FreeText,
+    /// A callsign word too odd for the other rules, once the text is too
+    /// long for free text
+    UnusualCall,
+}
+
+/// One rule tried by [`explain_parse`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseAttempt {
+    /// The rule tried
+    pub rule: ParseRule,
+    /// Why the rule didn't produce the message, or `None` if it did
+    pub rejection: Option<MessageError>,
+}
+
+/// Every rule [`parse_message_variant`] tried for a message, and the result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseTrace {
+    /// The rules tried, in order; only the last can have matched
+    pub attempts: Vec<ParseAttempt>,
+    /// What [`parse_message_variant`] returns for the message
+    pub result: Result<MessageVariant, MessageError>,
+}
+
+/// Whether any word is a callsign or a `CQ`/`QRZ`/`DE` token, so that
+/// text which falls through to free text was likely meant as a
+/// structured message.
+fn has_call_word(words: &[&str]) -> bool {
+    words.iter().any(|&word| {
+        is_call_token(word)
+            || matches!(
+                parse_call(word),
+                Some(CallWord {
+                    kind: CallKind::Standard { .. } | CallKind::Hashed,
+                    ..
+                })
+            )
+    })
+}
+
+/// Run the rules in order, reporting each one tried to `record`.
+///
+/// When `strict`, text with a callsign in it is not sent as free text;
+/// the reason the structured rules rejected it is returned instead.
+fn parse_words(
+    normalized: &str,
+    strict: bool,
+    record: &mut dyn FnMut(ParseRule, Option<&MessageError>),
+) -> Result<MessageVariant, MessageError> {
+    if normalized.is_empty() {
+        return Err(MessageError::Empty);
+    }
+    let words: Vec<&str> = normalized.split(' ').collect();
+
+    let rules: [(ParseRule, Rule); 3] = [
+        (ParseRule::Standard, parse_standard_message),
+        (ParseRule::RttyRoundup, parse_rtty_message),
+        (ParseRule::FieldDay, parse_field_day_message),
+    ];
+    let mut bad_modifier = None;
+    let mut first_rejection = None;
+    for (rule, parse) in rules {
+        match parse(&words) {
+            Ok(variant) => {
+                record(rule, None);
+                return Ok(variant);
+            }
+            Err(e) => {
+                record(rule, Some(&e));
+                if first_rejection.is_none() && !matches!(e, MessageError::WordCount { .. }) {
+                    first_rejection = Some(e.clone());
+                }
+                if let MessageError::InvalidCqModifier(_) = e {
+                    bad_modifier = Some(e);
+                }
+            }
+        }
+    }
+    let shaped: [(ParseRule, ShapedRule); 2] = [
+        (ParseRule::Telemetry, parse_telemetry_message),
+        (ParseRule::EuVhf, parse_eu_vhf_message),
+    ];
+    for (rule, parse) in shaped {
+        match parse(&words) {
+            Ok(result) => {
+                record(rule, result.as_ref().err());
+                return result;
+            }
+            Err(e) => record(rule, Some(&e)),
+        }
+    }
+
+    if let Err(e) = free_text::validate(normalized) {
+        record(ParseRule::FreeText, Some(&e));
+        return match parse_unusual_call_message(&words) {
+            Ok(result) => {
+                record(ParseRule::UnusualCall, result.as_ref().err());
+                result
+            }
+            Err(reason) => {
+                record(ParseRule::UnusualCall, Some(&reason));
+                // A directed CQ is a likelier intent than overlong text
+                Err(bad_modifier.unwrap_or(e))
+            }
+        };
+    }
+    if strict && has_call_word(&words) {
+        let e = bad_modifier
+            .or(first_rejection)
+            .unwrap_or_else(|| MessageError::NotStructured(normalized.to_string()));
+        record(ParseRule::FreeText, Some(&e));
+        return Err(e);
+    }
+    record(ParseRule::FreeText, None);
+    Ok(MessageVariant::FreeText {
+        text: normalized.to_string(),
+    })
+}
+
+/// Parse message text into the variant that will be used to encode it.
+///
+/// Falls back to free text when no structured rule matches; returns an error
+/// if the text cannot be sent at all (e.g. too long for free text).
+pub fn parse_message_variant(text: &str) -> Result<MessageVariant, MessageError> {
+    parse_words(&normalize(text), false, &mut |_, _| {})
+}
+
+/// Parse message text as [`parse_message_variant`] does, but without
+/// falling back to free text for text that contains a callsign or a
+/// `CQ`, `QRZ` or `DE`.
+///
+/// Such text was meant as a structured message, so the reason it isn't
+/// one is returned instead: `CQ 123 DM42` fails on the callsign `123`
+/// rather than going out as free text. Text without any callsign, such
+/// as `TNX BOB 73 GL`, is still free text.
+pub fn parse_message_variant_strict(text: &str) -> Result<MessageVariant, MessageError> {
+    parse_words(&normalize(text), true, &mut |_, _| {})
+}
+
+/// Parse message text as [`parse_message_variant`] does, recording why
+/// each rule before the one that matched was rejected.
+///
+/// Useful to see why, say, `CQ K1ABC XY12` is sent as free text.
+pub fn explain_parse(text: &str) -> ParseTrace {
+    let mut attempts = Vec::new();
+    let result = parse_words(&normalize(text), false, &mut |rule, rejection| {
+        attempts.push(ParseAttempt {
+            rule,
+            rejection: rejection.cloned(),
+        })
+    });
+    ParseTrace { attempts, result }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> MessageVariant {
+        parse_message_variant(text).unwrap_or_else(|e| panic!("{}: {}", text, e))
+    }
+
+    #[test]
+    fn test_standard_variants() {
+        assert_eq!(
+            parse("CQ K1ABC FN42"),
+            MessageVariant::Standard {
+                call1: "CQ".into(),
+                call1_suffix: false,
+                call2: "K1ABC".into(),
+                call2_suffix: false,
+                r_flag: false,
+                payload: GridReport::Grid("FN42".into()),
+            }
+        );
+        assert!(matches!(
+            parse("K1ABC W9XYZ R-09"),
+            MessageVariant::Standard {
+                r_flag: true,
+                payload: GridReport::Report(-9),
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("W9XYZ K1ABC/R R FN42"),
+            MessageVariant::Standard {
+                call2_suffix: true,
+                r_flag: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("K1ABC W9XYZ RR73"),
+            MessageVariant::Standard {
+                payload: GridReport::Rr73,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_directed_cq() {
+        assert!(matches!(
+            parse("CQ SOTA N0YPR DM42"),
+            MessageVariant::Standard { ref call1, .. } if call1 == "CQ SOTA"
+        ));
+        assert!(matches!(
+            parse("CQ DX K1ABC"),
+            MessageVariant::Standard { ref call1, payload: GridReport::Blank, .. } if call1 == "CQ DX"
+        ));
+        assert!(matches!(
+            parse("CQ SOTA N0YPR/R DM42"),
+            MessageVariant::Standard { ref call1, call1_suffix: false, call2_suffix: true, .. }
+                if call1 == "CQ SOTA"
+        ));
+        assert!(matches!(
+            parse("CQ DX G4ABC/P IO91"),
+            MessageVariant::EuVhfContestType2 { ref call1, call1_suffix: false, call2_suffix: true, .. }
+                if call1 == "CQ DX"
+        ));
+    }
+
+    #[test]
+    fn test_qrz_and_de_tokens() {
+        use crate::message::{decode, encode_to_vec, CallsignHashCache};
+
+        for (text, token, n28) in [
+            ("QRZ K1ABC FN42", "QRZ", 1u32),
+            ("QRZ K1ABC", "QRZ", 1),
+            ("DE K1ABC FN42", "DE", 0),
+            ("DE K1ABC", "DE", 0),
+        ] {
+            let variant = parse(text);
+            assert!(
+                matches!(&variant, MessageVariant::Standard { call1, call1_suffix: false, .. } if call1 == token),
+                "{}: {:?}",
+                text,
+                variant
+            );
+            let bits = encode_to_vec(text, &mut CallsignHashCache::new()).unwrap();
+            assert_eq!(crate::message::bits::get_u32(&bits, 0, 28), n28, "{}", text);
+            assert_eq!(decode(&bits, None).unwrap(), text);
+        }
+        // Only CQ takes a modifier
+        assert!(matches!(
+            parse("QRZ DX K1ABC"),
+            MessageVariant::FreeText { .. }
+        ));
+    }
+
+    #[test]
+    fn test_directed_cq_modifiers() {
+        use crate::message::{decode, encode, CallsignHashCache};
+        use bitvec::prelude::*;
+
+        for text in [
+            "CQ TEST K1ABC FN42",
+            "CQ FD K1ABC FN42",
+            "CQ 005 K1ABC FN42",
+            "CQ POTA K1ABC FN42",
+            "CQ DX N0YPR DM42",
+            "CQ POTA K1ABC",
+        ] {
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            encode(text, &mut bits[..77], &mut CallsignHashCache::new()).unwrap();
+            assert_eq!(decode(&bits[..77], None).unwrap(), text);
+        }
+
+        // Too long for the directed-CQ field, and for free text
+        for (text, modifier) in [
+            ("CQ DXPED K1ABC FN42", "DXPED"),
+            ("CQ 05 K1ABC FN42", "05"),
+            ("CQ 0050 N0YPR DM42", "0050"),
+            ("CQ NA1 K1ABC FN42", "NA1"),
+        ] {
+            assert_eq!(
+                parse_message_variant(text),
+                Err(MessageError::InvalidCqModifier(modifier.into())),
+                "{}",
+                text
+            );
+        }
+        // Short enough to go out as free text instead
+        assert!(matches!(
+            parse("CQ 05 K1A"),
+            MessageVariant::FreeText { .. }
+        ));
+    }
+
+    #[test]
+    fn test_portable_selects_type2() {
+        assert!(matches!(
+            parse("CQ K1ABC/P FN42"),
+            MessageVariant::EuVhfContestType2 {
+                call2_suffix: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("PA9XYZ G4ABC/P RR73"),
+            MessageVariant::EuVhfContestType2 {
+                call1_suffix: false,
+                call2_suffix: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("G4ABC/P PA9XYZ/P JO22"),
+            MessageVariant::EuVhfContestType2 {
+                call1_suffix: true,
+                call2_suffix: true,
+                r_flag: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_nonstandard() {
+        assert_eq!(
+            parse("<W9XYZ> PJ4/K1ABC RRR"),
+            MessageVariant::NonStandardCall {
+                hashed_call: "<W9XYZ>".into(),
+                full_call: "PJ4/K1ABC".into(),
+                hashed_first: true,
+                payload: GridReport::Rrr,
+                cq: false,
+            }
+        );
+        assert!(matches!(
+            parse("PJ4/K1ABC W9XYZ 73"),
+            MessageVariant::NonStandardCall {
+                hashed_first: false,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("CQ YW18FIFA"),
+            MessageVariant::NonStandardCall { cq: true, .. }
+        ));
+    }
+
+    #[test]
+    fn test_contest_and_telemetry() {
+        assert!(matches!(
+            parse("TU; W9XYZ K1ABC R 579 MA"),
+            MessageVariant::RttyRoundup { tu: true, .. }
+        ));
+        assert!(matches!(
+            parse("K1ABC W9XYZ 6A WI"),
+            MessageVariant::FieldDay {
+                transmitters: 6,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("123456789ABCDEF012"),
+            MessageVariant::Telemetry { .. }
+        ));
+    }
+
+    #[test]
+    fn test_eu_vhf_grid6() {
+        assert_eq!(
+            parse("<PA3XYZ> <G4ABC/P> R 590003 io91np"),
+            MessageVariant::EuVhfContestType5 {
+                call1: "<PA3XYZ>".into(),
+                call2: "<G4ABC/P>".into(),
+                r_flag: true,
+                rst: 59,
+                serial: 3,
+                grid6: "IO91NP".into(),
+            }
+        );
+        assert!(matches!(
+            parse("PA3XYZ G4ABC 572047 JO22AA"),
+            MessageVariant::EuVhfContestType5 {
+                r_flag: false,
+                rst: 57,
+                serial: 2047,
+                ..
+            }
+        ));
+        assert_eq!(
+            parse_message_variant("<PA3XYZ> <G4ABC> 590001 FN31ZZ"),
+            Err(MessageError::InvalidGrid("FN31ZZ".into()))
+        );
+        // Out-of-range exchanges are not EU VHF messages at all
+        assert!(parse_message_variant("<PA3XYZ> <G4ABC> 590001 FN31").is_err());
+        assert!(parse_message_variant("<PA3XYZ> <G4ABC> 592048 FN31PR").is_err());
+        assert!(parse_message_variant("<PA3XYZ> <G4ABC> 610001 FN31PR").is_err());
+        // A standard message only carries the 4-character square, so a
+        // 6-character locator is rejected there, and the text is too long
+        // to fall back to free text
+        let trace = explain_parse("K1ABC W9XYZ FN31PR");
+        assert_eq!(trace.attempts[0].rule, ParseRule::Standard);
+        assert!(trace.attempts[0].rejection.is_some());
+        assert_eq!(
+            trace.result,
+            Err(MessageError::TooLong { max: 13, got: 18 })
+        );
+    }
+
+    #[test]
+    fn test_free_text_fallback() {
+        assert_eq!(
+            parse("TNX BOB 73 GL"),
+            MessageVariant::FreeText {
+                text: "TNX BOB 73 GL".into()
+            }
+        );
+        assert_eq!(
+            parse("CQ 123 DM42"),
+            MessageVariant::FreeText {
+                text: "CQ 123 DM42".into()
+            }
+        );
+        assert!(parse_message_variant("THIS MESSAGE IS FAR TOO LONG").is_err());
+        assert!(parse_message_variant("   ").is_err());
+    }
+
+    #[test]
+    fn test_strict_rejects_what_would_fall_back_to_free_text() {
+        // Free text either way
+        for text in ["TNX BOB 73 GL", "HELLO WORLD", "73 GL"] {
+            let lenient = parse(text);
+            assert!(matches!(lenient, MessageVariant::FreeText { .. }));
+            assert_eq!(parse_message_variant_strict(text), Ok(lenient));
+        }
+
+        // Structured messages parse the same either way
+        for text in [
+            "CQ K1ABC FN42",
+            "K1ABC W9XYZ R-09",
+            "W1ABC NONSTANDARD",
+            "K1ABC W9XYZ 579 0013",
+            "123456789ABCDEF012",
+        ] {
+            assert_eq!(
+                parse_message_variant_strict(text),
+                Ok(parse(text)),
+                "{}",
+                text
+            );
+        }
+
+        // Free text in lenient mode, the structured rules' reason in strict
+        for (text, err) in [
+            ("CQ 123 DM42", MessageError::InvalidCallsign("123".into())),
+            ("W1ABC HELLO", MessageError::InvalidCallsign("HELLO".into())),
+            ("CQ K1ABC XY12", MessageError::InvalidGrid("XY12".into())),
+            (
+                "CQ DX1 K1ABC",
+                MessageError::InvalidCqModifier("DX1".into()),
+            ),
+        ] {
+            assert!(
+                matches!(parse(text), MessageVariant::FreeText { .. }),
+                "{}",
+                text
+            );
+            assert_eq!(parse_message_variant_strict(text), Err(err), "{}", text);
+        }
+
+        // Errors in both modes stay errors
+        assert!(parse_message_variant_strict("THIS MESSAGE IS FAR TOO LONG").is_err());
+        assert_eq!(parse_message_variant_strict("  "), Err(MessageError::Empty));
+    }
+
+    #[test]
+    fn test_long_and_unusual_calls() {
+        // Longer than six characters: sent in full with the other call hashed
+        assert!(matches!(
+            parse("W1ABC KH6ABCDE"),
+            MessageVariant::NonStandardCall {
+                hashed_first: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("W1ABC AB1CDEFGHIJ RR73"),
+            MessageVariant::NonStandardCall {
+                payload: GridReport::Rr73,
+                ..
+            }
+        ));
+
+        // No digit, so not recognised as a call, but too long for free text
+        assert_eq!(
+            parse("W1ABC NONSTANDARD"),
+            MessageVariant::NonStandardCall {
+                hashed_call: "<W1ABC>".into(),
+                full_call: "NONSTANDARD".into(),
+                hashed_first: true,
+                payload: GridReport::Blank,
+                cq: false,
+            }
+        );
+        assert!(matches!(
+            parse("NONSTANDARD W1ABC 73"),
+            MessageVariant::NonStandardCall {
+                hashed_first: false,
+                payload: GridReport::SeventyThree,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse("CQ NONSTANDARD"),
+            MessageVariant::NonStandardCall { cq: true, .. }
+        ));
+        // Short enough for free text, which is preferred
+        assert!(matches!(
+            parse("W1ABC HELLO"),
+            MessageVariant::FreeText { .. }
+        ));
+
+        assert_eq!(
+            parse_message_variant("W1ABC VERYLONGCALL"),
+            Err(MessageError::CallsignTooLong {
+                call: "VERYLONGCALL".into(),
+                max: MAX_C58_LEN
+            })
+        );
+        assert!(parse_message_variant("CQ VERYLONGCALL").is_err());
+        assert!(parse_message_variant("PJ4/K1ABC NONSTANDARD").is_err());
+        assert!(parse_message_variant("W1ABC NONSTANDARD -10").is_err());
+    }
+
+    #[test]
+    fn test_explain_parse() {
+        let trace = explain_parse("cq k1abc xy12");
+        assert_eq!(
+            trace.result,
+            Ok(MessageVariant::FreeText {
+                text: "CQ K1ABC XY12".into()
+            })
+        );
+        let rules: Vec<ParseRule> = trace.attempts.iter().map(|a| a.rule).collect();
+        assert_eq!(
+            rules,
+            [
+                ParseRule::Standard,
+                ParseRule::RttyRoundup,
+                ParseRule::FieldDay,
+                ParseRule::Telemetry,
+                ParseRule::EuVhf,
+                ParseRule::FreeText,
+            ]
+        );
+        // XY is past the last Maidenhead field, R
+        assert_eq!(
+            trace.attempts[0].rejection,
+            Some(MessageError::InvalidGrid("XY12".into()))
+        );
+        assert!(trace.attempts[..5].iter().all(|a| a.rejection.is_some()));
+        assert_eq!(
+            trace.attempts[1].rejection,
+            Some(MessageError::WordCount {
+                expected: "4, or 5 with R,",
+                got: 3
+            })
+        );
+        assert_eq!(trace.attempts[5].rejection, None);
+
+        let trace = explain_parse("K1ABC W9XYZ 6A WI");
+        assert_eq!(trace.attempts.len(), 3);
+        assert_eq!(
+            trace.attempts[0].rejection,
+            Some(MessageError::InvalidGrid("WI".into()))
+        );
+        assert_eq!(
+            trace.attempts[1].rejection,
+            Some(MessageError::InvalidRst("6A".into()))
+        );
+        assert_eq!(trace.attempts[2].rule, ParseRule::FieldDay);
+        assert_eq!(trace.result, parse_message_variant("K1ABC W9XYZ 6A WI"));
+
+        // Too long for free text, then not an unusual callsign either
+        let trace = explain_parse("K1ABC W9XYZ/Q -10");
+        // W9XYZ/Q can only go out as a non-standard call, without a report
+        assert_eq!(
+            trace.attempts[0].rejection,
+            Some(MessageError::NonStandardPayload)
+        );
+        let last = trace.attempts.last().unwrap();
+        assert_eq!(last.rule, ParseRule::UnusualCall);
+        assert_eq!(last.rejection, Some(MessageError::NonStandardPayload));
+        assert_eq!(
+            trace.result,
+            Err(MessageError::TooLong { max: 13, got: 17 })
+        );
+
+        assert_eq!(explain_parse(" ").result, Err(MessageError::Empty));
+        assert!(explain_parse(" ").attempts.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+//! ARRL RTTY Roundup messages (i3=3)
+//!
+//! `TU; W9XYZ K1ABC R 579 MA`. Layout: `t1 c28 c28 R1 r3 s13`, where `r3`
+//! encodes the RST 529–599 and `s13` is either a serial number (0–7999) or
+//! 8000 plus the 1-based index of a US state / Canadian province.
+
+use alloc::format;
+use alloc::string::ToString;
+use bitvec::prelude::*;
+
+use super::bits::{get_u32, put};
+use super::callsign::{pack_callsign, unpack_callsign};
+use super::error::MessageError;
+use super::hash::CallsignHashCache;
+use super::standard::remember_call;
+use super::types::{MessageVariant, RttyExchange};
+
+/// US states and Canadian provinces in WSJT-X order
+pub const RTTY_MULTIPLIERS: [&str; 65] = [
+    "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "FL", "GA", "HI", "ID", "IL", "IN", "IA", "KS",
+    "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV", "NH", "NJ", "NM", "NY",
+    "NC", "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD", "TN", "TX", "UT", "VT", "VA", "WA", "WV",
+    "WI", "WY", "NB", "NS", "QC", "ON", "MB", "SK", "AB", "BC", "NWT", "NF", "LB", "NU", "YT",
+    "PEI", "DC",
+];
+
+/// Whether `rst` is a valid RTTY Roundup report (529, 539, … 599).
+pub(crate) fn is_valid_rst(rst: u16) -> bool {
+    (529..=599).contains(&rst) && rst % 10 == 9
+}
+
+/// Encode an RTTY Roundup message.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn encode(
+    tu: bool,
+    call1: &str,
+    call2: &str,
+    r_flag: bool,
+    rst: u16,
+    exchange: &RttyExchange,
+    bits: &mut BitSlice<u8, Msb0>,
+    cache: &mut CallsignHashCache,
+) -> Result<(), MessageError> {
+    if !is_valid_rst(rst) {
+        return Err(MessageError::Encode(format!(
+            "Invalid RTTY report: {}",
+            rst
+        )));
+    }
+    let nexch = match exchange {
+        RttyExchange::Serial(n) if *n <= 7999 => *n as u32,
+        RttyExchange::Serial(n) => {
+            return Err(MessageError::Encode(format!(
+                "RTTY serial out of range: {}",
+                n
+            )))
+        }
+        RttyExchange::State(s) => {
+            let idx = RTTY_MULTIPLIERS
+                .iter()
+                .position(|&m| m == s)
+                .ok_or_else(|| MessageError::InvalidExchange(s.clone()))?;
+            8001 + idx as u32
+        }
+    };
+    let n28a = pack_callsign(call1)?;
+    let n28b = pack_callsign(call2)?;
+    let irpt = ((rst - 509) / 10 - 2) as u32;
+
+    put(bits, 0, 1, tu as u128);
+    put(bits, 1, 28, n28a as u128);
+    put(bits, 29, 28, n28b as u128);
+    put(bits, 57, 1, r_flag as u128);
+    put(bits, 58, 3, irpt as u128);
+    put(bits, 61, 13, nexch as u128);
+    put(bits, 74, 3, 3);
+
+    remember_call(cache, call1);
+    remember_call(cache, call2);
+    Ok(())
+}
+
+/// Decode an i3=3 message.
+pub(crate) fn decode(
+    bits: &BitSlice<u8, Msb0>,
+    cache: Option<&CallsignHashCache>,
+) -> Result<MessageVariant, MessageError> {
+    let tu = bits[0];
+    let call1 = unpack_callsign(get_u32(bits, 1, 28), cache)?;
+    let call2 = unpack_callsign(get_u32(bits, 29, 28), cache)?;
+    let r_flag = bits[57];
+    let rst = 529 + 10 * get_u32(bits, 58, 3) as u16;
+    let nexch = get_u32(bits, 61, 13);
+
+    let exchange = if nexch > 8000 {
+        let idx = (nexch - 8001) as usize;
+        let state = RTTY_MULTIPLIERS.get(idx).ok_or_else(|| {
+            MessageError::Decode(format!("Invalid state/province index: {}", nexch))
+        })?;
+        RttyExchange::State(state.to_string())
+    } else if nexch == 8000 {
+        return Err(MessageError::Decode("Invalid RTTY exchange: 8000".into()));
+    } else {
+        RttyExchange::Serial(nexch as u16)
+    };
+
+    Ok(MessageVariant::RttyRoundup {
+        tu,
+        call1,
+        call2,
+        r_flag,
+        rst,
+        exchange,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let cases = [
+            (
+                true,
+                "W9XYZ",
+                "K1ABC",
+                true,
+                579,
+                RttyExchange::State("MA".into()),
+                "TU; W9XYZ K1ABC R 579 MA",
+            ),
+            (
+                false,
+                "K1ABC",
+                "W9XYZ",
+                false,
+                599,
+                RttyExchange::Serial(13),
+                "K1ABC W9XYZ 599 0013",
+            ),
+            (
+                false,
+                "K1ABC",
+                "W9XYZ",
+                false,
+                529,
+                RttyExchange::State("PEI".into()),
+                "K1ABC W9XYZ 529 PEI",
+            ),
+        ];
+        for (tu, c1, c2, r, rst, exch, text) in cases {
+            let mut cache = CallsignHashCache::new();
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            encode(tu, c1, c2, r, rst, &exch, &mut bits[..77], &mut cache).unwrap();
+            assert_eq!(decode(&bits[..77], None).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn test_invalid_rst() {
+        assert!(!is_valid_rst(589 + 1));
+        assert!(!is_valid_rst(519));
+        assert!(is_valid_rst(559));
+    }
+}
@@ -0,0 +1,236 @@
+//! Standard messages (i3=1) and their EU VHF `/P` variant (i3=2)
+//!
+//! Layout: `c28 r1 c28 r1 R1 g15 i3`, where `r1` is the `/R` (i3=1) or
+//! `/P` (i3=2) suffix flag of the preceding callsign and `R1` the
+//! acknowledgement flag in front of a grid or report.
+
+use alloc::format;
+use bitvec::prelude::*;
+
+use super::bits::{get_u32, put};
+use super::callsign::{pack_callsign, unpack_callsign};
+use super::error::MessageError;
+use super::grid::{decode_grid4, encode_grid4, MAXGRID4};
+use super::hash::CallsignHashCache;
+use super::types::{GridReport, MessageVariant};
+
+/// Pack the final field of a standard message into `g15`.
+pub(crate) fn pack_g15(payload: &GridReport) -> Result<u32, MessageError> {
+    match payload {
+        GridReport::Grid(grid) => encode_grid4(grid),
+        GridReport::Blank => Ok(MAXGRID4 + 1),
+        GridReport::Rrr => Ok(MAXGRID4 + 2),
+        GridReport::Rr73 => Ok(MAXGRID4 + 3),
+        GridReport::SeventyThree => Ok(MAXGRID4 + 4),
+        GridReport::Report(db) => {
+            if !(-50..=49).contains(db) {
+                return Err(MessageError::ReportOutOfRange(*db));
+            }
+            let mut isnr = *db as i32;
+            if isnr <= -31 {
+                isnr += 101;
+            }
+            Ok(MAXGRID4 + (isnr + 35) as u32)
+        }
+    }
+}
+
+/// Unpack a `g15` field.
+pub(crate) fn unpack_g15(g15: u32) -> Result<GridReport, MessageError> {
+    if g15 < MAXGRID4 {
+        return decode_grid4(g15).map(GridReport::Grid);
+    }
+    match g15 - MAXGRID4 {
+        1 => Ok(GridReport::Blank),
+        2 => Ok(GridReport::Rrr),
+        3 => Ok(GridReport::Rr73),
+        4 => Ok(GridReport::SeventyThree),
+        irpt if irpt >= 5 => {
+            let mut isnr = irpt as i32 - 35;
+            if isnr > 50 {
+                isnr -= 101;
+            }
+            if !(-50..=50).contains(&isnr) {
+                return Err(MessageError::Decode(format!(
+                    "Invalid report field: {}",
+                    g15
+                )));
+            }
+            Ok(GridReport::Report(isnr as i8))
+        }
+        _ => Err(MessageError::Decode(format!("Invalid g15 field: {}", g15))),
+    }
+}
+
+/// Record any real callsign (not a token or placeholder) in the cache.
+pub(crate) fn remember_call(cache: &mut CallsignHashCache, call: &str) {
+    if call.starts_with("CQ") || call == "QRZ" || call == "DE" {
+        return;
+    }
+    cache.insert(call);
+}
+
+/// Encode a `Standard` or `EuVhfContestType2` variant.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn encode(
+    i3: u8,
+    call1: &str,
+    call1_suffix: bool,
+    call2: &str,
+    call2_suffix: bool,
+    r_flag: bool,
+    payload: &GridReport,
+    bits: &mut BitSlice<u8, Msb0>,
+    cache: &mut CallsignHashCache,
+) -> Result<(), MessageError> {
+    let n28a = pack_callsign(call1)?;
+    let n28b = pack_callsign(call2)?;
+    let g15 = pack_g15(payload)?;
+
+    put(bits, 0, 28, n28a as u128);
+    put(bits, 28, 1, call1_suffix as u128);
+    put(bits, 29, 28, n28b as u128);
+    put(bits, 57, 1, call2_suffix as u128);
+    put(bits, 58, 1, r_flag as u128);
+    put(bits, 59, 15, g15 as u128);
+    put(bits, 74, 3, i3 as u128);
+
+    // A suffixed call is hashed with its suffix when another message
+    // carries it in full, so remember that form too
+    let suffix = if i3 == 2 { "/P" } else { "/R" };
+    for (call, suffixed) in [(call1, call1_suffix), (call2, call2_suffix)] {
+        remember_call(cache, call);
+        if suffixed && !call.starts_with('<') {
+            remember_call(cache, &[call, suffix].concat());
+        }
+    }
+    Ok(())
+}
+
+/// Decode an i3=1 or i3=2 message.
+pub(crate) fn decode(
+    bits: &BitSlice<u8, Msb0>,
+    cache: Option<&CallsignHashCache>,
+) -> Result<MessageVariant, MessageError> {
+    let i3 = get_u32(bits, 74, 3);
+    let call1 = unpack_callsign(get_u32(bits, 0, 28), cache)?;
+    let call1_suffix = bits[28];
+    let call2 = unpack_callsign(get_u32(bits, 29, 28), cache)?;
+    let call2_suffix = bits[57];
+    let payload = unpack_g15(get_u32(bits, 59, 15))?;
+    // The R flag only has meaning in front of a grid or report
+    let r_flag = bits[58] && matches!(payload, GridReport::Grid(_) | GridReport::Report(_));
+
+    Ok(if i3 == 2 {
+        MessageVariant::EuVhfContestType2 {
+            call1,
+            call1_suffix,
+            call2,
+            call2_suffix,
+            r_flag,
+            payload,
+        }
+    } else {
+        MessageVariant::Standard {
+            call1,
+            call1_suffix,
+            call2,
+            call2_suffix,
+            r_flag,
+            payload,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_g15_reports() {
+        assert_eq!(pack_g15(&GridReport::Report(-10)), Ok(MAXGRID4 + 25));
+        assert_eq!(pack_g15(&GridReport::Report(0)), Ok(MAXGRID4 + 35));
+        // Reports below -30 wrap into the upper range
+        assert_eq!(pack_g15(&GridReport::Report(-31)), Ok(MAXGRID4 + 105));
+        for db in -50..=49 {
+            let g15 = pack_g15(&GridReport::Report(db)).unwrap();
+            assert_eq!(unpack_g15(g15), Ok(GridReport::Report(db)));
+        }
+        assert!(pack_g15(&GridReport::Report(50)).is_err());
+    }
+
+    #[test]
+    fn test_g15_tokens() {
+        for payload in [
+            GridReport::Blank,
+            GridReport::Rrr,
+            GridReport::Rr73,
+            GridReport::SeventyThree,
+            GridReport::Grid("FN42".into()),
+        ] {
+            let g15 = pack_g15(&payload).unwrap();
+            assert_eq!(unpack_g15(g15).unwrap(), payload);
+        }
+        assert!(unpack_g15(MAXGRID4).is_err());
+    }
+
+    #[test]
+    fn test_portable_and_rover_flags_are_distinct() {
+        use crate::message::{decode, encode};
+
+        // The suffix bits are identical; only i3 says which suffix they mean
+        for (text, i3) in [
+            ("CQ K1ABC/P FN42", 2),
+            ("CQ K1ABC/R FN42", 1),
+            ("G4ABC/P PA9XYZ/P R JO22", 2),
+            ("K1ABC/R W9XYZ/R R EN37", 1),
+            ("PA9XYZ G4ABC/P 73", 2),
+            ("W9XYZ K1ABC/R 73", 1),
+        ] {
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            let mut cache = CallsignHashCache::new();
+            encode(text, &mut bits[..77], &mut cache).unwrap();
+            assert_eq!(get_u32(&bits, 74, 3), i3, "{}", text);
+            assert_eq!(decode(&bits[..77], None).unwrap(), text);
+        }
+
+        // Each call has its own flag: c28 r1 c28 r1 puts them at bits 28 and 57
+        for (text, flags) in [
+            ("G4ABC/P PA9XYZ JO22", (true, false)),
+            ("G4ABC PA9XYZ/P JO22", (false, true)),
+            ("G4ABC/P PA9XYZ/P JO22", (true, true)),
+        ] {
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            let mut cache = CallsignHashCache::new();
+            encode(text, &mut bits[..77], &mut cache).unwrap();
+            assert_eq!((bits[28], bits[57]), flags, "{}", text);
+            assert_eq!(decode(&bits[..77], None).unwrap(), text);
+        }
+
+        // One message cannot carry both kinds of suffix
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        let mut cache = CallsignHashCache::new();
+        assert!(encode("G4ABC/P PA9XYZ/R JO22", &mut bits[..77], &mut cache).is_err());
+    }
+
+    #[test]
+    fn test_directed_cq_keeps_suffix_of_calling_station() {
+        use crate::message::{decode, encode};
+
+        // The directed CQ fills call1, so the suffix is call2's flag (bit 57)
+        for (text, i3) in [
+            ("CQ SOTA N0YPR/R DM42", 1),
+            ("CQ FD K1ABC/R FN42", 1),
+            ("CQ DX G4ABC/P IO91", 2),
+            ("CQ 005 K1ABC/R", 1),
+            ("CQ TEST PA9XYZ/P JO22", 2),
+        ] {
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            let mut cache = CallsignHashCache::new();
+            encode(text, &mut bits[..77], &mut cache).unwrap();
+            assert_eq!(get_u32(&bits, 74, 3), i3, "{}", text);
+            assert_eq!((bits[28], bits[57]), (false, true), "{}", text);
+            assert_eq!(decode(&bits[..77], None).unwrap(), text);
+        }
+    }
+}
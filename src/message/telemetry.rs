@@ -0,0 +1,119 @@
+//! Telemetry messages (i3=0 n3=5)
+//!
+//! 71 bits of user data written as up to 18 hexadecimal digits. The value is
+//! right-justified (left zero-padded) to 18 digits, so the leading digit may
+//! not exceed 7.
+
+use alloc::format;
+use bitvec::prelude::*;
+
+use super::bits::{get, put};
+use super::error::MessageError;
+use super::types::MessageVariant;
+
+/// Maximum number of hex digits in a telemetry message
+pub const MAX_TELEMETRY_DIGITS: usize = 18;
+
+/// Parse a telemetry hex string into its 71-bit value.
+///
+/// Both the parser and the encoder go through here. Case doesn't matter,
+/// and fewer than 18 digits are the same value left-padded with zeros.
+pub(crate) fn parse_hex(hex: &str) -> Result<u128, MessageError> {
+    if hex.is_empty() || hex.len() > MAX_TELEMETRY_DIGITS {
+        return Err(MessageError::InvalidTelemetry(hex.into()));
+    }
+    if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err(MessageError::InvalidTelemetry(hex.into()));
+    }
+    let value =
+        u128::from_str_radix(hex, 16).map_err(|_| MessageError::InvalidTelemetry(hex.into()))?;
+    // Left-padded to 18 digits, the first must stay below 8
+    if value >> 71 != 0 {
+        return Err(MessageError::TelemetryOverflow(hex.into()));
+    }
+    Ok(value)
+}
+
+/// Encode telemetry into the 77-bit message.
+pub(crate) fn encode(hex: &str, bits: &mut BitSlice<u8, Msb0>) -> Result<(), MessageError> {
+    let value = parse_hex(hex)?;
+    put(bits, 0, 71, value);
+    put(bits, 71, 3, 5);
+    put(bits, 74, 3, 0);
+    Ok(())
+}
+
+/// Decode an i3=0 n3=5 message. Leading zeros are stripped.
+pub(crate) fn decode(bits: &BitSlice<u8, Msb0>) -> Result<MessageVariant, MessageError> {
+    let value = get(bits, 0, 71);
+    Ok(MessageVariant::Telemetry {
+        hex: format!("{:X}", value),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        for hex in ["123456789ABCDEF012", "7FFFFFFFFFFFFFFFFF", "1", "ABCDEF"] {
+            let mut bits = bitarr![u8, Msb0; 0; 80];
+            encode(hex, &mut bits[..77]).unwrap();
+            assert_eq!(
+                decode(&bits[..77]).unwrap(),
+                MessageVariant::Telemetry { hex: hex.into() }
+            );
+        }
+    }
+
+    #[test]
+    fn test_leading_zeros_stripped() {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        encode("000000000000000ABC", &mut bits[..77]).unwrap();
+        assert_eq!(
+            decode(&bits[..77]).unwrap(),
+            MessageVariant::Telemetry { hex: "ABC".into() }
+        );
+    }
+
+    #[test]
+    fn test_overflow_rejected() {
+        assert!(parse_hex("800000000000000000").is_err());
+        assert!(parse_hex("1234567890123456789").is_err());
+        assert!(parse_hex("12G4").is_err());
+    }
+
+    #[test]
+    fn test_71_bit_boundary() {
+        assert_eq!(parse_hex("7FFFFFFFFFFFFFFFFF"), Ok((1 << 71) - 1));
+        assert_eq!(parse_hex("7fffffffffffffffff"), Ok((1 << 71) - 1));
+        for hex in [
+            "800000000000000000",
+            "FFFFFFFFFFFFFFFFFF",
+            "A00000000000000001",
+        ] {
+            assert_eq!(
+                parse_hex(hex),
+                Err(MessageError::TelemetryOverflow(hex.into()))
+            );
+            assert_eq!(
+                crate::message::parse_message_variant(hex),
+                Err(MessageError::TelemetryOverflow(hex.to_uppercase()))
+            );
+        }
+
+        // Short and lowercase values pack as their zero-padded form
+        let mut short = bitarr![u8, Msb0; 0; 80];
+        let mut padded = bitarr![u8, Msb0; 0; 80];
+        encode("abc", &mut short[..77]).unwrap();
+        encode("000000000000000ABC", &mut padded[..77]).unwrap();
+        assert_eq!(short, padded);
+        assert_eq!(
+            crate::message::parse_message_variant("7fffffffffffffffff"),
+            Ok(MessageVariant::Telemetry {
+                hex: "7FFFFFFFFFFFFFFFFF".into()
+            })
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Typed transmit constructors
+//!
+//! A QSO state machine knows exactly which message it wants to send next.
+//! These helpers build the standard (i3=1) message directly instead of
+//! formatting text and relying on the parser to pick the intended type, and
+//! return the 79 channel tones ready for the modulator.
+//!
+//! Messages follow the on-air convention of addressing the other station
+//! first: `tx_report("N0YPR", "K1ABC", -10, false)` sends `K1ABC N0YPR -10`.
+
+use alloc::string::{String, ToString};
+use bitvec::prelude::*;
+
+use super::callsign::{classify_callsign, strip_rover_portable, CallsignKind};
+use super::encode_variant;
+use super::error::MessageError;
+use super::grid::validate_grid_basic;
+use super::hash::CallsignHashCache;
+use super::types::{GridReport, MessageVariant};
+use crate::symbol::{channel_symbols_from_bits, NUM_SYMBOLS};
+
+/// Split a callsign into its base and `/R` flag, rejecting anything that
+/// does not fit a standard (i3=1) message.
+fn standard_call(callsign: &str) -> Result<(String, bool), MessageError> {
+    let call = callsign.trim().to_uppercase();
+    if classify_callsign(&call) != CallsignKind::Standard || call.ends_with("/P") {
+        return Err(MessageError::InvalidCallsign(callsign.to_string()));
+    }
+    let base = strip_rover_portable(&call).to_string();
+    let rover = base.len() != call.len();
+    Ok((base, rover))
+}
+
+fn standard_tones(
+    call1: (String, bool),
+    call2: (String, bool),
+    r_flag: bool,
+    payload: GridReport,
+) -> Result<[u8; NUM_SYMBOLS], MessageError> {
+    let variant = MessageVariant::Standard {
+        call1: call1.0,
+        call1_suffix: call1.1,
+        call2: call2.0,
+        call2_suffix: call2.1,
+        r_flag,
+        payload,
+    };
+    let mut bits = bitarr![u8, Msb0; 0; 80];
+    let mut cache = CallsignHashCache::new();
+    encode_variant(&variant, &mut bits[..77], &mut cache)?;
+    Ok(channel_symbols_from_bits(&bits[..77]))
+}
+
+fn grid_payload(grid: &str) -> Result<GridReport, MessageError> {
+    let grid = grid.trim().to_uppercase();
+    // RR73 is a valid square but always means the acknowledgement on air
+    if !validate_grid_basic(&grid) || grid == "RR73" {
+        return Err(MessageError::InvalidGrid(grid));
+    }
+    Ok(GridReport::Grid(grid))
+}
+
+/// `HISCALL MYCALL -10`, or `HISCALL MYCALL R-10` when `ack` is set.
+pub fn tx_report(
+    mycall: &str,
+    hiscall: &str,
+    report_db: i8,
+    ack: bool,
+) -> Result<[u8; NUM_SYMBOLS], MessageError> {
+    if !(-50..=49).contains(&report_db) {
+        return Err(MessageError::ReportOutOfRange(report_db));
+    }
+    standard_tones(
+        standard_call(hiscall)?,
+        standard_call(mycall)?,
+        ack,
+        GridReport::Report(report_db),
+    )
+}
+
+/// `HISCALL MYCALL GRID`
+pub fn tx_grid(mycall: &str, hiscall: &str, grid: &str) -> Result<[u8; NUM_SYMBOLS], MessageError> {
+    let payload = grid_payload(grid)?;
+    standard_tones(
+        standard_call(hiscall)?,
+        standard_call(mycall)?,
+        false,
+        payload,
+    )
+}
+
+/// `HISCALL MYCALL RR73`
+pub fn tx_rr73(mycall: &str, hiscall: &str) -> Result<[u8; NUM_SYMBOLS], MessageError> {
+    standard_tones(
+        standard_call(hiscall)?,
+        standard_call(mycall)?,
+        false,
+        GridReport::Rr73,
+    )
+}
+
+/// `CQ MYCALL GRID`
+pub fn tx_cq(mycall: &str, grid: &str) -> Result<[u8; NUM_SYMBOLS], MessageError> {
+    let payload = grid_payload(grid)?;
+    standard_tones(
+        ("CQ".to_string(), false),
+        standard_call(mycall)?,
+        false,
+        payload,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::bits::put;
+    use crate::message::callsign::{MAX22, NTOKENS};
+    use crate::message::encode;
+
+    /// c28 of " K1ABC" and " W9XYZ" / " N0YPR", computed by hand from the
+    /// WSJT-X alphabets (space-padded so the call area sits in position 3)
+    const K1ABC: u32 = NTOKENS + MAX22 + ((((20 * 10 + 1) * 27 + 1) * 27 + 2) * 27 + 3);
+    const W9XYZ: u32 = NTOKENS + MAX22 + ((((32 * 10 + 9) * 27 + 24) * 27 + 25) * 27 + 26);
+    const N0YPR: u32 = NTOKENS + MAX22 + ((((23 * 10) * 27 + 25) * 27 + 16) * 27 + 18);
+
+    fn expected_tones(c1: u32, c2: u32, r_flag: bool, g15: u32) -> [u8; NUM_SYMBOLS] {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        put(&mut bits, 0, 28, c1 as u128);
+        put(&mut bits, 29, 28, c2 as u128);
+        put(&mut bits, 58, 1, r_flag as u128);
+        put(&mut bits, 59, 15, g15 as u128);
+        put(&mut bits, 74, 3, 1);
+        channel_symbols_from_bits(&bits[..77])
+    }
+
+    fn text_tones(text: &str) -> [u8; NUM_SYMBOLS] {
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        let mut cache = CallsignHashCache::new();
+        encode(text, &mut bits[..77], &mut cache).unwrap();
+        channel_symbols_from_bits(&bits[..77])
+    }
+
+    #[test]
+    fn test_tx_report() {
+        // g15 = MAXGRID4 + report + 35
+        let tones = tx_report("W9XYZ", "K1ABC", -10, false).unwrap();
+        assert_eq!(tones, expected_tones(K1ABC, W9XYZ, false, 32400 + 25));
+        assert_eq!(tones, text_tones("K1ABC W9XYZ -10"));
+
+        let ack = tx_report("W9XYZ", "K1ABC", 5, true).unwrap();
+        assert_eq!(ack, expected_tones(K1ABC, W9XYZ, true, 32400 + 40));
+        assert_eq!(ack, text_tones("K1ABC W9XYZ R+05"));
+    }
+
+    #[test]
+    fn test_tx_grid() {
+        // EN37 = (4*18 + 13)*100 + 37
+        let tones = tx_grid("W9XYZ", "K1ABC", "EN37").unwrap();
+        assert_eq!(tones, expected_tones(K1ABC, W9XYZ, false, 8537));
+        assert_eq!(tones, text_tones("K1ABC W9XYZ EN37"));
+    }
+
+    #[test]
+    fn test_tx_rr73() {
+        let tones = tx_rr73("K1ABC", "W9XYZ").unwrap();
+        assert_eq!(tones, expected_tones(W9XYZ, K1ABC, false, 32400 + 3));
+        assert_eq!(tones, text_tones("W9XYZ K1ABC RR73"));
+    }
+
+    #[test]
+    fn test_tx_cq() {
+        // DM42 = (3*18 + 12)*100 + 42
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        assert_eq!(tones, expected_tones(2, N0YPR, false, 6642));
+        assert_eq!(tones, text_tones("CQ N0YPR DM42"));
+    }
+
+    #[test]
+    fn test_rejects_non_standard_inputs() {
+        assert_eq!(
+            tx_report("N0YPR", "PJ4/K1ABC", -10, false),
+            Err(MessageError::InvalidCallsign("PJ4/K1ABC".into()))
+        );
+        assert_eq!(
+            tx_report("N0YPR", "K1ABC", 60, false),
+            Err(MessageError::ReportOutOfRange(60))
+        );
+        assert_eq!(
+            tx_cq("N0YPR", "RR73"),
+            Err(MessageError::InvalidGrid("RR73".into()))
+        );
+        assert!(tx_cq("G4ABC/P", "IO91").is_err());
+    }
+
+    #[test]
+    fn test_rover_flag() {
+        let tones = tx_grid("W9XYZ", "K1ABC/R", "FN42").unwrap();
+        assert_eq!(tones, text_tones("K1ABC/R W9XYZ FN42"));
+    }
+}
@@ -0,0 +1,355 @@
+//! Structured representation of the FT8 message types
+
+use alloc::string::String;
+use core::fmt;
+
+/// Final field of a standard message (`g15`): grid, report or acknowledgement
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GridReport {
+    /// No final field (`K1ABC W9XYZ`)
+    Blank,
+    /// 4-character grid square
+    Grid(String),
+    /// Signal report in dB (-50..=49)
+    Report(i8),
+    /// `RRR`
+    Rrr,
+    /// `RR73`
+    Rr73,
+    /// `73`
+    SeventyThree,
+}
+
+/// Exchange sent in an RTTY Roundup message
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RttyExchange {
+    /// Serial number (0..=7999)
+    Serial(u16),
+    /// US state or Canadian province abbreviation
+    State(String),
+}
+
+/// A parsed FT8 message, one variant per 77-bit message type.
+///
+/// Callsigns are stored uppercased. Hashed callsigns keep their angle
+/// brackets (`<PJ4/K1ABC>`, or `<...>` when decoded without a cache hit);
+/// `/R` and `/P` suffixes are carried in the separate suffix flags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageVariant {
+    /// i3=0 n3=0: up to 13 characters of free text, always sent
+    /// uppercased since the alphabet has no lowercase letters
+    FreeText { text: String },
+    /// i3=0 n3=1: `K1ABC RR73; W9XYZ <KH1/KH7Z> -08`
+    Dxpedition {
+        call1: String,
+        call2: String,
+        call3: String,
+        report: i8,
+    },
+    /// i3=0 n3=3/4: ARRL Field Day, `WA9XYZ KA1ABC R 16A EMA`
+    FieldDay {
+        call1: String,
+        call2: String,
+        r_flag: bool,
+        transmitters: u8,
+        class: char,
+        section: String,
+    },
+    /// i3=0 n3=5: 71 bits of telemetry as up to 18 hex digits
+    Telemetry { hex: String },
+    /// i3=1: standard message, suffix flags mean `/R`
+    Standard {
+        call1: String,
+        call1_suffix: bool,
+        call2: String,
+        call2_suffix: bool,
+        r_flag: bool,
+        payload: GridReport,
+    },
+    /// i3=2: EU VHF contest variant of the standard message, suffix flags mean `/P`
+    EuVhfContestType2 {
+        call1: String,
+        call1_suffix: bool,
+        call2: String,
+        call2_suffix: bool,
+        r_flag: bool,
+        payload: GridReport,
+    },
+    /// i3=3: ARRL RTTY Roundup, `TU; W9XYZ K1ABC R 579 MA`
+    RttyRoundup {
+        tu: bool,
+        call1: String,
+        call2: String,
+        r_flag: bool,
+        rst: u16,
+        exchange: RttyExchange,
+    },
+    /// i3=4: one non-standard callsign in full plus one hashed callsign
+    NonStandardCall {
+        hashed_call: String,
+        full_call: String,
+        hashed_first: bool,
+        payload: GridReport,
+        cq: bool,
+    },
+    /// i3=5: EU VHF contest with serial number and 6-character locator
+    EuVhfContestType5 {
+        call1: String,
+        call2: String,
+        r_flag: bool,
+        rst: u8,
+        serial: u16,
+        grid6: String,
+    },
+}
+
+impl MessageVariant {
+    /// The 3-bit message type `i3`.
+    pub fn i3(&self) -> u8 {
+        match self {
+            MessageVariant::FreeText { .. }
+            | MessageVariant::Dxpedition { .. }
+            | MessageVariant::FieldDay { .. }
+            | MessageVariant::Telemetry { .. } => 0,
+            MessageVariant::Standard { .. } => 1,
+            MessageVariant::EuVhfContestType2 { .. } => 2,
+            MessageVariant::RttyRoundup { .. } => 3,
+            MessageVariant::NonStandardCall { .. } => 4,
+            MessageVariant::EuVhfContestType5 { .. } => 5,
+        }
+    }
+
+    /// The 3-bit subtype `n3`, meaningful only when `i3 == 0`.
+    pub fn n3(&self) -> Option<u8> {
+        match self {
+            MessageVariant::FreeText { .. } => Some(0),
+            MessageVariant::Dxpedition { .. } => Some(1),
+            MessageVariant::FieldDay { transmitters, .. } => {
+                Some(if *transmitters > 16 { 4 } else { 3 })
+            }
+            MessageVariant::Telemetry { .. } => Some(5),
+            _ => None,
+        }
+    }
+
+    /// The same message with every text field in uppercase, as it is
+    /// sent. [`encode_variant`](super::encode_variant) encodes this, so
+    /// case never changes the bits.
+    pub fn to_uppercase(&self) -> MessageVariant {
+        let mut upper = self.clone();
+        match &mut upper {
+            MessageVariant::FreeText { text } => text.make_ascii_uppercase(),
+            MessageVariant::Dxpedition {
+                call1,
+                call2,
+                call3,
+                ..
+            } => {
+                for call in [call1, call2, call3] {
+                    call.make_ascii_uppercase();
+                }
+            }
+            MessageVariant::FieldDay {
+                call1,
+                call2,
+                class,
+                section,
+                ..
+            } => {
+                for field in [call1, call2, section] {
+                    field.make_ascii_uppercase();
+                }
+                class.make_ascii_uppercase();
+            }
+            MessageVariant::Telemetry { hex } => hex.make_ascii_uppercase(),
+            MessageVariant::Standard {
+                call1,
+                call2,
+                payload,
+                ..
+            }
+            | MessageVariant::EuVhfContestType2 {
+                call1,
+                call2,
+                payload,
+                ..
+            } => {
+                call1.make_ascii_uppercase();
+                call2.make_ascii_uppercase();
+                if let GridReport::Grid(grid) = payload {
+                    grid.make_ascii_uppercase();
+                }
+            }
+            MessageVariant::RttyRoundup {
+                call1,
+                call2,
+                exchange,
+                ..
+            } => {
+                call1.make_ascii_uppercase();
+                call2.make_ascii_uppercase();
+                if let RttyExchange::State(state) = exchange {
+                    state.make_ascii_uppercase();
+                }
+            }
+            MessageVariant::NonStandardCall {
+                hashed_call,
+                full_call,
+                payload,
+                ..
+            } => {
+                hashed_call.make_ascii_uppercase();
+                full_call.make_ascii_uppercase();
+                if let GridReport::Grid(grid) = payload {
+                    grid.make_ascii_uppercase();
+                }
+            }
+            MessageVariant::EuVhfContestType5 {
+                call1,
+                call2,
+                grid6,
+                ..
+            } => {
+                for field in [call1, call2, grid6] {
+                    field.make_ascii_uppercase();
+                }
+            }
+        }
+        upper
+    }
+}
+
+fn write_payload(f: &mut fmt::Formatter<'_>, r_flag: bool, payload: &GridReport) -> fmt::Result {
+    match payload {
+        GridReport::Blank => Ok(()),
+        GridReport::Grid(grid) if r_flag => write!(f, " R {}", grid),
+        GridReport::Grid(grid) => write!(f, " {}", grid),
+        GridReport::Report(db) if r_flag => write!(f, " R{:+03}", db),
+        GridReport::Report(db) => write!(f, " {:+03}", db),
+        GridReport::Rrr => write!(f, " RRR"),
+        GridReport::Rr73 => write!(f, " RR73"),
+        GridReport::SeventyThree => write!(f, " 73"),
+    }
+}
+
+fn write_call(
+    f: &mut fmt::Formatter<'_>,
+    call: &str,
+    suffix: bool,
+    suffix_text: &str,
+) -> fmt::Result {
+    write!(f, "{}", call)?;
+    if suffix {
+        write!(f, "{}", suffix_text)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for MessageVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageVariant::FreeText { text } => write!(f, "{}", text),
+            MessageVariant::Dxpedition {
+                call1,
+                call2,
+                call3,
+                report,
+            } => write!(f, "{} RR73; {} {} {:+03}", call1, call2, call3, report),
+            MessageVariant::FieldDay {
+                call1,
+                call2,
+                r_flag,
+                transmitters,
+                class,
+                section,
+            } => {
+                write!(f, "{} {}", call1, call2)?;
+                if *r_flag {
+                    write!(f, " R")?;
+                }
+                write!(f, " {}{} {}", transmitters, class, section)
+            }
+            MessageVariant::Telemetry { hex } => write!(f, "{}", hex),
+            MessageVariant::Standard {
+                call1,
+                call1_suffix,
+                call2,
+                call2_suffix,
+                r_flag,
+                payload,
+            } => {
+                write_call(f, call1, *call1_suffix, "/R")?;
+                write!(f, " ")?;
+                write_call(f, call2, *call2_suffix, "/R")?;
+                write_payload(f, *r_flag, payload)
+            }
+            MessageVariant::EuVhfContestType2 {
+                call1,
+                call1_suffix,
+                call2,
+                call2_suffix,
+                r_flag,
+                payload,
+            } => {
+                write_call(f, call1, *call1_suffix, "/P")?;
+                write!(f, " ")?;
+                write_call(f, call2, *call2_suffix, "/P")?;
+                write_payload(f, *r_flag, payload)
+            }
+            MessageVariant::RttyRoundup {
+                tu,
+                call1,
+                call2,
+                r_flag,
+                rst,
+                exchange,
+            } => {
+                if *tu {
+                    write!(f, "TU; ")?;
+                }
+                write!(f, "{} {}", call1, call2)?;
+                if *r_flag {
+                    write!(f, " R")?;
+                }
+                match exchange {
+                    RttyExchange::Serial(n) => write!(f, " {} {:04}", rst, n),
+                    RttyExchange::State(s) => write!(f, " {} {}", rst, s),
+                }
+            }
+            MessageVariant::NonStandardCall {
+                hashed_call,
+                full_call,
+                hashed_first,
+                payload,
+                cq,
+            } => {
+                if *cq {
+                    return write!(f, "CQ {}", full_call);
+                }
+                if *hashed_first {
+                    write!(f, "{} {}", hashed_call, full_call)?;
+                } else {
+                    write!(f, "{} {}", full_call, hashed_call)?;
+                }
+                write_payload(f, false, payload)
+            }
+            MessageVariant::EuVhfContestType5 {
+                call1,
+                call2,
+                r_flag,
+                rst,
+                serial,
+                grid6,
+            } => {
+                write!(f, "{} {}", call1, call2)?;
+                if *r_flag {
+                    write!(f, " R")?;
+                }
+                write!(f, " {}{:04} {}", rst, serial, grid6)
+            }
+        }
+    }
+}
@@ -0,0 +1,642 @@
+//! FT8 waveform synthesis
+//!
+//! Channel tones are transmitted as continuous-phase 8-GFSK: each symbol's
+//! frequency step is smoothed by a Gaussian-filtered rectangular pulse
+//! (BT = 2) spanning three symbols, exactly as in WSJT-X `gen_ft8wave`. The
+//! first and last 1/8 symbol are ramped with a raised cosine to avoid key
+//! clicks.
+//!
+//! [`Modulator::ft4`] produces FT4's 4-GFSK instead, as in WSJT-X
+//! `gen_ft4wave`: BT = 1, and the extra pulse-tail symbols at each end are
+//! transmitted under a full-symbol raised-cosine ramp.
+//!
+//! [`transmit`] goes straight from message text to a 15 s FT8 slot, ready
+//! to be written to a WAV file.
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::channel::ChannelModel;
+use crate::fft::real_power_spectrum;
+use crate::message::{CallsignHashCache, MessageError};
+use crate::symbol::channel_symbols;
+
+/// The channel models, also reachable from here to pair with [`transmit`]
+/// when generating test signals, e.g. `modulation::channel::add_awgn`
+pub use crate::channel;
+
+/// Default audio sample rate in Hz
+pub const SAMPLE_RATE: f32 = 12_000.0;
+
+/// Symbol duration in seconds
+pub const SYMBOL_DURATION: f32 = 0.16;
+
+/// Spacing between adjacent tones in Hz (1 / symbol duration)
+pub const TONE_SPACING: f32 = 6.25;
+
+/// Bandwidth-time product of the Gaussian frequency filter
+pub const GFSK_BT: f32 = 2.0;
+
+/// Nominal occupied bandwidth of an FT8 signal in Hz (8 × 6.25)
+pub const FT8_BANDWIDTH: f32 = 50.0;
+
+/// Length of an FT8 receive period in seconds
+pub const SLOT_DURATION: f32 = 15.0;
+
+/// Nominal transmission start within the slot in seconds (DT = 0)
+pub const NOMINAL_START: f32 = 0.5;
+
+/// FT4 symbol duration in seconds (576 samples at 12 kHz)
+pub const FT4_SYMBOL_DURATION: f32 = 0.048;
+
+/// FT4 tone spacing in Hz (1 / symbol duration, 20.833 baud)
+pub const FT4_TONE_SPACING: f32 = 1.0 / FT4_SYMBOL_DURATION;
+
+/// Bandwidth-time product of the FT4 Gaussian frequency filter
+pub const FT4_GFSK_BT: f32 = 1.0;
+
+/// Length of an FT4 receive period in seconds
+pub const FT4_SLOT_DURATION: f32 = 7.5;
+
+/// Error function, Abramowitz & Stegun 7.1.26 (|error| < 1.5e-7).
+fn erf(x: f32) -> f32 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t
+        * (0.254_829_6
+            + t * (-0.284_496_74 + t * (1.421_413_8 + t * (-1.453_152_1 + t * 1.061_405_4))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+/// Gaussian-filtered rectangular frequency pulse at time `t` (in symbols,
+/// centred on 0) for bandwidth-time product `bt`.
+pub fn gaussian_boxcar(t: f32, bt: f32) -> f32 {
+    let c = PI * (2.0 / core::f32::consts::LN_2).sqrt();
+    0.5 * (erf(c * bt * (t + 0.5)) - erf(c * bt * (t - 0.5)))
+}
+
+/// GFSK modulator turning channel tones into audio samples.
+#[derive(Debug, Clone)]
+pub struct Modulator {
+    sample_rate: f32,
+    /// Standard symbol length in seconds, before any timing offset
+    symbol_duration: f32,
+    samples_per_symbol: usize,
+    pulse: Vec<f32>,
+    /// Transmit the pulse-tail symbols under full-symbol ramps (FT4)
+    tail_symbols: bool,
+    /// Linear frequency drift in Hz/s, zero at mid-transmission
+    drift: f32,
+}
+
+impl Default for Modulator {
+    fn default() -> Self {
+        Self::new(SAMPLE_RATE)
+    }
+}
+
+impl Modulator {
+    /// Create a modulator for the given output sample rate.
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_shape(sample_rate, SYMBOL_DURATION, GFSK_BT, false)
+    }
+
+    /// Create an FT4 modulator for the given output sample rate.
+    ///
+    /// [`modulate`](Self::modulate) then returns two symbols more than it
+    /// is given tones: the ramp-up and ramp-down symbols at either end.
+    pub fn ft4(sample_rate: f32) -> Self {
+        Self::with_shape(sample_rate, FT4_SYMBOL_DURATION, FT4_GFSK_BT, true)
+    }
+
+    fn with_shape(sample_rate: f32, symbol_duration: f32, bt: f32, tail_symbols: bool) -> Self {
+        let samples_per_symbol = (sample_rate * symbol_duration).round() as usize;
+        Self {
+            sample_rate,
+            symbol_duration,
+            samples_per_symbol,
+            pulse: Self::pulse(samples_per_symbol, bt),
+            tail_symbols,
+            drift: 0.0,
+        }
+    }
+
+    /// The GFSK frequency pulse sampled over its three symbols.
+    fn pulse(samples_per_symbol: usize, bt: f32) -> Vec<f32> {
+        let nsps = samples_per_symbol as f32;
+        (0..3 * samples_per_symbol)
+            .map(|i| gaussian_boxcar((i as f32 - 1.5 * nsps) / nsps, bt))
+            .collect()
+    }
+
+    /// Deliberately non-standard transmissions for testing receivers.
+    ///
+    /// `bt` replaces the standard bandwidth-time product of the Gaussian
+    /// pulse. Each symbol lasts `timing_offset_s` longer than standard
+    /// (shorter if negative), rounded to whole samples, as from a
+    /// transmitter with a drifting symbol clock. The frequency sweeps
+    /// linearly by `freq_drift_hz_per_s`, passing through `f0` halfway
+    /// through the transmission so the average frequency is unchanged.
+    /// Calling this again replaces all three settings.
+    pub fn with_params(self, bt: f32, timing_offset_s: f32, freq_drift_hz_per_s: f32) -> Self {
+        let mut modulator = Self::with_shape(
+            self.sample_rate,
+            self.symbol_duration + timing_offset_s,
+            bt,
+            self.tail_symbols,
+        );
+        modulator.symbol_duration = self.symbol_duration;
+        modulator.drift = freq_drift_hz_per_s;
+        modulator
+    }
+
+    /// Output sample rate in Hz
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    /// Number of output samples per channel symbol
+    pub fn samples_per_symbol(&self) -> usize {
+        self.samples_per_symbol
+    }
+
+    /// Modulate `tones` with tone 0 at `f0` Hz.
+    ///
+    /// Returns `tones.len() × samples_per_symbol` samples of unit amplitude,
+    /// or `(tones.len() + 2) × samples_per_symbol` for FT4.
+    pub fn modulate(&self, tones: &[u8], f0: f32) -> Vec<f32> {
+        let dphi = self.phase_increments(tones, f0, true);
+        self.synthesize(&dphi, tones.len())
+    }
+
+    /// Phase and amplitude envelope of each sample [`modulate`](Self::modulate)
+    /// returns, which is `envelope × sin(phase)`. Subtraction builds its
+    /// replicas from these, so they match transmitted signals exactly.
+    pub(crate) fn phase_envelope(&self, tones: &[u8], f0: f32) -> Vec<(f32, f32)> {
+        let dphi = self.phase_increments(tones, f0, true);
+        self.phase_envelope_of(&dphi, tones.len())
+    }
+
+    /// Per-sample phase increments, including one extra symbol of pulse
+    /// tail on each side of the transmission.
+    fn phase_increments(&self, tones: &[u8], f0: f32, shaped: bool) -> Vec<f32> {
+        let nsps = self.samples_per_symbol;
+        let nsym = tones.len();
+        let hmod = 1.0;
+        let dphi_peak = 2.0 * PI * hmod / nsps as f32;
+        let mut dphi = vec![0.0f32; (nsym + 2) * nsps];
+
+        if shaped {
+            for (j, &tone) in tones.iter().enumerate() {
+                let ib = j * nsps;
+                for (d, &p) in dphi[ib..ib + 3 * nsps].iter_mut().zip(&self.pulse) {
+                    *d += dphi_peak * p * tone as f32;
+                }
+            }
+            // Extend the first and last tones into the dummy symbols
+            if let (Some(&first), Some(&last)) = (tones.first(), tones.last()) {
+                for i in 0..2 * nsps {
+                    dphi[i] += dphi_peak * first as f32 * self.pulse[nsps + i];
+                    dphi[nsym * nsps + i] += dphi_peak * last as f32 * self.pulse[i];
+                }
+            }
+        } else {
+            for (j, &tone) in tones.iter().enumerate() {
+                let ib = (j + 1) * nsps;
+                dphi[ib..ib + nsps].fill(dphi_peak * tone as f32);
+            }
+        }
+
+        let carrier = 2.0 * PI * f0 / self.sample_rate;
+        // Sample index of mid-transmission; the buffer starts one pulse
+        // tail symbol early
+        let middle = (nsym + 2) as f32 * nsps as f32 / 2.0;
+        let sweep = 2.0 * PI * self.drift / (self.sample_rate * self.sample_rate);
+        for (n, d) in dphi.iter_mut().enumerate() {
+            *d += carrier + sweep * (n as f32 - middle);
+        }
+        dphi
+    }
+
+    fn synthesize(&self, dphi: &[f32], nsym: usize) -> Vec<f32> {
+        self.phase_envelope_of(dphi, nsym)
+            .into_iter()
+            .map(|(phi, env)| phi.sin() * env)
+            .collect()
+    }
+
+    fn phase_envelope_of(&self, dphi: &[f32], nsym: usize) -> Vec<(f32, f32)> {
+        let nsps = self.samples_per_symbol;
+        let (dphi, nramp) = if self.tail_symbols {
+            (dphi, nsps)
+        } else {
+            (&dphi[nsps..nsps + nsym * nsps], nsps / 8)
+        };
+        let nwave = dphi.len();
+        let mut wave = Vec::with_capacity(nwave);
+        let mut phi = 0.0f32;
+        for &d in dphi {
+            wave.push((phi, 1.0));
+            phi = (phi + d) % (2.0 * PI);
+        }
+
+        // Raised-cosine ramp over the first and last 1/8 symbol, or the
+        // whole tail symbols for FT4
+        if nwave >= 2 * nramp {
+            for i in 0..nramp {
+                let env = (1.0 - (PI * i as f32 / nramp as f32).cos()) / 2.0;
+                wave[i].1 = env;
+                wave[nwave - 1 - i].1 = env;
+            }
+        }
+        wave
+    }
+}
+
+struct BandSignal {
+    tones: Vec<u8>,
+    frequency: f32,
+    dt: f32,
+    amplitude: f32,
+    channel: Option<Box<dyn ChannelModel>>,
+}
+
+/// Builds a 15 s receive buffer containing any number of FT8 signals.
+///
+/// Each signal may pass through its own [`ChannelModel`] (fading,
+/// multipath) before being mixed into the band; band-wide models such as
+/// [`Awgn`](crate::channel::Awgn) are then applied to the mixed buffer in
+/// the order they were added.
+pub struct BandSynthesizer {
+    modulator: Modulator,
+    signals: Vec<BandSignal>,
+    channels: Vec<Box<dyn ChannelModel>>,
+}
+
+impl Default for BandSynthesizer {
+    fn default() -> Self {
+        Self::new(SAMPLE_RATE)
+    }
+}
+
+impl BandSynthesizer {
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_modulator(Modulator::new(sample_rate))
+    }
+
+    /// A band whose signals are all generated by `modulator`, such as one
+    /// set up with [`Modulator::with_params`].
+    pub fn with_modulator(modulator: Modulator) -> Self {
+        Self {
+            modulator,
+            signals: Vec::new(),
+            channels: Vec::new(),
+        }
+    }
+
+    /// Add a signal with tone 0 at `frequency` Hz, starting `dt` seconds
+    /// after the nominal 0.5 s start, with peak amplitude `amplitude`.
+    pub fn add_signal(
+        &mut self,
+        tones: &[u8],
+        frequency: f32,
+        dt: f32,
+        amplitude: f32,
+    ) -> &mut Self {
+        self.signals.push(BandSignal {
+            tones: tones.to_vec(),
+            frequency,
+            dt,
+            amplitude,
+            channel: None,
+        });
+        self
+    }
+
+    /// Add a signal that propagates through its own `channel`.
+    pub fn add_signal_with_channel(
+        &mut self,
+        tones: &[u8],
+        frequency: f32,
+        dt: f32,
+        amplitude: f32,
+        channel: Box<dyn ChannelModel>,
+    ) -> &mut Self {
+        self.add_signal(tones, frequency, dt, amplitude);
+        if let Some(signal) = self.signals.last_mut() {
+            signal.channel = Some(channel);
+        }
+        self
+    }
+
+    /// Add a channel model applied to the whole band after mixing.
+    pub fn add_channel(&mut self, channel: Box<dyn ChannelModel>) -> &mut Self {
+        self.channels.push(channel);
+        self
+    }
+
+    /// Render the 15 s buffer.
+    pub fn synthesize(&self) -> Vec<f32> {
+        let sample_rate = self.modulator.sample_rate();
+        let len = (SLOT_DURATION * sample_rate) as usize;
+        let mut band = vec![0.0f32; len];
+
+        for signal in &self.signals {
+            let mut wave = self.modulator.modulate(&signal.tones, signal.frequency);
+            if let Some(channel) = &signal.channel {
+                channel.apply(&mut wave, sample_rate);
+            }
+            let start = ((NOMINAL_START + signal.dt) * sample_rate).round() as isize;
+            for (i, &w) in wave.iter().enumerate() {
+                let idx = start + i as isize;
+                if idx >= 0 && (idx as usize) < len {
+                    band[idx as usize] += signal.amplitude * w;
+                }
+            }
+        }
+
+        for channel in &self.channels {
+            channel.apply(&mut band, sample_rate);
+        }
+        band
+    }
+}
+
+/// Encode message text as a complete 15 s FT8 slot at [`SAMPLE_RATE`].
+///
+/// The signal is GFSK-modulated with tone 0 at `f0` Hz, starts at the
+/// nominal 0.5 s and peaks at 1.0; the rest of the slot is silent.
+/// Callsigns in the message are recorded in `cache`, as by
+/// [`message::encode`](crate::message::encode).
+pub fn transmit(
+    text: &str,
+    f0: f32,
+    cache: &mut CallsignHashCache,
+) -> Result<Vec<f32>, MessageError> {
+    let tones = channel_symbols(text, cache)?;
+    Ok(BandSynthesizer::default()
+        .add_signal(&tones, f0, 0.0, 1.0)
+        .synthesize())
+}
+
+/// Measure the occupied bandwidth of `signal`.
+///
+/// The power spectrum is estimated with a Hann-windowed, 50%-overlapped
+/// Welch average; the band edges are where the cumulative power crosses
+/// `(100 − percent) / 2` percent from either side. Returns
+/// `(center_hz, bandwidth_hz)`; a silent signal measures `(0.0, 0.0)`.
+pub fn measure_occupied_bandwidth(signal: &[f32], sample_rate: f32, percent: f32) -> (f32, f32) {
+    const NFFT: usize = 4096;
+    let seg = NFFT.min(signal.len().next_power_of_two());
+    let window: Vec<f32> = (0..seg)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / seg as f32).cos())
+        .collect();
+
+    let mut psd = vec![0.0f32; seg / 2 + 1];
+    let mut start = 0;
+    loop {
+        let end = (start + seg).min(signal.len());
+        let frame: Vec<f32> = signal[start..end]
+            .iter()
+            .zip(&window)
+            .map(|(s, w)| s * w)
+            .collect();
+        for (p, v) in psd.iter_mut().zip(real_power_spectrum(&frame, seg)) {
+            *p += v;
+        }
+        if end == signal.len() {
+            break;
+        }
+        start += seg / 2;
+    }
+
+    let total: f32 = psd.iter().sum();
+    if total <= 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let tail = total * (1.0 - percent.clamp(0.0, 100.0) / 100.0) / 2.0;
+    let mut lo = 0;
+    let mut acc = 0.0;
+    for (k, &p) in psd.iter().enumerate() {
+        acc += p;
+        if acc > tail {
+            lo = k;
+            break;
+        }
+    }
+    let mut hi = psd.len() - 1;
+    acc = 0.0;
+    for (k, &p) in psd.iter().enumerate().rev() {
+        acc += p;
+        if acc > tail {
+            hi = k;
+            break;
+        }
+    }
+
+    let df = sample_rate / seg as f32;
+    let center = (lo + hi) as f32 / 2.0 * df;
+    let bandwidth = (hi + 1 - lo) as f32 * df;
+    (center, bandwidth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{Awgn, Multipath};
+    use crate::message::{tx_cq, tx_report};
+
+    #[test]
+    fn test_pulse_area() {
+        // The pulse integrates to one symbol of frequency deviation
+        let n = 3000;
+        let area: f32 = (0..n)
+            .map(|i| gaussian_boxcar(-1.5 + 3.0 * i as f32 / n as f32, GFSK_BT) * 3.0 / n as f32)
+            .sum();
+        assert!((area - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_waveform_length_and_amplitude() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let wave = Modulator::default().modulate(&tones, 1500.0);
+        assert_eq!(wave.len(), 79 * 1920);
+        assert!(wave.iter().all(|s| s.abs() <= 1.0));
+        assert!(wave[0].abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ft4_waveform() {
+        let modulator = Modulator::ft4(SAMPLE_RATE);
+        assert_eq!(modulator.samples_per_symbol(), 576);
+        let tones: Vec<u8> = (0..103).map(|k| (k % 4) as u8).collect();
+        let wave = modulator.modulate(&tones, 1000.0);
+        assert_eq!(wave.len(), 105 * 576);
+        assert!(wave[0].abs() < 1e-6);
+        // Full amplitude from the first tone symbol to the last
+        assert!(wave[576..104 * 576].iter().any(|s| s.abs() > 0.99));
+        assert!(wave[..288].iter().all(|s| s.abs() < 0.5));
+
+        let (center, bw) = measure_occupied_bandwidth(&wave, SAMPLE_RATE, 99.0);
+        assert!(
+            (center - (1000.0 + 1.5 * FT4_TONE_SPACING)).abs() < 10.0,
+            "center {}",
+            center
+        );
+        assert!(bw < 4.0 * FT4_TONE_SPACING + 20.0, "bandwidth {}", bw);
+    }
+
+    #[test]
+    fn test_transmit_loopback() {
+        use crate::decoder::{decode_ft8, DecoderConfig};
+
+        let mut cache = CallsignHashCache::new();
+        for (text, f0) in [("CQ N0YPR DM42", 1500.0), ("CQ PJ4/K1ABC", 731.5)] {
+            let wave = transmit(text, f0, &mut cache).unwrap();
+            assert_eq!(wave.len(), (SLOT_DURATION * SAMPLE_RATE) as usize);
+            assert!(wave[..(NOMINAL_START * SAMPLE_RATE) as usize - 1]
+                .iter()
+                .all(|&s| s == 0.0));
+            assert!(wave.iter().all(|s| s.abs() <= 1.0));
+
+            let decodes = decode_ft8(&wave, &DecoderConfig::default(), None).unwrap();
+            assert_eq!(decodes.len(), 1);
+            assert_eq!(decodes[0].message, text);
+            assert!((decodes[0].frequency - f0).abs() < 1.0);
+            assert!(decodes[0].time_offset.abs() < 0.02);
+        }
+        assert!(cache.contains("PJ4/K1ABC"));
+        assert!(transmit("THIS MESSAGE IS FAR TOO LONG", 1500.0, &mut cache).is_err());
+    }
+
+    #[test]
+    fn test_single_tone_center() {
+        let wave = Modulator::default().modulate(&[0; 20], 1000.0);
+        let (center, bw) = measure_occupied_bandwidth(&wave, SAMPLE_RATE, 99.0);
+        assert!((center - 1000.0).abs() < 6.0, "center {}", center);
+        assert!(bw < 15.0, "bandwidth {}", bw);
+    }
+
+    #[test]
+    fn test_gfsk_within_ft8_bandwidth() {
+        let f0 = 1500.0;
+        let modulator = Modulator::default();
+        for tones in [
+            tx_cq("N0YPR", "DM42").unwrap(),
+            tx_report("W9XYZ", "K1ABC", -10, false).unwrap(),
+        ] {
+            let shaped = modulator.modulate(&tones, f0);
+            let (center, bw) = measure_occupied_bandwidth(&shaped, SAMPLE_RATE, 99.0);
+            assert!(bw <= FT8_BANDWIDTH + 6.0, "GFSK bandwidth {}", bw);
+            // Tones span f0 .. f0 + 7 × 6.25
+            assert!(
+                (center - (f0 + 3.5 * TONE_SPACING)).abs() < 10.0,
+                "center {}",
+                center
+            );
+
+            let dphi = modulator.phase_increments(&tones, f0, false);
+            let hard = modulator.synthesize(&dphi, tones.len());
+            let (_, hard_bw) = measure_occupied_bandwidth(&hard, SAMPLE_RATE, 99.0);
+            assert!(hard_bw > bw, "hard-keyed {} vs GFSK {}", hard_bw, bw);
+        }
+    }
+
+    #[test]
+    fn test_band_synthesizer_placement() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let band = BandSynthesizer::default()
+            .add_signal(&tones, 1000.0, 0.2, 0.5)
+            .synthesize();
+        assert_eq!(band.len(), 180_000);
+        // Signal starts at (0.5 + 0.2) s and lasts 12.64 s
+        assert!(band[..8400].iter().all(|s| *s == 0.0));
+        assert!(band[8400 + 1000..8400 + 2000].iter().any(|s| s.abs() > 0.4));
+        assert!(band[8400 + 79 * 1920..].iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn test_band_synthesizer_channels() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let clean = BandSynthesizer::default()
+            .add_signal(&tones, 1000.0, 0.0, 1.0)
+            .synthesize();
+        let echoed = BandSynthesizer::default()
+            .add_signal_with_channel(
+                &tones,
+                1000.0,
+                0.0,
+                1.0,
+                Box::new(Multipath::new(vec![(0.0, 1.0), (0.002, 0.5)])),
+            )
+            .synthesize();
+        assert_ne!(clean, echoed);
+        // Before the echo arrives the direct path is unchanged
+        assert_eq!(clean[6000..6024], echoed[6000..6024]);
+
+        let noisy = BandSynthesizer::default()
+            .add_signal(&tones, 1000.0, 0.0, 1.0)
+            .add_channel(Box::new(Awgn::new(-10.0, 3)))
+            .synthesize();
+        assert!(noisy[..6000].iter().any(|s| *s != 0.0));
+    }
+
+    #[test]
+    fn test_modulator_params() {
+        use crate::decoder::{decode_ft8, DecoderConfig};
+
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let standard = Modulator::default();
+        assert_eq!(
+            standard
+                .clone()
+                .with_params(GFSK_BT, 0.0, 0.0)
+                .modulate(&tones, 1500.0),
+            standard.modulate(&tones, 1500.0)
+        );
+
+        // A symbol clock 1 ms slow, and a repeated call doesn't compound it
+        let slow = standard.clone().with_params(GFSK_BT, 0.001, 0.0);
+        assert_eq!(slow.samples_per_symbol(), 1932);
+        assert_eq!(
+            slow.with_params(GFSK_BT, 0.001, 0.0).samples_per_symbol(),
+            1932
+        );
+
+        // Heavier filtering narrows the spectrum
+        let (_, bw) =
+            measure_occupied_bandwidth(&standard.modulate(&tones, 1500.0), SAMPLE_RATE, 99.0);
+        let soft = standard.clone().with_params(0.5, 0.0, 0.0);
+        let (_, soft_bw) =
+            measure_occupied_bandwidth(&soft.modulate(&tones, 1500.0), SAMPLE_RATE, 99.0);
+        assert!(soft_bw < bw, "BT 0.5: {} vs {}", soft_bw, bw);
+
+        // A 1 Hz/s drift puts the first and last symbols 6 Hz either side
+        // of f0, yet the signal still decodes at its mean frequency
+        for (drift, snr) in [(1.0, None), (0.5, Some(-10.0))] {
+            let mut band =
+                BandSynthesizer::with_modulator(standard.clone().with_params(GFSK_BT, 0.0, drift));
+            band.add_signal(&tones, 1500.0, 0.0, 1.0);
+            if let Some(snr) = snr {
+                band.add_channel(Box::new(Awgn::new(snr, 3)));
+            }
+            let decodes = decode_ft8(&band.synthesize(), &DecoderConfig::default(), None).unwrap();
+            assert_eq!(decodes.len(), 1, "{} Hz/s", drift);
+            assert_eq!(decodes[0].message, "CQ N0YPR DM42");
+            assert!(
+                (decodes[0].frequency - 1500.0).abs() < 1.0,
+                "{:?}",
+                decodes[0]
+            );
+        }
+    }
+}
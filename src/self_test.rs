@@ -0,0 +1,59 @@
+//! End-to-end smoke test of the transmit and receive chains
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+
+use crate::channel::{Awgn, ChannelModel};
+use crate::decoder::{decode_ft8, DecoderConfig};
+use crate::message::{self, CallsignHashCache};
+use crate::modulation::{Modulator, NOMINAL_START, SAMPLE_RATE};
+use crate::symbol::channel_symbols_from_bits;
+use crate::sync::NMAX;
+
+const MESSAGE: &str = "CQ N0YPR DM42";
+const FREQUENCY: f32 = 1500.0;
+const SNR_DB: f32 = -10.0;
+
+/// Encode, modulate and decode a known message.
+///
+/// `CQ N0YPR DM42` is packed, modulated at 1500 Hz into a 15 s window with
+/// AWGN at −10 dB SNR and passed through [`decode_ft8`]. Succeeds if the
+/// same text comes back.
+pub fn self_test() -> Result<(), String> {
+    let mut bits = bitarr![u8, Msb0; 0; 80];
+    message::encode(MESSAGE, &mut bits[..77], &mut CallsignHashCache::new())
+        .map_err(|e| e.to_string())?;
+    let tones = channel_symbols_from_bits(&bits[..77]);
+
+    let wave = Modulator::default().modulate(&tones, FREQUENCY);
+    let mut signal = vec![0.0f32; NMAX];
+    let start = (NOMINAL_START * SAMPLE_RATE) as usize;
+    for (s, w) in signal[start..].iter_mut().zip(&wave) {
+        *s = *w;
+    }
+    Awgn::new(SNR_DB, 1).apply(&mut signal, SAMPLE_RATE);
+
+    let decodes =
+        decode_ft8(&signal, &DecoderConfig::default(), None).map_err(|e| e.to_string())?;
+    match decodes.iter().find(|d| d.message == MESSAGE) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "Self-test failed: sent \"{}\", decoded {:?}",
+            MESSAGE,
+            decodes.iter().map(|d| &d.message).collect::<Vec<_>>()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test_passes() {
+        self_test().unwrap();
+    }
+}
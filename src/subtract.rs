@@ -0,0 +1,324 @@
+//! Removing decoded signals from the receive buffer
+//!
+//! Strong signals mask weaker ones through their sidebands and by
+//! capturing coarse-sync candidates. Once a signal has been decoded its
+//! tones are known exactly, so a replica can be synthesized and subtracted
+//! and the residual searched again.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use num_complex::Complex32;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::fft::{fft, ifft};
+use crate::modulation::{Modulator, NOMINAL_START, SAMPLE_RATE};
+use crate::sync::NMAX;
+
+/// In-phase and quadrature replicas of a transmission in a 15 s window.
+fn template_iq(tones: &[u8], frequency: f32, time_offset: f32) -> (Vec<f32>, Vec<f32>) {
+    let mut i_wave = vec![0.0f32; NMAX];
+    let mut q_wave = vec![0.0f32; NMAX];
+    let start = ((time_offset + NOMINAL_START) * SAMPLE_RATE).round() as isize;
+
+    let samples = Modulator::default().phase_envelope(tones, frequency);
+    for (n, (phi, env)) in samples.into_iter().enumerate() {
+        let idx = start + n as isize;
+        if (0..NMAX as isize).contains(&idx) {
+            i_wave[idx as usize] = phi.sin() * env;
+            q_wave[idx as usize] = phi.cos() * env;
+        }
+    }
+    (i_wave, q_wave)
+}
+
+/// Unit-amplitude replica of a transmission of `tones` with tone 0 at
+/// `frequency` Hz and the given DT, in a 15 s window at 12 kHz.
+///
+/// The replica comes from the same [`Modulator`] as transmitted signals,
+/// GFSK pulse shaping and end ramps included, so it is sample for sample
+/// what [`BandSynthesizer`](crate::modulation::BandSynthesizer) produces.
+pub fn synthesize_ft8_signal(tones: &[u8], frequency: f32, time_offset: f32) -> Vec<f32> {
+    template_iq(tones, frequency, time_offset).0
+}
+
+/// Length of the amplitude low-pass filter in samples: a cos² window of
+/// 1/3 s, as in WSJT-X `subtractft8`
+const NFILT: usize = 4000;
+
+/// FFT length of the filter convolution, enough for a whole transmission
+/// plus the filter without wrapping
+const NFFT: usize = 262_144;
+
+/// Time step of the refinement search, in samples (7.5 ms)
+const REFINE_SAMPLES: isize = 90;
+
+/// Frequency step of the refinement search, in Hz
+const REFINE_HZ: f32 = 0.25;
+
+/// Fits replicas of a transmission to one buffer.
+///
+/// The complex amplitude of the replica is not assumed constant: the
+/// signal is mixed down with the replica's phase and low-pass filtered, so
+/// a slowly drifting amplitude or a small frequency error is followed
+/// instead of leaving residual energy behind.
+struct Fitter<'a> {
+    signal: &'a [f32],
+    modulator: Modulator,
+    /// Transform of the unit-sum filter, centred on sample 0
+    filter: Vec<Complex32>,
+    /// Running sums of the filter taps, for the gain near either end of
+    /// the transmission where the filter runs off it
+    tap_sums: Vec<f32>,
+}
+
+impl<'a> Fitter<'a> {
+    fn new(signal: &'a [f32]) -> Self {
+        let taps: Vec<f32> = (0..=NFILT)
+            .map(|k| {
+                (PI * (k as f32 - (NFILT / 2) as f32) / NFILT as f32)
+                    .cos()
+                    .powi(2)
+            })
+            .collect();
+        let sum: f32 = taps.iter().sum();
+        let mut filter = vec![Complex32::new(0.0, 0.0); NFFT];
+        for (k, &w) in taps.iter().enumerate() {
+            let lag = k as isize - (NFILT / 2) as isize;
+            filter[lag.rem_euclid(NFFT as isize) as usize].re = w / sum;
+        }
+        fft(&mut filter);
+        let tap_sums = taps
+            .iter()
+            .scan(0.0, |acc, &w| {
+                *acc += w / sum;
+                Some(*acc)
+            })
+            .collect();
+        Self {
+            signal,
+            modulator: Modulator::default(),
+            filter,
+            tap_sums,
+        }
+    }
+
+    /// Sum of the filter taps at lags `lo..=hi` (from `-NFILT/2`).
+    fn taps_between(&self, lo: isize, hi: isize) -> f32 {
+        let half = (NFILT / 2) as isize;
+        let (lo, hi) = (lo.max(-half), hi.min(half));
+        if lo > hi {
+            return 0.0;
+        }
+        let below = if lo > -half {
+            self.tap_sums[(lo + half - 1) as usize]
+        } else {
+            0.0
+        };
+        self.tap_sums[(hi + half) as usize] - below
+    }
+
+    /// Replica of `tones` with tone 0 at `frequency` and symbol 0 starting
+    /// at sample `start`, fitted to the signal. Returns the first sample
+    /// it covers and its samples.
+    fn fit(&self, tones: &[u8], frequency: f32, start: isize) -> (usize, Vec<f32>) {
+        let samples = self.modulator.phase_envelope(tones, frequency);
+        let end = start + samples.len() as isize;
+        let first = start.max(0) as usize;
+        let last = end.clamp(0, self.signal.len() as isize) as usize;
+        if first >= last {
+            return (0, Vec::new());
+        }
+
+        let reference: Vec<Complex32> = samples[(first as isize - start) as usize..]
+            .iter()
+            .take(last - first)
+            .map(|&(phi, env)| Complex32::from_polar(env, phi))
+            .collect();
+        let len = reference.len();
+
+        // Mixed down, the replica's amplitude sits at DC and its image at
+        // twice the carrier, which the filter removes
+        let mut amp = vec![Complex32::new(0.0, 0.0); NFFT];
+        for ((a, &x), r) in amp
+            .iter_mut()
+            .zip(&self.signal[first..last])
+            .zip(&reference)
+        {
+            *a = 2.0 * x * r.conj();
+        }
+        fft(&mut amp);
+        for (a, f) in amp.iter_mut().zip(&self.filter) {
+            *a *= f;
+        }
+        ifft(&mut amp);
+
+        let model = reference
+            .iter()
+            .zip(&amp)
+            .enumerate()
+            .map(|(n, (r, a))| {
+                // Filter taps that landed inside the transmission
+                let gain = self.taps_between(n as isize + 1 - len as isize, n as isize);
+                (a * r).re / (NFFT as f32 * gain)
+            })
+            .collect();
+        (first, model)
+    }
+
+    /// Energy left after removing `model` starting at sample `first`.
+    fn residual(&self, first: usize, model: &[f32]) -> f32 {
+        self.signal[first..]
+            .iter()
+            .zip(model)
+            .map(|(x, m)| (x - m).powi(2))
+            .sum()
+    }
+}
+
+/// Offset, in steps, of the minimum of a parabola through residual
+/// energies one step either side of and at the current estimate.
+fn parabola_minimum(below: f32, at: f32, above: f32) -> f32 {
+    let curvature = below - 2.0 * at + above;
+    if curvature > 0.0 {
+        (0.5 * (below - above) / curvature).clamp(-1.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Subtract a decoded transmission from `signal`.
+///
+/// As in WSJT-X `subtractft8`, the replica's complex amplitude is
+/// estimated by mixing `signal` down with the replica's phase and
+/// low-pass filtering over 1/3 s, so it may vary slowly over the
+/// transmission. The DT and then the frequency are first refined by a
+/// parabola through the residual energy at ±7.5 ms and ±0.25 Hz, which
+/// absorbs the small position errors of the decoder's estimate.
+pub fn subtract_ft8_signal(signal: &mut [f32], tones: &[u8], frequency: f32, time_offset: f32) {
+    let start = ((time_offset + NOMINAL_START) * SAMPLE_RATE).round() as isize;
+    let (first, model) = {
+        let fitter = Fitter::new(signal);
+        let residual_at = |start: isize, frequency: f32| {
+            let (first, model) = fitter.fit(tones, frequency, start);
+            fitter.residual(first, &model)
+        };
+
+        let around = |f: &dyn Fn(f32) -> f32| parabola_minimum(f(-1.0), f(0.0), f(1.0));
+        let shift = around(&|k| residual_at(start + k as isize * REFINE_SAMPLES, frequency));
+        let start = start + (shift * REFINE_SAMPLES as f32).round() as isize;
+        let offset = around(&|k| residual_at(start, frequency + k * REFINE_HZ));
+        fitter.fit(tones, frequency + offset * REFINE_HZ, start)
+    };
+    for (x, m) in signal[first..].iter_mut().zip(&model) {
+        *x -= m;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::Awgn;
+    use crate::decoder::{decode_ft8, DecoderConfig};
+    use crate::message::tx_cq;
+    use crate::modulation::BandSynthesizer;
+    use crate::sync::{coarse_sync, CandidateSearch};
+
+    #[test]
+    fn test_subtract_removes_replica_at_any_phase() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let (i_wave, q_wave) = template_iq(&tones, 1500.0, 0.0);
+        let (c, s) = (0.8 * 1.1f32.cos(), 0.8 * 1.1f32.sin());
+        let mut signal: Vec<f32> = i_wave
+            .iter()
+            .zip(&q_wave)
+            .map(|(i, q)| c * i + s * q)
+            .collect();
+        let before: f32 = signal.iter().map(|x| x * x).sum();
+
+        subtract_ft8_signal(&mut signal, &tones, 1500.0, 0.0);
+        let after: f32 = signal.iter().map(|x| x * x).sum();
+        assert!(after < 1e-4 * before, "residual {} of {}", after, before);
+    }
+
+    #[test]
+    fn test_replica_matches_band_synthesizer() {
+        let tones = tx_cq("K1ABC", "FN42").unwrap();
+        let replica = synthesize_ft8_signal(&tones, 1234.5, 0.37);
+        let transmitted = BandSynthesizer::default()
+            .add_signal(&tones, 1234.5, 0.37, 1.0)
+            .synthesize();
+        assert_eq!(replica.len(), transmitted.len());
+        let worst = replica
+            .iter()
+            .zip(&transmitted)
+            .map(|(r, t)| (r - t).abs())
+            .fold(0.0f32, f32::max);
+        assert!(worst < 1e-5, "{}", worst);
+    }
+
+    #[test]
+    fn test_subtract_at_decoded_position_leaves_no_residual() {
+        // Encode, modulate, decode, then subtract the replica at the
+        // frequency and DT the decoder reports
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1234.5, 0.37, 0.7)
+            .synthesize();
+        let decodes = decode_ft8(&signal, &DecoderConfig::default(), None).unwrap();
+        assert_eq!(decodes.len(), 1);
+        let d = &decodes[0];
+        assert_eq!(d.message, "CQ N0YPR DM42");
+
+        let before: f32 = signal.iter().map(|x| x * x).sum();
+        let mut residual = signal.clone();
+        subtract_ft8_signal(&mut residual, &tones, d.frequency, d.time_offset);
+        let after: f32 = residual.iter().map(|x| x * x).sum();
+        let db = 10.0 * (after / before).log10();
+        assert!(db < -35.0, "{} dB left", db);
+    }
+
+    #[test]
+    fn test_subtract_absorbs_position_error() {
+        // A modulated signal in noise, subtracted at a position 0.4 Hz and
+        // 5 ms off, as a decoder might report it
+        let tones = tx_cq("W9XYZ", "EN37").unwrap();
+        let clean = BandSynthesizer::default()
+            .add_signal(&tones, 1500.0, 0.0, 1.0)
+            .synthesize();
+        let noisy = BandSynthesizer::default()
+            .add_signal(&tones, 1500.0, 0.0, 1.0)
+            .add_channel(Box::new(Awgn::new(-5.0, 3)))
+            .synthesize();
+        let energy = |x: &[f32]| x.iter().map(|v| v * v).sum::<f32>();
+        let rms_db = |residual: &[f32]| {
+            // What is left of the signal once the noise is taken out
+            let left: Vec<f32> = residual
+                .iter()
+                .zip(&noisy)
+                .zip(&clean)
+                .map(|((r, n), c)| r - (n - c))
+                .collect();
+            10.0 * (energy(&left) / energy(&clean)).log10()
+        };
+        assert!(rms_db(&noisy).abs() < 0.01);
+
+        let mut residual = noisy.clone();
+        subtract_ft8_signal(&mut residual, &tones, 1500.4, 0.005);
+        let after = rms_db(&residual);
+        assert!(after < -15.0, "{} dB left", after);
+
+        let search = CandidateSearch {
+            freq_min: 1480.0,
+            freq_max: 1520.0,
+            ..CandidateSearch::default()
+        };
+        let ghosts = coarse_sync(&residual, &search).unwrap();
+        assert!(
+            ghosts.iter().all(|c| (c.frequency - 1500.0).abs() > 3.0),
+            "{:?}",
+            ghosts
+        );
+    }
+}
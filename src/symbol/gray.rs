@@ -0,0 +1,42 @@
+//! The FT8 Gray code between 3-bit data values and tones
+//!
+//! Adjacent tones differ in one bit, so a symbol demodulated one tone off
+//! costs a single bit error.
+
+/// Gray code: 3-bit value → tone number, as `graymap` in WSJT-X
+pub const GRAY_MAP: [u8; 8] = [0, 1, 3, 2, 5, 6, 4, 7];
+
+/// Tone number → 3-bit value, the inverse of [`GRAY_MAP`]
+const GRAY_MAP_INV: [u8; 8] = [0, 1, 3, 2, 6, 4, 5, 7];
+
+/// The tone carrying 3-bit data value `index`.
+///
+/// Panics if `index` is 8 or more.
+pub const fn to_tone(index: u8) -> u8 {
+    GRAY_MAP[index as usize]
+}
+
+/// The 3-bit data value carried by `tone`.
+///
+/// Panics if `tone` is 8 or more.
+pub const fn to_index(tone: u8) -> u8 {
+    GRAY_MAP_INV[tone as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gray_code() {
+        assert_eq!(GRAY_MAP, [0, 1, 3, 2, 5, 6, 4, 7]);
+        for i in 0..8 {
+            assert_eq!(to_index(to_tone(i)), i);
+            assert_eq!(to_tone(to_index(i)), i);
+        }
+        // Neighbouring tones carry values one bit apart
+        for tone in 0..7 {
+            assert_eq!((to_index(tone) ^ to_index(tone + 1)).count_ones(), 1);
+        }
+    }
+}
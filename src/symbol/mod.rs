@@ -0,0 +1,258 @@
+//! FT8 channel symbol mapping
+//!
+//! A transmission is 79 8-FSK symbols: three 7-symbol Costas sync arrays at
+//! symbols 0, 36 and 72, with the 174 codeword bits carried 3 bits per
+//! symbol (Gray coded) in the two 29-symbol data blocks between them.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bitvec::prelude::*;
+
+pub mod gray;
+
+pub use gray::GRAY_MAP;
+
+use crate::decoder::DecodeError;
+use crate::message::{self, CallsignHashCache, MessageError, MESSAGE_BITS};
+use crate::{crc, ldpc};
+
+/// Costas synchronization array (tone sequence)
+pub const COSTAS_PATTERN: [u8; 7] = [3, 1, 4, 0, 6, 5, 2];
+
+/// Symbol index of each of the three Costas arrays
+pub const COSTAS_POSITIONS: [usize; 3] = [0, 36, 72];
+
+/// Total number of channel symbols
+pub const NUM_SYMBOLS: usize = 79;
+
+/// Number of data (non-sync) symbols
+pub const NUM_DATA_SYMBOLS: usize = 58;
+
+/// Channel symbol index of data symbol `k` (0..58).
+pub const fn data_symbol_index(k: usize) -> usize {
+    if k < 29 {
+        k + 7
+    } else {
+        k + 14
+    }
+}
+
+/// Map a 174-bit codeword onto the 79 channel tones.
+pub(crate) fn codeword_to_symbols(codeword: &BitSlice<u8, Msb0>) -> [u8; NUM_SYMBOLS] {
+    let mut tones = [0u8; NUM_SYMBOLS];
+
+    for &pos in COSTAS_POSITIONS.iter() {
+        tones[pos..pos + 7].copy_from_slice(&COSTAS_PATTERN);
+    }
+
+    for k in 0..NUM_DATA_SYMBOLS {
+        let value: u8 = codeword[3 * k..3 * k + 3].load_be();
+        tones[data_symbol_index(k)] = gray::to_tone(value);
+    }
+
+    tones
+}
+
+/// Generate the 79 channel tones for a packed 77-bit message.
+///
+/// Runs CRC → LDPC → Gray mapping → Costas insertion.
+pub(crate) fn channel_symbols_from_bits(message77: &BitSlice<u8, Msb0>) -> [u8; NUM_SYMBOLS] {
+    let mut message91 = bitarr![u8, Msb0; 0; 96];
+    crc::append_crc(message77, &mut message91[..ldpc::K]);
+
+    let mut codeword = bitarr![u8, Msb0; 0; 176];
+    ldpc::encode(&message91[..ldpc::K], &mut codeword[..ldpc::N]);
+
+    codeword_to_symbols(&codeword[..ldpc::N])
+}
+
+/// The 79 channel tones for message text.
+///
+/// Runs encode → CRC → LDPC → Gray mapping → Costas insertion, giving the
+/// tones WSJT-X's `ft8code` prints, for driving an external modulator or
+/// SDR. Callsigns in the message are recorded in `cache`, as by
+/// [`message::encode`].
+pub fn channel_symbols(
+    text: &str,
+    cache: &mut CallsignHashCache,
+) -> Result<[u8; NUM_SYMBOLS], MessageError> {
+    let mut bits = bitarr![u8, Msb0; 0; 80];
+    message::encode(text, &mut bits[..MESSAGE_BITS], cache)?;
+    Ok(channel_symbols_from_bits(&bits[..MESSAGE_BITS]))
+}
+
+/// LLR magnitude given to every bit of a hard-decoded tone array
+const HARD_LLR: f32 = 4.0;
+
+/// Decode an ideal 79-tone array back to message text, without audio.
+///
+/// The reverse of [`channel_symbols`]: checks the Costas arrays, undoes
+/// the Gray mapping, hard-decodes the codeword with BP (so a few wrong
+/// data tones are corrected) and verifies the CRC. Hashed callsigns are
+/// resolved from `cache` as by [`message::decode`].
+pub fn decode_symbols(
+    tones: &[u8; NUM_SYMBOLS],
+    cache: Option<&CallsignHashCache>,
+) -> Result<String, DecodeError> {
+    use alloc::string::ToString;
+
+    for (i, &pos) in COSTAS_POSITIONS.iter().enumerate() {
+        if tones[pos..pos + 7] != COSTAS_PATTERN {
+            return Err(DecodeError::Decode(format!(
+                "Costas array {} does not match",
+                i + 1
+            )));
+        }
+    }
+
+    let mut codeword = bitarr![u8, Msb0; 0; 176];
+    for k in 0..NUM_DATA_SYMBOLS {
+        let index = data_symbol_index(k);
+        if tones[index] >= 8 {
+            return Err(DecodeError::Decode(format!(
+                "Invalid tone {} at symbol {}",
+                tones[index], index
+            )));
+        }
+        codeword[3 * k..3 * k + 3].store_be(gray::to_index(tones[index]));
+    }
+
+    let llr: Vec<f32> = codeword[..ldpc::N]
+        .iter()
+        .map(|bit| if *bit { HARD_LLR } else { -HARD_LLR })
+        .collect();
+    let (message91, _, _) =
+        ldpc::decode_hybrid(&llr, ldpc::DecodeDepth::BpOnly).ok_or_else(|| {
+            // A valid codeword that BP rejects can only have failed the CRC
+            if ldpc::syndrome_weight(&codeword[..ldpc::N]) == Some(0) {
+                DecodeError::Decode("CRC check failed".to_string())
+            } else {
+                DecodeError::Decode("LDPC decode failed".to_string())
+            }
+        })?;
+    message::decode(&message91[..MESSAGE_BITS], cache)
+        .map_err(|e| DecodeError::Decode(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_costas_arrays_inserted() {
+        let bits = bitarr![u8, Msb0; 0; 80];
+        let tones = channel_symbols_from_bits(&bits[..77]);
+        for &pos in COSTAS_POSITIONS.iter() {
+            assert_eq!(&tones[pos..pos + 7], &COSTAS_PATTERN);
+        }
+    }
+
+    #[test]
+    fn test_all_zero_message_has_zero_data_tones() {
+        // Zero message → zero CRC → zero codeword → tone 0 everywhere outside sync
+        let bits = bitarr![u8, Msb0; 0; 80];
+        let tones = channel_symbols_from_bits(&bits[..77]);
+        for k in 0..NUM_DATA_SYMBOLS {
+            assert_eq!(tones[data_symbol_index(k)], 0);
+        }
+    }
+
+    #[test]
+    fn test_channel_symbols_from_text() {
+        let mut cache = CallsignHashCache::new();
+        let tones = channel_symbols("CQ K1ABC FN42", &mut cache).unwrap();
+        for &pos in COSTAS_POSITIONS.iter() {
+            assert_eq!(&tones[pos..pos + 7], &[3, 1, 4, 0, 6, 5, 2]);
+        }
+
+        // Packed by hand: CQ (c28 = 2), r1 = 0, K1ABC (c28 = 10214965),
+        // r1 = 0, R = 0, FN42 (g15 = 10342), i3 = 1. The code is systematic,
+        // so its first 75 bits are the first 25 data tones, Gray coded
+        let message = 0x4_09bd_e351_4331u128;
+        assert_eq!(
+            tones[7..32],
+            [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 5, 4, 7, 6, 7, 0, 4, 6, 0, 6, 0, 2, 1, 5]
+        );
+
+        // Gray-decoded, the data tones are the message, its CRC and the
+        // parity bits of a codeword
+        const TONE_TO_BITS: [u8; 8] = [0, 1, 3, 2, 6, 4, 5, 7];
+        let mut codeword = bitarr![u8, Msb0; 0; 176];
+        for k in 0..NUM_DATA_SYMBOLS {
+            let tone = tones[data_symbol_index(k)];
+            codeword[3 * k..3 * k + 3].store_be(TONE_TO_BITS[tone as usize]);
+        }
+        assert_eq!(codeword[..77].load_be::<u128>(), message);
+        assert!(crc::verify(&codeword[..ldpc::K]));
+        assert_eq!(ldpc::syndrome_weight(&codeword[..ldpc::N]), Some(0));
+
+        // Calls are recorded so a later hashed reference resolves
+        channel_symbols("PJ4/K1ABC W9XYZ", &mut cache).unwrap();
+        assert!(cache.contains("PJ4/K1ABC"));
+
+        assert!(channel_symbols("CQ K1ABC FN42 TOO MANY WORDS", &mut cache).is_err());
+    }
+
+    #[test]
+    fn test_decode_symbols_round_trip() {
+        let mut cache = CallsignHashCache::new();
+        for text in [
+            "CQ K1ABC FN42",
+            "K1ABC W9XYZ R-09",
+            "PJ4/K1ABC <W9XYZ> RRR",
+            "TNX BOB 73 GL",
+        ] {
+            let tones = channel_symbols(text, &mut cache).unwrap();
+            assert_eq!(decode_symbols(&tones, Some(&cache)).unwrap(), text);
+        }
+
+        // Without the cache a hashed call is shown as <...>
+        let tones = channel_symbols("PJ4/K1ABC <W9XYZ> RRR", &mut cache).unwrap();
+        assert_eq!(decode_symbols(&tones, None).unwrap(), "PJ4/K1ABC <...> RRR");
+
+        // A wrong data tone is corrected
+        let mut tones = channel_symbols("CQ K1ABC FN42", &mut cache).unwrap();
+        tones[data_symbol_index(40)] ^= 1;
+        assert_eq!(decode_symbols(&tones, None).unwrap(), "CQ K1ABC FN42");
+    }
+
+    #[test]
+    fn test_decode_symbols_rejects() {
+        let mut cache = CallsignHashCache::new();
+        let tones = channel_symbols("CQ K1ABC FN42", &mut cache).unwrap();
+
+        let mut bad_sync = tones;
+        bad_sync[COSTAS_POSITIONS[1] + 2] = 0;
+        assert_eq!(
+            decode_symbols(&bad_sync, None),
+            Err(DecodeError::Decode("Costas array 2 does not match".into()))
+        );
+
+        let mut bad_tone = tones;
+        bad_tone[data_symbol_index(0)] = 8;
+        assert!(decode_symbols(&bad_tone, None).is_err());
+
+        // A valid codeword whose CRC does not match its message
+        let mut bits = bitarr![u8, Msb0; 0; 80];
+        message::encode("CQ K1ABC FN42", &mut bits[..77], &mut cache).unwrap();
+        let mut message91 = bitarr![u8, Msb0; 0; 96];
+        crc::append_crc(&bits[..77], &mut message91[..ldpc::K]);
+        let flipped = !message91[80];
+        message91.set(80, flipped);
+        let mut codeword = bitarr![u8, Msb0; 0; 176];
+        ldpc::encode(&message91[..ldpc::K], &mut codeword[..ldpc::N]);
+        assert_eq!(
+            decode_symbols(&codeword_to_symbols(&codeword[..ldpc::N]), None),
+            Err(DecodeError::Decode("CRC check failed".into()))
+        );
+    }
+
+    #[test]
+    fn test_data_symbol_index_skips_sync() {
+        assert_eq!(data_symbol_index(0), 7);
+        assert_eq!(data_symbol_index(28), 35);
+        assert_eq!(data_symbol_index(29), 43);
+        assert_eq!(data_symbol_index(57), 71);
+    }
+}
@@ -0,0 +1,497 @@
+//! Coarse candidate search (WSJT-X `sync8`)
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::{
+    compute_spectra, fine_sync_with, tone_bin, Candidate, Downsampler, SyncMetric, COARSE_LAG, DF,
+    JSTRT, MAX_LAG, NH1, NHSYM, TSTEP,
+};
+use crate::symbol::{COSTAS_PATTERN, COSTAS_POSITIONS};
+
+/// Number of time lags in a sync2d row (`-MAX_LAG..=MAX_LAG`)
+pub const NLAGS: usize = 2 * MAX_LAG as usize + 1;
+
+/// Spectrogram steps per symbol
+const NSSY: i32 = 4;
+
+/// How much the arrays 2+3 ratio must exceed the three-array ratio for a
+/// sync value to count as a BC-only detection
+const BC_ONLY_EXCESS: f32 = 1.25;
+
+/// Settings of the coarse candidate search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CandidateSearch {
+    /// Lowest tone-0 frequency searched, in Hz
+    pub freq_min: f32,
+    /// Highest tone-0 frequency searched, in Hz
+    pub freq_max: f32,
+    /// Minimum normalized sync for a candidate
+    pub sync_min: f32,
+    /// Maximum number of candidates returned
+    pub max_candidates: usize,
+    /// Half-width of the primary time search, in spectrogram steps
+    pub coarse_lag: i32,
+    /// Candidates closer than this in frequency (Hz) and in DT
+    /// (`merge_time`, s) are merged, keeping the stronger
+    pub merge_hz: f32,
+    /// See `merge_hz`
+    pub merge_time: f32,
+    /// Percentile of the band's sync peaks, as a fraction, taken as the
+    /// noise level that sync values are normalized by. Lower values
+    /// raise every normalized sync, passing weaker peaks on a quiet band
+    pub baseline_percentile: f32,
+}
+
+impl Default for CandidateSearch {
+    fn default() -> Self {
+        CandidateSearch {
+            freq_min: 200.0,
+            freq_max: 2800.0,
+            sync_min: 1.3,
+            max_candidates: 300,
+            coarse_lag: COARSE_LAG,
+            merge_hz: 6.0,
+            merge_time: 0.1,
+            baseline_percentile: 0.4,
+        }
+    }
+}
+
+/// One row of the sync2d table: the sync value at each lag and the metric
+/// that produced it
+pub type Sync2dRow = [(f32, SyncMetric); NLAGS];
+
+/// Costas sync metric for every bin in `ia..=ib` and every lag.
+///
+/// Row `i - ia` holds, for each lag `j` in `-MAX_LAG..=MAX_LAG`, the ratio
+/// of power on the expected Costas tones to the average power on the other
+/// tones. Both the three-array sum and the sum over arrays 2 and 3 alone
+/// are evaluated and the larger kept, so signals whose first array falls
+/// before the window start are still found. In both cases lag `j` places
+/// symbol 0 at spectrogram step `j + JSTRT`, so the lag converts to DT the
+/// same way whichever metric won.
+pub fn compute_sync2d(spectra: &[[f32; NHSYM]], ia: usize, ib: usize) -> Vec<Sync2dRow> {
+    let offsets: [usize; 7] = core::array::from_fn(tone_bin);
+    let mut sync2d = vec![[(0.0f32, SyncMetric::AllCostas); NLAGS]; ib + 1 - ia];
+
+    for (row, i) in sync2d.iter_mut().zip(ia..=ib) {
+        for (slot, j) in row.iter_mut().zip(-MAX_LAG..=MAX_LAG) {
+            let mut t = [0.0f32; 3];
+            let mut t0 = [0.0f32; 3];
+            for (array, &pos) in COSTAS_POSITIONS.iter().enumerate() {
+                for (n, &tone) in COSTAS_PATTERN.iter().enumerate() {
+                    let m = j + JSTRT + NSSY * (pos + n) as i32;
+                    if m < 0 || m >= NHSYM as i32 {
+                        continue;
+                    }
+                    let m = m as usize;
+                    t[array] += spectra[i + offsets[tone as usize]][m];
+                    t0[array] += offsets.iter().map(|&o| spectra[i + o][m]).sum::<f32>();
+                }
+            }
+
+            let ratio = |t: f32, t0: f32| {
+                let base = (t0 - t) / 6.0;
+                if base > 0.0 {
+                    t / base
+                } else {
+                    0.0
+                }
+            };
+            let sync_abc = ratio(t.iter().sum(), t0.iter().sum());
+            let sync_bc = ratio(t[1] + t[2], t0[1] + t0[2]);
+            // On a complete signal the two ratios are close and either may
+            // be larger; only a clear excess means array 1 is missing. With
+            // array 1 wholly before the window the ratios are equal.
+            let metric = if sync_bc > BC_ONLY_EXCESS * sync_abc || t0[0] == 0.0 {
+                SyncMetric::CostasBc
+            } else {
+                SyncMetric::AllCostas
+            };
+            *slot = (sync_abc.max(sync_bc), metric);
+        }
+    }
+
+    sync2d
+}
+
+/// Best lag, its sync value and the metric behind it within
+/// `-max_lag..=max_lag`.
+fn peak(row: &Sync2dRow, max_lag: i32) -> (i32, f32, SyncMetric) {
+    (-max_lag..=max_lag)
+        .map(|j| {
+            let (sync, metric) = row[(j + MAX_LAG) as usize];
+            (j, sync, metric)
+        })
+        .fold((0, f32::MIN, SyncMetric::AllCostas), |best, cur| {
+            if cur.1 > best.1 {
+                cur
+            } else {
+                best
+            }
+        })
+}
+
+/// Turn the sync2d table into deduplicated candidates, strongest first.
+///
+/// For every bin the best lag is taken both within ±`search.coarse_lag`
+/// steps (where well-timed signals sit; clamped to `MAX_LAG`) and over the
+/// full ±`MAX_LAG` range. Each set of peaks is normalized by its
+/// `search.baseline_percentile` percentile over the band, and peaks of at least `search.sync_min`
+/// become candidates, each recording which Costas metric its peak came
+/// from. `ia` is the bin of row 0; the frequency range of `search` is not
+/// used here.
+pub fn find_candidates(
+    sync2d: &[Sync2dRow],
+    ia: usize,
+    search: &CandidateSearch,
+) -> Vec<Candidate> {
+    // Merge near-duplicates, keeping the stronger of each pair
+    let mut kept: Vec<Candidate> = Vec::new();
+    for c in ranked_peaks(sync2d, ia, search) {
+        if !is_duplicate(&kept, &c, search) {
+            kept.push(c);
+        }
+        if kept.len() >= search.max_candidates {
+            break;
+        }
+    }
+    kept
+}
+
+/// Whether `c` is within the merge distance of `search` of a candidate
+/// already in `kept`.
+fn is_duplicate(kept: &[Candidate], c: &Candidate, search: &CandidateSearch) -> bool {
+    kept.iter().any(|k| {
+        (k.frequency - c.frequency).abs() < search.merge_hz
+            && (k.time_offset - c.time_offset).abs() < search.merge_time
+    })
+}
+
+/// Every peak of [`find_candidates`] before near-duplicates are merged,
+/// strongest first.
+fn ranked_peaks(sync2d: &[Sync2dRow], ia: usize, search: &CandidateSearch) -> Vec<Candidate> {
+    if sync2d.is_empty() {
+        return Vec::new();
+    }
+
+    let coarse_lag = search.coarse_lag.clamp(0, MAX_LAG);
+    let near: Vec<_> = sync2d.iter().map(|r| peak(r, coarse_lag)).collect();
+    let far: Vec<_> = sync2d.iter().map(|r| peak(r, MAX_LAG)).collect();
+
+    let baseline = |peaks: &[(i32, f32, SyncMetric)]| {
+        let mut values: Vec<f32> = peaks.iter().map(|p| p.1).collect();
+        values.sort_by(f32::total_cmp);
+        let idx =
+            ((values.len() as f32 * search.baseline_percentile) as usize).min(values.len() - 1);
+        values[idx].max(f32::MIN_POSITIVE)
+    };
+    let base_near = baseline(&near);
+    let base_far = baseline(&far);
+
+    let mut order: Vec<usize> = (0..sync2d.len()).collect();
+    order.sort_by(|&a, &b| near[b].1.total_cmp(&near[a].1));
+
+    let to_candidate = |k: usize, (lag, _, metric): (i32, f32, SyncMetric), sync| Candidate {
+        frequency: (ia + k) as f32 * DF,
+        time_offset: (lag as f32 - 0.5) * TSTEP,
+        sync_power: sync,
+        sync_metric: metric,
+    };
+
+    let mut candidates = Vec::new();
+    for &k in &order {
+        let near_sync = near[k].1 / base_near;
+        if near_sync >= search.sync_min && near_sync.is_finite() {
+            candidates.push(to_candidate(k, near[k], near_sync));
+        }
+        let far_sync = far[k].1 / base_far;
+        if far[k].0 != near[k].0 && far_sync >= search.sync_min && far_sync.is_finite() {
+            candidates.push(to_candidate(k, far[k], far_sync));
+        }
+    }
+
+    candidates.sort_by(|a, b| b.sync_power.total_cmp(&a.sync_power));
+    candidates
+}
+
+/// Find FT8 candidates with tone 0 between `search.freq_min` and
+/// `search.freq_max` Hz.
+pub fn coarse_sync(signal: &[f32], search: &CandidateSearch) -> Result<Vec<Candidate>, String> {
+    let spectra = compute_spectra(signal)?;
+    coarse_sync_spectra(&spectra, search)
+}
+
+/// [`coarse_sync`] on an already computed spectrogram.
+pub fn coarse_sync_spectra(
+    spectra: &[[f32; NHSYM]],
+    search: &CandidateSearch,
+) -> Result<Vec<Candidate>, String> {
+    let (ia, ib) = search_bins(search)?;
+    let sync2d = compute_sync2d(spectra, ia, ib);
+    Ok(find_candidates(&sync2d, ia, search))
+}
+
+/// Spectrogram bins of the lowest and highest tone 0 searched.
+fn search_bins(search: &CandidateSearch) -> Result<(usize, usize), String> {
+    let ia = ((search.freq_min / DF).round() as usize).max(1);
+    let ib = ((search.freq_max / DF).round() as usize).min(NH1 - 1 - tone_bin(7));
+    if ia > ib {
+        return Err(format!(
+            "Empty search range: {} Hz to {} Hz",
+            search.freq_min, search.freq_max
+        ));
+    }
+    Ok((ia, ib))
+}
+
+/// Candidates with tone 0 between `freq_min` and `freq_max` Hz and a sync
+/// of at least `sync_min`, yielded strongest first.
+///
+/// The spectrogram and sync table are computed up front, but candidates
+/// are merged with the stronger ones already yielded only as they are
+/// pulled, using the default [`CandidateSearch`] merge distance, so the
+/// stream matches [`coarse_sync`] without a candidate limit. Use
+/// [`CandidateStream::refine`] to fine-sync each one as it is taken.
+pub fn candidates(
+    signal: &[f32],
+    freq_min: f32,
+    freq_max: f32,
+    sync_min: f32,
+) -> Result<CandidateStream<'_>, String> {
+    let search = CandidateSearch {
+        freq_min,
+        freq_max,
+        sync_min,
+        max_candidates: usize::MAX,
+        ..CandidateSearch::default()
+    };
+    let (ia, ib) = search_bins(&search)?;
+    let spectra = compute_spectra(signal)?;
+    let sync2d = compute_sync2d(&spectra, ia, ib);
+    Ok(CandidateStream {
+        signal,
+        ranked: ranked_peaks(&sync2d, ia, &search).into_iter(),
+        kept: Vec::new(),
+        search,
+    })
+}
+
+/// Iterator returned by [`candidates`].
+#[derive(Debug, Clone)]
+pub struct CandidateStream<'a> {
+    signal: &'a [f32],
+    ranked: alloc::vec::IntoIter<Candidate>,
+    kept: Vec<Candidate>,
+    search: CandidateSearch,
+}
+
+impl<'a> CandidateStream<'a> {
+    /// Run [`fine_sync`](super::fine_sync) on each candidate as it is
+    /// pulled.
+    ///
+    /// The window is transformed for fine sync when the first candidate
+    /// is taken, and not at all if none is.
+    pub fn refine(self) -> RefinedCandidates<'a> {
+        RefinedCandidates {
+            candidates: self,
+            downsampler: None,
+        }
+    }
+}
+
+impl Iterator for CandidateStream<'_> {
+    type Item = Candidate;
+
+    fn next(&mut self) -> Option<Candidate> {
+        let (kept, search) = (&self.kept, &self.search);
+        let c = self.ranked.find(|c| !is_duplicate(kept, c, search))?;
+        self.kept.push(c);
+        Some(c)
+    }
+}
+
+/// Iterator returned by [`CandidateStream::refine`], yielding fine-synced
+/// candidates.
+pub struct RefinedCandidates<'a> {
+    candidates: CandidateStream<'a>,
+    downsampler: Option<Downsampler>,
+}
+
+impl Iterator for RefinedCandidates<'_> {
+    type Item = Result<Candidate, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let candidate = self.candidates.next()?;
+        let downsampler = match &mut self.downsampler {
+            Some(d) => d,
+            slot => match Downsampler::new(self.candidates.signal) {
+                Ok(d) => slot.insert(d),
+                Err(e) => return Some(Err(e)),
+            },
+        };
+        Some(fine_sync_with(downsampler, &candidate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::Awgn;
+    use crate::message::tx_cq;
+    use crate::modulation::BandSynthesizer;
+    use crate::sync::fine_sync;
+
+    #[test]
+    fn test_costas1_clipped_candidate_keeps_dt() {
+        // Starts 1.4 s before the window: the first Costas array is lost
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1200.0, -1.9, 1.0)
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1600.0, 0.1, 1.0)
+            .add_channel(Box::new(Awgn::new(-8.0, 7)))
+            .synthesize();
+        let search = CandidateSearch {
+            freq_min: 1100.0,
+            freq_max: 1700.0,
+            max_candidates: 20,
+            ..CandidateSearch::default()
+        };
+        let candidates = coarse_sync(&signal, &search).unwrap();
+        let near = |freq: f32| {
+            candidates
+                .iter()
+                .find(|c| (c.frequency - freq).abs() < 3.0)
+                .copied()
+                .unwrap()
+        };
+
+        let clipped = near(1200.0);
+        assert_eq!(clipped.sync_metric, SyncMetric::CostasBc);
+        assert!((clipped.time_offset + 1.9).abs() < 0.05, "{:?}", clipped);
+        let refined = fine_sync(&signal, &clipped).unwrap();
+        assert!((refined.time_offset + 1.9).abs() < 0.01, "{:?}", refined);
+        assert_eq!(refined.sync_metric, SyncMetric::CostasBc);
+
+        assert_eq!(near(1600.0).sync_metric, SyncMetric::AllCostas);
+    }
+
+    #[test]
+    fn test_strong_signal_gives_one_candidate() {
+        // Sidelobes of a strong signal peak one or two bins and steps away
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1000.7, -0.2, 1.0)
+            .add_channel(Box::new(Awgn::new(20.0, 3)))
+            .synthesize();
+        let around = |search: &CandidateSearch| {
+            coarse_sync(&signal, search)
+                .unwrap()
+                .into_iter()
+                .filter(|c| {
+                    (c.frequency - 1000.7).abs() < 10.0 && (c.time_offset + 0.2).abs() < 0.2
+                })
+                .count()
+        };
+        assert_eq!(around(&CandidateSearch::default()), 1);
+
+        let tight = CandidateSearch {
+            merge_hz: 2.0,
+            merge_time: 0.02,
+            ..CandidateSearch::default()
+        };
+        assert!(around(&tight) > 1);
+    }
+
+    #[test]
+    fn test_tighter_merge_keeps_more_candidates_in_crowded_band() {
+        // Pairs 5 Hz and 80 ms apart, inside the default merge distance
+        let mut synth = BandSynthesizer::default();
+        for (i, call) in ["K1ABC", "W9XYZ", "N0YPR", "G4ABC", "JA1XYZ", "VK2ABC"]
+            .iter()
+            .enumerate()
+        {
+            let freq = 600.0 + 300.0 * (i / 2) as f32 + 5.0 * (i % 2) as f32;
+            let dt = 0.08 * (i % 2) as f32;
+            synth.add_signal(&tx_cq(call, "FN42").unwrap(), freq, dt, 1.0);
+        }
+        let signal = synth.add_channel(Box::new(Awgn::new(0.0, 5))).synthesize();
+        let search = CandidateSearch {
+            freq_min: 500.0,
+            freq_max: 1500.0,
+            max_candidates: usize::MAX,
+            ..CandidateSearch::default()
+        };
+        let tight = CandidateSearch {
+            merge_hz: 2.0,
+            merge_time: 0.03,
+            ..search
+        };
+        let default = coarse_sync(&signal, &search).unwrap().len();
+        let tightened = coarse_sync(&signal, &tight).unwrap().len();
+        assert!(tightened > default, "{} vs {}", tightened, default);
+    }
+
+    #[test]
+    fn test_lower_baseline_percentile_passes_more_candidates() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1000.0, 0.0, 1.0)
+            .add_channel(Box::new(Awgn::new(-22.0, 9)))
+            .synthesize();
+        let count = |baseline_percentile: f32| {
+            let search = CandidateSearch {
+                baseline_percentile,
+                max_candidates: usize::MAX,
+                ..CandidateSearch::default()
+            };
+            coarse_sync(&signal, &search).unwrap().len()
+        };
+        assert!(count(0.1) > count(0.4));
+        assert!(count(0.4) > count(0.8));
+    }
+
+    #[test]
+    fn test_candidate_stream_matches_coarse_sync() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 800.0, 0.0, 1.0)
+            .add_signal(&tx_cq("W9XYZ", "EN37").unwrap(), 1300.0, 0.3, 0.5)
+            .add_signal(&tx_cq("N0YPR", "DM42").unwrap(), 1900.0, -0.4, 0.25)
+            .add_channel(Box::new(Awgn::new(-10.0, 11)))
+            .synthesize();
+        let search = CandidateSearch {
+            freq_min: 500.0,
+            freq_max: 2200.0,
+            max_candidates: usize::MAX,
+            ..CandidateSearch::default()
+        };
+        let all = coarse_sync(&signal, &search).unwrap();
+        let streamed: Vec<Candidate> = candidates(&signal, 500.0, 2200.0, search.sync_min)
+            .unwrap()
+            .collect();
+        assert_eq!(streamed, all);
+        assert!(streamed
+            .windows(2)
+            .all(|w| w[0].sync_power >= w[1].sync_power));
+
+        // Refined lazily, the first few match fine-syncing the stream and
+        // the first is the loudest signal
+        let refined: Vec<Candidate> = candidates(&signal, 500.0, 2200.0, search.sync_min)
+            .unwrap()
+            .refine()
+            .take(3)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for (r, c) in refined.iter().zip(&all) {
+            assert_eq!(*r, fine_sync(&signal, c).unwrap());
+        }
+        assert!(
+            (refined[0].frequency - 800.0).abs() < 1.0,
+            "{:?}",
+            refined[0]
+        );
+        assert!(refined[0].time_offset.abs() < 0.02, "{:?}", refined[0]);
+    }
+}
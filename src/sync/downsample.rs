@@ -0,0 +1,213 @@
+//! Mixing a candidate down to complex baseband (WSJT-X `ft8_downsample`)
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use num_complex::Complex32;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::NMAX;
+use crate::fft::{fft, Plan};
+use crate::modulation::{SAMPLE_RATE, TONE_SPACING};
+
+/// Forward FFT length: the 15 s window zero-padded to a power of two
+const NFFT_IN: usize = 262_144;
+
+/// Inverse FFT length, which sets the output rate to 187.5 Hz
+const NFFT_OUT: usize = 4096;
+
+/// Output sample rate of [`downsample_200hz`]: 187.5 Hz
+pub const DOWNSAMPLE_RATE: f32 = SAMPLE_RATE * NFFT_OUT as f32 / NFFT_IN as f32;
+
+/// Width in bins of the raised-cosine taper at each band edge
+const TAPER_BINS: usize = 100;
+
+/// Mixes candidates in one window down to baseband.
+///
+/// The forward transform of the window does not depend on the candidate,
+/// so it is computed once here and shared by every [`downsample`] call;
+/// each call then only cuts out its band and runs the 4096-point inverse
+/// transform.
+///
+/// [`downsample`]: Downsampler::downsample
+#[derive(Clone)]
+pub struct Downsampler {
+    /// Non-negative frequency bins of the zero-padded window
+    spectrum: Vec<Complex32>,
+    /// Inverse plan for the output transform
+    plan: Plan,
+}
+
+impl Downsampler {
+    /// Transform the first 15 s of `signal`.
+    pub fn new(signal: &[f32]) -> Result<Self, String> {
+        if signal.len() < NMAX {
+            return Err(format!(
+                "Signal too short: {} samples, need {}",
+                signal.len(),
+                NMAX
+            ));
+        }
+
+        let mut x = vec![Complex32::new(0.0, 0.0); NFFT_IN];
+        for (c, &s) in x.iter_mut().zip(&signal[..NMAX]) {
+            c.re = s;
+        }
+        fft(&mut x);
+        // Only bins up to Nyquist are ever cut out
+        x.truncate(NFFT_IN / 2 + 1);
+
+        Ok(Self {
+            spectrum: x,
+            plan: Plan::new(NFFT_OUT, true),
+        })
+    }
+
+    /// Extract the band around `f0` (tone 0) and resample it to ~200 Hz.
+    ///
+    /// The band from 1.5 tone spacings below tone 0 to 1.5 above tone 7 is
+    /// cut out of the spectrum, tapered, shifted so `f0` lands at DC and
+    /// inverse transformed. With the power-of-two FFT lengths the output
+    /// rate is 187.5 Hz, exactly 30 samples per symbol; it is returned
+    /// alongside the samples. Output sample `n` corresponds to time
+    /// `n / rate` in the window.
+    pub fn downsample(&self, f0: f32) -> Result<(Vec<Complex32>, f32), String> {
+        let df = SAMPLE_RATE / NFFT_IN as f32;
+        let i0 = (f0 / df).round() as usize;
+        let ib = (((f0 - 1.5 * TONE_SPACING) / df).round() as usize).max(1);
+        let it = (((f0 + 8.5 * TONE_SPACING) / df).round() as usize).min(NFFT_IN / 2);
+        if ib >= it || i0 < ib || i0 > it {
+            return Err(format!("Frequency out of range: {} Hz", f0));
+        }
+        #[cfg(feature = "tracing")]
+        tracing::trace!(f0, bins = ?(ib..=it), "downsampling");
+
+        let mut band: Vec<Complex32> = self.spectrum[ib..=it].to_vec();
+        let k = band.len();
+        if k > 2 * TAPER_BINS {
+            for i in 0..=TAPER_BINS {
+                let taper = 0.5 * (1.0 + (i as f32 * PI / TAPER_BINS as f32).cos());
+                band[TAPER_BINS - i] *= taper;
+                band[k - 1 - TAPER_BINS + i] *= taper;
+            }
+        }
+
+        // Place f0 at DC: bins above it at positive, below at negative indices
+        let mut c1 = vec![Complex32::new(0.0, 0.0); NFFT_OUT];
+        for (j, &v) in band.iter().enumerate() {
+            let offset = (ib + j) as isize - i0 as isize;
+            let idx = offset.rem_euclid(NFFT_OUT as isize) as usize;
+            c1[idx] = v;
+        }
+        self.plan.process(&mut c1);
+
+        let scale = 1.0 / ((NFFT_IN * NFFT_OUT) as f32).sqrt();
+        for c in c1.iter_mut() {
+            *c *= scale;
+        }
+
+        Ok((c1, DOWNSAMPLE_RATE))
+    }
+}
+
+/// Extract the band around `f0` (tone 0) and resample it to ~200 Hz.
+///
+/// A one-off [`Downsampler::downsample`]; when mixing several candidates
+/// from the same window, build one [`Downsampler`] and reuse it.
+pub fn downsample_200hz(signal: &[f32], f0: f32) -> Result<(Vec<Complex32>, f32), String> {
+    Downsampler::new(signal)?.downsample(f0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone_appears_at_baseband_offset() {
+        // A tone 12.5 Hz above f0 becomes a 12.5 Hz complex exponential
+        let f0 = 1000.0;
+        let signal: Vec<f32> = (0..NMAX)
+            .map(|i| (2.0 * PI * (f0 + 12.5) * i as f32 / SAMPLE_RATE).cos())
+            .collect();
+        let (cd, rate) = downsample_200hz(&signal, f0).unwrap();
+        assert_eq!(rate, 187.5);
+
+        let n = 300;
+        let start = 1000;
+        // 300 samples is exactly 20 cycles, so any phase drift is a frequency error
+        let phase = (cd[start + n] * cd[start].conj()).arg();
+        let freq_error = phase / (2.0 * PI * n as f32 / rate);
+        assert!(freq_error.abs() < 0.05, "frequency error {} Hz", freq_error);
+        assert!(cd[start].norm() > 0.1);
+    }
+
+    #[test]
+    fn test_downsampler_matches_direct_dft() {
+        // A short burst, so the band's DFT can be summed directly
+        use core::f64::consts::TAU;
+
+        let mut signal = vec![0.0f32; NMAX];
+        for (i, s) in signal[60_000..62_000].iter_mut().enumerate() {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            *s = ((TAU * (700.0 * t + 900.0 * t * t)).cos() + 0.3 * (i as f64 * 0.37).sin()) as f32;
+        }
+
+        let downsampler = Downsampler::new(&signal).unwrap();
+        for f0 in [650.0, 731.0, 880.6] {
+            let (cd, _) = downsampler.downsample(f0).unwrap();
+
+            // The bins from 1.5 spacings below tone 0 to 1.5 above tone 7,
+            // raised-cosine tapered over 100 bins at each edge and shifted
+            // so f0 is at DC, transformed back sample by sample
+            let df = SAMPLE_RATE as f64 / NFFT_IN as f64;
+            let bin = |f: f32| (f as f64 / df).round() as i64;
+            let (i0, ib, it) = (
+                bin(f0),
+                bin(f0 - 1.5 * TONE_SPACING),
+                bin(f0 + 8.5 * TONE_SPACING),
+            );
+            let band: Vec<(i64, (f64, f64))> = (ib..=it)
+                .map(|k| {
+                    let (mut re, mut im) = (0.0, 0.0);
+                    for (t, &x) in signal.iter().enumerate().skip(60_000).take(2000) {
+                        let phase = -TAU * (k * t as i64) as f64 / NFFT_IN as f64;
+                        re += x as f64 * phase.cos();
+                        im += x as f64 * phase.sin();
+                    }
+                    let edge = (k - ib).min(it - k) as f64;
+                    let taper = if edge < 100.0 {
+                        0.5 * (1.0 - (TAU * edge / 200.0).cos())
+                    } else {
+                        1.0
+                    };
+                    (k - i0, (taper * re, taper * im))
+                })
+                .collect();
+            let scale = 1.0 / ((NFFT_IN * NFFT_OUT) as f64).sqrt();
+            let peak = cd.iter().map(|c| c.norm()).fold(0.0, f32::max);
+            assert!(peak > 0.01, "{}", peak);
+            for n in (0..NFFT_OUT).step_by(7) {
+                let (mut re, mut im) = (0.0, 0.0);
+                for &(offset, (x_re, x_im)) in &band {
+                    let phase = TAU * (offset * n as i64) as f64 / NFFT_OUT as f64;
+                    re += x_re * phase.cos() - x_im * phase.sin();
+                    im += x_re * phase.sin() + x_im * phase.cos();
+                }
+                let direct = Complex32::new((scale * re) as f32, (scale * im) as f32);
+                assert!(
+                    (cd[n] - direct).norm() < 1e-3 * peak,
+                    "f0 = {} Hz, sample {}: {} vs {}",
+                    f0,
+                    n,
+                    cd[n],
+                    direct
+                );
+            }
+        }
+        assert!(downsampler.downsample(6100.0).is_err());
+        assert!(Downsampler::new(&signal[..NMAX - 1]).is_err());
+    }
+}
@@ -0,0 +1,638 @@
+//! Symbol demodulation and soft-bit (LLR) extraction (WSJT-X `ft8b`)
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use num_complex::Complex32;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::spectra::FAC;
+use super::{Candidate, Downsampler, DOWNSAMPLE_RATE, NSPS};
+use crate::channel::SNR_REFERENCE_BANDWIDTH;
+use crate::ldpc;
+use crate::modulation::{SYMBOL_DURATION, TONE_SPACING};
+use crate::symbol::{
+    data_symbol_index, gray, COSTAS_PATTERN, COSTAS_POSITIONS, NUM_DATA_SYMBOLS, NUM_SYMBOLS,
+};
+
+/// Scale applied to the normalized bit metrics to form LLRs
+pub(super) const LLR_SCALE: f32 = 2.83;
+
+/// Data symbols per half (between two Costas arrays)
+const HALF_SYMBOLS: usize = NUM_DATA_SYMBOLS / 2;
+
+/// Soft information extracted for one candidate.
+#[derive(Debug, Clone)]
+pub struct SymbolLlrs {
+    /// Per-symbol non-coherent metrics (nsym = 1)
+    pub llra: Vec<f32>,
+    /// Metrics from coherently combining pairs of symbols (nsym = 2),
+    /// after removing the phase drift measured on the Costas arrays
+    pub llrb: Vec<f32>,
+    /// Metrics from coherently combining triples of symbols (nsym = 3),
+    /// likewise phase-tracked
+    pub llrc: Vec<f32>,
+    /// nsym = 1 metrics normalized by the strongest tone
+    pub llrd: Vec<f32>,
+    /// Tone amplitudes, `s8[symbol][tone]`
+    pub s8: Vec<[f32; 8]>,
+    /// Number of the 21 Costas symbols whose strongest tone is the expected one
+    pub nsync: usize,
+    /// Residual frequency error in Hz measured from the phase drift
+    /// across the Costas arrays: the signal sits this far from the
+    /// candidate's frequency
+    pub freq_error: f32,
+}
+
+/// Complex tone amplitudes of all 79 symbols.
+///
+/// Each symbol is correlated against the 8 tone frequencies exactly (the
+/// tone spacing is one bin of an `nsps`-point DFT). The tones are therefore
+/// orthogonal over a symbol and fall on bins 0–7 with no rounding, which a
+/// zero-padded power-of-two FFT would not give: at 64 points the tones
+/// would sit 2.13 bins apart. Symbols extending past either end of `cd`
+/// are zero.
+fn extract_symbols_impl(cd: &[Complex32], i0: i32, nsps: usize) -> Vec<[Complex32; 8]> {
+    let twiddles: Vec<[Complex32; 8]> = (0..nsps)
+        .map(|n| {
+            core::array::from_fn(|t| {
+                Complex32::from_polar(1.0, -2.0 * PI * (t * n) as f32 / nsps as f32)
+            })
+        })
+        .collect();
+
+    (0..NUM_SYMBOLS)
+        .map(|k| {
+            let mut cs = [Complex32::new(0.0, 0.0); 8];
+            let start = i0 + (k * nsps) as i32;
+            if start < 0 || start as usize + nsps > cd.len() {
+                return cs;
+            }
+            let start = start as usize;
+            for (x, tw) in cd[start..start + nsps].iter().zip(&twiddles) {
+                for (c, w) in cs.iter_mut().zip(tw) {
+                    *c += x * w;
+                }
+            }
+            cs
+        })
+        .collect()
+}
+
+/// Count Costas symbols whose strongest tone is the expected one.
+fn count_sync(s8: &[[f32; 8]]) -> usize {
+    COSTAS_POSITIONS
+        .iter()
+        .flat_map(|&pos| {
+            COSTAS_PATTERN.iter().enumerate().map(move |(k, &tone)| {
+                let row = &s8[pos + k];
+                let best = (0..8).fold(0, |b, t| if row[t] > row[b] { t } else { b });
+                best == tone as usize
+            })
+        })
+        .filter(|&ok| ok)
+        .count()
+}
+
+/// Scale metrics to unit standard deviation.
+pub(super) fn normalize_bmet(bmet: &mut [f32]) {
+    let n = bmet.len() as f32;
+    let avg = bmet.iter().sum::<f32>() / n;
+    let avg2 = bmet.iter().map(|b| b * b).sum::<f32>() / n;
+    let var = avg2 - avg * avg;
+    let sig = if var > 0.0 { var.sqrt() } else { avg2.sqrt() };
+    if sig > 0.0 {
+        bmet.iter_mut().for_each(|b| *b /= sig);
+    }
+}
+
+/// Phase advance per symbol left by a residual frequency error, measured
+/// on the Costas arrays.
+///
+/// A coarse estimate comes from the phase step between adjacent Costas
+/// symbols, whose tones are known. The 36-symbol spacing of the arrays
+/// then refines it: with the coarse drift removed, the remaining phase
+/// difference between arrays is small enough to unwrap, and divided by 36
+/// gives the rest. Continuous-phase FSK keeps the phase of every tone
+/// aligned at symbol boundaries, so this is the only drift to undo.
+fn phase_drift(cs: &[[Complex32; 8]]) -> f32 {
+    let expected = |k: usize, pos: usize| cs[pos + k][COSTAS_PATTERN[k] as usize];
+
+    let coarse = COSTAS_POSITIONS
+        .iter()
+        .flat_map(|&pos| (1..COSTAS_PATTERN.len()).map(move |k| (k, pos)))
+        .map(|(k, pos)| expected(k, pos) * expected(k - 1, pos).conj())
+        .sum::<Complex32>()
+        .arg();
+
+    let array_phasor = |pos: usize| -> Complex32 {
+        (0..COSTAS_PATTERN.len())
+            .map(|k| expected(k, pos) * Complex32::from_polar(1.0, -coarse * (pos + k) as f32))
+            .sum()
+    };
+    let phasors: Vec<Complex32> = COSTAS_POSITIONS.iter().map(|&p| array_phasor(p)).collect();
+    let fine = phasors
+        .windows(2)
+        .map(|w| w[1] * w[0].conj())
+        .sum::<Complex32>()
+        .arg();
+    coarse + fine / (COSTAS_POSITIONS[1] - COSTAS_POSITIONS[0]) as f32
+}
+
+/// Remove a per-symbol phase drift from the complex tone amplitudes.
+fn derotate(cs: &[[Complex32; 8]], drift: f32) -> Vec<[Complex32; 8]> {
+    cs.iter()
+        .enumerate()
+        .map(|(k, row)| {
+            let rot = Complex32::from_polar(1.0, -drift * k as f32);
+            row.map(|c| c * rot)
+        })
+        .collect()
+}
+
+/// Relative margin below the largest squared magnitude within which a
+/// combination may still have the largest rounded magnitude. `hypot` is
+/// accurate to about 1e-7, so this leaves a wide safety factor.
+const TIE_MARGIN: f64 = 1e-5;
+
+/// Bits per group at the largest `nsym`
+const MAX_GROUP_BITS: usize = 9;
+
+/// Bit metrics from coherently combining `nsym` consecutive symbols.
+///
+/// For each group, every combination of tones is scored by the magnitude
+/// of the summed complex amplitudes; a bit's metric is the best score with
+/// the bit set minus the best with it clear. Returns (metrics, metrics
+/// normalized by the best score), the latter only meaningful for nsym = 1.
+///
+/// The magnitude is a `hypot` call and the costliest step, so the maxima
+/// are first found on exact squared magnitudes: halving the combinations
+/// by pairwise maxima yields each bit's two maxima in turn, lowest bit
+/// first. Only combinations within [`TIE_MARGIN`] of one of those then
+/// have their magnitude taken, which gives the same metrics as scoring
+/// every combination.
+fn bit_metrics(cs: &[[Complex32; 8]], nsym: usize) -> (Vec<f32>, Vec<f32>) {
+    let mut bmet = vec![0.0f32; ldpc::N];
+    let mut bmetd = vec![0.0f32; ldpc::N];
+    let size = 1 << (3 * nsym);
+    let mut sums = vec![Complex32::new(0.0, 0.0); size];
+    let mut next = vec![Complex32::new(0.0, 0.0); size];
+    let mut power = vec![0.0f64; size];
+    let mut level = vec![0.0f64; size];
+
+    for half in 0..2 {
+        let mut k = 0;
+        while k < HALF_SYMBOLS {
+            let n = nsym.min(HALF_SYMBOLS - k);
+            let nt = 1usize << (3 * n);
+            let nbits = 3 * n;
+
+            // Sums over every combination, extended one symbol at a time
+            // with that symbol's amplitudes in Gray-decoded order, so bit
+            // `nbits - 1 - ib` of the index is bit `ib` of the group
+            sums[0] = Complex32::new(0.0, 0.0);
+            for s in 0..n {
+                let row = &cs[data_symbol_index(half * HALF_SYMBOLS + k + s)];
+                for j in 0..1 << (3 * s) {
+                    let z = sums[j];
+                    for v in 0..8 {
+                        next[8 * j + v] = z + row[gray::to_tone(v as u8) as usize];
+                    }
+                }
+                core::mem::swap(&mut sums, &mut next);
+            }
+            for (p, z) in power[..nt].iter_mut().zip(&sums[..nt]) {
+                let (re, im) = (z.re as f64, z.im as f64);
+                *p = re * re + im * im;
+            }
+
+            // thresholds[m][b]: the largest power among combinations with
+            // index bit m equal to b, less the tie margin
+            let mut thresholds = [[0.0f64; 2]; MAX_GROUP_BITS];
+            level[..nt].copy_from_slice(&power[..nt]);
+            let mut len = nt;
+            for threshold in &mut thresholds[..nbits] {
+                len /= 2;
+                let mut peaks = [f64::MIN; 2];
+                for j in 0..len {
+                    let (clear, set) = (level[2 * j], level[2 * j + 1]);
+                    peaks[0] = peaks[0].max(clear);
+                    peaks[1] = peaks[1].max(set);
+                    level[j] = clear.max(set);
+                }
+                *threshold = peaks.map(|p| p * (1.0 - TIE_MARGIN));
+            }
+
+            let floor = thresholds[..nbits]
+                .iter()
+                .flatten()
+                .fold(f64::MAX, |m, &t| m.min(t));
+            let mut best = [[f32::MIN; 2]; MAX_GROUP_BITS];
+            for (i, &p) in power[..nt].iter().enumerate() {
+                if p < floor {
+                    continue;
+                }
+                let magnitude = sums[i].norm();
+                for (m, (best, threshold)) in best.iter_mut().zip(&thresholds[..nbits]).enumerate()
+                {
+                    let b = (i >> m) & 1;
+                    if p >= threshold[b] {
+                        best[b] = best[b].max(magnitude);
+                    }
+                }
+            }
+
+            let first_bit = 3 * (half * HALF_SYMBOLS + k);
+            for ib in 0..nbits {
+                let [max0, max1] = best[nbits - 1 - ib];
+                let bm = max1 - max0;
+                bmet[first_bit + ib] = bm;
+                let den = max1.max(max0);
+                bmetd[first_bit + ib] = if den > 0.0 { bm / den } else { 0.0 };
+            }
+            k += n;
+        }
+    }
+
+    (bmet, bmetd)
+}
+
+/// Demodulate a candidate and compute all four LLR variants.
+pub fn extract_symbols_all_llr(
+    signal: &[f32],
+    candidate: &Candidate,
+) -> Result<SymbolLlrs, String> {
+    extract_symbols_all_llr_with(&Downsampler::new(signal)?, candidate)
+}
+
+/// [`extract_symbols_all_llr`] with the window already transformed.
+pub fn extract_symbols_all_llr_with(
+    downsampler: &Downsampler,
+    candidate: &Candidate,
+) -> Result<SymbolLlrs, String> {
+    let (cd, rate) = downsampler.downsample(candidate.frequency)?;
+    let nsps = candidate.samples_per_symbol(rate);
+    let i0 = candidate.downsample_start_offset(rate);
+
+    let cs = extract_symbols_impl(&cd, i0, nsps);
+    let s8: Vec<[f32; 8]> = cs
+        .iter()
+        .map(|row| core::array::from_fn(|t| row[t].norm()))
+        .collect();
+    let nsync = count_sync(&s8);
+    #[cfg(feature = "tracing")]
+    trace_symbols(candidate, &s8, nsync);
+
+    let (mut llra, mut llrd) = bit_metrics(&cs, 1);
+    // Coherent combining needs the symbols in phase; single-symbol metrics
+    // only use magnitudes and are unaffected
+    let drift = phase_drift(&cs);
+    let tracked = derotate(&cs, drift);
+    let (mut llrb, _) = bit_metrics(&tracked, 2);
+    let (mut llrc, _) = bit_metrics(&tracked, 3);
+    for llr in [&mut llra, &mut llrb, &mut llrc, &mut llrd] {
+        normalize_bmet(llr);
+        llr.iter_mut().for_each(|l| *l *= LLR_SCALE);
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        freq = candidate.frequency,
+        dt = candidate.time_offset,
+        nsync,
+        mean_abs_llr = ?[&llra, &llrb, &llrc, &llrd].map(|l| mean_abs(l)),
+        "extracted LLRs"
+    );
+
+    Ok(SymbolLlrs {
+        llra,
+        llrb,
+        llrc,
+        llrd,
+        s8,
+        nsync,
+        freq_error: drift / (2.0 * PI * SYMBOL_DURATION),
+    })
+}
+
+/// Per-symbol tone amplitudes and Costas validation, at trace level.
+#[cfg(feature = "tracing")]
+fn trace_symbols(candidate: &Candidate, s8: &[[f32; 8]], nsync: usize) {
+    if !tracing::enabled!(tracing::Level::TRACE) {
+        return;
+    }
+    for (k, row) in s8.iter().enumerate() {
+        let best = (0..8).fold(0, |b, t| if row[t] > row[b] { t } else { b });
+        let costas = COSTAS_POSITIONS
+            .iter()
+            .find(|&&pos| (pos..pos + COSTAS_PATTERN.len()).contains(&k))
+            .map(|&pos| COSTAS_PATTERN[k - pos] as usize);
+        tracing::trace!(
+            freq = candidate.frequency,
+            symbol = k,
+            best,
+            expected = ?costas,
+            amplitudes = ?row,
+            "symbol"
+        );
+    }
+    tracing::trace!(
+        freq = candidate.frequency,
+        dt = candidate.time_offset,
+        "{}/{} Costas symbols on the expected tone",
+        nsync,
+        COSTAS_POSITIONS.len() * COSTAS_PATTERN.len()
+    );
+}
+
+/// Mean magnitude of a set of LLRs.
+#[cfg(feature = "tracing")]
+fn mean_abs(llr: &[f32]) -> f32 {
+    llr.iter().map(|l| l.abs()).sum::<f32>() / llr.len() as f32
+}
+
+/// Demodulate a candidate, returning the nsym = 1 LLRs and the Costas
+/// sync count.
+pub fn extract_symbols(signal: &[f32], candidate: &Candidate) -> Result<(Vec<f32>, usize), String> {
+    extract_symbols_all_llr(signal, candidate).map(|l| (l.llra, l.nsync))
+}
+
+/// Residual frequency error of a candidate in Hz, from the phase drift
+/// across its Costas arrays.
+///
+/// Add it to the candidate's frequency to land on the signal. The estimate
+/// is only meaningful for a candidate in sync (a high `nsync`) and within
+/// about ±3 Hz of the signal, where the drift between adjacent symbols
+/// stays under half a cycle.
+pub fn estimate_frequency_from_phase(signal: &[f32], candidate: &Candidate) -> Result<f32, String> {
+    extract_symbols_all_llr(signal, candidate).map(|l| l.freq_error)
+}
+
+/// SNR in dB (2500 Hz reference bandwidth) of a decoded signal.
+///
+/// Compares the power on each transmitted tone with the power on a tone
+/// four places away, which carries only noise.
+pub fn calculate_snr(s8: &[[f32; 8]], tones: &[u8]) -> f32 {
+    snr_from_ratio(off_tone_ratio(s8, tones))
+}
+
+/// The off-tone SNR in dB exactly as WSJT-X's `ft8b` computes it before
+/// flooring it at −24 dB: `10 log10(ratio) − 27`, about 1 dB below
+/// [`calculate_snr`]'s 2500 Hz scaling.
+pub(crate) fn wsjtx_off_tone_snr(s8: &[[f32; 8]], tones: &[u8]) -> f32 {
+    ratio_to_db(off_tone_ratio(s8, tones)) - 27.0
+}
+
+/// Signal-to-noise power ratio on the transmitted tones, against a tone
+/// four places away.
+fn off_tone_ratio(s8: &[[f32; 8]], tones: &[u8]) -> f32 {
+    let (xsig, xnoi) = s8
+        .iter()
+        .zip(tones)
+        .fold((0.0, 0.0), |(sig, noi), (row, &tone)| {
+            let off = (tone as usize + 4) % 7;
+            (sig + row[tone as usize].powi(2), noi + row[off].powi(2))
+        });
+    if xnoi > 0.0 {
+        xsig / xnoi - 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Expected noise power in one `s8` tone bin per unit of spectrogram
+/// noise baseline.
+///
+/// For white noise of variance σ² at 12 kHz, a spectrogram bin (an
+/// `NSPS`-sample DFT of the input scaled by `FAC`) holds `FAC² · NSPS · σ²`.
+/// The downsampler's FFT pair, normalized by `1/√(NFFT_IN · NFFT_OUT)`,
+/// preserves the noise density: within the passband the 187.5 Hz complex
+/// samples spanning the window carry noise of the same variance σ² (those
+/// from the zero padding to `NFFT_IN` carry none). A tone bin, a
+/// 30-sample DFT, therefore holds `30 · σ²`.
+fn baseline_to_tone_bin() -> f32 {
+    let nsps_ds = (DOWNSAMPLE_RATE * SYMBOL_DURATION).round();
+    nsps_ds / (FAC * FAC * NSPS as f32)
+}
+
+/// SNR in dB (2500 Hz reference bandwidth) of a decoded signal, against
+/// the noise level of the spectrogram around it.
+///
+/// `baseline` is the spectrogram's noise power per bin from
+/// [`noise_baseline`](super::noise_baseline), scaled to the power of
+/// noise in a tone bin. Unlike [`calculate_snr`] it does not rely on the
+/// off tones being free of the signal's own leakage or of other signals.
+pub fn calculate_snr_baseline(s8: &[[f32; 8]], tones: &[u8], baseline: f32) -> f32 {
+    let xsig: f32 = s8
+        .iter()
+        .zip(tones)
+        .map(|(row, &tone)| row[tone as usize].powi(2))
+        .sum();
+    let xnoi = baseline * baseline_to_tone_bin() * tones.len().min(s8.len()) as f32;
+    let ratio = if xnoi > 0.0 { xsig / xnoi - 1.0 } else { 0.0 };
+    snr_from_ratio(ratio)
+}
+
+/// Signal-to-noise power ratio in a tone bin, in dB in the 2500 Hz
+/// reference bandwidth, floored at −24 dB as WSJT-X reports.
+fn snr_from_ratio(ratio: f32) -> f32 {
+    let snr = ratio_to_db(ratio) - 10.0 * (SNR_REFERENCE_BANDWIDTH / TONE_SPACING).log10();
+    snr.max(-24.0)
+}
+
+/// A power ratio in dB, with ratios up to 0.1 counted as 0.001 as in
+/// WSJT-X.
+fn ratio_to_db(ratio: f32) -> f32 {
+    let ratio = if ratio > 0.1 { ratio } else { 0.001 };
+    10.0 * ratio.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::Awgn;
+    use crate::message::tx_cq;
+    use crate::modulation::BandSynthesizer;
+    use crate::sync::SyncMetric;
+
+    /// [`bit_metrics`] written directly from its definition: every
+    /// combination tested against every bit's mask.
+    pub(super) fn reference_bit_metrics(
+        cs: &[[Complex32; 8]],
+        nsym: usize,
+    ) -> (Vec<f32>, Vec<f32>) {
+        let mut bmet = vec![0.0f32; ldpc::N];
+        let mut bmetd = vec![0.0f32; ldpc::N];
+
+        for half in 0..2 {
+            let mut k = 0;
+            while k < HALF_SYMBOLS {
+                let n = nsym.min(HALF_SYMBOLS - k);
+                let nt = 1usize << (3 * n);
+                let syms: Vec<usize> = (0..n)
+                    .map(|s| data_symbol_index(half * HALF_SYMBOLS + k + s))
+                    .collect();
+
+                let s2: Vec<f32> = (0..nt)
+                    .map(|i| {
+                        syms.iter()
+                            .enumerate()
+                            .map(|(s, &ks)| {
+                                let value = (i >> (3 * (n - 1 - s))) & 7;
+                                cs[ks][gray::to_tone(value as u8) as usize]
+                            })
+                            .sum::<Complex32>()
+                            .norm()
+                    })
+                    .collect();
+
+                let first_bit = 3 * (half * HALF_SYMBOLS + k);
+                for ib in 0..3 * n {
+                    let mask = 1 << (3 * n - 1 - ib);
+                    let (mut max1, mut max0) = (f32::MIN, f32::MIN);
+                    for (i, &v) in s2.iter().enumerate() {
+                        if i & mask != 0 {
+                            max1 = max1.max(v);
+                        } else {
+                            max0 = max0.max(v);
+                        }
+                    }
+                    let bm = max1 - max0;
+                    bmet[first_bit + ib] = bm;
+                    let den = max1.max(max0);
+                    bmetd[first_bit + ib] = if den > 0.0 { bm / den } else { 0.0 };
+                }
+                k += n;
+            }
+        }
+
+        (bmet, bmetd)
+    }
+
+    #[test]
+    fn test_clean_signal_llrs() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1500.0, 0.0, 1.0)
+            .synthesize();
+        let candidate = Candidate {
+            frequency: 1500.0,
+            time_offset: 0.0,
+            sync_power: 10.0,
+            sync_metric: SyncMetric::AllCostas,
+        };
+        let llrs = extract_symbols_all_llr(&signal, &candidate).unwrap();
+        assert_eq!(llrs.nsync, 21);
+
+        // Hard decisions reproduce the transmitted tones
+        for (k, row) in llrs.s8.iter().enumerate() {
+            let best = (0..8).fold(0, |b, t| if row[t] > row[b] { t } else { b });
+            assert_eq!(best as u8, tones[k], "symbol {}", k);
+        }
+        for llr in [&llrs.llra, &llrs.llrb, &llrs.llrc, &llrs.llrd] {
+            assert_eq!(llr.len(), 174);
+        }
+        // Noise-free, so only GFSK leakage limits the estimate
+        assert!(calculate_snr(&llrs.s8, &tones) > 0.0);
+    }
+
+    #[test]
+    fn test_phase_drift_tracks_frequency_error() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1500.0, 0.0, 1.0)
+            .synthesize();
+        let downsampler = Downsampler::new(&signal).unwrap();
+        for error in [-1.5f32, 0.0, 0.4, 1.0] {
+            let (cd, rate) = downsampler.downsample(1500.0 + error).unwrap();
+            let cs = extract_symbols_impl(&cd, (0.5 * rate).round() as i32, 30);
+            // Tuned `error` Hz high, the signal sits at -error Hz
+            let expected = -2.0 * PI * error * 0.16;
+            let drift = phase_drift(&cs);
+            assert!((drift - expected).abs() < 0.05, "{} Hz: {}", error, drift);
+        }
+    }
+
+    #[test]
+    fn test_coherent_metrics_gain_with_frequency_error() {
+        // With 1.2 Hz of residual frequency error the symbols drift 1.2 rad
+        // apart; once tracked, combining them still beats single symbols
+        let tones = tx_cq("K1ABC", "FN42").unwrap();
+        let mut decoded = [0; 3];
+        for seed in 0..12 {
+            let f0 = 800.0 + 101.0 * seed as f32;
+            let signal = BandSynthesizer::default()
+                .add_signal(&tones, f0, 0.0, 1.0)
+                .add_channel(Box::new(Awgn::new(-20.0, 40 + seed)))
+                .synthesize();
+            let candidate = Candidate {
+                frequency: f0 + 1.2,
+                time_offset: 0.0,
+                sync_power: 1.0,
+                sync_metric: SyncMetric::AllCostas,
+            };
+            let llrs = extract_symbols_all_llr(&signal, &candidate).unwrap();
+            for (count, llr) in decoded.iter_mut().zip([&llrs.llra, &llrs.llrb, &llrs.llrc]) {
+                if ldpc::decode_hybrid(llr, ldpc::DecodeDepth::BpOsdHybrid).is_some() {
+                    *count += 1;
+                }
+            }
+        }
+        let [nsym1, nsym2, nsym3] = decoded;
+        assert!(nsym2 > nsym1 && nsym3 > nsym1, "{:?}", decoded);
+    }
+
+    #[test]
+    fn test_tones_do_not_leak_into_neighbours() {
+        let nsps = 30;
+        for tone in 0..8 {
+            let cd: Vec<Complex32> = (0..NUM_SYMBOLS * nsps)
+                .map(|n| {
+                    Complex32::from_polar(1.0, 2.0 * PI * ((tone * n) % nsps) as f32 / nsps as f32)
+                })
+                .collect();
+            let cs = extract_symbols_impl(&cd, 0, nsps);
+            for row in &cs {
+                for (t, c) in row.iter().enumerate() {
+                    let expected = if t == tone { nsps as f32 } else { 0.0 };
+                    assert!(
+                        (c.norm() - expected).abs() < 1e-3,
+                        "tone {} bin {}",
+                        tone,
+                        t
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bit_metrics_match_reference() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1500.0, 0.0, 1.0)
+            .add_channel(Box::new(Awgn::new(-16.0, 8)))
+            .synthesize();
+        let downsampler = Downsampler::new(&signal).unwrap();
+        // On the signal, off it by a fraction of a tone, on noise only, and
+        // started so late that the last symbols are zero
+        for (frequency, start) in [(1500.0, 0.5), (1500.7, 0.5), (1300.0, 0.5), (1500.0, 4.0)] {
+            let (cd, rate) = downsampler.downsample(frequency).unwrap();
+            let cs = extract_symbols_impl(&cd, (start * rate).round() as i32, 30);
+            for nsym in 1..=3 {
+                let bits = |(a, b): (Vec<f32>, Vec<f32>)| {
+                    a.iter().chain(&b).map(|v| v.to_bits()).collect::<Vec<_>>()
+                };
+                assert_eq!(
+                    bits(bit_metrics(&cs, nsym)),
+                    bits(reference_bit_metrics(&cs, nsym)),
+                    "{} Hz, nsym {}",
+                    frequency,
+                    nsym
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,408 @@
+//! Fine time/frequency alignment of a candidate (WSJT-X `sync8d`)
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use num_complex::Complex32;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::{Candidate, Downsampler, SyncMetric, DOWNSAMPLE_RATE, MAX_LAG, TSTEP};
+use crate::modulation::{NOMINAL_START, SYMBOL_DURATION};
+use crate::symbol::{COSTAS_PATTERN, COSTAS_POSITIONS};
+
+/// Time refinement after the frequency search, in downsampled samples
+const FINE_TIME_STEPS: i32 = 4;
+
+/// Step of the frequency search
+const FREQ_STEP_HZ: f32 = 0.5;
+
+/// Second frequency search at the refined time, in `FREQ_STEP_HZ` steps
+/// either side of the first estimate
+const RECHECK_FREQ_STEPS: i32 = 2;
+
+/// Time step of the open search in [`locate_candidate_near`], in
+/// downsampled samples
+const SEARCH_TIME_STEP: usize = 4;
+
+/// Frequency step of the open search in [`locate_candidate_near`], in Hz
+const SEARCH_FREQ_STEP_HZ: f32 = 1.0;
+
+/// Phase-coherent Costas correlation of downsampled data.
+///
+/// `i0` is the sample where symbol 0 starts and `freq_offset` a frequency
+/// error in Hz removed before correlating. Symbols falling outside `cd`
+/// are skipped.
+pub(crate) fn sync_downsampled(cd: &[Complex32], i0: i32, freq_offset: f32, rate: f32) -> f32 {
+    let nsps = (rate * SYMBOL_DURATION).round() as usize;
+    let dphi = 2.0 * PI * freq_offset / rate;
+    let mut sync = 0.0;
+
+    for &pos in COSTAS_POSITIONS.iter() {
+        for (k, &tone) in COSTAS_PATTERN.iter().enumerate() {
+            let start = i0 + ((pos + k) * nsps) as i32;
+            if start < 0 || start as usize + nsps > cd.len() {
+                continue;
+            }
+            let start = start as usize;
+            let w = 2.0 * PI * tone as f32 / nsps as f32;
+            let z: Complex32 = cd[start..start + nsps]
+                .iter()
+                .enumerate()
+                .map(|(n, &c)| {
+                    let phase = w * n as f32 + dphi * (start + n) as f32;
+                    c * Complex32::from_polar(1.0, -phase)
+                })
+                .sum();
+            sync += z.norm_sqr();
+        }
+    }
+    sync
+}
+
+/// Fraction of a candidate's energy in the Costas windows that matches
+/// the Costas waveform, from 0 (noise only) to 1 (a clean signal).
+///
+/// `cd` is the output of [`Downsampler::downsample`] at the candidate's
+/// frequency. Each 7-symbol array is correlated as one continuous-phase
+/// waveform, so unlike the per-symbol search in [`fine_sync`] the phase
+/// relation between its symbols counts; the three arrays are combined in
+/// power, which keeps the score tolerant of the residual frequency error
+/// after fine sync. Being normalized, it ranks candidates by how cleanly
+/// they match rather than by raw level. Arrays extending past either end
+/// of `cd` are skipped.
+pub fn matched_filter_score(cd: &[Complex32], candidate: &Candidate) -> f32 {
+    let nsps = candidate.samples_per_symbol(DOWNSAMPLE_RATE);
+    let i0 = candidate.downsample_start_offset(DOWNSAMPLE_RATE);
+    let len = COSTAS_PATTERN.len() * nsps;
+
+    // Every tone completes a whole number of cycles per symbol, so the
+    // continuous-phase reference restarts at phase 0 on each symbol
+    let reference: Vec<Complex32> = COSTAS_PATTERN
+        .iter()
+        .flat_map(|&tone| {
+            (0..nsps).map(move |n| {
+                Complex32::from_polar(1.0, -2.0 * PI * (tone as usize * n) as f32 / nsps as f32)
+            })
+        })
+        .collect();
+
+    let (matched, energy) = COSTAS_POSITIONS
+        .iter()
+        .filter_map(|&pos| {
+            let start = i0 + (pos * nsps) as i32;
+            if start < 0 || start as usize + len > cd.len() {
+                return None;
+            }
+            let block = &cd[start as usize..start as usize + len];
+            let z: Complex32 = block.iter().zip(&reference).map(|(x, r)| x * r).sum();
+            let e: f32 = block.iter().map(|x| x.norm_sqr()).sum();
+            Some((z.norm_sqr(), e))
+        })
+        .fold((0.0, 0.0), |(m, e), (bm, be)| (m + bm, e + be));
+
+    if energy > 0.0 {
+        matched / (len as f32 * energy)
+    } else {
+        0.0
+    }
+}
+
+/// Offset of the vertex of the parabola through three equally spaced
+/// values around a peak, in grid steps from the middle one and clamped to
+/// ±0.5. Zero when the middle value is not a maximum.
+fn parabolic_peak(left: f32, peak: f32, right: f32) -> f32 {
+    let curvature = left - 2.0 * peak + right;
+    if curvature >= 0.0 {
+        return 0.0;
+    }
+    (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+}
+
+/// Settings of the fine time and frequency search.
+///
+/// Both windows are centred on the candidate. The defaults reach just
+/// past one step of the coarse search each way (a 3.125 Hz bin and a
+/// 40 ms step), so a coarse estimate off by a whole step is still
+/// recovered. Anything wider starts to reach into a neighbouring signal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FineSearch {
+    /// Half-width of the frequency search, in Hz, searched in 0.5 Hz steps
+    pub freq_hz: f32,
+    /// Half-width of the initial time search, in seconds
+    pub time: f32,
+}
+
+impl Default for FineSearch {
+    fn default() -> Self {
+        FineSearch {
+            freq_hz: 3.5,
+            time: 0.05,
+        }
+    }
+}
+
+/// Refine a candidate's DT and frequency with the default [`FineSearch`].
+///
+/// The candidate is mixed to baseband, the symbol-0 start is searched over
+/// ±50 ms (10 samples), the frequency over ±3.5 Hz in 0.5 Hz steps, and
+/// the time again over ±4 samples at the best frequency. Both peaks are
+/// then interpolated between grid points with a parabola through the best
+/// point and its neighbours, so the estimates are not limited to the
+/// 0.5 Hz and 5 ms grids.
+pub fn fine_sync(signal: &[f32], candidate: &Candidate) -> Result<Candidate, String> {
+    fine_sync_with(&Downsampler::new(signal)?, candidate)
+}
+
+/// [`fine_sync`] with the window already transformed, for refining many
+/// candidates from one window.
+pub fn fine_sync_with(
+    downsampler: &Downsampler,
+    candidate: &Candidate,
+) -> Result<Candidate, String> {
+    fine_sync_search(downsampler, candidate, &FineSearch::default())
+}
+
+/// [`fine_sync_with`] over the windows of `search`.
+pub fn fine_sync_search(
+    downsampler: &Downsampler,
+    candidate: &Candidate,
+    search: &FineSearch,
+) -> Result<Candidate, String> {
+    let (cd, rate) = downsampler.downsample(candidate.frequency)?;
+    let i0 = candidate.downsample_start_offset(rate);
+    let time_steps = (search.time.max(0.0) * rate).ceil() as i32;
+    let freq_steps = (search.freq_hz.max(0.0) / FREQ_STEP_HZ).ceil() as i32;
+
+    let best_time = |center: i32, steps: i32, freq: f32| {
+        (center - steps..=center + steps)
+            .map(|i| (i, sync_downsampled(&cd, i, freq, rate)))
+            .fold((center, f32::MIN), |b, c| if c.1 > b.1 { c } else { b })
+            .0
+    };
+
+    // Best frequency offset within `steps` grid steps of `center` at
+    // start `i`, interpolated when the peak is inside the grid
+    let best_freq = |i: i32, center: f32, steps: i32| {
+        let sync: Vec<f32> = (-steps..=steps)
+            .map(|k| sync_downsampled(&cd, i, center + k as f32 * FREQ_STEP_HZ, rate))
+            .collect();
+        let kbest = (0..sync.len()).fold(0, |b, k| if sync[k] > sync[b] { k } else { b });
+        let mut df = center + (kbest as i32 - steps) as f32 * FREQ_STEP_HZ;
+        if kbest > 0 && kbest + 1 < sync.len() {
+            df += parabolic_peak(sync[kbest - 1], sync[kbest], sync[kbest + 1]) * FREQ_STEP_HZ;
+        }
+        df
+    };
+
+    let ibest = best_time(i0, time_steps, 0.0);
+    let dfbest = best_freq(ibest, 0.0, freq_steps);
+    let ibest = best_time(ibest, FINE_TIME_STEPS, dfbest);
+    // The first frequency search ran at a start found off-tune; look
+    // again either side now the start is right, within the window
+    let limit = freq_steps as f32 * FREQ_STEP_HZ;
+    let dfbest = best_freq(ibest, dfbest, RECHECK_FREQ_STEPS).clamp(-limit, limit);
+
+    let sync_at = |i: i32| sync_downsampled(&cd, i, dfbest, rate);
+    let time_fraction = parabolic_peak(sync_at(ibest - 1), sync_at(ibest), sync_at(ibest + 1));
+
+    Ok(Candidate {
+        frequency: candidate.frequency + dfbest,
+        time_offset: (ibest as f32 + time_fraction) / rate - NOMINAL_START,
+        sync_power: candidate.sync_power,
+        sync_metric: candidate.sync_metric,
+    })
+}
+
+/// Find the best-synchronized signal near a known frequency without a
+/// coarse candidate.
+///
+/// Tone 0 is searched within ±`window_hz` of `frequency` in 1 Hz steps and
+/// DT over the full coarse lag range, then the best point is refined with
+/// [`fine_sync`]. The returned `sync_power` is the peak correlation
+/// relative to the median over the search grid.
+pub fn locate_candidate(
+    signal: &[f32],
+    frequency: f32,
+    window_hz: f32,
+) -> Result<Candidate, String> {
+    locate_candidate_near(signal, frequency, 0.0, window_hz, MAX_LAG as f32 * TSTEP)
+}
+
+/// [`locate_candidate`] with DT searched only within ±`window_s` of
+/// `time_offset`, for a signal whose position is roughly known.
+pub fn locate_candidate_near(
+    signal: &[f32],
+    frequency: f32,
+    time_offset: f32,
+    window_hz: f32,
+    window_s: f32,
+) -> Result<Candidate, String> {
+    let downsampler = Downsampler::new(signal)?;
+    let (cd, rate) = downsampler.downsample(frequency)?;
+    let window_s = window_s.max(0.0);
+    let first = ((NOMINAL_START + time_offset - window_s) * rate).round() as i32;
+    let last = ((NOMINAL_START + time_offset + window_s) * rate).round() as i32;
+    let nfreq = (window_hz.max(0.0) / SEARCH_FREQ_STEP_HZ).round() as i32;
+
+    let mut grid = Vec::new();
+    for k in -nfreq..=nfreq {
+        let df = k as f32 * SEARCH_FREQ_STEP_HZ;
+        for i0 in (first..=last).step_by(SEARCH_TIME_STEP) {
+            grid.push((i0, df, sync_downsampled(&cd, i0, df, rate)));
+        }
+    }
+    let (ibest, dfbest, peak) =
+        grid.iter()
+            .copied()
+            .fold((0, 0.0, f32::MIN), |b, c| if c.2 > b.2 { c } else { b });
+    let mut values: Vec<f32> = grid.iter().map(|g| g.2).collect();
+    values.sort_by(f32::total_cmp);
+    let median = values[values.len() / 2];
+
+    let coarse = Candidate {
+        frequency: frequency + dfbest,
+        time_offset: ibest as f32 / rate - NOMINAL_START,
+        sync_power: if median > 0.0 { peak / median } else { 0.0 },
+        sync_metric: SyncMetric::AllCostas,
+    };
+    fine_sync_with(&downsampler, &coarse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::tx_cq;
+    use crate::modulation::BandSynthesizer;
+
+    #[test]
+    fn test_fine_sync_refines_offsets() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1203.0, 0.3, 1.0)
+            .synthesize();
+        let rough = Candidate {
+            frequency: 1201.5,
+            time_offset: 0.33,
+            sync_power: 10.0,
+            sync_metric: SyncMetric::AllCostas,
+        };
+        let refined = fine_sync(&signal, &rough).unwrap();
+        assert!((refined.frequency - 1203.0).abs() <= 0.25, "{:?}", refined);
+        assert!((refined.time_offset - 0.3).abs() < 0.006, "{:?}", refined);
+    }
+
+    #[test]
+    fn test_fine_sync_interpolates_between_grid_points() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1500.3, 0.3, 1.0)
+            .synthesize();
+        let rough = Candidate {
+            frequency: 1500.0,
+            time_offset: 0.3,
+            sync_power: 10.0,
+            sync_metric: SyncMetric::AllCostas,
+        };
+        let refined = fine_sync(&signal, &rough).unwrap();
+        assert!((refined.frequency - 1500.3).abs() < 0.1, "{:?}", refined);
+        assert!((refined.time_offset - 0.3).abs() < 0.003, "{:?}", refined);
+
+        assert_eq!(parabolic_peak(1.0, 2.0, 1.0), 0.0);
+        assert!((parabolic_peak(1.0, 2.0, 1.5) - 1.0 / 6.0).abs() < 1e-6);
+        assert_eq!(parabolic_peak(2.0, 1.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn test_fine_search_covers_a_coarse_bin() {
+        // A stronger neighbour just above must not pull the search over
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("N0YPR", "DM42").unwrap(), 1500.0, 0.3, 0.5)
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1550.0, 0.2, 1.0)
+            .synthesize();
+        let downsampler = Downsampler::new(&signal).unwrap();
+        // Almost a whole coarse bin and step off
+        let rough = Candidate {
+            frequency: 1503.0,
+            time_offset: 0.34,
+            sync_power: 10.0,
+            sync_metric: SyncMetric::AllCostas,
+        };
+        let refined = fine_sync_with(&downsampler, &rough).unwrap();
+        assert!((refined.frequency - 1500.0).abs() < 0.15, "{:?}", refined);
+        assert!((refined.time_offset - 0.3).abs() < 0.006, "{:?}", refined);
+
+        // The previous ±2.5 Hz search stops short of the signal
+        let narrow = FineSearch {
+            freq_hz: 2.5,
+            ..FineSearch::default()
+        };
+        let refined = fine_sync_search(&downsampler, &rough, &narrow).unwrap();
+        assert!((refined.frequency - 1500.0).abs() > 0.4, "{:?}", refined);
+    }
+
+    #[test]
+    fn test_matched_filter_ranks_by_cleanliness() {
+        use crate::channel::Awgn;
+        use crate::sync::coarse_sync;
+        use crate::sync::CandidateSearch;
+
+        // Four signals 6 dB apart, the strongest at -6 dB
+        let calls = ["K1ABC", "W9XYZ", "N0YPR", "G4ABC"];
+        let freqs = [700.0, 1200.0, 1700.0, 2200.0];
+        let mut synth = BandSynthesizer::default();
+        for (i, (call, &f)) in calls.iter().zip(&freqs).enumerate() {
+            let tones = tx_cq(call, "FN42").unwrap();
+            synth.add_signal(&tones, f, 0.1 * i as f32, 0.5f32.powi(i as i32));
+        }
+        let signal = synth
+            .add_channel(Box::new(Awgn::new(-6.0, 19)))
+            .synthesize();
+
+        let candidates = coarse_sync(&signal, &CandidateSearch::default()).unwrap();
+        let downsampler = Downsampler::new(&signal).unwrap();
+        let scored: Vec<(usize, f32, f32)> = freqs
+            .iter()
+            .enumerate()
+            .map(|(i, &f)| {
+                let c = candidates
+                    .iter()
+                    .filter(|c| (c.frequency - f).abs() < 4.0)
+                    .max_by(|a, b| a.sync_power.total_cmp(&b.sync_power))
+                    .unwrap();
+                let refined = fine_sync_with(&downsampler, c).unwrap();
+                let (cd, _) = downsampler.downsample(refined.frequency).unwrap();
+                (i, c.sync_power, matched_filter_score(&cd, &refined))
+            })
+            .collect();
+
+        let order = |key: fn(&(usize, f32, f32)) -> f32| {
+            let mut s = scored.clone();
+            s.sort_by(|a, b| key(b).total_cmp(&key(a)));
+            s.iter().map(|s| s.0).collect::<Vec<_>>()
+        };
+        let inversions = |order: &[usize]| {
+            (0..order.len())
+                .flat_map(|i| (i + 1..order.len()).map(move |j| (i, j)))
+                .filter(|&(i, j)| order[i] > order[j])
+                .count()
+        };
+        let by_mf = order(|s| s.2);
+        let by_sync = order(|s| s.1);
+        assert_eq!(by_mf, [0, 1, 2, 3], "{:?}", scored);
+        assert!(inversions(&by_mf) <= inversions(&by_sync), "{:?}", scored);
+        assert!(scored[0].2 > 0.5 && scored[0].2 <= 1.0, "{:?}", scored);
+    }
+
+    #[test]
+    fn test_locate_candidate_without_coarse_sync() {
+        let tones = tx_cq("N0YPR", "DM42").unwrap();
+        let signal = BandSynthesizer::default()
+            .add_signal(&tones, 1503.0, 1.2, 1.0)
+            .synthesize();
+        let found = locate_candidate(&signal, 1500.0, 5.0).unwrap();
+        assert!((found.frequency - 1503.0).abs() <= 0.25, "{:?}", found);
+        assert!((found.time_offset - 1.2).abs() < 0.006, "{:?}", found);
+        assert!(found.sync_power > 3.0, "{:?}", found);
+    }
+}
@@ -0,0 +1,266 @@
+//! FT4 signal acquisition (after WSJT-X `getcandidates4` and `sync4d`)
+//!
+//! The same steps as for FT8, scaled to FT4's 48 ms symbols: a
+//! half-bin spectrogram is correlated with the four Costas arrays to find
+//! candidates, each candidate is refined by measuring the Costas symbols
+//! coherently at the full 12 kHz rate, and the data symbols are then
+//! turned into bit LLRs.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use num_complex::Complex32;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::extract::{normalize_bmet, LLR_SCALE};
+use crate::channel::SNR_REFERENCE_BANDWIDTH;
+use crate::fft::real_power_spectrum;
+use crate::ft4::{data_symbol_index, COSTAS_ARRAYS, COSTAS_POSITIONS, GRAY_MAP, NUM_SYMBOLS};
+use crate::ldpc;
+use crate::modulation::{FT4_TONE_SPACING, NOMINAL_START, SAMPLE_RATE};
+
+/// Samples per FT4 symbol at 12 kHz
+pub const FT4_NSPS: usize = 576;
+
+/// Spectrogram time step: a quarter symbol
+const NSTEP: usize = FT4_NSPS / 4;
+
+/// Spectrogram FFT length: one symbol zero-padded to two, for two bins
+/// per tone
+const NFFT: usize = 2 * FT4_NSPS;
+
+/// Symbol duration in seconds
+const SYMBOL_SECONDS: f32 = FT4_NSPS as f32 / SAMPLE_RATE;
+
+/// Spectrogram bin spacing in Hz
+const DF: f32 = SAMPLE_RATE / NFFT as f32;
+
+/// Lowest and highest tone-0 frequency searched, in Hz
+const FREQ_RANGE: (f32, f32) = (100.0, 3000.0);
+
+/// DT range searched, in seconds
+const DT_RANGE: (f32, f32) = (-0.5, 1.0);
+
+/// Smallest normalized Costas correlation kept as a candidate
+const SYNC_MIN: f32 = 1.2;
+
+/// Most candidates returned by [`ft4_candidates`]
+const MAX_CANDIDATES: usize = 60;
+
+/// A possible FT4 signal: tone-0 frequency, DT and sync strength.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Ft4Candidate {
+    pub frequency: f32,
+    pub time_offset: f32,
+    pub sync_power: f32,
+}
+
+/// Sample index of the first tone symbol for a given DT: the waveform
+/// starts with a ramp symbol at the nominal start.
+fn first_symbol_sample(time_offset: f32) -> f32 {
+    (NOMINAL_START + time_offset + SYMBOL_SECONDS) * SAMPLE_RATE
+}
+
+/// Coarse Costas search over the spectrogram.
+///
+/// Scores every frequency bin and quarter-symbol lag by the power on the
+/// expected Costas tones relative to the mean of the other three tones,
+/// keeps each bin's best lag, and returns the local maxima in frequency,
+/// strongest first.
+pub(crate) fn ft4_candidates(signal: &[f32]) -> Vec<Ft4Candidate> {
+    if signal.len() < FT4_NSPS {
+        return Vec::new();
+    }
+    let nsteps = (signal.len() - FT4_NSPS) / NSTEP + 1;
+    let spectra: Vec<Vec<f32>> = (0..nsteps)
+        .map(|j| real_power_spectrum(&signal[j * NSTEP..j * NSTEP + FT4_NSPS], NFFT))
+        .collect();
+
+    let bin_lo = (FREQ_RANGE.0 / DF).round() as usize;
+    let bin_hi = ((FREQ_RANGE.1 / DF).round() as usize).min(NFFT / 2 - 6);
+    let lag_lo = (first_symbol_sample(DT_RANGE.0) / NSTEP as f32).round() as usize;
+    let lag_hi = (first_symbol_sample(DT_RANGE.1) / NSTEP as f32).round() as usize;
+
+    let sync_at = |bin: usize, lag: usize| -> f32 {
+        let (mut on, mut off) = (0.0, 0.0);
+        for (&pos, costas) in COSTAS_POSITIONS.iter().zip(&COSTAS_ARRAYS) {
+            for (k, &tone) in costas.iter().enumerate() {
+                let Some(row) = spectra.get(lag + 4 * (pos + k)) else {
+                    continue;
+                };
+                let total: f32 = (0..4).map(|t| row[bin + 2 * t]).sum();
+                let s = row[bin + 2 * tone as usize];
+                on += s;
+                off += (total - s) / 3.0;
+            }
+        }
+        if off > 0.0 {
+            on / off
+        } else {
+            0.0
+        }
+    };
+
+    let best: Vec<(f32, usize)> = (bin_lo..=bin_hi)
+        .map(|bin| {
+            (lag_lo..=lag_hi)
+                .map(|lag| (sync_at(bin, lag), lag))
+                .fold((0.0, lag_lo), |b, s| if s.0 > b.0 { s } else { b })
+        })
+        .collect();
+
+    let mut candidates: Vec<Ft4Candidate> = (0..best.len())
+        .filter(|&i| {
+            let s = best[i].0;
+            s >= SYNC_MIN
+                && best[i.saturating_sub(2)..(i + 3).min(best.len())]
+                    .iter()
+                    .all(|b| b.0 <= s)
+        })
+        .map(|i| {
+            let (sync_power, lag) = best[i];
+            Ft4Candidate {
+                frequency: (bin_lo + i) as f32 * DF,
+                time_offset: (lag * NSTEP) as f32 / SAMPLE_RATE - NOMINAL_START - SYMBOL_SECONDS,
+                sync_power,
+            }
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.sync_power.total_cmp(&a.sync_power));
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
+/// Complex amplitudes of the four tones of each of `symbols`, for a
+/// signal whose first tone symbol starts at sample `start` with tone 0 at
+/// `frequency`. Symbols extending past either end of `signal` are zero.
+fn tone_amplitudes(
+    signal: &[f32],
+    start: isize,
+    frequency: f32,
+    symbols: impl Iterator<Item = usize>,
+) -> Vec<[Complex32; 4]> {
+    // Phases are referenced to each symbol's start, which keeps them
+    // small and the four tones orthogonal over the symbol
+    let twiddles: Vec<[Complex32; 4]> = (0..FT4_NSPS)
+        .map(|n| {
+            core::array::from_fn(|t| {
+                let f = frequency + t as f32 * FT4_TONE_SPACING;
+                Complex32::from_polar(1.0, -2.0 * PI * f * n as f32 / SAMPLE_RATE)
+            })
+        })
+        .collect();
+    symbols
+        .map(|k| {
+            let mut cs = [Complex32::new(0.0, 0.0); 4];
+            let s0 = start + (k * FT4_NSPS) as isize;
+            if s0 < 0 || s0 as usize + FT4_NSPS > signal.len() {
+                return cs;
+            }
+            let s0 = s0 as usize;
+            for (&x, tw) in signal[s0..s0 + FT4_NSPS].iter().zip(&twiddles) {
+                for (c, w) in cs.iter_mut().zip(tw) {
+                    *c += w * x;
+                }
+            }
+            cs
+        })
+        .collect()
+}
+
+/// Coherent Costas metric: summed amplitude of the expected tones.
+fn sync_metric(signal: &[f32], start: isize, frequency: f32) -> f32 {
+    let symbols = COSTAS_POSITIONS.iter().flat_map(|&pos| pos..pos + 4);
+    let cs = tone_amplitudes(signal, start, frequency, symbols);
+    COSTAS_ARRAYS
+        .iter()
+        .flatten()
+        .zip(&cs)
+        .map(|(&tone, c)| c[tone as usize].norm())
+        .sum()
+}
+
+/// Refine a candidate's DT and frequency around the coarse estimate.
+///
+/// Time is searched over ±½ spectrogram step, frequency over ±½ bin, then
+/// time again more finely at the best frequency.
+pub(crate) fn ft4_refine(signal: &[f32], candidate: &Ft4Candidate) -> Ft4Candidate {
+    let start = first_symbol_sample(candidate.time_offset).round() as isize;
+    let best_of = |points: &mut dyn Iterator<Item = (isize, f32)>| {
+        points.map(|(s, f)| (sync_metric(signal, s, f), s, f)).fold(
+            (f32::MIN, start, candidate.frequency),
+            |b, p| {
+                if p.0 > b.0 {
+                    p
+                } else {
+                    b
+                }
+            },
+        )
+    };
+
+    let half_step = (NSTEP / 2) as isize;
+    let (_, start, _) =
+        best_of(&mut (-6..=6).map(|i| (start + i * half_step / 6, candidate.frequency)));
+    let (_, _, frequency) =
+        best_of(&mut (-6..=6).map(|i| (start, candidate.frequency + i as f32 * DF / 12.0)));
+    let (sync, start, frequency) = best_of(&mut (-4..=4).map(|i| (start + i * 2, frequency)));
+
+    Ft4Candidate {
+        frequency,
+        time_offset: start as f32 / SAMPLE_RATE - NOMINAL_START - SYMBOL_SECONDS,
+        sync_power: sync,
+    }
+}
+
+/// Demodulate a refined candidate.
+///
+/// Returns the 174 bit LLRs, from single-symbol non-coherent metrics
+/// scaled as for FT8, and the tone amplitudes of all 103 symbols.
+pub(crate) fn ft4_llrs(signal: &[f32], candidate: &Ft4Candidate) -> (Vec<f32>, Vec<[f32; 4]>) {
+    let start = first_symbol_sample(candidate.time_offset).round() as isize;
+    let s4: Vec<[f32; 4]> = tone_amplitudes(signal, start, candidate.frequency, 0..NUM_SYMBOLS)
+        .iter()
+        .map(|cs| core::array::from_fn(|t| cs[t].norm()))
+        .collect();
+
+    let mut bmet = vec![0.0f32; ldpc::N];
+    for (k, pair) in bmet.chunks_exact_mut(2).enumerate() {
+        let row = &s4[data_symbol_index(k)];
+        for (ib, b) in pair.iter_mut().enumerate() {
+            let mask = 2 >> ib;
+            let (mut max1, mut max0) = (f32::MIN, f32::MIN);
+            for (value, &tone) in GRAY_MAP.iter().enumerate() {
+                let v = row[tone as usize];
+                if value & mask != 0 {
+                    max1 = max1.max(v);
+                } else {
+                    max0 = max0.max(v);
+                }
+            }
+            *b = max1 - max0;
+        }
+    }
+    normalize_bmet(&mut bmet);
+    bmet.iter_mut().for_each(|b| *b *= LLR_SCALE);
+    (bmet, s4)
+}
+
+/// SNR in dB (2500 Hz reference bandwidth) of a decoded FT4 signal.
+///
+/// As for FT8, compares the power on each transmitted tone with the power
+/// on a tone two places away.
+pub(crate) fn ft4_snr(s4: &[[f32; 4]], tones: &[u8]) -> f32 {
+    let (xsig, xnoi) = s4
+        .iter()
+        .zip(tones)
+        .fold((0.0, 0.0), |(sig, noi), (row, &tone)| {
+            let off = (tone as usize + 2) % 4;
+            (sig + row[tone as usize].powi(2), noi + row[off].powi(2))
+        });
+    let ratio = if xnoi > 0.0 { xsig / xnoi - 1.0 } else { 0.0 };
+    let ratio = if ratio > 0.1 { ratio } else { 0.001 };
+    let snr = 10.0 * ratio.log10() - 10.0 * (SNR_REFERENCE_BANDWIDTH / FT4_TONE_SPACING).log10();
+    snr.max(-21.0)
+}
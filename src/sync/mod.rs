@@ -0,0 +1,226 @@
+//! Signal acquisition: finding FT8 transmissions and extracting soft bits
+//!
+//! The receive chain for one 15 s window is:
+//!
+//! 1. [`compute_spectra`]: quarter-symbol-step power spectra of the 12 kHz
+//!    input (other sound-card rates converted with [`to_12khz`]), or
+//!    [`SpectrogramBuilder`] to build them as live audio arrives
+//! 2. [`coarse_sync`]: correlate the spectra with the three Costas arrays
+//!    to produce [`Candidate`]s (frequency, DT, sync strength), or
+//!    [`candidates`] to take them one at a time, strongest first
+//! 3. [`fine_sync`]: mix each candidate down to baseband at ~200 Hz and
+//!    refine its time and frequency with a phase-coherent Costas search
+//!    ([`matched_filter_score`] then measures how cleanly it matches the
+//!    Costas waveform, which the decoder uses to order candidates)
+//! 4. [`extract_symbols_all_llr`]: measure the 8 tone amplitudes of every
+//!    symbol and turn them into bit log-likelihood ratios for the LDPC
+//!    decoder
+//!
+//! FT4 candidates go through the same steps in the crate-internal `ft4`
+//! submodule, used by [`ft4::decode`](crate::ft4::decode).
+//!
+//! [`waterfall`] exports the step 1 spectrogram, scaled for a waterfall
+//! display, with the mappings needed to overlay candidates on it.
+//!
+//! Steps 3 and 4 work on the window mixed down around each candidate; a
+//! [`Downsampler`] transforms the window once so that every candidate
+//! shares the expensive forward FFT.
+//!
+//! Times are expressed as DT, the offset in seconds from the nominal
+//! transmission start 0.5 s into the window, as displayed by WSJT-X.
+
+mod candidate;
+mod downsample;
+mod extract;
+mod fine;
+pub(crate) mod ft4;
+mod resample;
+mod spectra;
+mod waterfall;
+
+/// FFT plans shared by the spectrogram, the downsampler and symbol
+/// extraction
+#[cfg(feature = "std")]
+pub mod fft {
+    pub use crate::fft::FftCache;
+}
+
+pub use candidate::{
+    candidates, coarse_sync, coarse_sync_spectra, compute_sync2d, find_candidates, CandidateSearch,
+    CandidateStream, RefinedCandidates, Sync2dRow, NLAGS,
+};
+pub use downsample::{downsample_200hz, Downsampler, DOWNSAMPLE_RATE};
+pub(crate) use extract::wsjtx_off_tone_snr;
+pub use extract::{
+    calculate_snr, calculate_snr_baseline, estimate_frequency_from_phase, extract_symbols,
+    extract_symbols_all_llr, extract_symbols_all_llr_with, SymbolLlrs,
+};
+#[cfg(feature = "std")]
+pub use fft::FftCache;
+pub use fine::{
+    fine_sync, fine_sync_search, fine_sync_with, locate_candidate, locate_candidate_near,
+    matched_filter_score, FineSearch,
+};
+pub use resample::to_12khz;
+pub use spectra::{compute_spectra, compute_spectra_region, noise_baseline, SpectrogramBuilder};
+pub use waterfall::{waterfall, Waterfall, WaterfallScale};
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::modulation::{NOMINAL_START, SAMPLE_RATE, SYMBOL_DURATION, TONE_SPACING};
+
+/// Samples in a 15 s window at 12 kHz
+pub const NMAX: usize = 180_000;
+
+/// Samples per symbol at 12 kHz
+pub const NSPS: usize = 1920;
+
+/// Spectrogram time step: a quarter symbol
+pub const NSTEP: usize = NSPS / 4;
+
+/// FFT length of the spectrogram: one symbol zero-padded to two, as in
+/// WSJT-X, for a bin spacing of exactly half the tone spacing
+pub const NFFT1: usize = 2 * NSPS;
+
+/// Number of usable spectrogram frequency bins
+pub const NH1: usize = NFFT1 / 2;
+
+/// Number of spectrogram time steps in a window
+pub const NHSYM: usize = NMAX / NSTEP - 3;
+
+/// Spectrogram bin spacing in Hz
+pub const DF: f32 = SAMPLE_RATE / NFFT1 as f32;
+
+/// Spectrogram time step in seconds
+pub const TSTEP: f32 = NSTEP as f32 / SAMPLE_RATE;
+
+/// Largest coarse time lag searched, in spectrogram steps (±2.48 s)
+pub const MAX_LAG: i32 = 62;
+
+/// Lag range of the primary coarse search, in spectrogram steps (±0.4 s)
+pub const COARSE_LAG: i32 = 10;
+
+/// Spectrogram step at which a DT = 0 signal's first symbol window starts
+const JSTRT: i32 = 12;
+
+/// Spectrogram bin offset of tone `k` relative to tone 0: two bins per
+/// tone.
+pub(crate) fn tone_bin(k: usize) -> usize {
+    (k as f32 * TONE_SPACING / DF).round() as usize
+}
+
+/// Which Costas arrays produced a candidate's coarse sync peak.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SyncMetric {
+    /// All three arrays
+    #[default]
+    AllCostas,
+    /// Arrays 2 and 3 only, as for a signal whose first array was lost
+    /// before the window start
+    CostasBc,
+}
+
+/// A possible FT8 signal found by the coarse search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Candidate {
+    /// Frequency of tone 0 in Hz
+    pub frequency: f32,
+    /// DT in seconds, as WSJT-X reports it: when symbol 0 starts relative
+    /// to the nominal 0.5 s into the window, so a transmission started on
+    /// time has DT 0. [`absolute_time`](Self::absolute_time) gives the
+    /// start measured from the beginning of the window instead
+    pub time_offset: f32,
+    /// Coarse Costas correlation, normalized to the band's noise floor
+    pub sync_power: f32,
+    /// Costas metric behind `sync_power`
+    pub sync_metric: SyncMetric,
+}
+
+impl Candidate {
+    /// Start of symbol 0 in seconds from the beginning of the window: DT
+    /// plus the nominal 0.5 s.
+    pub fn absolute_time(&self) -> f32 {
+        self.time_offset + NOMINAL_START
+    }
+
+    /// Index of the first sample of symbol 0 in a buffer downsampled to
+    /// `actual_sample_rate` whose sample 0 is the start of the window.
+    ///
+    /// The [`absolute_time`](Self::absolute_time) in samples. May be
+    /// negative for signals that began before the window.
+    pub fn downsample_start_offset(&self, actual_sample_rate: f32) -> i32 {
+        (self.absolute_time() * actual_sample_rate).round() as i32
+    }
+
+    /// Samples per symbol at `actual_sample_rate`, rounded to the nearest
+    /// whole sample.
+    pub fn samples_per_symbol(&self, actual_sample_rate: f32) -> usize {
+        (actual_sample_rate * SYMBOL_DURATION).round() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time_offset: f32) -> Candidate {
+        Candidate {
+            frequency: 1500.0,
+            time_offset,
+            sync_power: 1.0,
+            sync_metric: SyncMetric::AllCostas,
+        }
+    }
+
+    #[test]
+    fn test_downsample_offsets() {
+        // DT = 0 starts at 0.5 s: 93.75 samples at 187.5 Hz, rounded
+        assert_eq!(at(0.0).downsample_start_offset(187.5), 94);
+        assert_eq!(at(-0.5).downsample_start_offset(187.5), 0);
+        assert_eq!(at(-1.0).downsample_start_offset(187.5), -94);
+        assert_eq!(at(0.5).downsample_start_offset(200.0), 200);
+        assert_eq!(at(0.0).downsample_start_offset(SAMPLE_RATE), 6000);
+
+        assert_eq!(at(0.0).absolute_time(), 0.5);
+        assert_eq!(at(-0.5).absolute_time(), 0.0);
+
+        assert_eq!(at(0.0).samples_per_symbol(187.5), 30);
+        assert_eq!(at(0.0).samples_per_symbol(200.0), 32);
+        assert_eq!(at(0.0).samples_per_symbol(SAMPLE_RATE), NSPS);
+    }
+
+    #[test]
+    fn test_known_dt_is_reported_relative_to_nominal_start() {
+        use crate::decoder::{decode_ft8, DecoderConfig};
+        use crate::message::tx_cq;
+        use crate::modulation::BandSynthesizer;
+
+        // Symbol 0 starts 1.2 s into the window: DT 0.7
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("N0YPR", "DM42").unwrap(), 1500.0, 0.7, 1.0)
+            .synthesize();
+        let candidate = coarse_sync(&signal, &CandidateSearch::default()).unwrap()[0];
+        assert!(
+            (candidate.time_offset - 0.7).abs() < 0.03,
+            "{:?}",
+            candidate
+        );
+        let refined = fine_sync(&signal, &candidate).unwrap();
+        assert!((refined.time_offset - 0.7).abs() < 0.003, "{:?}", refined);
+        assert!(
+            (refined.absolute_time() - 1.2).abs() < 0.003,
+            "{:?}",
+            refined
+        );
+
+        let decodes = decode_ft8(&signal, &DecoderConfig::default(), None).unwrap();
+        assert!(
+            (decodes[0].time_offset - 0.7).abs() < 0.003,
+            "{:?}",
+            decodes[0]
+        );
+    }
+}
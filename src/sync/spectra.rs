@@ -0,0 +1,340 @@
+//! Quarter-symbol-step power spectrogram used by the coarse search
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use num_complex::Complex32;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::{DF, NFFT1, NH1, NHSYM, NMAX, NSPS, NSTEP};
+use crate::fft::fft;
+
+/// Amplitude scale applied to the input samples
+pub(super) const FAC: f32 = 1.0 / 300.0;
+
+/// Half-width of the band around a signal whose spectrum sets its noise
+/// baseline, in Hz
+const BASELINE_HALF_WIDTH: f32 = 250.0;
+
+/// Quantile of that band's averaged spectrum taken as the noise level: low
+/// enough to skip the signals in it, high enough that the spread of the
+/// noise-only bins biases it by only −0.25 dB
+const BASELINE_QUANTILE: f32 = 0.3;
+
+/// Compute the power spectrogram `s[bin][step]` of a 15 s window.
+///
+/// Each column is the power spectrum of one symbol length (`NSPS`
+/// samples, zero-padded to `NFFT1`) starting at `step * NSTEP`.
+pub fn compute_spectra(signal: &[f32]) -> Result<Vec<[f32; NHSYM]>, String> {
+    if signal.len() < NMAX {
+        return Err(format!(
+            "Signal too short: {} samples, need {}",
+            signal.len(),
+            NMAX
+        ));
+    }
+
+    let mut spectra = vec![[0.0f32; NHSYM]; NH1];
+    let mut buf = vec![Complex32::new(0.0, 0.0); NFFT1];
+
+    for j in 0..NHSYM {
+        let start = j * NSTEP;
+        spectrum_column(&signal[start..start + NSPS], j, &mut buf, &mut spectra);
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        bins = NH1,
+        columns = NHSYM,
+        peak = spectra.iter().flatten().fold(0.0f32, |m, &p| m.max(p)),
+        "computed spectrogram"
+    );
+    Ok(spectra)
+}
+
+/// Fill column `j` of `spectra` from the `NSPS` samples of that step.
+fn spectrum_column(samples: &[f32], j: usize, buf: &mut [Complex32], spectra: &mut [[f32; NHSYM]]) {
+    buf.fill(Complex32::new(0.0, 0.0));
+    for (b, &x) in buf.iter_mut().zip(samples) {
+        b.re = FAC * x;
+    }
+    fft(buf);
+    for (row, c) in spectra.iter_mut().zip(&buf[..NH1]) {
+        row[j] = c.norm_sqr();
+    }
+}
+
+/// Noise power per spectrogram bin around `frequency`, in the units of
+/// [`compute_spectra`].
+///
+/// Averages the power spectra of the window's non-overlapping symbol
+/// periods and takes a low quantile of the bins within 250 Hz of
+/// `frequency`, so the signals there do not raise the estimate.
+pub fn noise_baseline(signal: &[f32], frequency: f32) -> Result<f32, String> {
+    if signal.len() < NMAX {
+        return Err(format!(
+            "Signal too short: {} samples, need {}",
+            signal.len(),
+            NMAX
+        ));
+    }
+    let lo = (((frequency - BASELINE_HALF_WIDTH) / DF).round() as usize).max(1);
+    let hi = (((frequency + BASELINE_HALF_WIDTH) / DF).round() as usize).min(NH1 - 1);
+    if lo >= hi {
+        return Err(format!("Frequency out of range: {} Hz", frequency));
+    }
+
+    let mut average = vec![0.0f32; NH1];
+    let mut buf = vec![Complex32::new(0.0, 0.0); NFFT1];
+    let columns = NMAX / NSPS;
+    for samples in signal[..columns * NSPS].chunks_exact(NSPS) {
+        buf.fill(Complex32::new(0.0, 0.0));
+        for (b, &x) in buf.iter_mut().zip(samples) {
+            b.re = FAC * x;
+        }
+        fft(&mut buf);
+        for (a, c) in average.iter_mut().zip(&buf[..NH1]) {
+            *a += c.norm_sqr() / columns as f32;
+        }
+    }
+
+    let mut band = average[lo..=hi].to_vec();
+    band.sort_by(f32::total_cmp);
+    Ok(band[((band.len() - 1) as f32 * BASELINE_QUANTILE).round() as usize])
+}
+
+/// Builds the [`compute_spectra`] spectrogram from audio as it arrives.
+///
+/// Each column is computed as soon as its samples are in, so when the
+/// window closes only the last few columns remain. The result is
+/// identical to [`compute_spectra`] on the same 15 s of audio.
+pub struct SpectrogramBuilder {
+    spectra: Vec<[f32; NHSYM]>,
+    buf: Vec<Complex32>,
+    /// Samples from the start of the next column onwards
+    pending: Vec<f32>,
+    columns: usize,
+}
+
+impl Default for SpectrogramBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpectrogramBuilder {
+    pub fn new() -> Self {
+        Self {
+            spectra: vec![[0.0f32; NHSYM]; NH1],
+            buf: vec![Complex32::new(0.0, 0.0); NFFT1],
+            pending: Vec::with_capacity(NSPS + NSTEP),
+            columns: 0,
+        }
+    }
+
+    /// Append the next samples of the window.
+    ///
+    /// Blocks may be any length; each `NSTEP` samples completes one column.
+    /// Samples beyond the 15 s window are ignored.
+    pub fn push(&mut self, block: &[f32]) {
+        for chunk in block.chunks(NSTEP) {
+            if self.columns == NHSYM {
+                return;
+            }
+            self.pending.extend_from_slice(chunk);
+            self.compute_ready_columns();
+        }
+    }
+
+    /// Number of spectrogram columns completed so far (of `NHSYM`).
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// The finished spectrogram.
+    ///
+    /// Columns whose samples have not all arrived are computed as if the
+    /// window were padded with silence.
+    pub fn finish(mut self) -> Vec<[f32; NHSYM]> {
+        while self.columns < NHSYM {
+            let len = self.pending.len().max(NSPS);
+            self.pending.resize(len, 0.0);
+            self.compute_ready_columns();
+        }
+        self.spectra
+    }
+
+    fn compute_ready_columns(&mut self) {
+        while self.columns < NHSYM && self.pending.len() >= NSPS {
+            spectrum_column(
+                &self.pending[..NSPS],
+                self.columns,
+                &mut self.buf,
+                &mut self.spectra,
+            );
+            self.columns += 1;
+            self.pending.drain(..NSTEP);
+        }
+    }
+}
+
+/// Recompute the rows of `spectra` covering `freq_lo..=freq_hi` Hz.
+///
+/// After a signal has been subtracted only the bins it occupied change
+/// appreciably, so rather than a full FFT per column the affected bins are
+/// evaluated directly. Rows outside the range are left untouched, including
+/// the small spectral leakage of the change into neighbouring bins.
+///
+/// The cost grows with the width of the range: the 70 Hz around one
+/// signal take a little over half the time of [`compute_spectra`], and
+/// from about 120 Hz the full recompute is quicker
+/// (`cargo bench --bench spectra`).
+pub fn compute_spectra_region(
+    signal: &[f32],
+    freq_lo: f32,
+    freq_hi: f32,
+    spectra: &mut [[f32; NHSYM]],
+) -> Result<(), String> {
+    if signal.len() < NMAX {
+        return Err(format!(
+            "Signal too short: {} samples, need {}",
+            signal.len(),
+            NMAX
+        ));
+    }
+    if spectra.len() < NH1 {
+        return Err(format!(
+            "Spectra too short: {} bins, need {}",
+            spectra.len(),
+            NH1
+        ));
+    }
+
+    let bin_lo = (freq_lo / DF).floor().max(0.0) as usize;
+    let bin_hi = ((freq_hi / DF).ceil() as usize).min(NH1 - 1);
+    if bin_lo > bin_hi {
+        return Ok(());
+    }
+
+    // Columns overlap by three quarters, so each bin's DFT over a
+    // quarter-symbol block is computed once and four blocks combined per
+    // column with the appropriate phase shifts
+    let twiddle = |m: usize| {
+        let m = m % NFFT1;
+        Complex32::from_polar(1.0, -2.0 * PI * m as f32 / NFFT1 as f32)
+    };
+    let nblocks = NHSYM + NSPS / NSTEP - 1;
+
+    for (k, row) in spectra.iter_mut().enumerate().take(bin_hi + 1).skip(bin_lo) {
+        let kernel: Vec<Complex32> = (0..NSTEP).map(|n| twiddle(k * n) * FAC).collect();
+        let blocks: Vec<Complex32> = signal[..nblocks * NSTEP]
+            .chunks_exact(NSTEP)
+            .map(|block| block.iter().zip(&kernel).map(|(&x, &w)| w * x).sum())
+            .collect();
+        let shifts: Vec<Complex32> = (0..NSPS / NSTEP).map(|q| twiddle(k * q * NSTEP)).collect();
+
+        for (j, power) in row.iter_mut().enumerate() {
+            let c: Complex32 = blocks[j..].iter().zip(&shifts).map(|(b, w)| b * w).sum();
+            *power = c.norm_sqr();
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::DF;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_rejects_short_signal() {
+        assert!(compute_spectra(&[0.0; 1000]).is_err());
+    }
+
+    #[test]
+    fn test_tone_lands_in_expected_bin() {
+        let freq = 100.0 * DF;
+        let signal: Vec<f32> = (0..NMAX)
+            .map(|i| (2.0 * PI * freq * i as f32 / 12000.0).sin())
+            .collect();
+        let spectra = compute_spectra(&signal).unwrap();
+        let peak = (0..NH1)
+            .max_by(|&a, &b| spectra[a][10].total_cmp(&spectra[b][10]))
+            .unwrap();
+        assert_eq!(peak, 100);
+    }
+
+    #[test]
+    fn test_builder_matches_batch() {
+        use crate::channel::Rng;
+        use crate::sync::{coarse_sync, coarse_sync_spectra, CandidateSearch};
+
+        let mut rng = Rng::new(1503);
+        let mut signal: Vec<f32> = (0..NMAX).map(|_| rng.gaussian()).collect();
+        let tones = crate::message::tx_cq("K1ABC", "FN42").unwrap();
+        let wave = crate::modulation::Modulator::default().modulate(&tones, 1200.0);
+        for (s, w) in signal[6000..].iter_mut().zip(&wave) {
+            *s += 3.0 * w;
+        }
+        let batch = compute_spectra(&signal).unwrap();
+
+        // Live audio in NSTEP blocks, and in odd sizes with trailing samples
+        for block in [NSTEP, 1000] {
+            let mut builder = SpectrogramBuilder::new();
+            for chunk in signal.chunks(block) {
+                builder.push(chunk);
+            }
+            builder.push(&[1.0; 5000]);
+            assert_eq!(builder.columns(), NHSYM);
+            let streamed = builder.finish();
+            assert!(streamed == batch, "block {}", block);
+
+            let search = CandidateSearch::default();
+            assert_eq!(
+                coarse_sync_spectra(&streamed, &search).unwrap(),
+                coarse_sync(&signal, &search).unwrap()
+            );
+        }
+
+        // A window cut short is padded with silence
+        let mut padded = signal[..NMAX - 3000].to_vec();
+        let mut builder = SpectrogramBuilder::new();
+        builder.push(&padded);
+        padded.resize(NMAX, 0.0);
+        assert!(builder.finish() == compute_spectra(&padded).unwrap());
+    }
+
+    #[test]
+    fn test_region_matches_full_recompute() {
+        let tone = |freq: f32, amp: f32| {
+            move |i: usize| amp * (2.0 * PI * freq * i as f32 / 12000.0).sin()
+        };
+        let (a, b) = (tone(800.0, 1.0), tone(1500.0, 0.5));
+        let before: Vec<f32> = (0..NMAX).map(|i| a(i) + b(i)).collect();
+        let mut spectra = compute_spectra(&before).unwrap();
+
+        // Remove the 1500 Hz tone and update only its neighbourhood
+        let after: Vec<f32> = (0..NMAX).map(a).collect();
+        compute_spectra_region(&after, 1450.0, 1550.0, &mut spectra).unwrap();
+        let full = compute_spectra(&after).unwrap();
+
+        let lo = (1450.0 / DF).floor() as usize;
+        let hi = (1550.0 / DF).ceil() as usize;
+        for k in lo..=hi {
+            for j in 0..NHSYM {
+                let (x, y) = (spectra[k][j], full[k][j]);
+                assert!((x - y).abs() <= 1e-3 * y.max(1e-3), "bin {} step {}", k, j);
+            }
+        }
+        // Far from the change only leakage differs
+        let k800 = (800.0 / DF).round() as usize;
+        for (x, y) in spectra[k800].iter().zip(&full[k800]) {
+            assert!((x - y).abs() < 1e-3 * y);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Spectrogram export for waterfall displays
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use super::{compute_spectra, Candidate, DF, NH1, NHSYM, TSTEP};
+
+/// How [`waterfall`] scales the power of each cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WaterfallScale {
+    /// Power in the units of [`compute_spectra`], raised to at least
+    /// `floor`
+    Linear { floor: f32 },
+    /// Decibels above the median power of the region, which on an
+    /// ordinary band is the noise floor, raised to at least `floor_db`
+    Db { floor_db: f32 },
+}
+
+/// A power spectrogram laid out for drawing: one row per time step, one
+/// column per frequency bin.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Waterfall {
+    /// `rows[step][column]`, oldest step first, lowest frequency first
+    pub rows: Vec<Vec<f32>>,
+    /// Frequency of column 0 in Hz
+    pub freq_start: f32,
+    /// Width of a column in Hz (3.125, half the tone spacing)
+    pub bin_hz: f32,
+    /// Time between rows in seconds (40 ms, a quarter symbol)
+    pub step_seconds: f32,
+}
+
+impl Waterfall {
+    /// Frequency of `column` in Hz.
+    pub fn frequency(&self, column: usize) -> f32 {
+        self.freq_start + column as f32 * self.bin_hz
+    }
+
+    /// Start of the symbol-length FFT of row `step`, in seconds from the
+    /// start of the window.
+    pub fn time(&self, step: usize) -> f32 {
+        step as f32 * self.step_seconds
+    }
+
+    /// Fractional `(row, column)` where a candidate's first symbol and
+    /// tone 0 fall, for overlaying it on the drawing. The signal then
+    /// spans 16 columns (eight tones) and 316 rows (79 symbols).
+    pub fn position(&self, candidate: &Candidate) -> (f32, f32) {
+        (
+            candidate.absolute_time() / self.step_seconds,
+            (candidate.frequency - self.freq_start) / self.bin_hz,
+        )
+    }
+}
+
+/// The [`compute_spectra`] spectrogram of a 15 s window between
+/// `freq_min` and `freq_max` Hz, scaled for display.
+pub fn waterfall(
+    signal: &[f32],
+    freq_min: f32,
+    freq_max: f32,
+    scale: WaterfallScale,
+) -> Result<Waterfall, String> {
+    let first = (freq_min / DF).round().max(0.0) as usize;
+    let last = ((freq_max / DF).round().max(0.0) as usize).min(NH1 - 1);
+    if freq_max < freq_min || first > last {
+        return Err(format!(
+            "Empty waterfall range: {} Hz to {} Hz",
+            freq_min, freq_max
+        ));
+    }
+    let spectra = compute_spectra(signal)?;
+    let bins = &spectra[first..=last];
+
+    // The noise floor that dB values are measured from
+    let reference = match scale {
+        WaterfallScale::Linear { .. } => 1.0,
+        WaterfallScale::Db { .. } => {
+            let mut all: Vec<f32> = bins.iter().flatten().copied().collect();
+            let mid = all.len() / 2;
+            let (_, median, _) = all.select_nth_unstable_by(mid, f32::total_cmp);
+            median.max(f32::MIN_POSITIVE)
+        }
+    };
+    let rows = (0..NHSYM)
+        .map(|j| {
+            bins.iter()
+                .map(|bin| match scale {
+                    WaterfallScale::Linear { floor } => bin[j].max(floor),
+                    // A zero cell gives -inf, which the floor replaces
+                    WaterfallScale::Db { floor_db } => {
+                        (10.0 * (bin[j] / reference).log10()).max(floor_db)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(Waterfall {
+        rows,
+        freq_start: first as f32 * DF,
+        bin_hz: DF,
+        step_seconds: TSTEP,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::Awgn;
+    use crate::message::tx_cq;
+    use crate::modulation::BandSynthesizer;
+    use crate::sync::{coarse_sync, CandidateSearch};
+
+    #[test]
+    fn test_waterfall_shows_signal_where_candidate_is() {
+        let signal = BandSynthesizer::default()
+            .add_signal(&tx_cq("K1ABC", "FN42").unwrap(), 1000.0, 0.3, 1.0)
+            .add_channel(Box::new(Awgn::new(0.0, 21)))
+            .synthesize();
+        let db = waterfall(
+            &signal,
+            500.0,
+            1500.0,
+            WaterfallScale::Db { floor_db: -20.0 },
+        )
+        .unwrap();
+        assert_eq!(db.rows.len(), NHSYM);
+        assert_eq!(db.rows[0].len(), 321);
+        assert_eq!(db.frequency(0), 500.0);
+        assert_eq!(db.frequency(320), 1500.0);
+        assert!((db.time(25) - 1.0).abs() < 1e-6);
+
+        // The candidate lands on the signal's first symbol and tone 0
+        let search = CandidateSearch {
+            freq_min: 500.0,
+            freq_max: 1500.0,
+            ..CandidateSearch::default()
+        };
+        let candidate = coarse_sync(&signal, &search).unwrap()[0];
+        let (row, column) = db.position(&candidate);
+        assert!((row - 20.0).abs() < 1.0, "{}", row);
+        assert!((column - 160.0).abs() < 1.0, "{}", column);
+
+        // Columns inside the signal stand well above the noise at 0 dB
+        let mean =
+            |column: usize| db.rows[20..20 + 316].iter().map(|r| r[column]).sum::<f32>() / 316.0;
+        let noise = mean(40);
+        assert!(noise.abs() < 2.0, "{}", noise);
+        let tones = (160..176).map(mean).fold(f32::MIN, f32::max);
+        assert!(tones > noise + 10.0, "{} vs {}", tones, noise);
+        assert!(db.rows.iter().flatten().all(|&v| v >= -20.0));
+
+        let linear = waterfall(
+            &signal,
+            500.0,
+            1500.0,
+            WaterfallScale::Linear { floor: 0.0 },
+        )
+        .unwrap();
+        let ratio = linear.rows[100][170] / linear.rows[100][40];
+        let db_diff = db.rows[100][170] - db.rows[100][40];
+        assert!((10.0 * ratio.log10() - db_diff).abs() < 1e-3);
+
+        assert!(waterfall(
+            &signal,
+            1500.0,
+            500.0,
+            WaterfallScale::Linear { floor: 0.0 }
+        )
+        .is_err());
+    }
+}